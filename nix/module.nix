@@ -47,7 +47,9 @@ in {
       serviceConfig = {
         Environment = optional (cfg.settings != {}) ["SUPERFREQ_CONFIG=${cfgFile}"];
         WorkingDirectory = "";
-        ExecStart = "${getExe cfg.package} daemon --verbose";
+        # Fail on an invalid configuration instead of running with defaults
+        ExecStart = "${getExe cfg.package} --strict-config daemon --verbose";
+        ExecReload = "${pkgs.coreutils}/bin/kill -HUP $MAINPID";
         Restart = "on-failure";
 
         RuntimeDirectory = "superfreq";
@@ -14,6 +14,7 @@ in
         (fs.fileFilter (file: builtins.any file.hasExt ["rs"]) ../src)
         ../Cargo.lock
         ../Cargo.toml
+        ../polkit
       ];
     };
 
@@ -21,6 +22,14 @@ in
     useFetchCargoVendor = true;
     enableParallelBuilding = true;
 
+    # pkexec only matches the actions of the policy to the installed binary
+    postInstall = ''
+      install -Dm644 polkit/com.github.notashelf.superfreq.policy \
+        $out/share/polkit-1/actions/com.github.notashelf.superfreq.policy
+      substituteInPlace $out/share/polkit-1/actions/com.github.notashelf.superfreq.policy \
+        --replace-fail /usr/bin/superfreq $out/bin/superfreq
+    '';
+
     meta = {
       description = "Automatic CPU speed & power optimizer for Linux";
       longDescription = ''
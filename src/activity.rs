@@ -0,0 +1,297 @@
+use crate::config::ActivityDetection;
+use crate::util::sysfs;
+use log::{debug, info, warn};
+use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::os::fd::AsFd;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const INPUT_DIR: &str = "/dev/input";
+
+/// How often the input devices are listed again, to pick up new keyboards and mice
+const RESCAN_INTERVAL_MS: u16 = 60_000;
+
+/// `EV_KEY` and `EV_REL` in the event type bitmask of an input device. Devices
+/// without either (accelerometers, lid switches, ...) do not mean a user is present.
+const USER_INPUT_EVENT_TYPES: u64 = (1 << 1) | (1 << 2);
+
+/// Without a user for this long, the system counts as idle
+const IDLE_THRESHOLD: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    Input,
+    #[cfg(feature = "dbus")]
+    Logind,
+}
+
+/// Where user activity is read from, or `None` to fall back to the heuristic
+static SOURCE: Mutex<Option<Source>> = Mutex::new(None);
+
+/// Time of the last event from an input device
+static LAST_INPUT: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Whether any input device is open, so `LAST_INPUT` is meaningful
+static INPUT_AVAILABLE: AtomicBool = AtomicBool::new(false);
+
+/// Choose how user activity is detected, starting the input listener if needed
+pub fn configure(detection: ActivityDetection) {
+    let source = match detection {
+        ActivityDetection::Heuristic => None,
+        ActivityDetection::Input => start_input_listener().then_some(Source::Input),
+        ActivityDetection::Logind => logind_source(),
+        ActivityDetection::Auto => start_input_listener()
+            .then_some(Source::Input)
+            .or_else(logind_source),
+    };
+
+    match source {
+        Some(Source::Input) => info!("Detecting user activity from input devices"),
+        #[cfg(feature = "dbus")]
+        Some(Source::Logind) => info!("Detecting user activity from the logind idle hint"),
+        None if detection == ActivityDetection::Heuristic => {
+            debug!("Detecting user activity from CPU usage and temperature");
+        }
+        None if detection == ActivityDetection::Auto => {
+            info!(
+                "Cannot detect user activity directly, guessing it from CPU usage and temperature"
+            );
+        }
+        None => warn!(
+            "Cannot detect user activity with activity_detection = \"{}\", guessing it from CPU usage and temperature",
+            format!("{detection:?}").to_lowercase()
+        ),
+    }
+
+    *SOURCE.lock().unwrap_or_else(PoisonError::into_inner) = source;
+}
+
+/// Time since the user last used the system, or `None` if it is not known
+/// and has to be guessed from system activity
+pub fn idle_time() -> Option<Duration> {
+    let source = *SOURCE.lock().unwrap_or_else(PoisonError::into_inner);
+
+    match source? {
+        Source::Input if !INPUT_AVAILABLE.load(Ordering::Relaxed) => None,
+        Source::Input => LAST_INPUT
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .map(|last| last.elapsed()),
+        #[cfg(feature = "dbus")]
+        Source::Logind => match logind::idle_time() {
+            Ok(idle) => Some(idle),
+            Err(e) => {
+                debug!("Cannot read the logind idle hint: {e}");
+                None
+            }
+        },
+    }
+}
+
+/// Whether the user has been away long enough for the system to count as idle
+pub fn is_user_idle(idle: Duration) -> bool {
+    idle >= IDLE_THRESHOLD
+}
+
+#[cfg(feature = "dbus")]
+fn logind_source() -> Option<Source> {
+    match logind::idle_time() {
+        Ok(_) => Some(Source::Logind),
+        Err(e) => {
+            debug!("Cannot read the logind idle hint: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "dbus"))]
+fn logind_source() -> Option<Source> {
+    debug!("Built without the dbus feature, cannot read the logind idle hint");
+    None
+}
+
+/// Whether an input device (e.g. `event3`) reports keys or relative motion
+fn is_user_input_device(name: &str) -> bool {
+    let path = sysfs::path("/sys/class/input")
+        .join(name)
+        .join("device/capabilities/ev");
+
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|ev| u64::from_str_radix(ev.trim(), 16).ok())
+        .is_some_and(|ev| ev & USER_INPUT_EVENT_TYPES != 0)
+}
+
+/// Open the keyboards, mice and touchpads that are not in `devices` yet.
+/// Events are only read to note their time; the devices are not grabbed, so
+/// other readers are unaffected.
+fn open_new_input_devices(devices: &mut BTreeMap<PathBuf, File>) {
+    let Ok(entries) = fs::read_dir(INPUT_DIR) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let path = entry.path();
+        if !name.starts_with("event") || devices.contains_key(&path) || !is_user_input_device(&name)
+        {
+            continue;
+        }
+
+        match File::open(&path) {
+            Ok(device) => {
+                devices.insert(path, device);
+            }
+            Err(e) => debug!("Cannot open {}: {e}", path.display()),
+        }
+    }
+}
+
+/// Start a background thread that records the time of the last input event.
+/// Returns `false` if no input device can be read, e.g. without root.
+///
+/// The devices stay open for as long as they are plugged in, so they can still
+/// be read after the daemon drops root. Devices that cannot be opened are
+/// skipped, and once none are left, activity is guessed from system load again.
+fn start_input_listener() -> bool {
+    let mut devices = BTreeMap::new();
+    open_new_input_devices(&mut devices);
+    if devices.is_empty() {
+        debug!("No readable input devices in {INPUT_DIR}");
+        return false;
+    }
+
+    // Assume the user is present when the daemon starts
+    *LAST_INPUT.lock().unwrap_or_else(PoisonError::into_inner) = Some(Instant::now());
+    INPUT_AVAILABLE.store(true, Ordering::Relaxed);
+
+    let spawned = thread::Builder::new()
+        .name("input-activity".to_string())
+        .spawn(move || {
+            let mut buf = [0; 4096];
+            loop {
+                watch_input_devices(&mut devices, &mut buf);
+                open_new_input_devices(&mut devices);
+
+                let available = !devices.is_empty();
+                if INPUT_AVAILABLE.swap(available, Ordering::Relaxed) != available {
+                    if available {
+                        info!("Detecting user activity from input devices again");
+                    } else {
+                        warn!(
+                            "No input device can be read anymore, guessing user activity from CPU usage and temperature"
+                        );
+                    }
+                }
+            }
+        });
+
+    match spawned {
+        Ok(_) => true,
+        Err(e) => {
+            warn!("Failed to start input activity listener: {e}");
+            false
+        }
+    }
+}
+
+/// Record input events until the devices should be listed again. Devices
+/// that fail to read have been unplugged and are closed.
+fn watch_input_devices(devices: &mut BTreeMap<PathBuf, File>, buf: &mut [u8]) {
+    if devices.is_empty() {
+        thread::sleep(Duration::from_millis(u64::from(RESCAN_INTERVAL_MS)));
+        return;
+    }
+
+    let mut unplugged = Vec::new();
+    {
+        let mut fds: Vec<PollFd<'_>> = devices
+            .values()
+            .map(|device| PollFd::new(device.as_fd(), PollFlags::POLLIN))
+            .collect();
+
+        loop {
+            match poll(&mut fds, PollTimeout::from(RESCAN_INTERVAL_MS)) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) => {
+                    debug!("Failed to wait for input events: {e}");
+                    thread::sleep(Duration::from_millis(u64::from(RESCAN_INTERVAL_MS)));
+                    break;
+                }
+            }
+
+            *LAST_INPUT.lock().unwrap_or_else(PoisonError::into_inner) = Some(Instant::now());
+
+            // Drain the events so the next wait blocks until new ones arrive
+            for ((path, mut device), fd) in devices.iter().zip(&fds) {
+                if fd.revents().is_some_and(|events| !events.is_empty())
+                    && device.read(buf).is_err()
+                {
+                    unplugged.push(path.clone());
+                }
+            }
+            if !unplugged.is_empty() {
+                break;
+            }
+        }
+    }
+
+    for path in unplugged {
+        debug!("Input device {} is gone", path.display());
+        devices.remove(&path);
+    }
+}
+
+#[cfg(feature = "dbus")]
+mod logind {
+    use nix::time::{ClockId, clock_gettime};
+    use std::sync::{Mutex, PoisonError};
+    use std::time::Duration;
+    use zbus::blocking::{Connection, Proxy};
+    use zbus::proxy::CacheProperties;
+
+    static CONNECTION: Mutex<Option<Connection>> = Mutex::new(None);
+
+    fn connection() -> zbus::Result<Connection> {
+        let mut connection = CONNECTION.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(connection) = connection.as_ref() {
+            return Ok(connection.clone());
+        }
+
+        let new = Connection::system()?;
+        *connection = Some(new.clone());
+        Ok(new)
+    }
+
+    /// Time since the seat went idle according to logind, or zero while it is
+    /// in use. The idle hint is combined across sessions and set by desktop
+    /// environments and screen lockers.
+    pub fn idle_time() -> zbus::Result<Duration> {
+        let connection = connection()?;
+        let manager = zbus::blocking::proxy::Builder::<Proxy<'_>>::new(&connection)
+            .destination("org.freedesktop.login1")?
+            .path("/org/freedesktop/login1")?
+            .interface("org.freedesktop.login1.Manager")?
+            .cache_properties(CacheProperties::No)
+            .build()?;
+
+        if !manager.get_property::<bool>("IdleHint")? {
+            return Ok(Duration::ZERO);
+        }
+
+        let idle_since_us: u64 = manager.get_property("IdleSinceHintMonotonic")?;
+        let now = clock_gettime(ClockId::CLOCK_MONOTONIC)
+            .map_err(|e| zbus::Error::Failure(format!("Cannot read the monotonic clock: {e}")))?;
+        let now = Duration::new(now.tv_sec() as u64, now.tv_nsec() as u32);
+
+        Ok(now.saturating_sub(Duration::from_micros(idle_since_us)))
+    }
+}
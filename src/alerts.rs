@@ -0,0 +1,240 @@
+use crate::config::AlertConfig;
+use crate::util::notify;
+use log::{debug, info, warn};
+use std::collections::HashMap;
+use std::fmt;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Window over which the battery drop is measured
+pub const BATTERY_DROP_WINDOW: Duration = Duration::from_secs(10 * 60);
+/// Window over which the temperature rise is measured
+pub const TEMPERATURE_RISE_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlertKind {
+    BatteryDrain,
+    TemperatureRise,
+    /// Discharge rate above the drain thresholds
+    HighDrain,
+}
+
+impl fmt::Display for AlertKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BatteryDrain => write!(f, "battery-drain"),
+            Self::TemperatureRise => write!(f, "temperature-rise"),
+            Self::HighDrain => write!(f, "high-drain"),
+        }
+    }
+}
+
+/// An alert raised for an abnormally fast change of a metric
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub kind: AlertKind,
+    /// How much the metric changed within its window
+    pub value: f32,
+    pub message: String,
+}
+
+/// Rates of change observed by the daemon, as computed from `SystemHistory`
+pub struct ObservedChanges {
+    /// Battery percentage lost within [`BATTERY_DROP_WINDOW`]
+    pub battery_drop_percent: Option<f32>,
+    /// Temperature gained within [`TEMPERATURE_RISE_WINDOW`]
+    pub temperature_rise_celsius: Option<f32>,
+    /// Power drawn from the batteries
+    pub discharge_watts: Option<f32>,
+    /// Battery percentage lost per hour
+    pub discharge_percent_per_hour: Option<f32>,
+    pub on_battery: bool,
+}
+
+/// Whether a drain alert has switched to the drain profile, which lasts until
+/// AC is connected so that the lower drain it causes does not switch back
+static DRAIN_PROFILE_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Whether the profile configured as `drain_profile` should be applied
+pub fn drain_profile_active() -> bool {
+    DRAIN_PROFILE_ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Evaluates alert thresholds and dispatches alerts, rate limited per kind
+#[derive(Default)]
+pub struct AlertTracker {
+    last_fired: HashMap<AlertKind, Instant>,
+}
+
+impl AlertTracker {
+    /// Raise and dispatch the alerts whose thresholds are exceeded at `now`
+    pub fn check(&mut self, config: &AlertConfig, changes: &ObservedChanges, now: Instant) {
+        if !changes.on_battery && DRAIN_PROFILE_ACTIVE.swap(false, Ordering::Relaxed) {
+            info!("Running on AC again, leaving the drain profile");
+        }
+
+        for alert in evaluate(config, changes) {
+            if alert.kind == AlertKind::HighDrain {
+                if let Some(profile) = &config.drain_profile {
+                    if !DRAIN_PROFILE_ACTIVE.swap(true, Ordering::Relaxed) {
+                        info!("Switching to the '{profile}' profile until AC is connected");
+                    }
+                }
+            }
+
+            let cooldown = Duration::from_secs(config.cooldown_sec);
+            if self
+                .last_fired
+                .get(&alert.kind)
+                .is_some_and(|&last| now.saturating_duration_since(last) < cooldown)
+            {
+                debug!("Suppressing {} alert during cooldown", alert.kind);
+                continue;
+            }
+
+            self.last_fired.insert(alert.kind, now);
+            dispatch(config, &alert);
+        }
+    }
+}
+
+/// Determine which alerts the observed changes trigger
+fn evaluate(config: &AlertConfig, changes: &ObservedChanges) -> Vec<Alert> {
+    let mut alerts = Vec::new();
+
+    if let (Some(threshold), Some(drop)) =
+        (config.battery_drop_percent, changes.battery_drop_percent)
+    {
+        if drop > threshold {
+            alerts.push(Alert {
+                kind: AlertKind::BatteryDrain,
+                value: drop,
+                message: format!(
+                    "Battery dropped {drop:.1}% in the last {} minutes",
+                    BATTERY_DROP_WINDOW.as_secs() / 60
+                ),
+            });
+        }
+    }
+
+    if let (Some(threshold), Some(rise)) = (
+        config.temperature_rise_celsius,
+        changes.temperature_rise_celsius,
+    ) {
+        if rise > threshold {
+            alerts.push(Alert {
+                kind: AlertKind::TemperatureRise,
+                value: rise,
+                message: format!("CPU temperature rose {rise:.1}°C in the last minute"),
+            });
+        }
+    }
+
+    let drain = [
+        (config.drain_watts, changes.discharge_watts, "W"),
+        (
+            config.drain_percent_per_hour,
+            changes.discharge_percent_per_hour,
+            "%/h",
+        ),
+    ]
+    .into_iter()
+    .find_map(|(threshold, rate, unit)| match (threshold, rate) {
+        (Some(threshold), Some(rate)) if rate > threshold => Some((rate, unit)),
+        _ => None,
+    });
+    if let Some((rate, unit)) = drain {
+        alerts.push(Alert {
+            kind: AlertKind::HighDrain,
+            value: rate,
+            message: format!("Battery is draining at {rate:.1} {unit}"),
+        });
+    }
+
+    alerts
+}
+
+/// Log the alert, then notify and run the hook if configured
+fn dispatch(config: &AlertConfig, alert: &Alert) {
+    warn!(
+        alert:% = alert.kind, value = alert.value;
+        "Alert ({}): {}", alert.kind, alert.message
+    );
+
+    if config.notify {
+        notify::send("Superfreq alert", &alert.message);
+    }
+
+    if let Some(hook) = &config.hook {
+        run_hook(hook, alert);
+    }
+}
+
+/// Run the alert hook in the background, passing the alert through the environment
+fn run_hook(hook: &str, alert: &Alert) {
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(hook)
+        .env("SUPERFREQ_ALERT", alert.kind.to_string())
+        .env("SUPERFREQ_ALERT_VALUE", format!("{:.1}", alert.value))
+        .env("SUPERFREQ_ALERT_MESSAGE", &alert.message)
+        .stdin(Stdio::null())
+        .spawn();
+
+    match child {
+        Ok(mut child) => {
+            // Reap the hook without blocking the daemon loop
+            std::thread::spawn(move || match child.wait() {
+                Ok(status) if !status.success() => warn!("Alert hook exited with {status}"),
+                Ok(_) => {}
+                Err(e) => warn!("Failed to wait for alert hook: {e}"),
+            });
+        }
+        Err(e) => warn!("Failed to run alert hook '{hook}': {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::clock::{Clock, ManualClock};
+
+    fn temperature_rise(rise: f32) -> ObservedChanges {
+        ObservedChanges {
+            battery_drop_percent: None,
+            temperature_rise_celsius: Some(rise),
+            discharge_watts: None,
+            discharge_percent_per_hour: None,
+            on_battery: false,
+        }
+    }
+
+    #[test]
+    fn alert_is_suppressed_during_cooldown() {
+        let clock = ManualClock::new();
+        let config = AlertConfig {
+            temperature_rise_celsius: Some(10.0),
+            cooldown_sec: 60,
+            ..AlertConfig::default()
+        };
+        let mut tracker = AlertTracker::default();
+        let last_fired =
+            |tracker: &AlertTracker| tracker.last_fired.get(&AlertKind::TemperatureRise).copied();
+
+        tracker.check(&config, &temperature_rise(5.0), clock.now());
+        assert_eq!(last_fired(&tracker), None);
+
+        let first = clock.now();
+        tracker.check(&config, &temperature_rise(15.0), first);
+        assert_eq!(last_fired(&tracker), Some(first));
+
+        clock.advance(Duration::from_secs(59));
+        tracker.check(&config, &temperature_rise(15.0), clock.now());
+        assert_eq!(last_fired(&tracker), Some(first));
+
+        clock.advance(Duration::from_secs(1));
+        tracker.check(&config, &temperature_rise(15.0), clock.now());
+        assert_eq!(last_fired(&tracker), Some(clock.now()));
+    }
+}
@@ -0,0 +1,204 @@
+use crate::config::EppAutoSettings;
+use crate::core::SystemReport;
+use log::{debug, info};
+use std::sync::Mutex;
+
+/// The EPP ladder walked by `epp = "auto"`, from most efficient to fastest
+pub const EPP_LADDER: [&str; 4] = [
+    "power",
+    "balance_power",
+    "balance_performance",
+    "performance",
+];
+
+/// Value of the profile `epp` field that enables dynamic EPP
+pub const AUTO_EPP: &str = "auto";
+
+#[derive(Debug, Clone, Copy)]
+struct AutoEppState {
+    /// Index into [`EPP_LADDER`] of the level currently in effect
+    level: Option<usize>,
+    /// Whether EPP is held at `power` because the CPU is too hot
+    hot: bool,
+}
+
+static STATE: Mutex<AutoEppState> = Mutex::new(AutoEppState {
+    level: None,
+    hot: false,
+});
+
+/// Select the ladder level for `load`. Stepping up happens as soon as a
+/// breakpoint is reached, but a level is only left once the load has fallen
+/// `hysteresis` below the breakpoint that led to it.
+fn select_level(
+    breakpoints: &[f32; 3],
+    hysteresis: f32,
+    load: f32,
+    current: Option<usize>,
+) -> usize {
+    let reached = breakpoints
+        .iter()
+        .take_while(|&&breakpoint| load >= breakpoint)
+        .count();
+
+    match current.filter(|&current| current < EPP_LADDER.len()) {
+        Some(current) if reached < current => (reached..=current)
+            .rev()
+            .find(|&level| level == 0 || load >= breakpoints[level - 1] - hysteresis)
+            .unwrap_or(reached),
+        _ => reached,
+    }
+}
+
+/// Average CPU usage across all cores that report usage data
+fn average_usage(report: &SystemReport) -> Option<f32> {
+    let usages: Vec<f32> = report
+        .cpu_cores
+        .iter()
+        .filter_map(|core| core.usage_percent)
+        .collect();
+
+    if usages.is_empty() {
+        None
+    } else {
+        Some(usages.iter().sum::<f32>() / usages.len() as f32)
+    }
+}
+
+/// Update the dynamic EPP state from the report and return the EPP to apply,
+/// or `None` if there is not enough data to pick one yet
+pub fn evaluate(report: &SystemReport, settings: &EppAutoSettings) -> Option<&'static str> {
+    let mut state = STATE
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+    // Without a temperature reading, stay in whatever thermal state we are in
+    if let (Some(limit), Some(temp)) = (
+        settings.temp_threshold_high,
+        report.cpu_global.average_temperature_celsius,
+    ) {
+        let hot = if state.hot {
+            temp > limit - settings.temp_hysteresis_celsius
+        } else {
+            temp >= limit
+        };
+
+        if hot != state.hot {
+            if hot {
+                info!(
+                    "Auto EPP: {temp:.1}°C >= {limit:.1}°C, holding EPP at '{}'",
+                    EPP_LADDER[0]
+                );
+            } else {
+                info!("Auto EPP: cooled down to {temp:.1}°C, following CPU load again");
+            }
+        }
+        state.hot = hot;
+    } else if settings.temp_threshold_high.is_none() {
+        state.hot = false;
+    }
+
+    let previous = state.level;
+    if let Some(load) = average_usage(report) {
+        let level = select_level(
+            &settings.load_breakpoints,
+            settings.load_hysteresis,
+            load,
+            previous,
+        );
+        if previous != Some(level) {
+            info!(
+                "Auto EPP: CPU load {load:.1}%, moving to '{}'",
+                EPP_LADDER[level]
+            );
+        }
+        state.level = Some(level);
+    } else {
+        debug!("Auto EPP: no CPU load data, keeping the current level");
+    }
+
+    if state.hot {
+        return Some(EPP_LADDER[0]);
+    }
+    state.level.map(|level| EPP_LADDER[level])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BREAKPOINTS: [f32; 3] = [25.0, 50.0, 75.0];
+    const HYSTERESIS: f32 = 10.0;
+
+    fn check(cases: &[(Option<usize>, f32, usize)]) {
+        for &(current, load, expected) in cases {
+            assert_eq!(
+                select_level(&BREAKPOINTS, HYSTERESIS, load, current),
+                expected,
+                "from {current:?} at {load}%"
+            );
+        }
+    }
+
+    #[test]
+    fn each_band_selects_its_level() {
+        check(&[
+            (None, 0.0, 0),
+            (None, 24.9, 0),
+            (None, 25.0, 1),
+            (None, 49.9, 1),
+            (None, 50.0, 2),
+            (None, 74.9, 2),
+            (None, 75.0, 3),
+            (None, 100.0, 3),
+        ]);
+    }
+
+    #[test]
+    fn rising_load_steps_up_at_the_breakpoint() {
+        check(&[
+            (Some(0), 24.9, 0),
+            (Some(0), 25.0, 1),
+            (Some(1), 50.0, 2),
+            (Some(2), 74.9, 2),
+            (Some(2), 75.0, 3),
+            (Some(0), 80.0, 3),
+        ]);
+    }
+
+    #[test]
+    fn falling_load_steps_down_below_the_hysteresis() {
+        check(&[
+            (Some(3), 65.0, 3),
+            (Some(3), 64.9, 2),
+            (Some(2), 40.0, 2),
+            (Some(2), 39.9, 1),
+            (Some(1), 15.0, 1),
+            (Some(1), 14.9, 0),
+            // Several levels at once
+            (Some(3), 30.0, 1),
+            (Some(3), 5.0, 0),
+        ]);
+    }
+
+    #[test]
+    fn load_around_a_breakpoint_does_not_flap() {
+        let mut level = None;
+        let mut levels = Vec::new();
+        for load in [76.0, 73.0, 76.0, 70.0, 74.9, 66.0, 75.0] {
+            level = Some(select_level(&BREAKPOINTS, HYSTERESIS, load, level));
+            levels.push(level.unwrap());
+        }
+        assert_eq!(levels, [3; 7]);
+
+        // Without hysteresis the same loads flap
+        let levels: Vec<usize> = [76.0, 73.0, 76.0]
+            .into_iter()
+            .scan(None, |level, load| {
+                *level = Some(select_level(&BREAKPOINTS, 0.0, load, *level));
+                *level
+            })
+            .collect();
+        assert_eq!(levels, [3, 2, 3]);
+    }
+}
@@ -1,6 +1,14 @@
-use crate::{config::types::BatteryChargeThresholds, util::error::ControlError, util::sysfs};
+//! Battery charge thresholds and charge behaviour, for the vendor interfaces
+//! the kernel exposes under `/sys/class/power_supply`.
+
+use crate::{
+    config::types::BatteryChargeThresholds, core::ChargeBehaviour, util::error::ControlError,
+    util::sysfs, vendor::cros_ec, vendor::msi, vendor::system76,
+};
+use clap::ValueEnum;
 use log::{debug, warn};
 use std::{
+    collections::BTreeMap,
     fs, io,
     path::{Path, PathBuf},
 };
@@ -14,31 +22,69 @@ pub struct ThresholdPathPattern {
     pub description: &'static str,
     pub start_path: &'static str,
     pub stop_path: &'static str,
+    /// Platform driver directory that must exist for the pattern to apply, for
+    /// drivers that reuse the file names of another pattern
+    pub driver_path: Option<&'static str>,
+    /// Set when the driver derives the start threshold from the stop threshold,
+    /// as this many percent below it, so that only the stop threshold is written
+    pub fixed_start_offset: Option<u8>,
 }
 
 // Threshold patterns
-const THRESHOLD_PATTERNS: &[ThresholdPathPattern] = &[
+pub const THRESHOLD_PATTERNS: &[ThresholdPathPattern] = &[
+    // msi-ec uses the standard names, but writing either threshold moves the
+    // other one to keep them 10% apart
+    ThresholdPathPattern {
+        description: "MSI",
+        start_path: "charge_control_start_threshold",
+        stop_path: "charge_control_end_threshold",
+        driver_path: Some(msi::MSI_EC_DIR),
+        fixed_start_offset: Some(10),
+    },
+    // Named for the logs, as these behave like the standard interface
+    ThresholdPathPattern {
+        description: "ChromeOS EC",
+        start_path: "charge_control_start_threshold",
+        stop_path: "charge_control_end_threshold",
+        driver_path: Some(cros_ec::CHARGE_CONTROL_DRIVER_DIR),
+        fixed_start_offset: None,
+    },
+    ThresholdPathPattern {
+        description: "System76",
+        start_path: "charge_control_start_threshold",
+        stop_path: "charge_control_end_threshold",
+        driver_path: Some(system76::SYSTEM76_ACPI_DIR),
+        fixed_start_offset: None,
+    },
     ThresholdPathPattern {
         description: "Standard",
         start_path: "charge_control_start_threshold",
         stop_path: "charge_control_end_threshold",
+        driver_path: None,
+        fixed_start_offset: None,
     },
     ThresholdPathPattern {
         description: "ASUS",
         start_path: "charge_control_start_percentage",
         stop_path: "charge_control_end_percentage",
+        driver_path: None,
+        fixed_start_offset: None,
     },
     // Combine Huawei and ThinkPad since they use identical paths
     ThresholdPathPattern {
         description: "ThinkPad/Huawei",
         start_path: "charge_start_threshold",
         stop_path: "charge_stop_threshold",
+        driver_path: None,
+        fixed_start_offset: None,
     },
     // Framework laptop support
     ThresholdPathPattern {
         description: "Framework",
         start_path: "charge_behaviour_start_threshold",
         stop_path: "charge_behaviour_end_threshold",
+        driver_path: None,
+        fixed_start_offset: None,
     },
 ];
 
@@ -80,7 +126,7 @@ pub fn set_battery_charge_thresholds(start_threshold: u8, stop_threshold: u8) ->
             _ => ControlError::InvalidValueError(format!("Invalid battery threshold values: {e}")),
         })?;
 
-    let power_supply_path = Path::new("/sys/class/power_supply");
+    let power_supply_path = sysfs::path("/sys/class/power_supply");
     if !power_supply_path.exists() {
         return Err(ControlError::NotSupported(
             "Power supply path not found, battery threshold control not supported".to_string(),
@@ -90,14 +136,162 @@ pub fn set_battery_charge_thresholds(start_threshold: u8, stop_threshold: u8) ->
     // XXX: Skip checking directory writability since /sys is a virtual filesystem
     // Individual file writability will be checked by find_battery_with_threshold_support
 
-    let supported_batteries = find_supported_batteries(power_supply_path)?;
+    let supported_batteries = find_supported_batteries(&power_supply_path)?;
+    if supported_batteries.is_empty() {
+        return Err(ControlError::NotSupported(
+            "No batteries with charge threshold control support found".to_string(),
+        ));
+    }
+
+    let targets: Vec<_> = supported_batteries
+        .iter()
+        .map(|battery| (battery, thresholds))
+        .collect();
+    apply_thresholds_to_batteries(&targets)
+}
+
+/// Set charge thresholds for a single battery by name
+///
+/// # Errors
+///
+/// Returns an error if the thresholds are invalid, the battery does not exist or
+/// does not support threshold control, or writing the thresholds fails.
+pub fn set_battery_charge_thresholds_for(
+    battery_name: &str,
+    start_threshold: u8,
+    stop_threshold: u8,
+) -> Result<()> {
+    let thresholds = BatteryChargeThresholds::new(start_threshold, stop_threshold)
+        .map_err(|e| ControlError::InvalidValueError(e.to_string()))?;
+
+    let supported_batteries = get_supported_batteries()?;
+    let battery = supported_batteries
+        .iter()
+        .find(|battery| battery.name == battery_name)
+        .ok_or_else(|| {
+            ControlError::NotSupported(format!(
+                "Battery '{battery_name}' not found or does not support charge thresholds"
+            ))
+        })?;
+
+    apply_thresholds_to_batteries(&[(battery, thresholds)])
+}
+
+/// Set charge thresholds individually per battery
+///
+/// Batteries with an entry in `per_battery` use those thresholds, all other
+/// batteries use `default` if it is set and are left untouched otherwise.
+///
+/// # Errors
+///
+/// Returns an error if no battery could be configured.
+pub fn set_battery_charge_thresholds_per_battery(
+    default: Option<BatteryChargeThresholds>,
+    per_battery: &BTreeMap<String, BatteryChargeThresholds>,
+) -> Result<()> {
+    let supported_batteries = get_supported_batteries()?;
     if supported_batteries.is_empty() {
         return Err(ControlError::NotSupported(
             "No batteries with charge threshold control support found".to_string(),
         ));
     }
 
-    apply_thresholds_to_batteries(&supported_batteries, thresholds.start, thresholds.stop)
+    for name in per_battery.keys() {
+        if !supported_batteries
+            .iter()
+            .any(|battery| &battery.name == name)
+        {
+            warn!(
+                "Battery '{name}' has configured charge thresholds but was not found or does not support them"
+            );
+        }
+    }
+
+    let targets: Vec<_> = supported_batteries
+        .iter()
+        .filter_map(|battery| {
+            per_battery
+                .get(&battery.name)
+                .copied()
+                .or(default)
+                .map(|thresholds| (battery, thresholds))
+        })
+        .collect();
+
+    if targets.is_empty() {
+        return Ok(());
+    }
+
+    for (_, thresholds) in &targets {
+        thresholds
+            .validate()
+            .map_err(|e| ControlError::InvalidValueError(e.to_string()))?;
+    }
+
+    apply_thresholds_to_batteries(&targets)
+}
+
+/// Read the charge thresholds currently set on a battery
+///
+/// # Errors
+///
+/// Returns an error if the battery does not exist or does not support charge
+/// thresholds, or the current values cannot be read.
+pub fn get_battery_charge_thresholds(battery_name: &str) -> Result<BatteryChargeThresholds> {
+    let supported_batteries = get_supported_batteries()?;
+    let battery = supported_batteries
+        .iter()
+        .find(|battery| battery.name == battery_name)
+        .ok_or_else(|| {
+            ControlError::NotSupported(format!(
+                "Battery '{battery_name}' not found or does not support charge thresholds"
+            ))
+        })?;
+
+    let read_threshold = |file: &str| -> Result<u8> {
+        let value = sysfs::read_sysfs_value(battery.path.join(file))?;
+        value.parse().map_err(|_| {
+            ControlError::ParseError(format!(
+                "Invalid charge threshold '{value}' for battery '{battery_name}'"
+            ))
+        })
+    };
+
+    Ok(BatteryChargeThresholds {
+        start: read_threshold(battery.pattern.start_path)?,
+        stop: read_threshold(battery.pattern.stop_path)?,
+    })
+}
+
+/// Read the active `charge_behaviour` of a battery, if it supports one
+pub fn get_charge_behaviour(battery_name: &str) -> Option<ChargeBehaviour> {
+    let path = sysfs::path("/sys/class/power_supply")
+        .join(battery_name)
+        .join("charge_behaviour");
+    let content = sysfs::read_sysfs_value(path).ok()?;
+    let (active, _) = parse_charge_behaviour(&content);
+    ChargeBehaviour::from_str(&active?, false).ok()
+}
+
+/// Check whether a battery supports the given `charge_behaviour` mode
+pub fn supports_charge_behaviour(battery_name: &str, behaviour: ChargeBehaviour) -> bool {
+    let path = sysfs::path("/sys/class/power_supply")
+        .join(battery_name)
+        .join("charge_behaviour");
+    sysfs::read_sysfs_value(path).is_ok_and(|content| {
+        let (_, available) = parse_charge_behaviour(&content);
+        available.contains(&behaviour.to_string())
+    })
+}
+
+/// Returns all batteries in the system that support charge threshold control
+pub fn get_supported_batteries() -> Result<Vec<SupportedBattery<'static>>> {
+    let power_supply_path = sysfs::path("/sys/class/power_supply");
+    if !power_supply_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    find_supported_batteries(&power_supply_path)
 }
 
 /// Finds all batteries in the system that support threshold control
@@ -148,19 +342,44 @@ fn find_supported_batteries(power_supply_path: &Path) -> Result<Vec<SupportedBat
     Ok(supported_batteries)
 }
 
-/// Applies the threshold settings to all supported batteries
+/// Applies the threshold settings to each of the given batteries
 fn apply_thresholds_to_batteries(
-    batteries: &[SupportedBattery<'_>],
-    start_threshold: u8,
-    stop_threshold: u8,
+    batteries: &[(&SupportedBattery<'_>, BatteryChargeThresholds)],
 ) -> Result<()> {
     let mut errors = Vec::new();
     let mut success_count = 0;
 
-    for battery in batteries {
+    for (battery, thresholds) in batteries {
+        let start_threshold = thresholds.start;
+        let stop_threshold = thresholds.stop;
         let start_path = battery.path.join(battery.pattern.start_path);
         let stop_path = battery.path.join(battery.pattern.stop_path);
 
+        // The driver sets the start threshold along with the stop threshold
+        if let Some(offset) = battery.pattern.fixed_start_offset {
+            let derived_start = stop_threshold.saturating_sub(offset);
+            if start_threshold != derived_start {
+                warn!(
+                    "{} battery '{}' always starts charging {offset}% below the stop threshold, at {derived_start}% instead of {start_threshold}%",
+                    battery.pattern.description, battery.name
+                );
+            }
+            match sysfs::write_sysfs_value(&stop_path, &stop_threshold.to_string()) {
+                Ok(()) => {
+                    debug!(
+                        "Set {}-{}% charge thresholds for {} battery '{}'",
+                        derived_start, stop_threshold, battery.pattern.description, battery.name
+                    );
+                    success_count += 1;
+                }
+                Err(e) => errors.push(format!(
+                    "Failed to set stop threshold for {} battery '{}': {}",
+                    battery.pattern.description, battery.name, e
+                )),
+            }
+            continue;
+        }
+
         // Read current thresholds in case we need to restore them
         let current_stop = sysfs::read_sysfs_value(&stop_path).ok();
 
@@ -244,6 +463,13 @@ fn is_battery(path: &Path) -> Result<bool> {
 /// Identifies if a battery supports threshold control and which pattern it uses
 fn find_battery_with_threshold_support(ps_path: &Path) -> Option<SupportedBattery<'static>> {
     for pattern in THRESHOLD_PATTERNS {
+        if pattern
+            .driver_path
+            .is_some_and(|driver_path| !sysfs::path(driver_path).exists())
+        {
+            continue;
+        }
+
         let start_threshold_path = ps_path.join(pattern.start_path);
         let stop_threshold_path = ps_path.join(pattern.stop_path);
 
@@ -260,3 +486,143 @@ fn find_battery_with_threshold_support(ps_path: &Path) -> Option<SupportedBatter
     }
     None
 }
+
+/// Parse a `charge_behaviour` attribute such as `[auto] inhibit-charge force-discharge`
+/// into the active mode and the list of modes the battery supports
+pub fn parse_charge_behaviour(content: &str) -> (Option<String>, Vec<String>) {
+    let mut active = None;
+    let mut available = Vec::new();
+
+    for token in content.split_whitespace() {
+        let mode = token.trim_start_matches('[').trim_end_matches(']');
+        if token.starts_with('[') {
+            active = Some(mode.to_string());
+        }
+        available.push(mode.to_string());
+    }
+
+    (active, available)
+}
+
+/// Set the `charge_behaviour` of a single battery by name
+///
+/// # Errors
+///
+/// Returns an error if the battery does not exist, does not support
+/// `charge_behaviour`, does not support the requested mode, or the write fails.
+pub fn set_charge_behaviour(battery_name: &str, behaviour: ChargeBehaviour) -> Result<()> {
+    let ps_path = sysfs::path("/sys/class/power_supply").join(battery_name);
+    if !ps_path.exists() {
+        return Err(ControlError::NotSupported(format!(
+            "Battery '{battery_name}' not found"
+        )));
+    }
+
+    write_charge_behaviour(battery_name, &ps_path, behaviour)
+}
+
+/// Set the `charge_behaviour` of every battery that supports it, except the
+/// power supplies named in `ignored_supplies`
+///
+/// # Errors
+///
+/// Returns `ControlError::NotSupported` if no battery supports `charge_behaviour`,
+/// or the first write error otherwise.
+pub fn set_charge_behaviour_all(
+    behaviour: ChargeBehaviour,
+    ignored_supplies: &[String],
+) -> Result<()> {
+    let power_supply_path = sysfs::path("/sys/class/power_supply");
+    let mut applied = false;
+
+    if let Ok(entries) = fs::read_dir(&power_supply_path) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if ignored_supplies.contains(&name) {
+                debug!("Not setting charge behaviour of ignored power supply '{name}'");
+                continue;
+            }
+
+            let ps_path = entry.path();
+            if !is_battery(&ps_path)? || !ps_path.join("charge_behaviour").exists() {
+                continue;
+            }
+
+            write_charge_behaviour(&name, &ps_path, behaviour)?;
+            applied = true;
+        }
+    }
+
+    if applied {
+        Ok(())
+    } else {
+        Err(ControlError::NotSupported(
+            "No batteries with charge_behaviour support found".to_string(),
+        ))
+    }
+}
+
+fn write_charge_behaviour(
+    battery_name: &str,
+    ps_path: &Path,
+    behaviour: ChargeBehaviour,
+) -> Result<()> {
+    let path = ps_path.join("charge_behaviour");
+    if !path.exists() {
+        return Err(ControlError::NotSupported(format!(
+            "Battery '{battery_name}' does not support charge_behaviour"
+        )));
+    }
+
+    let (_, available) = parse_charge_behaviour(&sysfs::read_sysfs_value(&path)?);
+    let value = behaviour.to_string();
+    if !available.contains(&value) {
+        return Err(ControlError::InvalidValueError(format!(
+            "Battery '{battery_name}' does not support charge behaviour '{value}'. Available: {}",
+            available.join(", ")
+        )));
+    }
+
+    sysfs::write_sysfs_value(&path, &value)?;
+    debug!("Set charge behaviour of battery '{battery_name}' to '{value}'");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::fixture::Fixture;
+
+    fn add_battery(fixture: &Fixture, name: &str) {
+        let path = format!("/sys/class/power_supply/{name}");
+        fixture.write(&format!("{path}/type"), "Battery\n");
+        fixture.write(
+            &format!("{path}/charge_behaviour"),
+            "[auto] inhibit-charge force-discharge\n",
+        );
+    }
+
+    #[test]
+    fn charge_behaviour_skips_ignored_supplies() {
+        let fixture = Fixture::new();
+        add_battery(&fixture, "BAT0");
+        add_battery(&fixture, "BAT1");
+
+        set_charge_behaviour_all(ChargeBehaviour::InhibitCharge, &["BAT1".to_string()]).unwrap();
+        assert_eq!(
+            fixture.read("/sys/class/power_supply/BAT0/charge_behaviour"),
+            "inhibit-charge"
+        );
+        assert_eq!(
+            fixture.read("/sys/class/power_supply/BAT1/charge_behaviour"),
+            "[auto] inhibit-charge force-discharge"
+        );
+
+        // Ignoring every battery leaves none to apply the behaviour to
+        let all = ["BAT0".to_string(), "BAT1".to_string()];
+        assert!(matches!(
+            set_charge_behaviour_all(ChargeBehaviour::Auto, &all),
+            Err(ControlError::NotSupported(_))
+        ));
+    }
+}
@@ -0,0 +1,239 @@
+use crate::battery;
+use crate::config::types::BatteryChargeThresholds;
+use crate::core::{ChargeBehaviour, SystemReport};
+use crate::util::error::ControlError;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+pub type Result<T, E = ControlError> = std::result::Result<T, E>;
+
+/// Where an in-progress calibration is recorded, so it survives daemon restarts
+pub const CALIBRATION_STATE_PATH: &str = "/var/lib/superfreq/calibration.toml";
+
+/// Thresholds used while charging, so the battery charges whenever it is not full
+const CALIBRATION_THRESHOLDS: BatteryChargeThresholds = BatteryChargeThresholds {
+    start: 99,
+    stop: 100,
+};
+
+/// Capacity at which the controlled discharge ends
+const DISCHARGE_TARGET_PERCENT: u8 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CalibrationPhase {
+    /// Charging to 100% with thresholds removed
+    Charging,
+    /// Discharging down to the target capacity
+    Discharging,
+}
+
+impl fmt::Display for CalibrationPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Charging => write!(f, "charging to 100%"),
+            Self::Discharging => write!(f, "discharging to {DISCHARGE_TARGET_PERCENT}%"),
+        }
+    }
+}
+
+/// Persistent state of a battery calibration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CalibrationState {
+    pub battery: String,
+    pub phase: CalibrationPhase,
+    pub started_at: String,
+    pub phase_started_at: String,
+    /// Thresholds in effect before the calibration started
+    pub saved_thresholds: Option<BatteryChargeThresholds>,
+    /// `charge_behaviour` in effect before the calibration started
+    pub saved_charge_behaviour: Option<ChargeBehaviour>,
+    /// Whether the discharge is driven by `charge_behaviour = force-discharge`
+    #[serde(default)]
+    pub forced_discharge: bool,
+}
+
+impl CalibrationState {
+    fn save(&self) -> Result<()> {
+        let content = toml::to_string(self).map_err(|e| {
+            ControlError::WriteError(format!("Failed to serialize calibration state: {e}"))
+        })?;
+
+        if let Some(parent) = Path::new(CALIBRATION_STATE_PATH).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(CALIBRATION_STATE_PATH, content)?;
+        Ok(())
+    }
+
+    fn enter_phase(&mut self, phase: CalibrationPhase) {
+        self.phase = phase;
+        self.phase_started_at = jiff::Timestamp::now().to_string();
+    }
+}
+
+/// Load the current calibration state, if a calibration is in progress
+pub fn load_state() -> Option<CalibrationState> {
+    let content = fs::read_to_string(CALIBRATION_STATE_PATH).ok()?;
+    match toml::from_str(&content) {
+        Ok(state) => Some(state),
+        Err(e) => {
+            warn!("Ignoring invalid calibration state in {CALIBRATION_STATE_PATH}: {e}");
+            None
+        }
+    }
+}
+
+/// Whether a calibration is in progress. Threshold and charge behaviour settings
+/// from the configuration are not applied while this is the case.
+pub fn is_active() -> bool {
+    Path::new(CALIBRATION_STATE_PATH).exists()
+}
+
+/// Start calibrating `battery_name`: remove its thresholds and charge it to 100%
+///
+/// # Errors
+///
+/// Returns an error if a calibration is already in progress, the battery does
+/// not support charge thresholds, or the state cannot be written.
+pub fn start(battery_name: &str) -> Result<CalibrationState> {
+    if let Some(state) = load_state() {
+        return Err(ControlError::InvalidValueError(format!(
+            "A calibration of battery '{}' is already in progress",
+            state.battery
+        )));
+    }
+
+    let saved_thresholds = battery::get_battery_charge_thresholds(battery_name)?;
+    let saved_charge_behaviour = battery::get_charge_behaviour(battery_name);
+
+    battery::set_battery_charge_thresholds_for(
+        battery_name,
+        CALIBRATION_THRESHOLDS.start,
+        CALIBRATION_THRESHOLDS.stop,
+    )?;
+    if saved_charge_behaviour.is_some_and(|b| b != ChargeBehaviour::Auto) {
+        battery::set_charge_behaviour(battery_name, ChargeBehaviour::Auto)?;
+    }
+
+    let now = jiff::Timestamp::now().to_string();
+    let state = CalibrationState {
+        battery: battery_name.to_string(),
+        phase: CalibrationPhase::Charging,
+        started_at: now.clone(),
+        phase_started_at: now,
+        saved_thresholds: Some(saved_thresholds),
+        saved_charge_behaviour,
+        forced_discharge: false,
+    };
+    state.save()?;
+
+    info!("Started calibration of battery '{battery_name}'");
+    Ok(state)
+}
+
+/// Abort the calibration in progress and restore the user's settings
+///
+/// # Errors
+///
+/// Returns an error if no calibration is in progress or restoring fails.
+pub fn cancel() -> Result<CalibrationState> {
+    let state = load_state().ok_or_else(|| {
+        ControlError::InvalidValueError("No calibration is in progress".to_string())
+    })?;
+
+    finish(&state)?;
+    info!("Cancelled calibration of battery '{}'", state.battery);
+    Ok(state)
+}
+
+/// Restore the settings saved when the calibration started and clear the state
+fn finish(state: &CalibrationState) -> Result<()> {
+    if state.forced_discharge || state.saved_charge_behaviour.is_some() {
+        let behaviour = state
+            .saved_charge_behaviour
+            .unwrap_or(ChargeBehaviour::Auto);
+        battery::set_charge_behaviour(&state.battery, behaviour)?;
+    }
+
+    if let Some(thresholds) = state.saved_thresholds {
+        battery::set_battery_charge_thresholds_for(
+            &state.battery,
+            thresholds.start,
+            thresholds.stop,
+        )?;
+    }
+
+    fs::remove_file(CALIBRATION_STATE_PATH)?;
+    Ok(())
+}
+
+/// Advance the calibration in progress based on the latest report.
+/// Called by the daemon on every cycle.
+pub fn step(report: &SystemReport) {
+    let Some(mut state) = load_state() else {
+        return;
+    };
+
+    let Some(battery_info) = report.batteries.iter().find(|b| b.name == state.battery) else {
+        warn!(
+            "Calibration: battery '{}' is not present, waiting for it to reappear",
+            state.battery
+        );
+        return;
+    };
+    let Some(capacity) = battery_info.capacity_percent else {
+        return;
+    };
+    let is_full = capacity >= 100 || battery_info.charging_state.as_deref() == Some("Full");
+
+    match state.phase {
+        CalibrationPhase::Charging if is_full => {
+            state.enter_phase(CalibrationPhase::Discharging);
+
+            if battery::supports_charge_behaviour(&state.battery, ChargeBehaviour::ForceDischarge) {
+                match battery::set_charge_behaviour(&state.battery, ChargeBehaviour::ForceDischarge)
+                {
+                    Ok(()) => state.forced_discharge = true,
+                    Err(e) => warn!("Calibration: failed to force discharge: {e}"),
+                }
+            }
+
+            if state.forced_discharge {
+                info!(
+                    "Calibration: battery '{}' is full, discharging to {DISCHARGE_TARGET_PERCENT}%",
+                    state.battery
+                );
+            } else {
+                info!(
+                    "Calibration: battery '{}' is full. Disconnect AC power to discharge it to {DISCHARGE_TARGET_PERCENT}%",
+                    state.battery
+                );
+            }
+        }
+        CalibrationPhase::Discharging if capacity <= DISCHARGE_TARGET_PERCENT => {
+            match finish(&state) {
+                Ok(()) => info!(
+                    "Calibration of battery '{}' complete, restored previous settings",
+                    state.battery
+                ),
+                Err(e) => warn!("Calibration: failed to restore previous settings: {e}"),
+            }
+            return;
+        }
+        phase => {
+            debug!(
+                "Calibration: battery '{}' at {capacity}%, {phase}",
+                state.battery
+            );
+            return;
+        }
+    }
+
+    if let Err(e) = state.save() {
+        warn!("Calibration: failed to save state: {e}");
+    }
+}
@@ -0,0 +1,38 @@
+use crate::util::error::{AppError, ControlError};
+use crate::vendor::asus;
+
+fn show<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| "N/A".to_string(), |value| value.to_string())
+}
+
+/// Print the current values of the asus-wmi attributes
+pub fn run_asus_status(json: bool) -> Result<(), AppError> {
+    if !asus::is_available() {
+        return Err(AppError::Control(ControlError::NotSupported(
+            "The asus-wmi driver is not loaded.".to_string(),
+        )));
+    }
+
+    let status = asus::get_status();
+    if json {
+        let output = serde_json::to_string_pretty(&status)
+            .map_err(|e| AppError::Generic(format!("Failed to serialize ASUS status: {e}")))?;
+        println!("{output}");
+        return Ok(());
+    }
+
+    println!("Fan Boost Mode:          {}", show(status.fan_boost_mode));
+    println!(
+        "Throttle Thermal Policy: {}",
+        show(status.throttle_thermal_policy)
+    );
+    println!(
+        "Panel Overdrive:         {}",
+        show(
+            status
+                .panel_od
+                .map(|enabled| if enabled { "on" } else { "off" })
+        )
+    );
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use crate::battery;
+use crate::calibration;
+use crate::util::error::{AppError, ControlError};
+
+/// Print the state of the calibration in progress
+fn print_calibration_status() {
+    match calibration::load_state() {
+        Some(state) => {
+            println!("Battery:       {}", state.battery);
+            println!("Phase:         {}", state.phase);
+            println!("Started:       {}", state.started_at);
+            println!("Phase Started: {}", state.phase_started_at);
+            if let Some(thresholds) = state.saved_thresholds {
+                println!(
+                    "Restores:      {}-{}% charge thresholds",
+                    thresholds.start, thresholds.stop
+                );
+            }
+        }
+        None => println!("No calibration is in progress."),
+    }
+}
+
+/// Start, cancel or show the status of a battery calibration
+pub fn run_battery_calibrate(
+    battery_name: Option<&str>,
+    status: bool,
+    cancel: bool,
+) -> Result<(), AppError> {
+    if status {
+        print_calibration_status();
+        return Ok(());
+    }
+
+    if cancel {
+        let state = calibration::cancel()?;
+        println!(
+            "Cancelled calibration of battery '{}' and restored its previous settings.",
+            state.battery
+        );
+        return Ok(());
+    }
+
+    let battery_name = match battery_name {
+        Some(name) => name.to_string(),
+        None => battery::get_supported_batteries()?
+            .into_iter()
+            .next()
+            .map(|battery| battery.name)
+            .ok_or_else(|| {
+                ControlError::NotSupported(
+                    "No batteries with charge threshold control support found".to_string(),
+                )
+            })?,
+    };
+
+    let state = calibration::start(&battery_name)?;
+    println!("Started calibration of battery '{}'.", state.battery);
+    println!("Keep AC power connected until the battery is full. The daemon will then");
+    println!("discharge it and restore your charge thresholds once it is done.");
+    println!("Use `superfreq battery calibrate --status` to follow the progress.");
+    Ok(())
+}
@@ -0,0 +1,417 @@
+use crate::config::{AppConfig, ProfileConfig, ProfileConfigToml};
+use crate::core::SystemReport;
+use crate::engine;
+use crate::monitor;
+use crate::snapshot;
+use crate::util::duration;
+use crate::util::error::AppError;
+use log::{info, warn};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often an interruption is checked for while waiting for the next sample
+const INTERRUPT_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Set once Ctrl-C is pressed, which ends the benchmark early
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// A profile to benchmark, read from a file
+#[derive(Debug)]
+struct BenchProfile {
+    name: String,
+    file: PathBuf,
+    config: ProfileConfig,
+}
+
+/// Averages over the samples taken while a profile was applied
+#[derive(Debug, Serialize)]
+struct BenchResult {
+    profile: String,
+    file: PathBuf,
+    samples: usize,
+    duration_secs: f32,
+    system_watts: Option<f32>,
+    cpu_package_watts: Option<f32>,
+    battery_discharge_watts: Option<f32>,
+    energy_wh: Option<f32>,
+    frequency_mhz: Option<f32>,
+    cpu_usage_percent: Option<f32>,
+    max_temperature_celsius: Option<f32>,
+    throttle_events: Option<u64>,
+}
+
+/// Readings of every sample, averaged into a `BenchResult`
+#[derive(Debug, Default)]
+struct Samples {
+    count: usize,
+    system_watts: Vec<f32>,
+    cpu_package_watts: Vec<f32>,
+    battery_discharge_watts: Vec<f32>,
+    frequency_mhz: Vec<f32>,
+    cpu_usage_percent: Vec<f32>,
+    temperature_celsius: Vec<f32>,
+    throttle_events: Option<u64>,
+}
+
+fn mean(values: &[f32]) -> Option<f32> {
+    (!values.is_empty()).then(|| values.iter().sum::<f32>() / values.len() as f32)
+}
+
+impl Samples {
+    fn add(&mut self, report: &SystemReport) {
+        self.count += 1;
+        let power = &report.power;
+        self.system_watts.extend(power.estimated_system_watts);
+        self.cpu_package_watts.extend(power.cpu_package_watts);
+        self.battery_discharge_watts
+            .extend(power.battery_discharge_watts);
+
+        let frequencies: Vec<f32> = report
+            .cpu_cores
+            .iter()
+            .filter_map(|core| core.current_frequency_mhz)
+            .map(|mhz| mhz as f32)
+            .collect();
+        self.frequency_mhz.extend(mean(&frequencies));
+        let usages: Vec<f32> = report
+            .cpu_cores
+            .iter()
+            .filter_map(|core| core.usage_percent)
+            .collect();
+        self.cpu_usage_percent.extend(mean(&usages));
+        self.temperature_celsius
+            .extend(report.cpu_global.average_temperature_celsius);
+
+        let deltas = report.throttle.deltas;
+        let events = [
+            deltas.core_thermal,
+            deltas.package_thermal,
+            deltas.core_power_limit,
+            deltas.package_power_limit,
+        ]
+        .into_iter()
+        .flatten()
+        .reduce(|sum, events| sum + events);
+        if let Some(events) = events {
+            *self.throttle_events.get_or_insert(0) += events;
+        }
+    }
+
+    fn result(&self, profile: &BenchProfile, elapsed: Duration) -> BenchResult {
+        let system_watts = mean(&self.system_watts);
+        BenchResult {
+            profile: profile.name.clone(),
+            file: profile.file.clone(),
+            samples: self.count,
+            duration_secs: elapsed.as_secs_f32(),
+            system_watts,
+            cpu_package_watts: mean(&self.cpu_package_watts),
+            battery_discharge_watts: mean(&self.battery_discharge_watts),
+            energy_wh: system_watts.map(|watts| watts * elapsed.as_secs_f32() / 3600.0),
+            frequency_mhz: mean(&self.frequency_mhz),
+            cpu_usage_percent: mean(&self.cpu_usage_percent),
+            max_temperature_celsius: self.temperature_celsius.iter().copied().reduce(f32::max),
+            throttle_events: self.throttle_events,
+        }
+    }
+}
+
+/// Read a profile from a bare profile section, or from a bundle written by
+/// `superfreq profile export`, which names the profile
+fn load_profile(file: &Path) -> Result<BenchProfile, AppError> {
+    let content = fs::read_to_string(file)
+        .map_err(|e| AppError::Generic(format!("Cannot read {}: {e}", file.display())))?;
+    let table: toml::Table = toml::from_str(&content)
+        .map_err(|e| AppError::Generic(format!("Invalid profile {}: {e}", file.display())))?;
+
+    let bundled = table
+        .get("profiles")
+        .and_then(toml::Value::as_table)
+        .filter(|profiles| profiles.len() == 1)
+        .and_then(|profiles| profiles.iter().next())
+        .and_then(|(name, value)| Some((name.clone(), value.as_table()?.clone())));
+    let (name, table) = bundled.unwrap_or_else(|| {
+        let stem = file.file_stem().map_or_else(
+            || file.display().to_string(),
+            |stem| stem.to_string_lossy().into_owned(),
+        );
+        (stem, table)
+    });
+
+    let profile: ProfileConfigToml = table
+        .try_into()
+        .map_err(|e| AppError::Generic(format!("Invalid profile {}: {e}", file.display())))?;
+    profile.validate(&name)?;
+
+    Ok(BenchProfile {
+        name,
+        file: file.to_path_buf(),
+        config: ProfileConfig::from(profile),
+    })
+}
+
+/// Stop the benchmark on Ctrl-C instead of exiting with the profile still applied
+fn watch_interrupt() {
+    let spawned = thread::Builder::new()
+        .name("bench-signal".to_string())
+        .spawn(|| {
+            let runtime = match tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    warn!("Failed to watch for Ctrl-C: {e}");
+                    return;
+                }
+            };
+            if runtime.block_on(tokio::signal::ctrl_c()).is_ok() {
+                INTERRUPTED.store(true, Ordering::Relaxed);
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Failed to watch for Ctrl-C: {e}");
+    }
+}
+
+fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// Sleep for `duration`, returning early if the benchmark is interrupted
+fn sleep_unless_interrupted(duration: Duration) {
+    let deadline = Instant::now() + duration;
+    while !is_interrupted() {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        thread::sleep(remaining.min(INTERRUPT_CHECK_INTERVAL));
+    }
+}
+
+/// Apply `profile` and sample the system until `duration` has passed. The
+/// profile is applied again after every sample, as the daemon does, so
+/// settings that follow the load such as automatic turbo keep working.
+fn bench_profile(
+    config: &AppConfig,
+    profile: &BenchProfile,
+    duration: Duration,
+    interval: Duration,
+) -> Result<BenchResult, AppError> {
+    let mut config = config.clone();
+    match profile.name.as_str() {
+        "charger" => config.charger = profile.config.clone(),
+        "battery" => config.battery = profile.config.clone(),
+        name => {
+            config
+                .profiles
+                .insert(name.to_string(), profile.config.clone());
+        }
+    }
+    config.daemon.profile = Some(profile.name.clone());
+
+    let report = monitor::collect_system_report(&config)?;
+    engine::determine_and_apply_settings(&report, &config, None)?;
+    // Measure from here, so the readings only cover the profile
+    monitor::collect_system_report(&config)?;
+
+    let start = Instant::now();
+    let mut samples = Samples::default();
+    while start.elapsed() < duration && !is_interrupted() {
+        sleep_unless_interrupted(interval.min(duration.saturating_sub(start.elapsed())));
+        let report = monitor::collect_system_report(&config)?;
+        samples.add(&report);
+        engine::determine_and_apply_settings(&report, &config, None)?;
+    }
+
+    Ok(samples.result(profile, start.elapsed()))
+}
+
+fn watts(value: Option<f32>) -> String {
+    value.map_or_else(|| "N/A".to_string(), |watts| format!("{watts:.2} W"))
+}
+
+/// Change of `value` relative to the same metric of the first profile
+fn relative(value: Option<f32>, baseline: Option<f32>) -> String {
+    match (value, baseline) {
+        (Some(value), Some(baseline)) if baseline != 0.0 => {
+            format!(" ({:+.1}%)", (value - baseline) / baseline * 100.0)
+        }
+        _ => String::new(),
+    }
+}
+
+/// Print one column per profile, with differences to the first profile
+fn print_table(results: &[BenchResult]) {
+    type Metric = (
+        &'static str,
+        fn(&BenchResult) -> Option<f32>,
+        fn(Option<f32>) -> String,
+    );
+    let metrics: &[Metric] = &[
+        ("System power", |r| r.system_watts, watts),
+        ("CPU package power", |r| r.cpu_package_watts, watts),
+        ("Battery discharge", |r| r.battery_discharge_watts, watts),
+        (
+            "Energy",
+            |r| r.energy_wh,
+            |value| value.map_or_else(|| "N/A".to_string(), |wh| format!("{wh:.3} Wh")),
+        ),
+        (
+            "Average frequency",
+            |r| r.frequency_mhz,
+            |value| value.map_or_else(|| "N/A".to_string(), |mhz| format!("{mhz:.0} MHz")),
+        ),
+        (
+            "CPU usage",
+            |r| r.cpu_usage_percent,
+            |value| value.map_or_else(|| "N/A".to_string(), |usage| format!("{usage:.1}%")),
+        ),
+        (
+            "Max temperature",
+            |r| r.max_temperature_celsius,
+            |value| value.map_or_else(|| "N/A".to_string(), |t| format!("{t:.1}°C")),
+        ),
+        (
+            "Throttle events",
+            |r| r.throttle_events.map(|events| events as f32),
+            |value| value.map_or_else(|| "N/A".to_string(), |events| format!("{events:.0}")),
+        ),
+    ];
+
+    let rows: Vec<(&str, Vec<String>)> = metrics
+        .iter()
+        .map(|(name, metric, format)| {
+            let baseline = metric(&results[0]);
+            let cells = results
+                .iter()
+                .enumerate()
+                .map(|(index, result)| {
+                    let value = metric(result);
+                    let change = if index == 0 {
+                        String::new()
+                    } else {
+                        relative(value, baseline)
+                    };
+                    format!("{}{change}", format(value))
+                })
+                .collect();
+            (*name, cells)
+        })
+        .collect();
+
+    let label_width = metrics
+        .iter()
+        .map(|(name, ..)| name.len())
+        .max()
+        .unwrap_or(0);
+    let widths: Vec<usize> = results
+        .iter()
+        .enumerate()
+        .map(|(index, result)| {
+            rows.iter()
+                .map(|(_, cells)| cells[index].chars().count())
+                .chain([result.profile.chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    print!("{:label_width$}", "");
+    for (result, width) in results.iter().zip(&widths) {
+        print!("  {:>width$}", result.profile);
+    }
+    println!();
+    for (name, cells) in &rows {
+        print!("{name:label_width$}");
+        for (cell, width) in cells.iter().zip(&widths) {
+            print!("  {cell:>width$}");
+        }
+        println!();
+    }
+}
+
+/// Apply each profile in turn for `duration`, measure the power draw and the
+/// CPU's frequency, usage and throttling, and compare the profiles. The
+/// settings in place before the benchmark are restored afterwards.
+pub fn run_bench(
+    config: &AppConfig,
+    files: &[PathBuf],
+    duration: &str,
+    interval_secs: u64,
+    json: bool,
+) -> Result<(), AppError> {
+    let duration = duration::parse_duration(duration)
+        .filter(|duration| !duration.is_zero())
+        .ok_or_else(|| {
+            AppError::Generic(format!(
+                "Invalid duration '{duration}', expected e.g. 30s, 10m or 1h"
+            ))
+        })?;
+    let interval = Duration::from_secs(interval_secs);
+
+    let profiles = files
+        .iter()
+        .map(|file| load_profile(file))
+        .collect::<Result<Vec<_>, _>>()?;
+    for (index, profile) in profiles.iter().enumerate() {
+        if profiles[..index]
+            .iter()
+            .any(|other| other.name == profile.name)
+        {
+            return Err(AppError::Generic(format!(
+                "Two profiles are named '{}'. Rename one of the files.",
+                profile.name
+            )));
+        }
+    }
+
+    watch_interrupt();
+    let before = snapshot::capture();
+    let mut results = Vec::new();
+    let mut failure = None;
+    for profile in &profiles {
+        info!(
+            "Benchmarking profile '{}' from {} for {}s",
+            profile.name,
+            profile.file.display(),
+            duration.as_secs()
+        );
+        match bench_profile(config, profile, duration, interval) {
+            Ok(result) => results.push(result),
+            Err(e) => {
+                failure = Some(e);
+                break;
+            }
+        }
+        if is_interrupted() {
+            warn!("Interrupted, only comparing the profiles measured so far");
+            break;
+        }
+    }
+
+    let restored = snapshot::restore(&before);
+    for (path, e) in &restored.failed {
+        warn!("Failed to restore {}: {e}", path.display());
+    }
+    if let Some(e) = failure {
+        return Err(e);
+    }
+    if results.is_empty() {
+        return Err(AppError::Generic("No profile was measured".to_string()));
+    }
+
+    if json {
+        let output = serde_json::to_string_pretty(&results)
+            .map_err(|e| AppError::Generic(format!("Failed to serialize results: {e}")))?;
+        println!("{output}");
+    } else {
+        print_table(&results);
+    }
+    Ok(())
+}
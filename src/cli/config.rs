@@ -0,0 +1,216 @@
+use crate::battery;
+use crate::cpu;
+use crate::util::error::AppError;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Default location for a generated configuration file
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/xdg/superfreq/config.toml";
+
+/// Capabilities of this machine that influence the generated configuration
+struct ProbedSystem {
+    governors: Vec<String>,
+    epp_values: Option<Vec<String>>,
+    platform_profiles: Vec<String>,
+    threshold_batteries: Vec<String>,
+}
+
+impl ProbedSystem {
+    fn probe() -> Self {
+        let governors = cpu::get_available_governors().unwrap_or_default();
+        let epp_values = if cpu::is_epp_supported() {
+            cpu::get_available_epp_values().ok()
+        } else {
+            None
+        };
+        let platform_profiles = cpu::get_platform_profiles().unwrap_or_default();
+        let threshold_batteries = battery::get_supported_batteries()
+            .map(|batteries| batteries.into_iter().map(|b| b.name).collect())
+            .unwrap_or_default();
+
+        Self {
+            governors,
+            epp_values,
+            platform_profiles,
+            threshold_batteries,
+        }
+    }
+}
+
+/// Return the first candidate that appears in `available`
+fn pick<'a>(available: &[String], candidates: &[&'a str]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .find(|candidate| available.iter().any(|a| a == *candidate))
+        .copied()
+}
+
+/// Write a profile section using only values that are valid on this machine
+fn write_profile(
+    out: &mut String,
+    name: &str,
+    system: &ProbedSystem,
+    governors: &[&str],
+    epps: &[&str],
+    platform_profiles: &[&str],
+) -> std::fmt::Result {
+    writeln!(out, "[{name}]")?;
+
+    if system.governors.is_empty() {
+        writeln!(out, "# Could not determine the available governors")?;
+    } else {
+        writeln!(
+            out,
+            "# Available governors: {}",
+            system.governors.join(", ")
+        )?;
+    }
+    match pick(&system.governors, governors) {
+        Some(governor) => writeln!(out, "governor = \"{governor}\"")?,
+        None => writeln!(out, "# governor = \"schedutil\"")?,
+    }
+
+    writeln!(
+        out,
+        "# Turbo boost setting: \"always\", \"auto\", or \"never\""
+    )?;
+    writeln!(out, "turbo = \"auto\"")?;
+    writeln!(out, "enable_auto_turbo = true")?;
+
+    match &system.epp_values {
+        Some(values) => {
+            writeln!(out, "# Available EPP values: {}", values.join(", "))?;
+            match pick(values, epps) {
+                Some(epp) => writeln!(out, "epp = \"{epp}\"")?,
+                None => writeln!(out, "# epp = \"default\"")?,
+            }
+        }
+        None => {
+            writeln!(
+                out,
+                "# EPP is not supported by the cpufreq driver on this system"
+            )?;
+            writeln!(out, "# epp = \"default\"")?;
+        }
+    }
+
+    if system.platform_profiles.is_empty() {
+        writeln!(out, "# Platform profiles are not supported on this system")?;
+        writeln!(out, "# platform_profile = \"balanced\"")?;
+    } else {
+        writeln!(
+            out,
+            "# Available platform profiles: {}",
+            system.platform_profiles.join(", ")
+        )?;
+        match pick(&system.platform_profiles, platform_profiles) {
+            Some(profile) => writeln!(out, "platform_profile = \"{profile}\"")?,
+            None => writeln!(out, "# platform_profile = \"balanced\"")?,
+        }
+    }
+
+    writeln!(out, "# Min/max frequency in MHz")?;
+    writeln!(out, "# min_freq_mhz = 800")?;
+    writeln!(out, "# max_freq_mhz = 3000")?;
+    writeln!(out)
+}
+
+/// Render a commented configuration pre-filled with values valid for this machine
+fn render_config(system: &ProbedSystem) -> Result<String, std::fmt::Error> {
+    let mut out = String::new();
+
+    writeln!(out, "# Superfreq configuration")?;
+    writeln!(
+        out,
+        "# Generated by `superfreq config init` on superfreq {}",
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(out)?;
+
+    writeln!(out, "# Settings for when connected to a power source")?;
+    write_profile(
+        &mut out,
+        "charger",
+        system,
+        &["performance", "schedutil", "ondemand"],
+        &["balance_performance", "performance", "default"],
+        &["performance", "balanced-performance", "balanced"],
+    )?;
+
+    writeln!(out, "# Settings for when on battery power")?;
+    write_profile(
+        &mut out,
+        "battery",
+        system,
+        &["powersave", "schedutil", "conservative"],
+        &["power", "balance_power", "default"],
+        &["low-power", "quiet", "cool", "balanced"],
+    )?;
+
+    if system.threshold_batteries.is_empty() {
+        writeln!(
+            out,
+            "# No batteries with charge threshold support were found on this system"
+        )?;
+        writeln!(out, "# battery_charge_thresholds = [40, 80]")?;
+    } else {
+        writeln!(
+            out,
+            "# Batteries with charge threshold support: {}",
+            system.threshold_batteries.join(", ")
+        )?;
+        writeln!(out, "# Start charging at 40%, stop at 80%")?;
+        writeln!(out, "battery_charge_thresholds = [40, 80]")?;
+    }
+    writeln!(out)?;
+
+    writeln!(out, "[daemon]")?;
+    writeln!(out, "poll_interval_sec = 5")?;
+    writeln!(out, "adaptive_interval = false")?;
+    writeln!(out, "min_poll_interval_sec = 1")?;
+    writeln!(out, "max_poll_interval_sec = 30")?;
+    writeln!(out, "throttle_on_battery = true")?;
+    writeln!(
+        out,
+        "# Log level: \"Error\", \"Warning\", \"Info\", or \"Debug\""
+    )?;
+    writeln!(out, "log_level = \"Info\"")?;
+    writeln!(out, "# stats_file_path = \"/var/run/superfreq/stats\"")?;
+    writeln!(out, "# stats_file_format = \"text\" # or \"json\"")?;
+    writeln!(
+        out,
+        "# history_csv_path = \"/var/log/superfreq-history.csv\""
+    )?;
+    writeln!(out, "# log_file_path = \"/var/log/superfreq/daemon.log\"")?;
+
+    Ok(out)
+}
+
+/// Probe the system and write a commented configuration file
+pub fn run_config_init(output: Option<&Path>, stdout: bool, force: bool) -> Result<(), AppError> {
+    let system = ProbedSystem::probe();
+    let content = render_config(&system)
+        .map_err(|e| AppError::Generic(format!("Failed to render configuration: {e}")))?;
+
+    if stdout {
+        print!("{content}");
+        return Ok(());
+    }
+
+    let path = output.unwrap_or_else(|| Path::new(DEFAULT_CONFIG_PATH));
+    if path.exists() && !force {
+        return Err(AppError::Generic(format!(
+            "Configuration file {} already exists. Use --force to overwrite it.",
+            path.display()
+        )));
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)?;
+
+    println!("Configuration written to {}", path.display());
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use crate::conflict::{self, Conflict, Detection};
+use crate::util::error::AppError;
+use crate::util::privilege;
+use std::io::{self, BufRead, Write};
+use std::process::Command;
+
+fn confirm(question: &str) -> io::Result<bool> {
+    print!("{question} [y/N] ");
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// How to stop a service found outside of systemd, which is left to the user
+fn manual_instructions(conflict: &Conflict) -> String {
+    let service = conflict.service.service;
+    match conflict.detection {
+        Detection::OpenRc => format!("rc-service {service} stop && rc-update del {service}"),
+        Detection::Runit => {
+            format!("sv down {service}, then remove its link from the runit service directory")
+        }
+        Detection::Systemd | Detection::Process(_) => format!(
+            "stop {} and keep it from starting at boot",
+            conflict.service.name
+        ),
+    }
+}
+
+/// List the conflicting services and stop and mask the systemd ones after
+/// confirmation. Without root, print the command to run instead.
+pub fn run_conflicts_resolve(yes: bool) -> Result<(), AppError> {
+    let conflicts = conflict::detect_conflicts();
+    if conflicts.is_empty() {
+        println!("No conflicting power management services are running.");
+        return Ok(());
+    }
+
+    println!("Conflicting power management services:");
+    for conflict in &conflicts {
+        println!("  {conflict}");
+    }
+
+    let (systemd, manual): (Vec<&Conflict>, Vec<&Conflict>) = conflicts
+        .iter()
+        .partition(|conflict| conflict.detection == Detection::Systemd);
+
+    if !manual.is_empty() {
+        println!();
+        println!("These are not managed by systemd and have to be stopped by hand:");
+        for conflict in &manual {
+            println!(
+                "  {}: {}",
+                conflict.service.name,
+                manual_instructions(conflict)
+            );
+        }
+    }
+
+    if systemd.is_empty() {
+        return Ok(());
+    }
+
+    // `mask --now` stops the units and keeps them from being started again,
+    // by hand or as a dependency of another unit
+    let units: Vec<&str> = systemd
+        .iter()
+        .map(|conflict| conflict.service.systemd_unit)
+        .collect();
+    let mut args = vec!["mask", "--now"];
+    args.extend(&units);
+
+    if !privilege::is_root() {
+        println!();
+        println!("Run this as root to stop and mask them:");
+        println!("  systemctl {}", args.join(" "));
+        return Ok(());
+    }
+
+    println!();
+    if !yes && !confirm("Stop and mask the systemd services?")? {
+        println!("Nothing was changed.");
+        return Ok(());
+    }
+
+    let status = Command::new("systemctl").args(&args).status()?;
+    if !status.success() {
+        return Err(AppError::Generic(format!(
+            "systemctl {} failed with {status}",
+            args.join(" ")
+        )));
+    }
+
+    println!("Stopped and masked the services. Allow them to run again with:");
+    println!("  systemctl unmask {}", units.join(" "));
+    Ok(())
+}
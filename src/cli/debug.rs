@@ -1,15 +1,200 @@
+use crate::cli::thermal;
 use crate::config::AppConfig;
-use crate::cpu;
+use crate::core::{BoostControl, SystemReport};
+use crate::cpu::{self, BoostControlStatus};
 use crate::monitor;
+#[cfg(feature = "msr")]
+use crate::msr;
 use crate::util::error::AppError;
+use crate::util::features;
+use crate::util::privilege;
+use serde_json::json;
 use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::time::Duration;
 
-/// Prints comprehensive debug information about the system
-pub fn run_debug(config: &AppConfig) -> Result<(), AppError> {
+/// Sysfs paths whose presence tells which controls the system offers
+const SYSFS_PATHS: &[(&str, &str)] = &[
+    (
+        "/sys/devices/system/cpu/intel_pstate/no_turbo",
+        "Intel P-State Turbo Control",
+    ),
+    (
+        "/sys/devices/system/cpu/cpufreq/boost",
+        "Generic CPU Boost Control",
+    ),
+    (
+        "/sys/devices/system/cpu/amd_pstate/cpufreq/boost",
+        "AMD P-State Boost Control",
+    ),
+    (
+        "/sys/firmware/acpi/platform_profile",
+        "ACPI Platform Profile Control",
+    ),
+    (
+        "/sys/class/firmware-attributes/dell-wmi-sysman/attributes/ThermalManagement",
+        "Dell Thermal Management Control",
+    ),
+    (
+        "/sys/bus/platform/drivers/cros-charge-control",
+        "ChromeOS EC Charge Control",
+    ),
+    ("/dev/cros_ec", "ChromeOS EC Fan Control"),
+    (
+        "/sys/devices/platform/msi-ec/shift_mode",
+        "MSI EC Shift Mode Control",
+    ),
+    (
+        "/sys/devices/platform/asus-nb-wmi",
+        "ASUS WMI Platform Control",
+    ),
+    (
+        "/sys/devices/platform/soc/soc:firmware/get_throttled",
+        "Raspberry Pi Firmware Throttling Status",
+    ),
+    ("/sys/class/power_supply", "Power Supply Information"),
+];
+
+/// Result of write-verifying one boost control mechanism
+struct BoostControlCheck {
+    control: BoostControl,
+    status: BoostControlStatus,
+    paths: Vec<PathBuf>,
+}
+
+/// Write-verify every boost control mechanism, in order of preference
+fn check_boost_controls(config: &AppConfig) -> Vec<BoostControlCheck> {
+    cpu::boost_order(&config.boost_control)
+        .into_iter()
+        .map(|control| BoostControlCheck {
+            control,
+            status: cpu::verify_boost_control(control),
+            paths: cpu::boost_control_paths(control),
+        })
+        .collect()
+}
+
+/// The mechanism in effect: the first one that passed the write-verify test
+fn active_boost_control(checks: &[BoostControlCheck]) -> Option<&BoostControlCheck> {
+    checks
+        .iter()
+        .find(|check| matches!(check.status, BoostControlStatus::Verified))
+}
+
+/// Write-verify every boost control mechanism and report which one is in effect
+fn print_boost_control(config: &AppConfig) {
+    let driver = cpu::get_scaling_driver();
+    println!(
+        "Scaling Driver: {}",
+        driver.as_deref().unwrap_or("Unable to determine")
+    );
+
+    let checks = check_boost_controls(config);
+    let order_names: Vec<String> = checks
+        .iter()
+        .map(|check| check.control.to_string())
+        .collect();
+    println!("Preference Order: {}", order_names.join(", "));
+
+    for check in &checks {
+        let control = check.control;
+        match &check.status {
+            BoostControlStatus::Unavailable => println!("  {control}: not present"),
+            BoostControlStatus::Verified => {
+                println!("  {control}: verified ({} file(s))", check.paths.len());
+            }
+            BoostControlStatus::Failed(reason) => println!("  {control}: failed ({reason})"),
+        }
+    }
+
+    match active_boost_control(&checks) {
+        Some(check) => {
+            println!("Active Boost Control: {}", check.control);
+            for path in &check.paths {
+                println!("  {}", path.display());
+            }
+        }
+        None => println!("Active Boost Control: None (no mechanism passed the write-verify test)"),
+    }
+}
+
+/// All debug information as a single JSON object, for attaching to bug reports
+fn debug_json(config: &AppConfig, report: &SystemReport) -> serde_json::Value {
+    let boost_checks = check_boost_controls(config);
+    let boost_mechanisms: Vec<serde_json::Value> = boost_checks
+        .iter()
+        .map(|check| {
+            let (status, reason) = match &check.status {
+                BoostControlStatus::Unavailable => ("unavailable", None),
+                BoostControlStatus::Verified => ("verified", None),
+                BoostControlStatus::Failed(reason) => ("failed", Some(reason)),
+            };
+            json!({
+                "control": check.control.to_string(),
+                "status": status,
+                "reason": reason,
+                "paths": check.paths,
+            })
+        })
+        .collect();
+
+    let sysfs_paths: serde_json::Map<String, serde_json::Value> = SYSFS_PATHS
+        .iter()
+        .map(|(path, _)| ((*path).to_string(), Path::new(path).exists().into()))
+        .collect();
+
+    #[cfg(feature = "msr")]
+    let epb_msr = (report.cpu_global.epb.is_none() && msr::is_epb_supported())
+        .then(|| msr::read_epb(0).ok())
+        .flatten();
+    #[cfg(not(feature = "msr"))]
+    let epb_msr: Option<u8> = None;
+
+    json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "features": features::enabled_features(),
+        "timestamp": jiff::Timestamp::now().to_string(),
+        "kernel_version": get_kernel_info().ok(),
+        "uptime_secs": get_system_uptime().ok().map(|uptime| uptime.as_secs()),
+        "privileges": {
+            "root": privilege::is_root(),
+            "unreadable_files": report.permission_denied,
+        },
+        "config": config,
+        "sysfs_paths": sysfs_paths,
+        "boost_control": {
+            "scaling_driver": cpu::get_scaling_driver(),
+            "mechanisms": boost_mechanisms,
+            "active": active_boost_control(&boost_checks).map(|check| check.control.to_string()),
+        },
+        "governor_override": cpu::get_governor_override().map(|governor| governor.trim().to_string()),
+        "epb_msr": epb_msr,
+        "topology": monitor::get_cpu_topology(),
+        "thermal_zones": monitor::get_thermal_zones(),
+        "report": report,
+        "daemon": {
+            "running": fs::metadata("/var/run/superfreq.pid").is_ok(),
+            "systemd_service_active": is_systemd_service_active("superfreq").ok(),
+        },
+    })
+}
+
+/// Prints comprehensive debug information about the system, or a single JSON
+/// object with the same information
+pub fn run_debug(config: &AppConfig, json: bool) -> Result<(), AppError> {
+    if json {
+        let report = monitor::collect_system_report(config).map_err(AppError::Monitor)?;
+        let output = serde_json::to_string_pretty(&debug_json(config, &report)).map_err(|e| {
+            AppError::Generic(format!("Failed to serialize debug information: {e}"))
+        })?;
+        println!("{output}");
+        return Ok(());
+    }
+
     println!("=== SUPERFREQ DEBUG INFORMATION ===");
     println!("Version: {}", env!("CARGO_PKG_VERSION"));
+    println!("Features: {}", features::enabled_features_summary());
 
     // Current date and time
     println!("Timestamp: {}", jiff::Timestamp::now());
@@ -42,31 +227,44 @@ pub fn run_debug(config: &AppConfig) -> Result<(), AppError> {
                 "Linux Distribution: {}",
                 report.system_info.linux_distribution
             );
+            match &report.system_info.virtualization {
+                Some(virtualization) if config.daemon.apply_virtualized => {
+                    println!("Virtualization: {virtualization} (applying profiles anyway)");
+                }
+                Some(virtualization) => {
+                    println!("Virtualization: {virtualization} (monitoring only)");
+                }
+                None => println!("Virtualization: none"),
+            }
+
+            println!("\n--- PRIVILEGES ---");
+            println!("Running as root: {}", privilege::is_root());
+            if report.permission_denied.is_empty() {
+                println!("All monitored files were readable");
+            } else {
+                println!("Unreadable files:");
+                for path in &report.permission_denied {
+                    println!("  {path} ({})", privilege::describe_restricted_path(path));
+                }
+            }
 
             println!("\n--- CONFIGURATION ---");
             println!("Current Configuration: {config:#?}");
 
             // Print important sysfs paths and whether they exist
             println!("\n--- SYSFS PATHS ---");
-            check_and_print_sysfs_path(
-                "/sys/devices/system/cpu/intel_pstate/no_turbo",
-                "Intel P-State Turbo Control",
-            );
-            check_and_print_sysfs_path(
-                "/sys/devices/system/cpu/cpufreq/boost",
-                "Generic CPU Boost Control",
-            );
-            check_and_print_sysfs_path(
-                "/sys/devices/system/cpu/amd_pstate/cpufreq/boost",
-                "AMD P-State Boost Control",
-            );
-            check_and_print_sysfs_path(
-                "/sys/firmware/acpi/platform_profile",
-                "ACPI Platform Profile Control",
-            );
-            check_and_print_sysfs_path("/sys/class/power_supply", "Power Supply Information");
+            for (path, description) in SYSFS_PATHS {
+                check_and_print_sysfs_path(path, description);
+            }
+
+            println!("\n--- BOOST CONTROL ---");
+            print_boost_control(config);
 
             println!("\n--- CPU INFORMATION ---");
+            for (policy, driver) in &report.cpu_global.scaling_drivers {
+                println!("Scaling Driver (policy{policy}): {driver}");
+            }
+            println!("P-State Mode: {:?}", report.cpu_global.pstate_mode);
             println!("Current Governor: {:?}", report.cpu_global.current_governor);
             println!(
                 "Available Governors: {}",
@@ -78,6 +276,13 @@ pub fn run_debug(config: &AppConfig) -> Result<(), AppError> {
                 report.cpu_global.epp
             );
             println!("Energy Performance Bias (EPB): {:?}", report.cpu_global.epb);
+            #[cfg(feature = "msr")]
+            if report.cpu_global.epb.is_none() && msr::is_epb_supported() {
+                match msr::read_epb(0) {
+                    Ok(epb) => println!("Energy Performance Bias (MSR): {epb}"),
+                    Err(e) => println!("Energy Performance Bias (MSR): unreadable ({e})"),
+                }
+            }
 
             // Add governor override information
             if let Some(override_governor) = cpu::get_governor_override() {
@@ -86,14 +291,31 @@ pub fn run_debug(config: &AppConfig) -> Result<(), AppError> {
                 println!("Governor Override: None");
             }
 
+            println!("\n--- CPU TOPOLOGY ---");
+            let topology = monitor::get_cpu_topology();
+            println!(
+                "Packages: {}, Physical Cores: {}, Threads: {}",
+                topology.package_count(),
+                topology.core_count(),
+                topology.thread_count()
+            );
+            for policy in &topology.policies {
+                let cpus: Vec<String> = policy.cpus.iter().map(u32::to_string).collect();
+                println!("cpufreq policy{}: CPUs {}", policy.id, cpus.join(","));
+            }
+
             println!("\n--- PLATFORM PROFILE ---");
             println!(
                 "Current Platform Profile: {:?}",
                 report.cpu_global.platform_profile
             );
-            match cpu::get_platform_profiles() {
-                Ok(profiles) => println!("Available Platform Profiles: {}", profiles.join(", ")),
-                Err(_) => println!("Available Platform Profiles: Not supported on this system"),
+            if report.cpu_global.available_platform_profiles.is_empty() {
+                println!("Available Platform Profiles: Not supported on this system");
+            } else {
+                println!(
+                    "Available Platform Profiles: {}",
+                    report.cpu_global.available_platform_profiles.join(", ")
+                );
             }
 
             println!("\n--- CPU CORES DETAIL ---");
@@ -127,6 +349,16 @@ pub fn run_debug(config: &AppConfig) -> Result<(), AppError> {
                 );
             }
 
+            if !report.collection_errors.is_empty() {
+                println!("Collection Errors:");
+                for collection_error in &report.collection_errors {
+                    println!(
+                        "  Core {}: {}",
+                        collection_error.core_id, collection_error.kind
+                    );
+                }
+            }
+
             println!("\n--- TEMPERATURE INFORMATION ---");
             println!(
                 "Average CPU Temperature: {}",
@@ -136,6 +368,45 @@ pub fn run_debug(config: &AppConfig) -> Result<(), AppError> {
                 )
             );
 
+            println!("\n--- THROTTLING ---");
+            let (counts, deltas) = (&report.throttle.counts, &report.throttle.deltas);
+            for (name, count, delta) in [
+                ("Core Thermal", counts.core_thermal, deltas.core_thermal),
+                (
+                    "Package Thermal",
+                    counts.package_thermal,
+                    deltas.package_thermal,
+                ),
+                (
+                    "Core Power Limit",
+                    counts.core_power_limit,
+                    deltas.core_power_limit,
+                ),
+                (
+                    "Package Power Limit",
+                    counts.package_power_limit,
+                    deltas.package_power_limit,
+                ),
+            ] {
+                match (count, delta) {
+                    (Some(count), Some(delta)) => {
+                        println!("{name} Events: {count} (+{delta} since the previous sample)");
+                    }
+                    (Some(count), None) => println!("{name} Events: {count}"),
+                    (None, _) => println!("{name} Events: N/A"),
+                }
+            }
+            println!(
+                "PROCHOT Asserted: {}",
+                report
+                    .throttle
+                    .prochot_active
+                    .map_or_else(|| "N/A".to_string(), |active| active.to_string())
+            );
+
+            println!("\n--- THERMAL ZONES ---");
+            thermal::print_thermal_zones(&monitor::get_thermal_zones());
+
             println!("\n--- BATTERY INFORMATION ---");
             if report.batteries.is_empty() {
                 println!("No batteries found or all are ignored.");
@@ -171,6 +442,48 @@ pub fn run_debug(config: &AppConfig) -> Result<(), AppError> {
                             .charge_stop_threshold
                             .map_or_else(|| "N/A".to_string(), |t| t.to_string())
                     );
+                    println!(
+                        "  Charge Behaviour: {}",
+                        battery.charge_behaviour.as_deref().unwrap_or("N/A")
+                    );
+                    println!(
+                        "  Manufacturer: {}",
+                        battery.manufacturer.as_deref().unwrap_or("N/A")
+                    );
+                    println!(
+                        "  Model: {}",
+                        battery.model_name.as_deref().unwrap_or("N/A")
+                    );
+                    println!(
+                        "  Cycle Count: {}",
+                        battery
+                            .cycle_count
+                            .map_or_else(|| "N/A".to_string(), |c| c.to_string())
+                    );
+                    println!(
+                        "  Energy Full: {} Wh",
+                        battery
+                            .energy_full_wh
+                            .map_or_else(|| "N/A".to_string(), |e| format!("{e:.2}"))
+                    );
+                    println!(
+                        "  Energy Full Design: {} Wh",
+                        battery
+                            .energy_full_design_wh
+                            .map_or_else(|| "N/A".to_string(), |e| format!("{e:.2}"))
+                    );
+                    println!(
+                        "  Health: {}%",
+                        battery
+                            .health_percent
+                            .map_or_else(|| "N/A".to_string(), |h| format!("{h:.1}"))
+                    );
+                    println!(
+                        "  Voltage: {} V",
+                        battery
+                            .voltage_volts
+                            .map_or_else(|| "N/A".to_string(), |v| format!("{v:.2}"))
+                    );
                 }
             }
 
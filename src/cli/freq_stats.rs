@@ -0,0 +1,203 @@
+use crate::monitor;
+use crate::util::error::AppError;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the current frequency is read while sampling
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Width of a full histogram bar, in characters
+const BAR_WIDTH: f64 = 40.0;
+
+/// Where the residency of the CPUs was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    /// The kernel's cpufreq statistics since boot
+    TimeInState,
+    /// The kernel's cpufreq statistics over an interval
+    TimeInStateInterval,
+    /// `scaling_cur_freq` read every `SAMPLE_INTERVAL`
+    Sampled,
+}
+
+impl Source {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::TimeInState => "time_in_state",
+            Self::TimeInStateInterval => "time_in_state_interval",
+            Self::Sampled => "sampled",
+        }
+    }
+}
+
+/// Time spent at each frequency (in MHz), per CPU
+type Residency = BTreeMap<u32, BTreeMap<u32, Duration>>;
+
+/// Read the residency from the cpufreq statistics, over `interval` or since
+/// boot. `None` if a CPU has no statistics.
+fn time_in_state(cpus: &[u32], interval: Option<Duration>) -> Option<Residency> {
+    let read = || -> Option<Residency> {
+        cpus.iter()
+            .map(|&cpu| Some((cpu, monitor::get_time_in_state(cpu)?)))
+            .collect()
+    };
+
+    let start = read()?;
+    let Some(interval) = interval else {
+        return Some(start);
+    };
+
+    thread::sleep(interval);
+    let mut end = read()?;
+    for (cpu, states) in &mut end {
+        for (freq, time) in states.iter_mut() {
+            let before = start
+                .get(cpu)
+                .and_then(|states| states.get(freq))
+                .copied()
+                .unwrap_or_default();
+            *time = time.saturating_sub(before);
+        }
+    }
+    Some(end)
+}
+
+/// Read the current frequency of each CPU repeatedly for `duration`
+fn sample(cpus: &[u32], duration: Duration) -> Residency {
+    let mut residency: Residency = BTreeMap::new();
+    let start = Instant::now();
+
+    while start.elapsed() < duration {
+        for &cpu in cpus {
+            if let Some(freq) = monitor::get_current_frequency_mhz(cpu) {
+                *residency.entry(cpu).or_default().entry(freq).or_default() += SAMPLE_INTERVAL;
+            }
+        }
+        thread::sleep(SAMPLE_INTERVAL);
+    }
+    residency
+}
+
+/// Group frequencies into buckets `bucket_mhz` wide, keyed by their lower bound.
+/// Empty buckets between the lowest and highest one are included so the
+/// histogram shows gaps.
+fn buckets(states: &BTreeMap<u32, Duration>, bucket_mhz: u32) -> BTreeMap<u32, Duration> {
+    let mut buckets: BTreeMap<u32, Duration> = BTreeMap::new();
+    for (freq, time) in states {
+        *buckets.entry(freq / bucket_mhz * bucket_mhz).or_default() += *time;
+    }
+
+    if let (Some(&low), Some(&high)) = (buckets.keys().next(), buckets.keys().next_back()) {
+        for bucket in (low..high).step_by(bucket_mhz as usize) {
+            buckets.entry(bucket).or_default();
+        }
+    }
+    buckets
+}
+
+fn print_histogram(cpu: u32, states: &BTreeMap<u32, Duration>, bucket_mhz: u32) {
+    let total: Duration = states.values().sum();
+    println!("CPU {cpu} ({:.1} s)", total.as_secs_f64());
+    if total.is_zero() {
+        println!("  No time recorded");
+        return;
+    }
+
+    for (bucket, time) in buckets(states, bucket_mhz) {
+        let share = time.as_secs_f64() / total.as_secs_f64();
+        let bar = "█".repeat((share * BAR_WIDTH).round() as usize);
+        println!(
+            "  {:>5}-{:<5} MHz {:>5.1}% {bar}",
+            bucket,
+            bucket + bucket_mhz - 1,
+            share * 100.0
+        );
+    }
+}
+
+/// Show how long each CPU spent in each frequency range. The cpufreq
+/// statistics are used where the driver keeps them, since boot or over
+/// `interval`. Otherwise the current frequency is sampled for `interval`.
+pub fn run_freq_stats(
+    core_id: Option<u32>,
+    interval: Option<u64>,
+    bucket_mhz: u32,
+    json: bool,
+) -> Result<(), AppError> {
+    let mut cpus: Vec<u32> = monitor::get_cpu_topology()
+        .threads
+        .iter()
+        .filter(|thread| thread.cpufreq_policy.is_some())
+        .map(|thread| thread.cpu)
+        .collect();
+    if let Some(core_id) = core_id {
+        if !cpus.contains(&core_id) {
+            return Err(AppError::Generic(format!(
+                "CPU {core_id} does not exist or has no cpufreq policy"
+            )));
+        }
+        cpus = vec![core_id];
+    }
+    if cpus.is_empty() {
+        return Err(AppError::Generic(
+            "No CPUs with cpufreq support were found".to_string(),
+        ));
+    }
+
+    let interval = interval.map(Duration::from_secs);
+    let (source, residency) = match time_in_state(&cpus, interval) {
+        Some(residency) if interval.is_some() => (Source::TimeInStateInterval, residency),
+        Some(residency) => (Source::TimeInState, residency),
+        None => {
+            let duration = interval.unwrap_or(Duration::from_secs(5));
+            if !json {
+                println!(
+                    "The scaling driver keeps no frequency statistics, sampling for {} s...",
+                    duration.as_secs()
+                );
+            }
+            (Source::Sampled, sample(&cpus, duration))
+        }
+    };
+
+    if json {
+        let cpus: Vec<serde_json::Value> = residency
+            .iter()
+            .map(|(cpu, states)| {
+                let buckets: Vec<serde_json::Value> = buckets(states, bucket_mhz)
+                    .into_iter()
+                    .map(|(bucket, time)| {
+                        json!({
+                            "min_mhz": bucket,
+                            "max_mhz": bucket + bucket_mhz - 1,
+                            "secs": time.as_secs_f64(),
+                        })
+                    })
+                    .collect();
+                json!({ "cpu": cpu, "buckets": buckets })
+            })
+            .collect();
+        let output = serde_json::to_string_pretty(&json!({
+            "source": source.name(),
+            "cpus": cpus,
+        }))
+        .map_err(|e| AppError::Generic(format!("Failed to serialize frequency statistics: {e}")))?;
+        println!("{output}");
+        return Ok(());
+    }
+
+    match source {
+        Source::TimeInState => println!("Frequency residency since boot:"),
+        Source::TimeInStateInterval | Source::Sampled => println!(
+            "Frequency residency over {} s:",
+            interval.unwrap_or(Duration::from_secs(5)).as_secs()
+        ),
+    }
+    for (cpu, states) in &residency {
+        println!();
+        print_histogram(*cpu, states, bucket_mhz);
+    }
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use crate::config::AppConfig;
+#[cfg(feature = "history")]
+use crate::history::{self, History, HistoryEntry};
+use crate::util::error::AppError;
+use std::fs;
+#[cfg(feature = "history")]
+use std::path::Path;
+#[cfg(feature = "history")]
+use std::time::SystemTime;
+
+/// Format an optional value, or `-` when it was not recorded
+#[cfg(feature = "history")]
+fn or_dash<T>(value: Option<T>, format: impl FnOnce(T) -> String) -> String {
+    value.map_or_else(|| "-".to_string(), format)
+}
+
+#[cfg(feature = "history")]
+fn format_timestamp(timestamp: i64) -> String {
+    jiff::Timestamp::from_second(timestamp).map_or_else(
+        |_| timestamp.to_string(),
+        |t| {
+            t.to_zoned(jiff::tz::TimeZone::system())
+                .strftime("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        },
+    )
+}
+
+#[cfg(feature = "history")]
+fn print_entries(entries: &[HistoryEntry]) {
+    println!(
+        "{:<19}  {:>6}  {:>7}  {:>9}  {:>6}  {:>7}  {:>8}  {:<5}  {:<12}  Turbo",
+        "Time", "CPU", "Temp", "Freq", "Load", "Battery", "Power", "AC", "Governor"
+    );
+
+    for entry in entries {
+        println!(
+            "{:<19}  {:>6}  {:>7}  {:>9}  {:>6.2}  {:>7}  {:>8}  {:<5}  {:<12}  {}",
+            format_timestamp(entry.timestamp),
+            or_dash(entry.cpu_usage_percent, |u| format!("{u:.1}%")),
+            or_dash(entry.cpu_temp_celsius, |t| format!("{t:.1}°C")),
+            or_dash(entry.avg_freq_mhz, |f| format!("{f:.0} MHz")),
+            entry.load_1m,
+            or_dash(entry.battery_percent, |p| format!("{p}%")),
+            or_dash(entry.battery_power_watts, |w| format!("{w:.2} W")),
+            or_dash(entry.ac_connected, |ac| if ac { "yes" } else { "no" }
+                .to_string()),
+            entry.governor.as_deref().unwrap_or("-"),
+            or_dash(entry.turbo, |t| if t { "on" } else { "off" }.to_string()),
+        );
+    }
+}
+
+/// Print the reports recorded by the daemon within the last `since`
+#[cfg(feature = "history")]
+pub fn run_history_query(config: &AppConfig, since: &str, json: bool) -> Result<(), AppError> {
+    let window = history::parse_duration(since)?;
+
+    if !Path::new(&config.history.database_path).exists() {
+        return Err(AppError::Generic(format!(
+            "No history has been recorded at {}. Set `enable = true` in the [history] section and run the daemon.",
+            config.history.database_path
+        )));
+    }
+
+    let since = SystemTime::now()
+        .checked_sub(window)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let entries = History::open_read_only(&config.history)?.query(since)?;
+
+    if json {
+        let output = serde_json::to_string_pretty(&entries)
+            .map_err(|e| AppError::Generic(format!("Failed to serialize history: {e}")))?;
+        println!("{output}");
+    } else if entries.is_empty() {
+        println!("No reports were recorded in this period.");
+    } else {
+        print_entries(&entries);
+    }
+
+    Ok(())
+}
+
+/// Format a duration as e.g. `2h 05m`, `4m 10s` or `12s`
+fn format_duration(secs: u64) -> String {
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, seconds) => format!("{seconds}s"),
+        (0, minutes, seconds) => format!("{minutes}m {seconds:02}s"),
+        (hours, minutes, _) => format!("{hours}h {minutes:02}m"),
+    }
+}
+
+/// Print the cumulative time the running daemon has spent in each system
+/// state (on battery, high load, idle, ...), as written to its stats file
+pub fn run_history_summary(config: &AppConfig, json: bool) -> Result<(), AppError> {
+    let Some(stats_path) = &config.daemon.stats_file_path else {
+        return Err(AppError::Generic(
+            "The summary is read from the daemon's stats file. Set `stats_file_path` in the [daemon] section and run the daemon.".to_string(),
+        ));
+    };
+
+    let content = fs::read_to_string(stats_path).map_err(|e| {
+        AppError::Generic(format!(
+            "Cannot read the stats file {stats_path} (is the daemon running?): {e}"
+        ))
+    })?;
+
+    // Written with `stats_file_format = "json"`
+    let stats: Option<serde_json::Value> = serde_json::from_str(&content).ok();
+    let (current_state, mut durations): (Option<&str>, Vec<(&str, u64)>) = match &stats {
+        Some(stats) => (
+            stats
+                .pointer("/daemon/current_state")
+                .and_then(serde_json::Value::as_str),
+            stats
+                .pointer("/daemon/state_secs")
+                .and_then(serde_json::Value::as_object)
+                .into_iter()
+                .flatten()
+                .filter_map(|(state, secs)| secs.as_u64().map(|secs| (state.as_str(), secs)))
+                .collect(),
+        ),
+        None => (
+            content
+                .lines()
+                .find_map(|line| line.strip_prefix("current_state=")),
+            content
+                .lines()
+                .filter_map(|line| line.strip_prefix("state_secs."))
+                .filter_map(|line| line.split_once('='))
+                .filter_map(|(state, secs)| secs.parse().ok().map(|secs| (state, secs)))
+                .collect(),
+        ),
+    };
+    // The longest first, as in the text format
+    durations.sort_by(|(_, a), (_, b)| b.cmp(a));
+    let total: u64 = durations.iter().map(|(_, secs)| secs).sum();
+
+    if json {
+        let output = serde_json::json!({
+            "current_state": current_state,
+            "total_secs": total,
+            "state_secs": durations
+                .iter()
+                .map(|(state, secs)| ((*state).to_string(), serde_json::Value::from(*secs)))
+                .collect::<serde_json::Map<_, _>>(),
+        });
+        println!("{output}");
+        return Ok(());
+    }
+
+    if durations.is_empty() {
+        println!("The daemon has not recorded any state durations yet.");
+        return Ok(());
+    }
+
+    println!(
+        "Time in each state since the daemon started ({}):",
+        format_duration(total)
+    );
+    for (state, secs) in &durations {
+        println!(
+            "  {state:<10} {:>8}  {:>5.1}%",
+            format_duration(*secs),
+            *secs as f64 / total.max(1) as f64 * 100.0
+        );
+    }
+    if let Some(state) = current_state {
+        println!("Current state: {state}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,591 @@
+use crate::config::AppConfig;
+use crate::core::{
+    BatteryInfo, CollectionError, CpuCoreInfo, CpuGlobalInfo, NvidiaGpuInfo, PowerInfo,
+    RaspberryPiInfo, SystemInfo, SystemLoad, SystemReport, ThrottleInfo, WakeupInfo,
+};
+use crate::monitor;
+use crate::util::error::AppError;
+use crate::util::privilege;
+use serde::Serialize;
+use std::io::{self, Write as _};
+use std::thread;
+use std::time::Duration;
+
+/// JSON form of the `info` report. Domains only shown with `--all` are omitted otherwise.
+#[derive(Serialize)]
+struct InfoJson<'a> {
+    timestamp: String,
+    system_info: &'a SystemInfo,
+    cpu_global: &'a CpuGlobalInfo,
+    cpu_cores: &'a [CpuCoreInfo],
+    batteries: Vec<&'a BatteryInfo>,
+    system_load: &'a SystemLoad,
+    power: &'a PowerInfo,
+    throttle: &'a ThrottleInfo,
+    wakeups: Option<&'a WakeupInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    raspberry_pi: Option<&'a RaspberryPiInfo>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    nvidia_gpus: &'a [NvidiaGpuInfo],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    collection_errors: Option<&'a [CollectionError]>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    missing_data: Vec<MissingData<'a>>,
+}
+
+/// Data that could not be read because the process lacks privileges
+#[derive(Serialize)]
+struct MissingData<'a> {
+    path: &'a str,
+    description: &'static str,
+}
+
+fn missing_data(report: &SystemReport) -> Vec<MissingData<'_>> {
+    report
+        .permission_denied
+        .iter()
+        .map(|path| MissingData {
+            path,
+            description: privilege::describe_restricted_path(path),
+        })
+        .collect()
+}
+
+/// Explain which data is missing because the report was collected without privileges
+fn print_missing_data(missing: &[MissingData<'_>]) {
+    format_section("Missing Data");
+    if privilege::is_root() {
+        println!("Some files could not be read even though running as root:");
+    } else {
+        println!("Running without root, so the following data is unavailable:");
+    }
+
+    let mut descriptions: Vec<&str> = missing.iter().map(|m| m.description).collect();
+    descriptions.sort_unstable();
+    descriptions.dedup();
+    for description in descriptions {
+        println!("  {description}");
+        for entry in missing.iter().filter(|m| m.description == description) {
+            println!("    {}", entry.path);
+        }
+    }
+    if !privilege::is_root() {
+        println!("Run with sudo, or use the daemon's stats file, to see everything.");
+    }
+}
+
+/// Whether a power supply reports actual battery data
+/// (as opposed to peripherals like wireless mice)
+const fn is_real_battery(battery: &BatteryInfo) -> bool {
+    battery.capacity_percent.is_some() || battery.power_rate_watts.is_some()
+}
+
+/// Print a section header with proper centering
+fn format_section(title: &str) {
+    let title_len = title.len();
+    let total_width = title_len + 8; // 8 is for padding (4 on each side)
+    let separator = "═".repeat(total_width);
+
+    println!("\n╔{separator}╗");
+
+    // Calculate centering
+    println!("║    {title}    ║");
+
+    println!("╚{separator}╝");
+}
+
+fn print_system_info(report: &SystemReport) {
+    format_section("System Information");
+    println!("CPU Model:          {}", report.system_info.cpu_model);
+    println!("Architecture:       {}", report.system_info.architecture);
+    println!(
+        "Linux Distribution: {}",
+        report.system_info.linux_distribution
+    );
+    if let Some(virtualization) = &report.system_info.virtualization {
+        println!("Virtualization:     {virtualization}");
+    }
+
+    if let Some(closed) = report.lid_closed {
+        println!(
+            "Lid:                {}",
+            if closed { "Closed" } else { "Open" }
+        );
+    }
+
+    println!(
+        "Docked:             {}",
+        if report.docked { "Yes" } else { "No" }
+    );
+
+    // Format timestamp in a readable way
+    println!("Current Time:       {}", jiff::Timestamp::now());
+}
+
+fn print_cpu_global(cpu_global: &CpuGlobalInfo) {
+    format_section("CPU Global Info");
+    println!(
+        "Current Governor:    {}",
+        cpu_global.current_governor.as_deref().unwrap_or("N/A")
+    );
+    println!(
+        "Scaling Driver:      {}",
+        cpu_global
+            .scaling_driver_summary()
+            .as_deref()
+            .unwrap_or("N/A")
+    );
+    println!(
+        "Available Governors: {}", // 21 length baseline
+        cpu_global.available_governors.join(", ")
+    );
+    println!(
+        "Turbo Status:        {}",
+        match cpu_global.turbo_status {
+            Some(true) => "Enabled",
+            Some(false) => "Disabled",
+            None => "Unknown",
+        }
+    );
+
+    println!(
+        "EPP:                 {}",
+        cpu_global.epp.as_deref().unwrap_or("N/A")
+    );
+    println!(
+        "EPB:                 {}",
+        cpu_global.epb.as_deref().unwrap_or("N/A")
+    );
+    println!(
+        "Platform Profile:    {}",
+        cpu_global.platform_profile.as_deref().unwrap_or("N/A")
+    );
+    println!(
+        "CPU Temperature:     {}",
+        cpu_global.average_temperature_celsius.map_or_else(
+            || "N/A (No sensor detected)".to_string(),
+            |t| format!("{t:.1}°C")
+        )
+    );
+}
+
+fn print_cpu_cores(cpu_cores: &[CpuCoreInfo]) {
+    format_section("CPU Core Info");
+
+    // Core IDs are labelled with their type on hybrid CPUs, e.g. `0 (P)`
+    let core_label = |core: &CpuCoreInfo| match core.core_type {
+        Some(core_type) => format!("{} ({})", core.core_id, core_type.short_name()),
+        None => core.core_id.to_string(),
+    };
+
+    // Get max core label length for padding
+    let max_core_id_len = cpu_cores
+        .iter()
+        .map(|core| core_label(core).len())
+        .max()
+        .unwrap_or(1);
+
+    // Table headers
+    println!(
+        "  {:>width$}  │ {:^10} │ {:^10} │ {:^10} │ {:^7} │ {:^9}",
+        "Core",
+        "Current",
+        "Min",
+        "Max",
+        "Usage",
+        "Temp",
+        width = max_core_id_len + 4
+    );
+    println!(
+        "  {:─>width$}──┼─{:─^10}─┼─{:─^10}─┼─{:─^10}─┼─{:─^7}─┼─{:─^9}",
+        "",
+        "",
+        "",
+        "",
+        "",
+        "",
+        width = max_core_id_len + 4
+    );
+
+    for core_info in cpu_cores {
+        // Format frequencies: if current > max, show in a special way
+        let current_freq = match core_info.current_frequency_mhz {
+            Some(freq) => {
+                let max_freq = core_info.max_frequency_mhz.unwrap_or(0);
+                if freq > max_freq && max_freq > 0 {
+                    // Special format for boosted frequencies
+                    format!("{freq}*")
+                } else {
+                    format!("{freq}")
+                }
+            }
+            None => "N/A".to_string(),
+        };
+
+        // CPU core display
+        println!(
+            "  Core {:<width$} │ {:>10} │ {:>10} │ {:>10} │ {:>7} │ {:>9}",
+            core_label(core_info),
+            format!("{} MHz", current_freq),
+            format!(
+                "{} MHz",
+                core_info
+                    .min_frequency_mhz
+                    .map_or_else(|| "N/A".to_string(), |f| f.to_string())
+            ),
+            format!(
+                "{} MHz",
+                core_info
+                    .max_frequency_mhz
+                    .map_or_else(|| "N/A".to_string(), |f| f.to_string())
+            ),
+            format!(
+                "{}%",
+                core_info
+                    .usage_percent
+                    .map_or_else(|| "N/A".to_string(), |f| format!("{f:.1}"))
+            ),
+            format!(
+                "{}°C",
+                core_info
+                    .temperature_celsius
+                    .map_or_else(|| "N/A".to_string(), |f| format!("{f:.1}"))
+            ),
+            width = max_core_id_len
+        );
+    }
+}
+
+fn print_battery(battery_info: &BatteryInfo) {
+    let power_status = if battery_info.ac_connected {
+        "Connected to AC"
+    } else {
+        "Running on Battery"
+    };
+
+    println!("Battery {}:", battery_info.name);
+    println!("  Power Status:     {power_status}");
+    println!(
+        "  State:            {}",
+        battery_info.charging_state.as_deref().unwrap_or("Unknown")
+    );
+
+    if let Some(capacity) = battery_info.capacity_percent {
+        println!("  Capacity:         {capacity}%");
+    }
+
+    if let Some(power) = battery_info.power_rate_watts {
+        let direction = if power >= 0.0 {
+            "charging"
+        } else {
+            "discharging"
+        };
+        println!("  Power Rate:       {:.2} W ({})", power.abs(), direction);
+    }
+
+    // Display charge thresholds if available
+    if battery_info.charge_start_threshold.is_some() || battery_info.charge_stop_threshold.is_some()
+    {
+        println!(
+            "  Charge Thresholds: {}-{}",
+            battery_info
+                .charge_start_threshold
+                .map_or_else(|| "N/A".to_string(), |t| t.to_string()),
+            battery_info
+                .charge_stop_threshold
+                .map_or_else(|| "N/A".to_string(), |t| t.to_string())
+        );
+    }
+
+    if let Some(behaviour) = &battery_info.charge_behaviour {
+        println!("  Charge Behaviour: {behaviour}");
+    }
+
+    if let Some(health) = battery_info.health_percent {
+        match (
+            battery_info.energy_full_wh,
+            battery_info.energy_full_design_wh,
+        ) {
+            (Some(full), Some(design)) => {
+                println!("  Health:           {health:.1}% ({full:.1} / {design:.1} Wh)")
+            }
+            _ => println!("  Health:           {health:.1}%"),
+        }
+    }
+
+    if let Some(cycles) = battery_info.cycle_count {
+        println!("  Cycle Count:      {cycles}");
+    }
+
+    if let Some(voltage) = battery_info.voltage_volts {
+        println!("  Voltage:          {voltage:.2} V");
+    }
+
+    let model = [&battery_info.manufacturer, &battery_info.model_name]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    if !model.is_empty() {
+        println!("  Model:            {}", model.join(" "));
+    }
+}
+
+fn print_system_load(system_load: &SystemLoad) {
+    format_section("System Load");
+    println!("Load Average (1m):  {:.2}", system_load.load_avg_1min);
+    println!("Load Average (5m):  {:.2}", system_load.load_avg_5min);
+    println!("Load Average (15m): {:.2}", system_load.load_avg_15min);
+}
+
+fn print_power(power: &PowerInfo) {
+    let watts =
+        |value: Option<f32>| value.map_or_else(|| "N/A".to_string(), |w| format!("{w:.2} W"));
+
+    format_section("Power");
+    println!(
+        "Estimated System:   {}",
+        watts(power.estimated_system_watts)
+    );
+    println!("CPU Package (RAPL): {}", watts(power.cpu_package_watts));
+    println!("GPU:                {}", watts(power.gpu_watts));
+    if power.battery_discharge_watts.is_some() {
+        println!(
+            "Battery Discharge:  {}",
+            watts(power.battery_discharge_watts)
+        );
+    }
+    if power.charger_watts.is_some() {
+        println!("Charger:            {}", watts(power.charger_watts));
+    }
+}
+
+/// Print the load, temperature and power of each NVIDIA GPU
+fn print_nvidia_gpus(gpus: &[NvidiaGpuInfo]) {
+    let percent =
+        |value: Option<u32>| value.map_or_else(|| "N/A".to_string(), |value| format!("{value}%"));
+    let watts =
+        |value: Option<f32>| value.map_or_else(|| "N/A".to_string(), |w| format!("{w:.2} W"));
+
+    format_section("NVIDIA GPUs");
+    for gpu in gpus {
+        println!("GPU {}: {}", gpu.index, gpu.name);
+        println!("  Utilization:    {}", percent(gpu.utilization_percent));
+        println!(
+            "  Memory Load:    {}",
+            percent(gpu.memory_utilization_percent)
+        );
+        println!(
+            "  Temperature:    {}",
+            gpu.temperature_celsius
+                .map_or_else(|| "N/A".to_string(), |temp| format!("{temp}°C"))
+        );
+        println!("  Power:          {}", watts(gpu.power_watts));
+        println!("  Power Limit:    {}", watts(gpu.power_limit_watts));
+    }
+}
+
+/// Print the throttle event counters, with the events since the previous
+/// report in parentheses. Nothing is printed on CPUs without them.
+fn print_throttle(throttle: &ThrottleInfo) {
+    if throttle.counts.is_empty() && throttle.prochot_active.is_none() {
+        return;
+    }
+
+    let events = |count: Option<u64>, delta: Option<u64>| match (count, delta) {
+        (None, _) => "N/A".to_string(),
+        (Some(count), Some(delta)) if delta > 0 => format!("{count} (+{delta})"),
+        (Some(count), _) => count.to_string(),
+    };
+
+    format_section("Throttling");
+    let (counts, deltas) = (&throttle.counts, &throttle.deltas);
+    println!(
+        "Core Thermal:        {}",
+        events(counts.core_thermal, deltas.core_thermal)
+    );
+    println!(
+        "Package Thermal:     {}",
+        events(counts.package_thermal, deltas.package_thermal)
+    );
+    println!(
+        "Core Power Limit:    {}",
+        events(counts.core_power_limit, deltas.core_power_limit)
+    );
+    println!(
+        "Package Power Limit: {}",
+        events(counts.package_power_limit, deltas.package_power_limit)
+    );
+    if let Some(active) = throttle.prochot_active {
+        println!(
+            "PROCHOT:             {}",
+            if active { "Asserted" } else { "Inactive" }
+        );
+    }
+}
+
+/// Print what the Raspberry Pi firmware reports, flagging each condition as
+/// active now or as having occurred since boot
+fn print_raspberry_pi(pi: &RaspberryPiInfo) {
+    let state = |now: bool, occurred: bool| match (now, occurred) {
+        (true, _) => "Active",
+        (false, true) => "Occurred since boot",
+        (false, false) => "No",
+    };
+
+    format_section("Raspberry Pi");
+    println!("Model:               {}", pi.model);
+    println!(
+        "SoC Temperature:     {}",
+        pi.soc_temperature_celsius
+            .map_or_else(|| "N/A".to_string(), |temp| format!("{temp:.1}°C"))
+    );
+    println!(
+        "ARM Clock:           {}",
+        pi.arm_clock_mhz
+            .map_or_else(|| "N/A".to_string(), |mhz| format!("{mhz} MHz"))
+    );
+    let Some(throttled) = pi.throttled else {
+        println!("Throttling:          N/A");
+        return;
+    };
+    println!(
+        "Under-Voltage:       {}",
+        state(throttled.under_voltage, throttled.under_voltage_occurred)
+    );
+    println!(
+        "ARM Frequency Cap:   {}",
+        state(
+            throttled.arm_frequency_capped,
+            throttled.arm_frequency_capped_occurred
+        )
+    );
+    println!(
+        "Throttled:           {}",
+        state(throttled.throttled, throttled.throttled_occurred)
+    );
+    println!(
+        "Soft Temp Limit:     {}",
+        state(
+            throttled.soft_temp_limit,
+            throttled.soft_temp_limit_occurred
+        )
+    );
+}
+
+/// Print the interrupts per second and the sources causing most of them
+fn print_wakeups(wakeups: &WakeupInfo) {
+    format_section("Wakeups");
+    println!("Wakeups per Second: {:.1}", wakeups.per_second);
+    if wakeups.top_sources.is_empty() {
+        return;
+    }
+    println!("Top Sources:");
+    for source in &wakeups.top_sources {
+        println!("  {:>8.1}/s  {}", source.per_second, source.name);
+    }
+}
+
+fn print_collection_errors(errors: &[CollectionError]) {
+    format_section("Collection Errors");
+    if errors.is_empty() {
+        println!("None");
+    }
+    for error in errors {
+        println!("Core {}: {}", error.core_id, error.kind);
+    }
+}
+
+/// Print the system report. `all` includes every monitored domain instead of
+/// just the CPU and battery overview.
+/// Print the report once, or every `watch` seconds until interrupted
+pub fn run_info(
+    config: &AppConfig,
+    all: bool,
+    json: bool,
+    watch: Option<u64>,
+) -> Result<(), AppError> {
+    let Some(interval) = watch else {
+        let report = monitor::collect_system_report(config)?;
+        return print_report(&report, all, json);
+    };
+
+    let interval = Duration::from_secs(interval.max(1));
+    loop {
+        let report = monitor::collect_system_report(config)?;
+        if !json {
+            // Clear the screen and move the cursor home before re-rendering
+            print!("\x1b[2J\x1b[H");
+        }
+        print_report(&report, all, json)?;
+        io::stdout().flush()?;
+        thread::sleep(interval);
+    }
+}
+
+fn print_report(report: &SystemReport, all: bool, json: bool) -> Result<(), AppError> {
+    // Peripherals are only shown in the full report
+    // Skip battery info entirely on desktop systems
+    let batteries: Vec<&BatteryInfo> = report
+        .batteries
+        .iter()
+        .filter(|battery| all || is_real_battery(battery))
+        .collect();
+
+    if json {
+        let info = InfoJson {
+            timestamp: jiff::Timestamp::now().to_string(),
+            system_info: &report.system_info,
+            cpu_global: &report.cpu_global,
+            cpu_cores: &report.cpu_cores,
+            batteries,
+            system_load: &report.system_load,
+            power: &report.power,
+            throttle: &report.throttle,
+            wakeups: report.wakeups.as_ref(),
+            raspberry_pi: report.raspberry_pi.as_ref(),
+            nvidia_gpus: &report.nvidia_gpus,
+            collection_errors: all.then_some(report.collection_errors.as_slice()),
+            missing_data: missing_data(report),
+        };
+        let output = serde_json::to_string_pretty(&info)
+            .map_err(|e| AppError::Generic(format!("Failed to serialize report: {e}")))?;
+        println!("{output}");
+        return Ok(());
+    }
+
+    print_system_info(report);
+    print_cpu_global(&report.cpu_global);
+    print_cpu_cores(&report.cpu_cores);
+
+    if !batteries.is_empty() {
+        format_section("Battery Info");
+        for battery_info in batteries {
+            print_battery(battery_info);
+        }
+    }
+
+    print_system_load(&report.system_load);
+    print_power(&report.power);
+    if !report.nvidia_gpus.is_empty() {
+        print_nvidia_gpus(&report.nvidia_gpus);
+    }
+    print_throttle(&report.throttle);
+    if let Some(pi) = &report.raspberry_pi {
+        print_raspberry_pi(pi);
+    }
+    if let Some(wakeups) = &report.wakeups {
+        print_wakeups(wakeups);
+    }
+
+    if all {
+        print_collection_errors(&report.collection_errors);
+    }
+
+    let missing = missing_data(report);
+    if !missing.is_empty() {
+        print_missing_data(&missing);
+    }
+
+    Ok(())
+}
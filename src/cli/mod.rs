@@ -1 +1,17 @@
+pub mod asus;
+pub mod battery;
+pub mod bench;
+pub mod config;
+pub mod conflicts;
 pub mod debug;
+pub mod freq_stats;
+pub mod history;
+pub mod info;
+pub mod polkit;
+pub mod profile;
+pub mod snapshot;
+pub mod status;
+pub mod sysfs_dump;
+pub mod thermal;
+pub mod tunables;
+pub mod tune;
@@ -0,0 +1,89 @@
+use crate::util::privilege;
+use std::env;
+use std::ffi::OsString;
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::Command;
+
+/// File name of the policy in `polkit/`, which defines one action per command
+const POLICY_FILE: &str = "com.github.notashelf.superfreq.policy";
+
+/// Directories polkit reads actions from
+const ACTION_DIRS: &[&str] = &[
+    "/usr/share/polkit-1/actions",
+    "/usr/local/share/polkit-1/actions",
+    "/run/current-system/sw/share/polkit-1/actions",
+];
+
+/// Commands the policy has an action for, as given on the command line. pkexec
+/// picks the action whose `exec.argv1` annotation matches the command.
+const PRIVILEGED_COMMANDS: &[&str] = &[
+    "set-governor",
+    "force-governor",
+    "set-turbo",
+    "set-epp",
+    "set-epb",
+    "set-min-freq",
+    "set-max-freq",
+    "set-platform-profile",
+    "set-pstate-mode",
+    "set-battery-thresholds",
+    "set-charge-behaviour",
+    "apply",
+    "tune",
+];
+
+/// Global flag that may come before the command, which is moved after it
+const STRICT_CONFIG_FLAG: &str = "--strict-config";
+
+/// Whether the process was started by pkexec on behalf of another user
+pub fn is_pkexec_child() -> bool {
+    env::var_os("PKEXEC_UID").is_some()
+}
+
+fn is_policy_installed() -> bool {
+    ACTION_DIRS
+        .iter()
+        .any(|dir| Path::new(dir).join(POLICY_FILE).exists())
+}
+
+/// The arguments of the current invocation after the program name, without
+/// `--strict-config`, which may come before the command
+fn command_args() -> Vec<OsString> {
+    env::args_os()
+        .skip(1)
+        .filter(|arg| arg != STRICT_CONFIG_FLAG)
+        .collect()
+}
+
+/// The command to run through pkexec: one that changes hardware settings,
+/// run by an unprivileged user on a system with the policy installed
+pub fn command_to_escalate() -> Option<&'static str> {
+    if privilege::is_root() || is_pkexec_child() || !is_policy_installed() {
+        return None;
+    }
+    let args = command_args();
+    let command = args.first()?.to_str()?;
+    PRIVILEGED_COMMANDS
+        .iter()
+        .find(|&&privileged| privileged == command)
+        .copied()
+}
+
+/// Replace the process with the same command run through pkexec, which asks
+/// polkit for authorization. Only returns if pkexec could not be started.
+pub fn reexec(strict_config: bool) -> io::Error {
+    let exe = match env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => return e,
+    };
+
+    let mut command = Command::new("pkexec");
+    // The command must directly follow the program for pkexec to match it
+    command.arg(exe).args(command_args());
+    if strict_config {
+        command.arg(STRICT_CONFIG_FLAG);
+    }
+    command.exec()
+}
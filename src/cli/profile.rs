@@ -0,0 +1,332 @@
+use crate::auto_epp::AUTO_EPP;
+use crate::battery;
+use crate::cli::config::DEFAULT_CONFIG_PATH;
+use crate::config::{self, AppConfig, AppConfigToml, ProfileConfig, ProfileConfigToml};
+use crate::cpu;
+use crate::fan;
+use crate::gpu;
+use crate::keyboard_backlight;
+use crate::util::error::AppError;
+use crate::vendor::asus;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Profiles that live in their own top-level section instead of `[profiles]`
+const BUILTIN_PROFILES: &[&str] = &["charger", "battery"];
+
+/// Serialize a profile to a TOML table
+fn to_table(profile: &ProfileConfig) -> Result<toml::Table, AppError> {
+    toml::Table::try_from(profile)
+        .map_err(|e| AppError::Generic(format!("Failed to serialize profile: {e}")))
+}
+
+/// A profile as loaded from an empty section, to tell which settings a
+/// profile actually sets
+fn empty_profile() -> Result<ProfileConfig, AppError> {
+    toml::from_str::<ProfileConfigToml>("")
+        .map(ProfileConfig::from)
+        .map_err(|e| AppError::Generic(format!("Failed to build an empty profile: {e}")))
+}
+
+/// Render `table` as the section of profile `name` in a configuration file
+fn render_section(name: &str, table: toml::Table) -> Result<String, AppError> {
+    let mut root = toml::Table::new();
+    if BUILTIN_PROFILES.contains(&name) {
+        root.insert(name.to_string(), toml::Value::Table(table));
+    } else {
+        let mut profiles = toml::Table::new();
+        profiles.insert(name.to_string(), toml::Value::Table(table));
+        root.insert("profiles".to_string(), toml::Value::Table(profiles));
+    }
+    toml::to_string(&root).map_err(|e| AppError::Generic(format!("Failed to render profile: {e}")))
+}
+
+/// Whether the configuration file already sets profile `name`
+fn has_profile(config: &toml::Table, name: &str) -> bool {
+    if BUILTIN_PROFILES.contains(&name) {
+        return config.contains_key(name);
+    }
+    config
+        .get("profiles")
+        .and_then(toml::Value::as_table)
+        .is_some_and(|profiles| profiles.contains_key(name))
+}
+
+fn list_or_none(values: &[String]) -> String {
+    if values.is_empty() {
+        "none".to_string()
+    } else {
+        values.join(", ")
+    }
+}
+
+/// Settings of `profile` that this machine does not support
+fn unsupported_settings(name: &str, profile: &ProfileConfigToml) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if let Some(governor) = &profile.governor {
+        let governors = cpu::get_available_governors().unwrap_or_default();
+        if !governors.iter().any(|g| g.eq_ignore_ascii_case(governor)) {
+            problems.push(format!(
+                "{name}: governor '{governor}' is not available (available: {})",
+                list_or_none(&governors)
+            ));
+        }
+    }
+
+    if let Some(epp) = profile.epp.as_deref().filter(|&epp| epp != AUTO_EPP) {
+        if !cpu::is_epp_supported() {
+            problems.push(format!("{name}: EPP is not supported"));
+        } else {
+            let values = cpu::get_available_epp_values().unwrap_or_default();
+            if !values.iter().any(|value| value == epp) {
+                problems.push(format!(
+                    "{name}: EPP '{epp}' is not available (available: {})",
+                    list_or_none(&values)
+                ));
+            }
+        }
+    }
+
+    if let Some(platform_profile) = &profile.platform_profile {
+        let profiles = cpu::get_platform_profiles().unwrap_or_default();
+        if !profiles.contains(platform_profile) {
+            problems.push(format!(
+                "{name}: platform profile '{platform_profile}' is not available (available: {})",
+                list_or_none(&profiles)
+            ));
+        }
+    }
+
+    if profile.min_freq_mhz.is_some() || profile.max_freq_mhz.is_some() {
+        match cpu::get_hardware_frequency_limits_mhz() {
+            Ok((min, max)) => {
+                for (setting, value) in [
+                    ("min_freq_mhz", profile.min_freq_mhz),
+                    ("max_freq_mhz", profile.max_freq_mhz),
+                ] {
+                    if let Some(value) = value.filter(|value| !(min..=max).contains(value)) {
+                        problems.push(format!(
+                            "{name}: {setting} = {value} is outside the hardware range of {min}-{max} MHz"
+                        ));
+                    }
+                }
+            }
+            Err(_) => problems.push(format!(
+                "{name}: frequency limits are set but the CPU frequency range is unknown"
+            )),
+        }
+    }
+
+    if profile.pstate_mode.is_some() && !cpu::is_intel_pstate_available() {
+        problems.push(format!(
+            "{name}: pstate_mode is set but intel_pstate is not the scaling driver"
+        ));
+    }
+
+    let asus = &profile.asus;
+    for (setting, is_set, attribute) in [
+        (
+            "fan_boost_mode",
+            asus.fan_boost_mode.is_some(),
+            asus::FAN_BOOST_MODE,
+        ),
+        (
+            "throttle_thermal_policy",
+            asus.throttle_thermal_policy.is_some(),
+            asus::THROTTLE_THERMAL_POLICY,
+        ),
+        ("panel_od", asus.panel_od.is_some(), asus::PANEL_OD),
+    ] {
+        if is_set && !asus::attribute_path(attribute).exists() {
+            problems.push(format!(
+                "{name}: asus.{setting} is set but this machine has no asus-wmi {attribute}"
+            ));
+        }
+    }
+
+    for cluster in profile.clusters.keys() {
+        let exists = config::parse_policy_name(cluster)
+            .is_some_and(|policy_id| cpu::policy_cpus(policy_id).is_ok());
+        if !exists {
+            problems.push(format!(
+                "{name}: cluster {cluster} does not exist on this machine"
+            ));
+        }
+    }
+
+    if profile.gpu.has_intel_limits() && gpu::intel_cards().is_empty() {
+        problems.push(format!(
+            "{name}: GPU frequency limits are set but this machine has no Intel GPU driven by i915"
+        ));
+    }
+
+    if profile.gpu.nvidia_power_limit_watts.is_some() && !gpu::has_nvidia_gpu() {
+        problems.push(format!(
+            "{name}: gpu.nvidia_power_limit_watts is set but no NVIDIA GPU was found through NVML"
+        ));
+    }
+
+    if profile.keyboard_backlight_percent.is_some() && keyboard_backlight::find_leds().is_empty() {
+        problems.push(format!(
+            "{name}: keyboard_backlight_percent is set but this machine has no keyboard backlight"
+        ));
+    }
+
+    if !profile.fan_curve.is_empty() && !fan::is_supported() {
+        problems.push(format!(
+            "{name}: fan_curve is set but this machine has no supported fan control"
+        ));
+    }
+
+    if profile.battery_charge_thresholds.is_some()
+        && battery::get_supported_batteries().is_ok_and(|batteries| batteries.is_empty())
+    {
+        problems.push(format!(
+            "{name}: battery charge thresholds are set but no battery supports them"
+        ));
+    }
+
+    problems
+}
+
+/// Print the settings of profile `name` that differ from an empty profile,
+/// as a bundle that `superfreq profile import` reads
+pub fn run_profile_export(config: &AppConfig, name: &str) -> Result<(), AppError> {
+    let profile = config
+        .profile(name)
+        .ok_or_else(|| AppError::Generic(format!("No profile named '{name}'")))?;
+
+    let empty = to_table(&empty_profile()?)?;
+    let mut table = to_table(profile)?;
+    table.retain(|key, value| empty.get(key) != Some(value));
+    // Turbo is written in lowercase in configuration files
+    if let Some(toml::Value::String(turbo)) = table.get_mut("turbo") {
+        *turbo = turbo.to_lowercase();
+    }
+
+    let mut profiles = toml::Table::new();
+    profiles.insert(name.to_string(), toml::Value::Table(table));
+    let mut bundle = toml::Table::new();
+    bundle.insert("profiles".to_string(), toml::Value::Table(profiles));
+    let content = toml::to_string(&bundle)
+        .map_err(|e| AppError::Generic(format!("Failed to render profile: {e}")))?;
+
+    println!("# superfreq profile '{name}'");
+    println!("# Import it with `superfreq profile import <file>`");
+    println!();
+    print!("{content}");
+    Ok(())
+}
+
+/// Add the profiles of an exported bundle to the configuration file, after
+/// checking that this machine supports their settings
+pub fn run_profile_import(
+    file: &Path,
+    rename: Option<&str>,
+    force: bool,
+    config_path: Option<&Path>,
+) -> Result<(), AppError> {
+    let content = fs::read_to_string(file)?;
+    let bundle: toml::Table = toml::from_str(&content).map_err(|e| {
+        AppError::Generic(format!("Invalid profile bundle {}: {e}", file.display()))
+    })?;
+    let profiles = bundle
+        .get("profiles")
+        .and_then(toml::Value::as_table)
+        .filter(|profiles| !profiles.is_empty())
+        .ok_or_else(|| {
+            AppError::Generic(format!(
+                "{} contains no [profiles.<name>] section",
+                file.display()
+            ))
+        })?;
+    if rename.is_some() && profiles.len() > 1 {
+        return Err(AppError::Generic(
+            "--name can only be used with a bundle of a single profile".to_string(),
+        ));
+    }
+
+    let target = config_path
+        .map(Path::to_path_buf)
+        .or_else(config::config_file_path)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH));
+    let mut config_content = if target.exists() {
+        fs::read_to_string(&target)?
+    } else {
+        String::new()
+    };
+    let existing: toml::Table = toml::from_str(&config_content).map_err(|e| {
+        AppError::Generic(format!("Invalid configuration {}: {e}", target.display()))
+    })?;
+
+    let mut names = Vec::new();
+    let mut sections = Vec::new();
+    let mut problems = Vec::new();
+    for (bundle_name, value) in profiles {
+        let name = rename.unwrap_or(bundle_name);
+        let table = value
+            .as_table()
+            .cloned()
+            .ok_or_else(|| AppError::Generic(format!("Profile '{bundle_name}' is not a table")))?;
+
+        let profile: ProfileConfigToml = table
+            .clone()
+            .try_into()
+            .map_err(|e| AppError::Generic(format!("Invalid profile '{bundle_name}': {e}")))?;
+        profile.validate(name)?;
+
+        if has_profile(&existing, name) {
+            return Err(AppError::Generic(format!(
+                "{} already has a profile named '{name}'. Remove it first or use --name.",
+                target.display()
+            )));
+        }
+
+        problems.extend(unsupported_settings(name, &profile));
+        sections.push(render_section(name, table)?);
+        names.push(name.to_string());
+    }
+
+    if !problems.is_empty() {
+        println!("This machine does not support every setting of the bundle:");
+        for problem in &problems {
+            println!("  {problem}");
+        }
+        if !force {
+            return Err(AppError::Generic(
+                "Not importing the bundle. Use --force to import it anyway.".to_string(),
+            ));
+        }
+    }
+
+    for section in sections {
+        if !config_content.is_empty() {
+            if !config_content.ends_with('\n') {
+                config_content.push('\n');
+            }
+            config_content.push('\n');
+        }
+        config_content.push_str(&section);
+    }
+    // The file may define profiles in a way the appended sections clash with,
+    // e.g. as an inline table
+    toml::from_str::<AppConfigToml>(&config_content).map_err(|e| {
+        AppError::Generic(format!(
+            "The profiles cannot be added to {}: {e}",
+            target.display()
+        ))
+    })?;
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&target, config_content)?;
+
+    println!(
+        "Imported profile '{}' into {}",
+        names.join("', '"),
+        target.display()
+    );
+    Ok(())
+}
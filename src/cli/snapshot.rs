@@ -0,0 +1,62 @@
+use crate::snapshot;
+use crate::util::error::AppError;
+
+/// Record the current value of every managed setting under `name`
+pub fn run_snapshot_save(name: &str) -> Result<(), AppError> {
+    let snapshot = snapshot::save(name)?;
+    println!(
+        "Saved {} values to snapshot '{name}'. Restore them with `superfreq snapshot restore {name}`.",
+        snapshot.values.len()
+    );
+    Ok(())
+}
+
+/// Write back the values recorded in snapshot `name`
+pub fn run_snapshot_restore(name: &str) -> Result<(), AppError> {
+    let snapshot = snapshot::load(name)?;
+    let report = snapshot::restore(&snapshot);
+
+    println!(
+        "Restored {} of {} values from snapshot '{name}' (saved {}).",
+        report.restored,
+        snapshot.values.len(),
+        snapshot.created_at
+    );
+    if !report.missing.is_empty() {
+        println!("No longer present:");
+        for path in &report.missing {
+            println!("  {}", path.display());
+        }
+    }
+    if report.failed.is_empty() {
+        return Ok(());
+    }
+
+    println!("Failed:");
+    for (path, error) in &report.failed {
+        println!("  {}: {error}", path.display());
+    }
+    Err(AppError::Generic(format!(
+        "{} values could not be restored",
+        report.failed.len()
+    )))
+}
+
+/// List the saved snapshots
+pub fn run_snapshot_list() -> Result<(), AppError> {
+    let names = snapshot::list();
+    if names.is_empty() {
+        println!("No snapshots in {}.", snapshot::SNAPSHOT_DIR);
+    }
+    for name in names {
+        match snapshot::load(&name) {
+            Ok(snapshot) => println!(
+                "{name}  ({} values, saved {})",
+                snapshot.values.len(),
+                snapshot.created_at
+            ),
+            Err(e) => println!("{name}  (unreadable: {e})"),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,214 @@
+use crate::config::AppConfig;
+use crate::core::SystemReport;
+use crate::monitor;
+use crate::util::error::AppError;
+use clap::ValueEnum;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+/// Output format of `superfreq status`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StatusFormat {
+    /// One field per line
+    Text,
+    /// A JSON object for Waybar custom modules (also understood by other bars)
+    Waybar,
+}
+
+/// The state shown by `superfreq status`
+#[derive(Debug, Default)]
+struct Status {
+    /// Only known while the daemon is running
+    profile: Option<String>,
+    governor: Option<String>,
+    turbo: Option<bool>,
+    temp_celsius: Option<f32>,
+    battery_percent: Option<u8>,
+    ac_power: Option<bool>,
+    power_watts: Option<f32>,
+    safe_mode: bool,
+}
+
+fn parse_value<T: FromStr>(values: &HashMap<&str, &str>, key: &str) -> Option<T> {
+    values.get(key).and_then(|value| value.parse().ok())
+}
+
+impl Status {
+    /// Read the state from the daemon's stats file, unless it is missing or
+    /// has not been updated for a while (the daemon is not running)
+    fn from_stats_file(config: &AppConfig) -> Option<Self> {
+        let path = config.daemon.stats_file_path.as_ref()?;
+        let max_age =
+            Duration::from_secs(config.daemon.max_poll_interval_sec.max(1).saturating_mul(2));
+        let age = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())?;
+        if age > max_age {
+            return None;
+        }
+
+        let content = fs::read_to_string(path).ok()?;
+        if let Ok(stats) = serde_json::from_str::<Value>(&content) {
+            return Some(Self::from_stats_json(&stats));
+        }
+        let values: HashMap<&str, &str> = content
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .collect();
+
+        Some(Self {
+            profile: values.get("profile").map(|value| (*value).to_string()),
+            governor: values.get("governor").map(|value| (*value).to_string()),
+            turbo: parse_value(&values, "turbo"),
+            temp_celsius: parse_value(&values, "cpu_temp"),
+            battery_percent: parse_value(&values, "battery_percent"),
+            ac_power: parse_value(&values, "ac_power"),
+            power_watts: parse_value(&values, "power_watts"),
+            safe_mode: parse_value(&values, "safe_mode").unwrap_or(false),
+        })
+    }
+
+    /// Read the state from a stats file written with `stats_file_format = "json"`
+    fn from_stats_json(stats: &Value) -> Self {
+        let string = |pointer| stats.pointer(pointer).and_then(Value::as_str);
+        let number = |pointer| stats.pointer(pointer).and_then(Value::as_f64);
+        let flag = |pointer| stats.pointer(pointer).and_then(Value::as_bool);
+
+        Self {
+            profile: string("/daemon/profile").map(str::to_string),
+            governor: string("/cpu_global/current_governor").map(str::to_string),
+            turbo: flag("/cpu_global/turbo_status"),
+            temp_celsius: number("/cpu_global/average_temperature_celsius").map(|v| v as f32),
+            battery_percent: stats
+                .pointer("/batteries/0/capacity_percent")
+                .and_then(Value::as_u64)
+                .and_then(|percent| u8::try_from(percent).ok()),
+            ac_power: flag("/batteries/0/ac_connected"),
+            power_watts: number("/power/estimated_system_watts").map(|v| v as f32),
+            safe_mode: flag("/daemon/safe_mode").unwrap_or(false),
+        }
+    }
+
+    fn from_report(report: &SystemReport) -> Self {
+        let battery = report.batteries.first();
+
+        Self {
+            profile: None,
+            governor: report.cpu_global.current_governor.clone(),
+            turbo: report.cpu_global.turbo_status,
+            temp_celsius: report.cpu_global.average_temperature_celsius,
+            battery_percent: battery.and_then(|b| b.capacity_percent),
+            ac_power: battery.map(|b| b.ac_connected),
+            power_watts: report.power.estimated_system_watts,
+            safe_mode: false,
+        }
+    }
+
+    /// `(label, value)` pairs of the fields that are known
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        [
+            ("Profile", self.profile.clone()),
+            ("Governor", self.governor.clone()),
+            (
+                "Turbo",
+                self.turbo.map(|t| if t { "on" } else { "off" }.to_string()),
+            ),
+            (
+                "Temperature",
+                self.temp_celsius.map(|t| format!("{t:.0}°C")),
+            ),
+            ("Battery", self.battery_percent.map(|p| format!("{p}%"))),
+            (
+                "Power source",
+                self.ac_power
+                    .map(|ac| if ac { "AC" } else { "battery" }.to_string()),
+            ),
+            ("Power draw", self.power_watts.map(|w| format!("{w:.1} W"))),
+            ("Safe mode", self.safe_mode.then(|| "active".to_string())),
+        ]
+        .into_iter()
+        .filter_map(|(label, value)| value.map(|value| (label, value)))
+        .collect()
+    }
+
+    /// e.g. `profile=battery governor=powersave turbo=off temp=54C bat=37%`
+    fn short_line(&self) -> String {
+        [
+            self.profile.as_ref().map(|p| format!("profile={p}")),
+            self.governor.as_ref().map(|g| format!("governor={g}")),
+            self.turbo
+                .map(|t| format!("turbo={}", if t { "on" } else { "off" })),
+            self.temp_celsius.map(|t| format!("temp={t:.0}C")),
+            self.battery_percent.map(|p| format!("bat={p}%")),
+            self.ac_power
+                .map(|ac| format!("ac={}", if ac { "on" } else { "off" })),
+            self.safe_mode.then(|| "safe_mode=on".to_string()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ")
+    }
+
+    fn waybar_json(&self) -> serde_json::Value {
+        let text = [
+            self.profile.clone().or_else(|| self.governor.clone()),
+            self.temp_celsius.map(|t| format!("{t:.0}°C")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+        let tooltip = self
+            .fields()
+            .iter()
+            .map(|(label, value)| format!("{label}: {value}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut classes: Vec<String> = self.profile.iter().cloned().collect();
+        if self.safe_mode {
+            classes.push("safe-mode".to_string());
+        }
+
+        let mut output = serde_json::json!({
+            "text": text,
+            "tooltip": tooltip,
+            "class": classes,
+        });
+        if let Some(percent) = self.battery_percent {
+            output["percentage"] = percent.into();
+        }
+        output
+    }
+}
+
+/// Print a compact summary of the power management state, taken from the
+/// daemon's stats file when it is running
+pub fn run_status(config: &AppConfig, format: StatusFormat, short: bool) -> Result<(), AppError> {
+    let status = match Status::from_stats_file(config) {
+        Some(status) => status,
+        None => Status::from_report(&monitor::collect_system_report(config)?),
+    };
+
+    if short {
+        println!("{}", status.short_line());
+        return Ok(());
+    }
+
+    match format {
+        StatusFormat::Text => {
+            for (label, value) in status.fields() {
+                println!("{:<13} {value}", format!("{label}:"));
+            }
+        }
+        StatusFormat::Waybar => println!("{}", status.waybar_json()),
+    }
+
+    Ok(())
+}
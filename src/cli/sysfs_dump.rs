@@ -0,0 +1,249 @@
+use crate::gpu;
+use crate::util::error::AppError;
+use crate::util::sysfs;
+use log::debug;
+use serde_json::json;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Files and trees superfreq reads, copied into a dump
+const DUMP_PATHS: &[&str] = &[
+    "/sys/devices/system/cpu",
+    "/sys/devices/cpu_core/cpus",
+    "/sys/devices/cpu_atom/cpus",
+    "/sys/class/power_supply",
+    "/sys/class/thermal",
+    "/sys/class/hwmon",
+    "/sys/class/powercap",
+    "/sys/class/drm",
+    "/sys/class/leds",
+    "/sys/class/dmi/id/chassis_type",
+    "/sys/class/dmi/id/product_name",
+    "/sys/class/dmi/id/sys_vendor",
+    "/sys/class/dmi/id/board_vendor",
+    "/sys/class/dmi/id/bios_vendor",
+    "/sys/class/dmi/id/product_version",
+    "/sys/hypervisor/type",
+    "/sys/firmware/acpi/platform_profile",
+    "/sys/firmware/acpi/platform_profile_choices",
+    "/sys/devices/platform/asus-nb-wmi",
+    "/sys/devices/platform/msi-ec",
+    "/sys/devices/platform/soc/soc:firmware/get_throttled",
+    "/sys/class/firmware-attributes/dell-wmi-sysman/attributes/ThermalManagement",
+    "/sys/module/intel_pstate/parameters",
+    "/sys/power/state",
+    "/sys/power/disk",
+    "/proc/stat",
+    "/proc/loadavg",
+    "/proc/uptime",
+    "/proc/cpuinfo",
+    "/proc/device-tree/model",
+    "/proc/interrupts",
+    "/proc/acpi/button/lid",
+    "/proc/sys/kernel/sched_itmt_enabled",
+    "/proc/sys/kernel/osrelease",
+    "/proc/xen/capabilities",
+];
+
+/// Links that lead out of a tree or back into it, e.g. `cpu0/node0/cpu0`
+const SKIPPED_LINKS: &[&str] = &[
+    "device",
+    "driver",
+    "firmware_node",
+    "module",
+    "of_node",
+    "subsystem",
+];
+
+/// Files that identify the machine and are not needed to reproduce a bug
+const PRIVATE_FILES: &[&str] = &["serial_number"];
+
+/// Sysfs trees have no deeper files superfreq reads
+const MAX_DEPTH: usize = 8;
+
+/// Larger files are binary attributes superfreq does not read
+const MAX_FILE_BYTES: u64 = 64 * 1024;
+
+/// File at the top of a dump describing where it came from
+const INFO_FILE: &str = "superfreq-dump.json";
+
+#[derive(Debug, Default)]
+struct DumpCount {
+    files: usize,
+    unreadable: usize,
+}
+
+fn is_skipped_link(name: &str) -> bool {
+    SKIPPED_LINKS.contains(&name) || name.starts_with("node")
+}
+
+/// Copy `source` to `target`, following links so the dump holds the files
+/// themselves. Files that cannot be read, e.g. write-only attributes, are left out.
+fn copy_tree(source: &Path, target: &Path, depth: usize, count: &mut DumpCount) {
+    let Ok(metadata) = fs::metadata(source) else {
+        return;
+    };
+
+    if metadata.is_dir() {
+        if depth > MAX_DEPTH {
+            return;
+        }
+        let Ok(entries) = fs::read_dir(source) else {
+            count.unreadable += 1;
+            return;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let is_link = entry
+                .file_type()
+                .is_ok_and(|file_type| file_type.is_symlink());
+            if is_link && is_skipped_link(&name.to_string_lossy()) {
+                continue;
+            }
+            copy_tree(&entry.path(), &target.join(&name), depth + 1, count);
+        }
+        return;
+    }
+
+    let is_private = source
+        .file_name()
+        .is_some_and(|name| PRIVATE_FILES.iter().any(|private| name == *private));
+    if is_private {
+        return;
+    }
+
+    let mut content = Vec::new();
+    let read =
+        fs::File::open(source).and_then(|file| file.take(MAX_FILE_BYTES).read_to_end(&mut content));
+    if let Err(e) = read {
+        debug!("Not dumping {}: {e}", source.display());
+        count.unreadable += 1;
+        return;
+    }
+
+    let written = target
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| fs::write(target, &content));
+    match written {
+        Ok(()) => count.files += 1,
+        Err(e) => {
+            debug!("Failed to write {}: {e}", target.display());
+            count.unreadable += 1;
+        }
+    }
+}
+
+fn run_tar(args: &[&OsStr]) -> Result<(), AppError> {
+    let status = Command::new("tar").args(args).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(AppError::Generic(format!("tar exited with {status}")))
+    }
+}
+
+/// Copy every sysfs and procfs file superfreq reads into a tarball in `dir`,
+/// which `--sysfs-root` replays on another machine
+pub fn run_dump_sysfs(dir: &Path) -> Result<(), AppError> {
+    let name = format!(
+        "superfreq-sysfs-{}",
+        jiff::Zoned::now().strftime("%Y%m%d-%H%M%S")
+    );
+    let staging = dir.join(&name);
+    let archive = dir.join(format!("{name}.tar.gz"));
+    fs::create_dir_all(&staging)?;
+
+    let mut count = DumpCount::default();
+    for path in DUMP_PATHS {
+        let relative = Path::new(path).strip_prefix("/").unwrap_or(Path::new(path));
+        copy_tree(&sysfs::path(path), &staging.join(relative), 0, &mut count);
+    }
+    // GPU power is read through the `device` link of each card, which is
+    // skipped above
+    for card in gpu::cards() {
+        let Some(name) = card.file_name() else {
+            continue;
+        };
+        let target = staging
+            .join("sys/class/drm")
+            .join(name)
+            .join("device/hwmon");
+        copy_tree(&card.join("device/hwmon"), &target, 0, &mut count);
+    }
+
+    let info = json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "created_at": jiff::Timestamp::now().to_string(),
+        "kernel_version": fs::read_to_string(sysfs::path("/proc/sys/kernel/osrelease"))
+            .ok()
+            .map(|release| release.trim().to_string()),
+        "files": count.files,
+    });
+    fs::write(staging.join(INFO_FILE), format!("{info:#}\n"))?;
+
+    let result = run_tar(&[
+        "-czf".as_ref(),
+        archive.as_os_str(),
+        "-C".as_ref(),
+        staging.as_os_str(),
+        ".".as_ref(),
+    ]);
+    if let Err(e) = fs::remove_dir_all(&staging) {
+        debug!("Failed to remove {}: {e}", staging.display());
+    }
+    result?;
+
+    println!(
+        "Dumped {} files to {} ({} unreadable files left out).",
+        count.files,
+        archive.display(),
+        count.unreadable
+    );
+    println!(
+        "Replay it with `superfreq --sysfs-root {} info`.",
+        archive.display()
+    );
+    Ok(())
+}
+
+/// A dump unpacked into a temporary directory, removed when dropped
+#[derive(Debug)]
+pub struct UnpackedDump {
+    pub root: PathBuf,
+}
+
+impl Drop for UnpackedDump {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.root) {
+            debug!("Failed to remove {}: {e}", self.root.display());
+        }
+    }
+}
+
+/// Unpack a tarball written by `debug --dump-sysfs`, to use as the sysfs root
+pub fn unpack(archive: &Path) -> Result<UnpackedDump, AppError> {
+    let root = std::env::temp_dir().join(format!("superfreq-sysfs-{}", std::process::id()));
+    if root.exists() {
+        fs::remove_dir_all(&root)?;
+    }
+    fs::create_dir_all(&root)?;
+    let dump = UnpackedDump { root };
+
+    run_tar(&[
+        "-xzf".as_ref(),
+        archive.as_os_str(),
+        "-C".as_ref(),
+        dump.root.as_os_str(),
+    ])?;
+    if !dump.root.join(INFO_FILE).exists() {
+        return Err(AppError::Generic(format!(
+            "{} is not a superfreq sysfs dump",
+            archive.display()
+        )));
+    }
+    Ok(dump)
+}
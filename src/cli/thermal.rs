@@ -0,0 +1,89 @@
+use crate::core::ThermalZone;
+use crate::monitor;
+use crate::util::error::AppError;
+
+fn celsius(value: Option<f32>) -> String {
+    value.map_or_else(|| "N/A".to_string(), |t| format!("{t:.1}°C"))
+}
+
+/// Print each thermal zone with its trip points and the cooling devices the
+/// kernel engages at them
+pub fn print_thermal_zones(zones: &[ThermalZone]) {
+    if zones.is_empty() {
+        println!("No thermal zones found.");
+        return;
+    }
+
+    for zone in zones {
+        let mut details = Vec::new();
+        if let Some(policy) = &zone.policy {
+            details.push(policy.clone());
+        }
+        if let Some(mode) = &zone.mode {
+            details.push(mode.clone());
+        }
+        println!(
+            "thermal_zone{} ({}): {}{}",
+            zone.id,
+            zone.zone_type,
+            celsius(zone.temperature_celsius),
+            if details.is_empty() {
+                String::new()
+            } else {
+                format!(" [{}]", details.join(", "))
+            }
+        );
+
+        let next = zone.next_trip_point().map(|trip| trip.id);
+        for trip in &zone.trip_points {
+            let mut line = format!(
+                "  Trip {}: {:<8} {:>9}",
+                trip.id,
+                trip.trip_type,
+                celsius(trip.temperature_celsius)
+            );
+            if let Some(hysteresis) = trip.hysteresis_celsius.filter(|&h| h > 0.0) {
+                line.push_str(&format!(" (hysteresis {hysteresis:.1}°C)"));
+            }
+            match (zone.temperature_celsius, trip.temperature_celsius) {
+                (Some(current), Some(trip_temperature)) if current >= trip_temperature => {
+                    line.push_str(", reached");
+                }
+                (Some(current), Some(trip_temperature)) if next == Some(trip.id) => {
+                    line.push_str(&format!(", next in {:.1}°C", trip_temperature - current));
+                }
+                _ => {}
+            }
+            println!("{line}");
+
+            for device in &trip.cooling_devices {
+                println!(
+                    "    cooling_device{} ({}): state {}/{}",
+                    device.id,
+                    device.device_type,
+                    device
+                        .cur_state
+                        .map_or_else(|| "?".to_string(), |s| s.to_string()),
+                    device
+                        .max_state
+                        .map_or_else(|| "?".to_string(), |s| s.to_string())
+                );
+            }
+        }
+    }
+}
+
+/// List the thermal zones and what the kernel does as they heat up
+pub fn run_thermal(json: bool) -> Result<(), AppError> {
+    let zones = monitor::get_thermal_zones();
+
+    if json {
+        let output = serde_json::to_string_pretty(&zones)
+            .map_err(|e| AppError::Generic(format!("Failed to serialize thermal zones: {e}")))?;
+        println!("{output}");
+        return Ok(());
+    }
+
+    print_thermal_zones(&zones);
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use crate::config::AppConfig;
+use crate::overrides;
+use crate::tunables::{self, Tunable};
+use crate::util::error::AppError;
+use crate::util::sysfs;
+use serde_json::json;
+use std::path::Path;
+
+/// Names of the profiles that set `path` through their `sysctl` or `sysfs` options
+fn managed_by(config: &AppConfig, path: &Path) -> Vec<String> {
+    let profiles = [("charger", &config.charger), ("battery", &config.battery)]
+        .into_iter()
+        .chain(
+            config
+                .profiles
+                .iter()
+                .map(|(name, profile)| (name.as_str(), profile)),
+        );
+
+    profiles
+        .filter(|(_, profile)| {
+            profile
+                .sysctl
+                .iter()
+                .any(|(name, _)| overrides::sysctl_path(name) == path)
+                || profile
+                    .sysfs
+                    .iter()
+                    .any(|write| sysfs::path(&write.path) == path)
+        })
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+fn state(tunable: &Tunable) -> &'static str {
+    match tunable.is_good() {
+        Some(true) => "Good",
+        Some(false) => "Bad",
+        None => "Unknown",
+    }
+}
+
+/// List the power tunables powertop checks, with their current values and
+/// the profiles that set them
+pub fn run_tunables(config: &AppConfig, json: bool) -> Result<(), AppError> {
+    let tunables = tunables::discover();
+
+    if json {
+        let entries: Vec<serde_json::Value> = tunables
+            .iter()
+            .map(|tunable| {
+                json!({
+                    "kind": tunable.kind,
+                    "name": tunable.name,
+                    "path": tunable.path,
+                    "value": tunable.value,
+                    "good_value": tunable.kind.good_value(),
+                    "good": tunable.is_good(),
+                    "managed_by": managed_by(config, &tunable.path),
+                })
+            })
+            .collect();
+        let output = serde_json::to_string_pretty(&entries)
+            .map_err(|e| AppError::Generic(format!("Failed to serialize tunables: {e}")))?;
+        println!("{output}");
+        return Ok(());
+    }
+
+    if tunables.is_empty() {
+        println!("No known power tunables were found.");
+        return Ok(());
+    }
+
+    let rows: Vec<[String; 4]> = tunables
+        .iter()
+        .map(|tunable| {
+            let managed = managed_by(config, &tunable.path);
+            [
+                state(tunable).to_string(),
+                tunable.value.clone().unwrap_or_else(|| "N/A".to_string()),
+                if tunable.name == tunable.kind.title() {
+                    tunable.name.clone()
+                } else {
+                    format!("{}: {}", tunable.kind.title(), tunable.name)
+                },
+                if managed.is_empty() {
+                    "-".to_string()
+                } else {
+                    managed.join(", ")
+                },
+            ]
+        })
+        .collect();
+    let header = ["State", "Value", "Tunable", "Managed by"];
+    let widths: Vec<usize> = (0..header.len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .chain([header[column].len()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    for row in std::iter::once(header.map(str::to_string)).chain(rows) {
+        println!(
+            "{:w0$}  {:w1$}  {:w2$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+        );
+    }
+
+    let bad = tunables
+        .iter()
+        .filter(|tunable| tunable.is_good() == Some(false))
+        .count();
+    println!();
+    println!(
+        "{bad} of {} tunables are not set to save power.",
+        tunables.len()
+    );
+    Ok(())
+}
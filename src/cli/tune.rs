@@ -0,0 +1,124 @@
+use crate::snapshot::{self, Snapshot, SnapshotValue};
+use crate::tunables::{self, Tunable};
+use crate::util::error::AppError;
+use crate::util::sysfs;
+
+/// Snapshot holding the values `tune --auto` replaced, written back by `tune --undo`
+const TUNE_SNAPSHOT: &str = "before-tune";
+
+fn describe(tunable: &Tunable) -> String {
+    if tunable.name == tunable.kind.title() {
+        tunable.name.clone()
+    } else {
+        format!("{}: {}", tunable.kind.title(), tunable.name)
+    }
+}
+
+/// Set every tunable powertop flags to its power-saving value, except those
+/// with a caveat. The replaced values are added to a snapshot first, so
+/// running it again keeps the values from before the first run.
+pub fn run_tune_auto() -> Result<(), AppError> {
+    let discovered = tunables::discover();
+    let (candidates, skipped): (Vec<&Tunable>, Vec<&Tunable>) = discovered
+        .iter()
+        .filter(|tunable| tunable.is_good() == Some(false))
+        .partition(|tunable| tunable.caveat.is_none());
+
+    for tunable in &skipped {
+        println!(
+            "Skipping {} ({})",
+            describe(tunable),
+            tunable.caveat.unwrap_or_default()
+        );
+    }
+    if candidates.is_empty() {
+        println!("Every tunable already saves power, nothing to change.");
+        return Ok(());
+    }
+
+    let mut before = if snapshot::exists(TUNE_SNAPSHOT) {
+        snapshot::load(TUNE_SNAPSHOT)?
+    } else {
+        Snapshot {
+            created_at: jiff::Timestamp::now().to_string(),
+            values: Vec::new(),
+        }
+    };
+    for tunable in &candidates {
+        let Some(value) = &tunable.value else {
+            continue;
+        };
+        if !before.values.iter().any(|entry| entry.path == tunable.path) {
+            before.values.push(SnapshotValue {
+                path: tunable.path.clone(),
+                value: value.clone(),
+            });
+        }
+    }
+    // Written before changing anything, so an interrupted run can be undone
+    snapshot::write(TUNE_SNAPSHOT, &before)?;
+
+    let mut failed = 0;
+    for tunable in &candidates {
+        let good = tunable.kind.good_value();
+        match sysfs::write_sysfs_value(&tunable.path, good) {
+            Ok(()) => println!(
+                "{}: {} -> {good}",
+                describe(tunable),
+                tunable.value.as_deref().unwrap_or("N/A")
+            ),
+            Err(e) => {
+                println!("Failed to change {}: {e}", describe(tunable));
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "Changed {} of {} tunables. Undo with `superfreq tune --undo`.",
+        candidates.len() - failed,
+        candidates.len()
+    );
+    if failed > 0 {
+        return Err(AppError::Generic(format!(
+            "{failed} tunables could not be changed"
+        )));
+    }
+    Ok(())
+}
+
+/// Write back the values `tune --auto` replaced
+pub fn run_tune_undo() -> Result<(), AppError> {
+    if !snapshot::exists(TUNE_SNAPSHOT) {
+        println!("`superfreq tune --auto` has not changed anything, nothing to undo.");
+        return Ok(());
+    }
+
+    let before = snapshot::load(TUNE_SNAPSHOT)?;
+    let report = snapshot::restore(&before);
+    for entry in &before.values {
+        let failed = report.failed.iter().any(|(path, _)| *path == entry.path);
+        if !failed && !report.missing.contains(&entry.path) {
+            println!("Restored {} to {}", entry.path.display(), entry.value);
+        }
+    }
+    for path in &report.missing {
+        println!("No longer present: {}", path.display());
+    }
+    if !report.failed.is_empty() {
+        for (path, e) in &report.failed {
+            println!("Failed to restore {}: {e}", path.display());
+        }
+        return Err(AppError::Generic(format!(
+            "{} values could not be restored. Run `superfreq tune --undo` again to retry.",
+            report.failed.len()
+        )));
+    }
+
+    snapshot::remove(TUNE_SNAPSHOT)?;
+    println!(
+        "Restored {} tunables changed by `superfreq tune --auto`.",
+        report.restored
+    );
+    Ok(())
+}
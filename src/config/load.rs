@@ -1,33 +1,100 @@
 // Configuration loading functionality
+use log::{info, warn};
+use std::ffi::OsString;
 use std::fs;
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
 
 use crate::config::types::{AppConfig, AppConfigToml, ConfigError, DaemonConfig, ProfileConfig};
 
+/// System-wide configuration files, in the order they are tried
+const DEFAULT_CONFIG_PATHS: &[&str] = &["/etc/xdg/superfreq/config.toml", "/etc/superfreq.toml"];
+
+/// Prefix of environment variables that override configuration values
+const ENV_OVERRIDE_PREFIX: &str = "SUPERFREQ__";
+
+/// Top-level keys of the configuration file that overrides may set
+const CONFIG_SECTIONS: &[&str] = &[
+    "charger",
+    "battery",
+    "battery_charge_thresholds",
+    "profiles",
+    "rules",
+    "batteries",
+    "boost_control",
+    "ignored_power_supplies",
+    "ignored_cores",
+    "alerts",
+    "history",
+    "mqtt",
+    "http",
+    "dock",
+    "notifications",
+    "battery_backend",
+    "power_profiles",
+    "daemon",
+];
+
+/// The configuration file that is loaded without an explicit path, or `None`
+/// if there is none and the defaults are used
+pub fn config_file_path() -> Option<PathBuf> {
+    if let Ok(env_path) = std::env::var("SUPERFREQ_CONFIG") {
+        let env_path = PathBuf::from(env_path);
+        if env_path.exists() {
+            return Some(env_path);
+        }
+    }
+
+    DEFAULT_CONFIG_PATHS
+        .iter()
+        .map(PathBuf::from)
+        .find(|path| path.exists())
+}
+
 /// The primary function to load application configuration from a specific path or from default locations.
 ///
 /// # Arguments
 ///
-/// * `specific_path` - If provided, only attempts to load from this path and errors if not found
+/// * `strict` - Fail instead of skipping configuration files that cannot be loaded
 ///
 /// # Returns
 ///
 /// * `Ok(AppConfig)` - Successfully loaded configuration
 /// * `Err(ConfigError)` - Error loading or parsing configuration
-pub fn load_config() -> Result<AppConfig, ConfigError> {
-    load_config_from_path(None)
+pub fn load_config(strict: bool) -> Result<AppConfig, ConfigError> {
+    load_config_from_path(None, strict)
+}
+
+/// Load configuration from an inline TOML string, or from stdin if `source` is `-`
+pub fn load_config_inline(source: &str) -> Result<AppConfig, ConfigError> {
+    if source == "-" {
+        let mut contents = String::new();
+        io::stdin()
+            .read_to_string(&mut contents)
+            .map_err(ConfigError::Io)?;
+        info!("Loading config from stdin");
+        return parse_config(&contents);
+    }
+
+    info!("Loading inline config");
+    parse_config(source)
 }
 
 /// Load configuration from a specific path or try default paths
-pub fn load_config_from_path(specific_path: Option<&str>) -> Result<AppConfig, ConfigError> {
+///
+/// * `specific_path` - If provided, only attempts to load from this path and errors if not found
+pub fn load_config_from_path(
+    specific_path: Option<&Path>,
+    strict: bool,
+) -> Result<AppConfig, ConfigError> {
     // If a specific path is provided, only try that one
-    if let Some(path_str) = specific_path {
-        let path = Path::new(path_str);
+    if let Some(path) = specific_path {
         if path.exists() {
+            info!("Loading config from: {}", path.display());
             return load_and_parse_config(path);
         }
-        return Err(ConfigError::Io(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
+        return Err(ConfigError::Io(io::Error::new(
+            io::ErrorKind::NotFound,
             format!("Specified config file not found: {}", path.display()),
         )));
     }
@@ -36,63 +103,185 @@ pub fn load_config_from_path(specific_path: Option<&str>) -> Result<AppConfig, C
     if let Ok(env_path) = std::env::var("SUPERFREQ_CONFIG") {
         let env_path = Path::new(&env_path);
         if env_path.exists() {
-            println!(
+            info!(
                 "Loading config from SUPERFREQ_CONFIG: {}",
                 env_path.display()
             );
             return load_and_parse_config(env_path);
         }
-        eprintln!(
-            "Warning: Config file specified by SUPERFREQ_CONFIG not found: {}",
+        if strict {
+            return Err(ConfigError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Config file specified by SUPERFREQ_CONFIG not found: {}",
+                    env_path.display()
+                ),
+            )));
+        }
+        warn!(
+            "Config file specified by SUPERFREQ_CONFIG not found: {}",
             env_path.display()
         );
     }
 
     // System-wide paths
-    let config_paths = vec![
-        PathBuf::from("/etc/xdg/superfreq/config.toml"),
-        PathBuf::from("/etc/superfreq.toml"),
-    ];
-
-    for path in config_paths {
+    for path in DEFAULT_CONFIG_PATHS.iter().map(PathBuf::from) {
         if path.exists() {
-            println!("Loading config from: {}", path.display());
+            info!("Loading config from: {}", path.display());
             match load_and_parse_config(&path) {
                 Ok(config) => return Ok(config),
+                Err(e) if strict => return Err(e),
                 Err(e) => {
-                    eprintln!("Error with config file {}: {}", path.display(), e);
+                    warn!("Error with config file {}: {}", path.display(), e);
                     // Continue trying other files
                 }
             }
         }
     }
 
-    println!("No configuration file found or all failed to parse. Using default configuration.");
-    // Construct default AppConfig by converting default AppConfigToml
-    let default_toml_config = AppConfigToml::default();
-    Ok(AppConfig {
-        charger: ProfileConfig::from(default_toml_config.charger),
-        battery: ProfileConfig::from(default_toml_config.battery),
-        ignored_power_supplies: default_toml_config.ignored_power_supplies,
-        daemon: DaemonConfig::default(),
-    })
+    info!("No configuration file found or all failed to parse. Using default configuration.");
+    // Parse an empty file, so environment overrides still apply
+    parse_config("")
+}
+
+/// Parse the value of an override as TOML, e.g. `2`, `true` or `["a", "b"]`.
+/// Anything else is taken as a plain string, so `performance` needs no quotes.
+fn parse_override_value(value: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {value}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(value.to_string()))
+}
+
+/// Apply `SUPERFREQ__SECTION__KEY=value` environment variables on top of the
+/// parsed file. Each `__` separates a level of nesting, e.g.
+/// `SUPERFREQ__PROFILES__GAMING__GOVERNOR` sets `governor` in `[profiles.gaming]`.
+///
+/// The section and the key are lowercased. Names in between (profiles,
+/// batteries) match an existing entry regardless of case and are otherwise
+/// kept as written. Variables whose name or value is not unicode are skipped.
+fn apply_env_overrides(
+    table: &mut toml::Table,
+    vars: impl IntoIterator<Item = (OsString, OsString)>,
+) -> Result<(), ConfigError> {
+    let mut overrides: Vec<(String, String)> = vars
+        .into_iter()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .filter(|(name, _)| name.starts_with(ENV_OVERRIDE_PREFIX))
+        .collect();
+    overrides.sort();
+
+    for (name, value) in overrides {
+        let segments: Vec<&str> = name[ENV_OVERRIDE_PREFIX.len()..].split("__").collect();
+        if segments.iter().any(|segment| segment.is_empty()) {
+            return Err(ConfigError::Validation(format!(
+                "Invalid override {name}, expected e.g. {ENV_OVERRIDE_PREFIX}DAEMON__POLL_INTERVAL_SEC"
+            )));
+        }
+
+        let section = segments[0].to_lowercase();
+        if !CONFIG_SECTIONS.contains(&section.as_str()) {
+            return Err(ConfigError::Validation(format!(
+                "Override {name} sets unknown section '{section}'"
+            )));
+        }
+        let Some((key, names)) = segments[1..].split_last() else {
+            info!("Overriding {section} from {name}");
+            table.insert(section, parse_override_value(&value));
+            continue;
+        };
+        let key = key.to_lowercase();
+
+        let mut keys = vec![section.clone()];
+        let mut current = sub_table(table, section, &name)?;
+        for segment in names {
+            let segment = current
+                .keys()
+                .find(|existing| existing.eq_ignore_ascii_case(segment))
+                .cloned()
+                .unwrap_or_else(|| (*segment).to_string());
+            keys.push(segment.clone());
+            current = sub_table(current, segment, &name)?;
+        }
+
+        info!("Overriding {}.{key} from {name}", keys.join("."));
+        current.insert(key, parse_override_value(&value));
+    }
+    Ok(())
+}
+
+/// The table at `key` in `table`, created if missing
+fn sub_table<'a>(
+    table: &'a mut toml::Table,
+    key: String,
+    name: &str,
+) -> Result<&'a mut toml::Table, ConfigError> {
+    let error = ConfigError::Validation(format!(
+        "Override {name} sets a key in '{key}', which is not a table"
+    ));
+    table
+        .entry(key)
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+        .as_table_mut()
+        .ok_or(error)
 }
 
 /// Load and parse a configuration file
 fn load_and_parse_config(path: &Path) -> Result<AppConfig, ConfigError> {
     let contents = fs::read_to_string(path).map_err(ConfigError::Io)?;
+    parse_config(&contents)
+}
+
+/// Parse and validate configuration from TOML contents
+fn parse_config(contents: &str) -> Result<AppConfig, ConfigError> {
+    parse_config_with_env(contents, std::env::vars_os())
+}
+
+/// Parse and validate configuration from TOML contents, with overrides taken
+/// from `vars` instead of the process environment
+fn parse_config_with_env(
+    contents: &str,
+    vars: impl IntoIterator<Item = (OsString, OsString)>,
+) -> Result<AppConfig, ConfigError> {
+    let mut table = toml::from_str::<toml::Table>(contents).map_err(ConfigError::Toml)?;
+    apply_env_overrides(&mut table, vars)?;
+    let toml_app_config: AppConfigToml = table.try_into().map_err(ConfigError::Toml)?;
 
-    let toml_app_config = toml::from_str::<AppConfigToml>(&contents).map_err(ConfigError::Toml)?;
+    toml_app_config.charger.validate("charger")?;
+    toml_app_config.battery.validate("battery")?;
+    for (name, profile) in &toml_app_config.profiles {
+        profile.validate(name)?;
+    }
+    if let Some(thresholds) = &toml_app_config.battery_charge_thresholds {
+        thresholds.validate()?;
+    }
+    toml_app_config.dock.validate()?;
+    toml_app_config.http.validate()?;
+    toml_app_config.mqtt.validate()?;
+    for (name, thresholds) in &toml_app_config.batteries {
+        thresholds
+            .validate()
+            .map_err(|e| ConfigError::Validation(format!("Battery '{name}': {e}")))?;
+    }
 
     // Handle inheritance of values from global to profile configs
     let mut charger_profile = toml_app_config.charger.clone();
     let mut battery_profile = toml_app_config.battery.clone();
 
+    let mut named_profiles = toml_app_config.profiles;
+
     // Clone global battery_charge_thresholds once if it exists
     if let Some(global_thresholds) = toml_app_config.battery_charge_thresholds {
         // Apply to charger profile if not already set
         if charger_profile.battery_charge_thresholds.is_none() {
-            charger_profile.battery_charge_thresholds = Some(global_thresholds.clone());
+            charger_profile.battery_charge_thresholds = Some(global_thresholds);
+        }
+
+        // Apply to named profiles if not already set
+        for profile in named_profiles.values_mut() {
+            if profile.battery_charge_thresholds.is_none() {
+                profile.battery_charge_thresholds = Some(global_thresholds);
+            }
         }
 
         // Apply to battery profile if not already set
@@ -102,10 +291,26 @@ fn load_and_parse_config(path: &Path) -> Result<AppConfig, ConfigError> {
     }
 
     // Convert AppConfigToml to AppConfig
-    Ok(AppConfig {
+    let app_config = AppConfig {
         charger: ProfileConfig::from(charger_profile),
         battery: ProfileConfig::from(battery_profile),
+        profiles: named_profiles
+            .into_iter()
+            .map(|(name, profile)| (name, ProfileConfig::from(profile)))
+            .collect(),
+        rules: toml_app_config.rules,
+        batteries: toml_app_config.batteries,
+        boost_control: toml_app_config.boost_control,
+        alerts: toml_app_config.alerts,
+        history: toml_app_config.history,
+        mqtt: toml_app_config.mqtt,
+        http: toml_app_config.http,
+        dock: toml_app_config.dock,
+        notifications: toml_app_config.notifications,
+        battery_backend: toml_app_config.battery_backend,
+        power_profiles: toml_app_config.power_profiles,
         ignored_power_supplies: toml_app_config.ignored_power_supplies,
+        ignored_cores: toml_app_config.ignored_cores,
         daemon: DaemonConfig {
             poll_interval_sec: toml_app_config.daemon.poll_interval_sec,
             adaptive_interval: toml_app_config.daemon.adaptive_interval,
@@ -114,6 +319,143 @@ fn load_and_parse_config(path: &Path) -> Result<AppConfig, ConfigError> {
             throttle_on_battery: toml_app_config.daemon.throttle_on_battery,
             log_level: toml_app_config.daemon.log_level,
             stats_file_path: toml_app_config.daemon.stats_file_path,
+            stats_file_format: toml_app_config.daemon.stats_file_format,
+            safe_mode_failures: toml_app_config.daemon.safe_mode_failures,
+            safe_mode_retry_sec: toml_app_config.daemon.safe_mode_retry_sec,
+            history_csv_path: toml_app_config.daemon.history_csv_path,
+            log_file_path: toml_app_config.daemon.log_file_path,
+            log_max_size_mb: toml_app_config.daemon.log_max_size_mb,
+            log_max_age_days: toml_app_config.daemon.log_max_age_days,
+            log_max_files: toml_app_config.daemon.log_max_files,
+            log_format: toml_app_config.daemon.log_format,
+            activity_detection: toml_app_config.daemon.activity_detection,
+            on_conflict: toml_app_config.daemon.on_conflict,
+            enforce: toml_app_config.daemon.enforce,
+            mode: toml_app_config.daemon.mode,
+            apply_virtualized: toml_app_config.daemon.apply_virtualized,
+            keyboard_backlight_grace_sec: toml_app_config.daemon.keyboard_backlight_grace_sec,
+            user: toml_app_config.daemon.user,
+            profile: None,
         },
-    })
+    };
+
+    app_config.validate_rules()?;
+
+    Ok(app_config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::ffi::OsStringExt;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(OsString, OsString)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (OsString::from(name), OsString::from(value)))
+            .collect()
+    }
+
+    #[test]
+    fn override_values_keep_their_toml_type() {
+        assert_eq!(parse_override_value("true"), toml::Value::Boolean(true));
+        assert_eq!(parse_override_value("2"), toml::Value::Integer(2));
+        assert_eq!(parse_override_value("0.5"), toml::Value::Float(0.5));
+        assert_eq!(
+            parse_override_value(r#"["a", "b"]"#),
+            toml::Value::Array(vec!["a".into(), "b".into()])
+        );
+        assert_eq!(
+            parse_override_value("performance"),
+            toml::Value::String("performance".to_string())
+        );
+        assert_eq!(
+            parse_override_value("\"2\""),
+            toml::Value::String("2".to_string())
+        );
+    }
+
+    #[test]
+    fn overrides_set_nested_keys() {
+        let mut table: toml::Table =
+            toml::from_str("[profiles.Gaming]\ngovernor = \"powersave\"").unwrap();
+        apply_env_overrides(
+            &mut table,
+            vars(&[
+                ("SUPERFREQ__DAEMON__POLL_INTERVAL_SEC", "2"),
+                ("SUPERFREQ__PROFILES__GAMING__GOVERNOR", "performance"),
+                ("SUPERFREQ__PROFILES__Quiet__TURBO", "never"),
+                ("SUPERFREQ__IGNORED_CORES", "[3]"),
+                ("OTHER__DAEMON__POLL_INTERVAL_SEC", "9"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(table["daemon"]["poll_interval_sec"].as_integer(), Some(2));
+        assert_eq!(
+            table["profiles"]["Gaming"]["governor"].as_str(),
+            Some("performance")
+        );
+        assert_eq!(table["profiles"]["Quiet"]["turbo"].as_str(), Some("never"));
+        assert_eq!(table["ignored_cores"], toml::Value::Array(vec![3.into()]));
+        assert!(!table["profiles"].as_table().unwrap().contains_key("GAMING"));
+    }
+
+    #[test]
+    fn overrides_are_validated_with_the_config() {
+        let config =
+            parse_config_with_env("", vars(&[("SUPERFREQ__CHARGER__GOVERNOR", "performance")]))
+                .unwrap();
+        assert_eq!(config.charger.governor.as_deref(), Some("performance"));
+
+        let unknown = parse_config_with_env("", vars(&[("SUPERFREQ__BOGUS__KEY", "1")]));
+        assert!(matches!(unknown, Err(ConfigError::Validation(_))));
+
+        let empty_segment = parse_config_with_env("", vars(&[("SUPERFREQ__DAEMON____KEY", "1")]));
+        assert!(matches!(empty_segment, Err(ConfigError::Validation(_))));
+
+        let not_a_table =
+            parse_config_with_env("", vars(&[("SUPERFREQ__IGNORED_CORES__KEY", "1")]));
+        assert!(not_a_table.is_err());
+    }
+
+    #[test]
+    fn non_unicode_variables_are_skipped() {
+        let mut table = toml::Table::new();
+        let mut env = vars(&[("SUPERFREQ__DAEMON__POLL_INTERVAL_SEC", "2")]);
+        env.push((
+            OsString::from("SUPERFREQ__DAEMON__LOG_LEVEL"),
+            OsString::from_vec(vec![0xff, 0xfe]),
+        ));
+        env.push((
+            OsString::from_vec(b"SUPERFREQ__DAEMON__\xff".to_vec()),
+            OsString::from("1"),
+        ));
+        env.push((OsString::from_vec(vec![0xff]), OsString::from("1")));
+
+        apply_env_overrides(&mut table, env).unwrap();
+        assert_eq!(
+            table["daemon"]
+                .as_table()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            ["poll_interval_sec"]
+        );
+    }
+
+    #[test]
+    fn config_sections_cover_every_top_level_key() {
+        let config = AppConfigToml {
+            battery_charge_thresholds: Some(
+                crate::config::types::BatteryChargeThresholds::new(40, 80).unwrap(),
+            ),
+            ignored_power_supplies: Some(Vec::new()),
+            ignored_cores: Some(Vec::new()),
+            ..AppConfigToml::default()
+        };
+        for key in toml::Table::try_from(config).unwrap().keys() {
+            assert!(CONFIG_SECTIONS.contains(&key.as_str()), "{key}");
+        }
+    }
 }
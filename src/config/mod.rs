@@ -1,3 +1,5 @@
+//! Loading and validating the TOML configuration.
+
 pub mod load;
 pub mod types;
 
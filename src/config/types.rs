@@ -1,7 +1,10 @@
 // Configuration types and structures for superfreq
-use crate::core::TurboSetting;
+use crate::core::{AsusMode, BoostControl, ChargeBehaviour, PstateMode, TurboSetting};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
+use std::fmt;
+use std::path::{Component, Path};
 
 /// Defines constant-returning functions used for default values.
 /// This hopefully reduces repetition since we have way too many default functions
@@ -14,7 +17,7 @@ macro_rules! default_const {
     };
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct BatteryChargeThresholds {
     pub start: u8,
     pub stop: u8,
@@ -67,6 +70,41 @@ pub struct ProfileConfig {
     pub enable_auto_turbo: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub battery_charge_thresholds: Option<BatteryChargeThresholds>,
+    pub charge_behaviour: Option<ChargeBehaviour>,
+    #[serde(default)]
+    pub thermal: ThermalConfig,
+    #[serde(default)]
+    pub epp_auto_settings: EppAutoSettings,
+    #[serde(default)]
+    pub userspace: UserspaceSettings,
+    pub disabled_idle_states: Option<Vec<String>>,
+    pub cpu_dma_latency_us: Option<u32>,
+    pub pcore: Option<CoreGroupConfig>,
+    pub ecore: Option<CoreGroupConfig>,
+    #[serde(default)]
+    pub clusters: BTreeMap<String, CoreGroupConfig>,
+    pub sched_itmt: Option<bool>,
+    #[serde(default)]
+    pub rapl: RaplConfig,
+    #[serde(default)]
+    pub gpu: GpuConfig,
+    #[serde(default)]
+    pub asus: AsusConfig,
+    #[serde(default)]
+    pub fan_curve: Vec<FanCurvePoint>,
+    pub poll_interval_sec: Option<u64>,
+    #[serde(default)]
+    pub sysctl: SysctlSettings,
+    #[serde(default)]
+    pub sysfs: Vec<SysfsWrite>,
+    #[serde(default)]
+    pub governor_tunables: GovernorTunables,
+    pub pstate_mode: Option<PstateMode>,
+    pub critical_percent: Option<u8>,
+    #[serde(default)]
+    pub critical_action: CriticalAction,
+    pub critical_script: Option<String>,
+    pub keyboard_backlight_percent: Option<u8>,
 }
 
 impl Default for ProfileConfig {
@@ -82,21 +120,348 @@ impl Default for ProfileConfig {
             turbo_auto_settings: TurboAutoSettings::default(),
             enable_auto_turbo: default_enable_auto_turbo(),
             battery_charge_thresholds: None,
+            charge_behaviour: None,
+            thermal: ThermalConfig::default(),
+            epp_auto_settings: EppAutoSettings::default(),
+            userspace: UserspaceSettings::default(),
+            disabled_idle_states: None,
+            cpu_dma_latency_us: None,
+            pcore: None,
+            ecore: None,
+            clusters: BTreeMap::new(),
+            sched_itmt: None,
+            rapl: RaplConfig::default(),
+            gpu: GpuConfig::default(),
+            asus: AsusConfig::default(),
+            fan_curve: Vec::new(),
+            poll_interval_sec: None,
+            sysctl: SysctlSettings::default(),
+            sysfs: Vec::new(),
+            governor_tunables: GovernorTunables::default(),
+            pstate_mode: None,
+            critical_percent: None,
+            critical_action: CriticalAction::default(),
+            critical_script: None,
+            keyboard_backlight_percent: None,
+        }
+    }
+}
+
+/// A daily time window in `HH:MM-HH:MM` form. Windows may wrap past midnight,
+/// e.g. `22:00-07:00`. The end is exclusive, so a window whose start and end
+/// are equal never matches.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeWindow {
+    start_minute: u16,
+    end_minute: u16,
+}
+
+impl TimeWindow {
+    /// Check whether a minute of the day (0-1439) falls inside this window
+    pub const fn contains(&self, minute_of_day: u16) -> bool {
+        if self.start_minute <= self.end_minute {
+            minute_of_day >= self.start_minute && minute_of_day < self.end_minute
+        } else {
+            minute_of_day >= self.start_minute || minute_of_day < self.end_minute
         }
     }
 }
 
+fn parse_minute_of_day(value: &str) -> Result<u16, ConfigError> {
+    let invalid = || ConfigError::Validation(format!("Invalid time '{value}', expected HH:MM"));
+
+    let (hours, minutes) = value.trim().split_once(':').ok_or_else(invalid)?;
+    let hours: u16 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u16 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 23 || minutes > 59 {
+        return Err(invalid());
+    }
+
+    Ok(hours * 60 + minutes)
+}
+
+impl TryFrom<String> for TimeWindow {
+    type Error = ConfigError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let (start, end) = value.split_once('-').ok_or_else(|| {
+            ConfigError::Validation(format!(
+                "Invalid time window '{value}', expected HH:MM-HH:MM"
+            ))
+        })?;
+
+        Ok(Self {
+            start_minute: parse_minute_of_day(start)?,
+            end_minute: parse_minute_of_day(end)?,
+        })
+    }
+}
+
+impl From<TimeWindow> for String {
+    fn from(window: TimeWindow) -> Self {
+        format!(
+            "{:02}:{:02}-{:02}:{:02}",
+            window.start_minute / 60,
+            window.start_minute % 60,
+            window.end_minute / 60,
+            window.end_minute % 60
+        )
+    }
+}
+
+/// Kernel tunables written under `/proc/sys` while a profile is active, keyed
+/// by sysctl name (e.g. `vm.laptop_mode`). Dotted names may be written as
+/// nested TOML tables.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(try_from = "toml::Table", into = "BTreeMap<String, String>")]
+pub struct SysctlSettings(BTreeMap<String, String>);
+
+impl SysctlSettings {
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Add the settings of a table, prefixing nested keys with their parents
+    fn flatten(&mut self, prefix: &str, table: toml::Table) -> Result<(), ConfigError> {
+        for (key, value) in table {
+            let name = if prefix.is_empty() {
+                key
+            } else {
+                format!("{prefix}.{key}")
+            };
+
+            let value = match value {
+                toml::Value::Table(table) => {
+                    self.flatten(&name, table)?;
+                    continue;
+                }
+                toml::Value::String(value) => value,
+                toml::Value::Integer(value) => value.to_string(),
+                toml::Value::Boolean(value) => u8::from(value).to_string(),
+                other => {
+                    return Err(ConfigError::Validation(format!(
+                        "Invalid value for sysctl '{name}': expected a string, integer or boolean, got {}",
+                        other.type_str()
+                    )));
+                }
+            };
+
+            // Slashes separate path components like in `sysctl`, for names
+            // whose components contain dots (e.g. interface names)
+            if name
+                .split(['.', '/'])
+                .any(|component| component.is_empty() || component == "..")
+            {
+                return Err(ConfigError::Validation(format!(
+                    "Invalid sysctl name '{name}'"
+                )));
+            }
+
+            self.0.insert(name, value);
+        }
+
+        Ok(())
+    }
+}
+
+impl TryFrom<toml::Table> for SysctlSettings {
+    type Error = ConfigError;
+
+    fn try_from(table: toml::Table) -> Result<Self, Self::Error> {
+        let mut settings = Self::default();
+        settings.flatten("", table)?;
+        Ok(settings)
+    }
+}
+
+impl From<SysctlSettings> for BTreeMap<String, String> {
+    fn from(settings: SysctlSettings) -> Self {
+        settings.0
+    }
+}
+
+/// A value written to a sysfs file after the built-in settings, for hardware
+/// superfreq has no option for
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct SysfsWrite {
+    pub path: String,
+    pub value: String,
+    /// Remember the original value and write it back once the active profile
+    /// no longer sets this file, and when the daemon exits
+    #[serde(default)]
+    pub restore: bool,
+}
+
+impl SysfsWrite {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let path = Path::new(&self.path);
+        if !path.starts_with("/sys") || path.components().any(|c| c == Component::ParentDir) {
+            return Err(ConfigError::Validation(format!(
+                "sysfs path '{}' must be an absolute path under /sys",
+                self.path
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Conditions under which a profile rule matches. All conditions that are set
+/// must hold for the rule to match; a condition whose data is unavailable
+/// (e.g. no temperature sensor) never matches.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConditions {
+    /// Match when connected to (`true`) or disconnected from (`false`) AC power
+    pub on_ac: Option<bool>,
+    /// Match when the battery capacity is below this percentage
+    pub battery_below: Option<u8>,
+    /// Match when the battery capacity is above this percentage
+    pub battery_above: Option<u8>,
+    /// Match when the average CPU temperature is above this value in °C
+    pub temp_above: Option<f32>,
+    /// Match when the average CPU temperature is below this value in °C
+    pub temp_below: Option<f32>,
+    /// Match when the average CPU usage is above this percentage
+    pub load_above: Option<f32>,
+    /// Match when the average CPU usage is below this percentage
+    pub load_below: Option<f32>,
+    /// Match during a daily local time window, e.g. `22:00-07:00`
+    pub time: Option<TimeWindow>,
+    /// Match when the laptop lid is closed (`true`) or open (`false`)
+    pub lid_closed: Option<bool>,
+    /// Match when the system is docked (`true`) or not (`false`), as detected
+    /// according to the `[dock]` section
+    pub docked: Option<bool>,
+    /// Match when the connected charger is rated below this many watts, e.g.
+    /// a USB-C charger too weak to sustain turbo
+    pub charger_watts_below: Option<f32>,
+}
+
+/// A rule that selects a profile when its conditions match.
+/// Rules are evaluated in order and the first match wins.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ProfileRule {
+    /// Name of the profile to apply: `charger`, `battery`, or a `[profiles.<name>]` entry
+    pub profile: String,
+    #[serde(default)]
+    pub when: RuleConditions,
+}
+
+/// Where battery state is read from
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BatteryBackend {
+    /// `/sys/class/power_supply`
+    #[default]
+    Sysfs,
+    /// UPower over D-Bus, falling back to sysfs. Requires the `dbus` cargo feature.
+    Upower,
+}
+
 #[derive(Deserialize, Serialize, Debug, Default, Clone)]
 pub struct AppConfig {
     #[serde(default)]
     pub charger: ProfileConfig,
     #[serde(default)]
     pub battery: ProfileConfig,
+    /// Additional named profiles that can be selected by rules
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileConfig>,
+    #[serde(default)]
+    pub rules: Vec<ProfileRule>,
+    /// Charge thresholds for individual batteries, keyed by power supply name (e.g. `BAT0`).
+    /// These take precedence over profile and global thresholds.
+    #[serde(default)]
+    pub batteries: BTreeMap<String, BatteryChargeThresholds>,
+    /// Preferred order of boost control mechanisms, keyed by cpufreq scaling driver
+    #[serde(default)]
+    pub boost_control: BTreeMap<String, Vec<BoostControl>>,
     pub ignored_power_supplies: Option<Vec<String>>,
+    /// Cores left alone by superfreq (e.g. isolated real-time cores) and left
+    /// out of usage and temperature averages
+    pub ignored_cores: Option<Vec<u32>>,
+    #[serde(default)]
+    pub alerts: AlertConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
+    pub mqtt: MqttConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub dock: DockConfig,
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+    #[serde(default)]
+    pub battery_backend: BatteryBackend,
+    #[serde(default)]
+    pub power_profiles: PowerProfilesConfig,
     #[serde(default)]
     pub daemon: DaemonConfig,
 }
 
+impl AppConfig {
+    /// Look up a profile by name. `charger` and `battery` refer to the built-in profiles.
+    pub fn profile(&self, name: &str) -> Option<&ProfileConfig> {
+        match name {
+            "charger" => Some(&self.charger),
+            "battery" => Some(&self.battery),
+            _ => self.profiles.get(name),
+        }
+    }
+
+    /// Whether any rule depends on the lid state
+    pub fn uses_lid_rules(&self) -> bool {
+        self.rules.iter().any(|rule| rule.when.lid_closed.is_some())
+    }
+
+    /// Ensure every rule, power profile mapping and alert action refers to a
+    /// profile that exists
+    pub fn validate_rules(&self) -> Result<(), ConfigError> {
+        for rule in &self.rules {
+            if self.profile(&rule.profile).is_none() {
+                return Err(ConfigError::Validation(format!(
+                    "Rule refers to unknown profile '{}'",
+                    rule.profile
+                )));
+            }
+        }
+
+        let power_profiles = &self.power_profiles;
+        for profile in [&power_profiles.power_saver, &power_profiles.performance]
+            .into_iter()
+            .chain(&power_profiles.balanced)
+        {
+            if self.profile(profile).is_none() {
+                return Err(ConfigError::Validation(format!(
+                    "[power_profiles] refers to unknown profile '{profile}'"
+                )));
+            }
+        }
+
+        if let Some(profile) = &self.alerts.drain_profile {
+            if self.profile(profile).is_none() {
+                return Err(ConfigError::Validation(format!(
+                    "[alerts] drain_profile refers to unknown profile '{profile}'"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
 // Error type for config loading
 #[derive(Debug, thiserror::Error)]
 pub enum ConfigError {
@@ -125,6 +490,41 @@ pub struct ProfileConfigToml {
     pub enable_auto_turbo: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub battery_charge_thresholds: Option<BatteryChargeThresholds>,
+    pub charge_behaviour: Option<ChargeBehaviour>,
+    #[serde(default)]
+    pub thermal: ThermalConfig,
+    #[serde(default)]
+    pub epp_auto_settings: EppAutoSettings,
+    #[serde(default)]
+    pub userspace: UserspaceSettings,
+    pub disabled_idle_states: Option<Vec<String>>,
+    pub cpu_dma_latency_us: Option<u32>,
+    pub pcore: Option<CoreGroupConfig>,
+    pub ecore: Option<CoreGroupConfig>,
+    #[serde(default)]
+    pub clusters: BTreeMap<String, CoreGroupConfig>,
+    pub sched_itmt: Option<bool>,
+    #[serde(default)]
+    pub rapl: RaplConfig,
+    #[serde(default)]
+    pub gpu: GpuConfig,
+    #[serde(default)]
+    pub asus: AsusConfig,
+    #[serde(default)]
+    pub fan_curve: Vec<FanCurvePoint>,
+    pub poll_interval_sec: Option<u64>,
+    #[serde(default)]
+    pub sysctl: SysctlSettings,
+    #[serde(default)]
+    pub sysfs: Vec<SysfsWrite>,
+    #[serde(default)]
+    pub governor_tunables: GovernorTunables,
+    pub pstate_mode: Option<PstateMode>,
+    pub critical_percent: Option<u8>,
+    #[serde(default)]
+    pub critical_action: CriticalAction,
+    pub critical_script: Option<String>,
+    pub keyboard_backlight_percent: Option<u8>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
@@ -135,11 +535,130 @@ pub struct AppConfigToml {
     pub battery: ProfileConfigToml,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub battery_charge_thresholds: Option<BatteryChargeThresholds>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileConfigToml>,
+    #[serde(default)]
+    pub rules: Vec<ProfileRule>,
+    #[serde(default)]
+    pub batteries: BTreeMap<String, BatteryChargeThresholds>,
+    #[serde(default)]
+    pub boost_control: BTreeMap<String, Vec<BoostControl>>,
     pub ignored_power_supplies: Option<Vec<String>>,
+    /// Cores left alone by superfreq (e.g. isolated real-time cores) and left
+    /// out of usage and temperature averages
+    pub ignored_cores: Option<Vec<u32>>,
+    #[serde(default)]
+    pub alerts: AlertConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
+    pub mqtt: MqttConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub dock: DockConfig,
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+    #[serde(default)]
+    pub battery_backend: BatteryBackend,
+    #[serde(default)]
+    pub power_profiles: PowerProfilesConfig,
     #[serde(default)]
     pub daemon: DaemonConfigToml,
 }
 
+impl BatteryChargeThresholds {
+    /// Check that deserialized thresholds satisfy the same rules as [`Self::new`]
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        Self::new(self.start, self.stop).map(|_| ())
+    }
+}
+
+impl ProfileConfigToml {
+    /// Validate values that would otherwise be silently ignored during conversion
+    pub fn validate(&self, profile_name: &str) -> Result<(), ConfigError> {
+        if let Some(turbo) = &self.turbo {
+            if !matches!(turbo.to_lowercase().as_str(), "always" | "auto" | "never") {
+                return Err(ConfigError::Validation(format!(
+                    "Invalid turbo setting '{turbo}' in profile '{profile_name}', expected \"always\", \"auto\" or \"never\""
+                )));
+            }
+        }
+
+        if self
+            .keyboard_backlight_percent
+            .is_some_and(|percent| percent > 100)
+        {
+            return Err(ConfigError::Validation(format!(
+                "Profile '{profile_name}': keyboard_backlight_percent must be between 0 and 100"
+            )));
+        }
+
+        if self
+            .critical_percent
+            .is_some_and(|percent| !(1..=99).contains(&percent))
+        {
+            return Err(ConfigError::Validation(format!(
+                "Profile '{profile_name}': critical_percent must be between 1 and 99"
+            )));
+        }
+        if self.critical_action == CriticalAction::Script && self.critical_script.is_none() {
+            return Err(ConfigError::Validation(format!(
+                "Profile '{profile_name}': critical_action = \"script\" requires critical_script"
+            )));
+        }
+
+        if self.poll_interval_sec == Some(0) {
+            return Err(ConfigError::Validation(format!(
+                "Profile '{profile_name}': poll_interval_sec must be at least 1"
+            )));
+        }
+
+        if let Some(thresholds) = &self.battery_charge_thresholds {
+            thresholds
+                .validate()
+                .map_err(|e| ConfigError::Validation(format!("Profile '{profile_name}': {e}")))?;
+        }
+
+        self.thermal
+            .validate()
+            .map_err(|e| ConfigError::Validation(format!("Profile '{profile_name}': {e}")))?;
+        self.epp_auto_settings
+            .validate()
+            .map_err(|e| ConfigError::Validation(format!("Profile '{profile_name}': {e}")))?;
+        self.userspace
+            .validate()
+            .map_err(|e| ConfigError::Validation(format!("Profile '{profile_name}': {e}")))?;
+        self.rapl
+            .validate()
+            .map_err(|e| ConfigError::Validation(format!("Profile '{profile_name}': {e}")))?;
+        self.gpu
+            .validate()
+            .map_err(|e| ConfigError::Validation(format!("Profile '{profile_name}': {e}")))?;
+        if let Some(name) = self
+            .clusters
+            .keys()
+            .find(|name| parse_policy_name(name).is_none())
+        {
+            return Err(ConfigError::Validation(format!(
+                "Invalid cluster '{name}' in profile '{profile_name}', expected a cpufreq policy such as \"policy0\""
+            )));
+        }
+        validate_fan_curve(&self.fan_curve)
+            .map_err(|e| ConfigError::Validation(format!("Profile '{profile_name}': {e}")))?;
+        self.governor_tunables
+            .validate()
+            .map_err(|e| ConfigError::Validation(format!("Profile '{profile_name}': {e}")))?;
+        for entry in &self.sysfs {
+            entry
+                .validate()
+                .map_err(|e| ConfigError::Validation(format!("Profile '{profile_name}': {e}")))?;
+        }
+
+        Ok(())
+    }
+}
+
 impl Default for ProfileConfigToml {
     fn default() -> Self {
         Self {
@@ -153,6 +672,29 @@ impl Default for ProfileConfigToml {
             turbo_auto_settings: None,
             enable_auto_turbo: default_enable_auto_turbo(),
             battery_charge_thresholds: None,
+            charge_behaviour: None,
+            thermal: ThermalConfig::default(),
+            epp_auto_settings: EppAutoSettings::default(),
+            userspace: UserspaceSettings::default(),
+            disabled_idle_states: None,
+            cpu_dma_latency_us: None,
+            pcore: None,
+            ecore: None,
+            clusters: BTreeMap::new(),
+            sched_itmt: None,
+            rapl: RaplConfig::default(),
+            gpu: GpuConfig::default(),
+            asus: AsusConfig::default(),
+            fan_curve: Vec::new(),
+            poll_interval_sec: None,
+            sysctl: SysctlSettings::default(),
+            sysfs: Vec::new(),
+            governor_tunables: GovernorTunables::default(),
+            pstate_mode: None,
+            critical_percent: None,
+            critical_action: CriticalAction::default(),
+            critical_script: None,
+            keyboard_backlight_percent: None,
         }
     }
 }
@@ -165,11 +707,82 @@ pub struct TurboAutoSettings {
     pub load_threshold_low: f32,
     #[serde(default = "default_temp_threshold_high")]
     pub temp_threshold_high: f32,
+    /// Once turbo was disabled for temperature, keep it disabled until the
+    /// CPU cools below this temperature. Unset resumes as soon as load allows.
+    #[serde(default)]
+    pub temp_threshold_resume: Option<f32>,
     /// Initial turbo boost state when no previous state exists.
     /// Set to `true` to start with turbo enabled, `false` to start with turbo disabled.
     /// This is only used at first launch or after a reset.
     #[serde(default = "default_initial_turbo_state")]
     pub initial_turbo_state: bool,
+    /// Which CPU usage figure is compared against the load thresholds
+    #[serde(default)]
+    pub load_metric: TurboLoadMetric,
+    /// Number of busiest cores averaged with `load_metric = "top_cores"`
+    #[serde(default = "default_top_cores")]
+    pub top_cores: usize,
+    /// How turbo follows the load: the load thresholds, or a PID controller
+    #[serde(default)]
+    pub controller: TurboController,
+    /// Settings of the PID controller, used with `controller = "pid"`
+    #[serde(default)]
+    pub pid: TurboPidSettings,
+}
+
+/// How auto turbo decides between enabling and disabling turbo
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TurboController {
+    /// Enable above `load_threshold_high`, disable below `load_threshold_low`
+    #[default]
+    Hysteresis,
+    /// Enable while a PID controller tracking `pid.setpoint` has a positive output
+    Pid,
+}
+
+/// Reading the PID controller of auto turbo holds at its setpoint
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TurboPidTarget {
+    /// CPU usage according to `load_metric`. Turbo is enabled while the
+    /// load stays above the setpoint.
+    #[default]
+    Load,
+    /// Average CPU temperature. Turbo is enabled while the CPU stays below
+    /// the setpoint.
+    Temperature,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TurboPidSettings {
+    #[serde(default)]
+    pub target: TurboPidTarget,
+    /// Load in percent or temperature in °C, depending on `target`
+    #[serde(default = "default_pid_setpoint")]
+    pub setpoint: f32,
+    #[serde(default = "default_pid_kp")]
+    pub kp: f32,
+    /// Integral gain, per second. It averages out oscillating loads.
+    #[serde(default = "default_pid_ki")]
+    pub ki: f32,
+    /// Derivative gain, in seconds
+    #[serde(default)]
+    pub kd: f32,
+}
+
+/// CPU usage figure used for auto turbo decisions
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TurboLoadMetric {
+    /// Average usage over all cores
+    #[default]
+    Average,
+    /// Usage of the busiest core, so single-threaded load can trigger turbo
+    MaxCore,
+    /// Average usage of the `top_cores` busiest cores
+    TopCores,
 }
 
 // Default thresholds for Auto turbo mode
@@ -177,6 +790,10 @@ pub const DEFAULT_LOAD_THRESHOLD_HIGH: f32 = 70.0; // enable turbo if load is ab
 pub const DEFAULT_LOAD_THRESHOLD_LOW: f32 = 30.0; // disable turbo if load is below this
 pub const DEFAULT_TEMP_THRESHOLD_HIGH: f32 = 75.0; // disable turbo if temperature is above this
 pub const DEFAULT_INITIAL_TURBO_STATE: bool = false; // by default, start with turbo disabled
+pub const DEFAULT_TOP_CORES: usize = 2; // busiest cores averaged for the top_cores metric
+pub const DEFAULT_PID_SETPOINT: f32 = 50.0; // load the PID controller holds turbo around
+pub const DEFAULT_PID_KP: f32 = 0.5;
+pub const DEFAULT_PID_KI: f32 = 0.05;
 
 default_const!(
     default_load_threshold_high,
@@ -194,6 +811,10 @@ default_const!(
     bool,
     DEFAULT_INITIAL_TURBO_STATE
 );
+default_const!(default_top_cores, usize, DEFAULT_TOP_CORES);
+default_const!(default_pid_setpoint, f32, DEFAULT_PID_SETPOINT);
+default_const!(default_pid_kp, f32, DEFAULT_PID_KP);
+default_const!(default_pid_ki, f32, DEFAULT_PID_KI);
 
 impl Default for TurboAutoSettings {
     fn default() -> Self {
@@ -201,9 +822,560 @@ impl Default for TurboAutoSettings {
             load_threshold_high: DEFAULT_LOAD_THRESHOLD_HIGH,
             load_threshold_low: DEFAULT_LOAD_THRESHOLD_LOW,
             temp_threshold_high: DEFAULT_TEMP_THRESHOLD_HIGH,
+            temp_threshold_resume: None,
             initial_turbo_state: DEFAULT_INITIAL_TURBO_STATE,
+            load_metric: TurboLoadMetric::default(),
+            top_cores: DEFAULT_TOP_CORES,
+            controller: TurboController::default(),
+            pid: TurboPidSettings::default(),
+        }
+    }
+}
+
+impl Default for TurboPidSettings {
+    fn default() -> Self {
+        Self {
+            target: TurboPidTarget::default(),
+            setpoint: DEFAULT_PID_SETPOINT,
+            kp: DEFAULT_PID_KP,
+            ki: DEFAULT_PID_KI,
+            kd: 0.0,
+        }
+    }
+}
+
+/// Frequency cap applied once the CPU reaches a temperature
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ThermalStep {
+    pub temp_celsius: f32,
+    pub max_freq_mhz: u32,
+}
+
+/// Fan duty cycle once the CPU reaches a temperature
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct FanCurvePoint {
+    pub temp_celsius: f32,
+    pub duty_percent: u8,
+}
+
+/// Check that fan curve points are in ascending order of temperature and
+/// within 0-100% duty
+pub fn validate_fan_curve(curve: &[FanCurvePoint]) -> Result<(), ConfigError> {
+    for pair in curve.windows(2) {
+        if pair[1].temp_celsius <= pair[0].temp_celsius {
+            return Err(ConfigError::Validation(format!(
+                "Fan curve points must be in ascending order of temperature ({}°C listed after {}°C)",
+                pair[1].temp_celsius, pair[0].temp_celsius
+            )));
         }
     }
+
+    if let Some(point) = curve.iter().find(|point| point.duty_percent > 100) {
+        return Err(ConfigError::Validation(format!(
+            "Fan duty cycle at {}°C ({}%) cannot be above 100%",
+            point.temp_celsius, point.duty_percent
+        )));
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ThermalConfig {
+    /// Temperature steps, in ascending order of temperature
+    #[serde(default)]
+    pub steps: Vec<ThermalStep>,
+    /// How far the temperature must fall below a step before its cap is released
+    #[serde(default = "default_thermal_hysteresis_celsius")]
+    pub hysteresis_celsius: f32,
+    /// Temperature at which the daemon injects idle time, or forces the minimum
+    /// frequency where idle injection is unavailable, until the CPU has cooled
+    /// `hysteresis_celsius` below it
+    pub emergency_celsius: Option<f32>,
+    /// Share of idle time injected during a thermal emergency
+    #[serde(default = "default_emergency_idle_percent")]
+    pub emergency_idle_percent: u8,
+}
+
+pub const DEFAULT_THERMAL_HYSTERESIS_CELSIUS: f32 = 5.0;
+pub const DEFAULT_EMERGENCY_IDLE_PERCENT: u8 = 50;
+
+default_const!(
+    default_emergency_idle_percent,
+    u8,
+    DEFAULT_EMERGENCY_IDLE_PERCENT
+);
+
+default_const!(
+    default_thermal_hysteresis_celsius,
+    f32,
+    DEFAULT_THERMAL_HYSTERESIS_CELSIUS
+);
+
+impl Default for ThermalConfig {
+    fn default() -> Self {
+        Self {
+            steps: Vec::new(),
+            hysteresis_celsius: DEFAULT_THERMAL_HYSTERESIS_CELSIUS,
+            emergency_celsius: None,
+            emergency_idle_percent: DEFAULT_EMERGENCY_IDLE_PERCENT,
+        }
+    }
+}
+
+impl ThermalConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.hysteresis_celsius < 0.0 {
+            return Err(ConfigError::Validation(format!(
+                "Thermal hysteresis ({}°C) cannot be negative",
+                self.hysteresis_celsius
+            )));
+        }
+
+        for pair in self.steps.windows(2) {
+            if pair[1].temp_celsius <= pair[0].temp_celsius {
+                return Err(ConfigError::Validation(format!(
+                    "Thermal steps must be in ascending order of temperature ({}°C listed after {}°C)",
+                    pair[1].temp_celsius, pair[0].temp_celsius
+                )));
+            }
+        }
+
+        if !(1..=100).contains(&self.emergency_idle_percent) {
+            return Err(ConfigError::Validation(format!(
+                "Emergency idle injection ({}%) must be between 1% and 100%",
+                self.emergency_idle_percent
+            )));
+        }
+
+        if let Some(step) = self.steps.iter().find(|step| step.max_freq_mhz == 0) {
+            return Err(ConfigError::Validation(format!(
+                "Thermal step at {}°C must cap the frequency above 0 MHz",
+                step.temp_celsius
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Breakpoints for `epp = "auto"`, which moves along the EPP ladder
+/// `power` → `balance_power` → `balance_performance` → `performance`
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct EppAutoSettings {
+    /// CPU usage (%) at which each step up the ladder is taken
+    #[serde(default = "default_epp_load_breakpoints")]
+    pub load_breakpoints: [f32; 3],
+    /// How far the usage must fall below a breakpoint before stepping back down
+    #[serde(default = "default_epp_load_hysteresis")]
+    pub load_hysteresis: f32,
+    /// Hold EPP at `power` once the CPU reaches this temperature
+    pub temp_threshold_high: Option<f32>,
+    /// How far the temperature must fall below `temp_threshold_high` before the ladder is used again
+    #[serde(default = "default_thermal_hysteresis_celsius")]
+    pub temp_hysteresis_celsius: f32,
+}
+
+pub const DEFAULT_EPP_LOAD_BREAKPOINTS: [f32; 3] = [25.0, 50.0, 75.0];
+pub const DEFAULT_EPP_LOAD_HYSTERESIS: f32 = 10.0;
+
+default_const!(
+    default_epp_load_breakpoints,
+    [f32; 3],
+    DEFAULT_EPP_LOAD_BREAKPOINTS
+);
+default_const!(
+    default_epp_load_hysteresis,
+    f32,
+    DEFAULT_EPP_LOAD_HYSTERESIS
+);
+
+impl Default for EppAutoSettings {
+    fn default() -> Self {
+        Self {
+            load_breakpoints: DEFAULT_EPP_LOAD_BREAKPOINTS,
+            load_hysteresis: DEFAULT_EPP_LOAD_HYSTERESIS,
+            temp_threshold_high: None,
+            temp_hysteresis_celsius: DEFAULT_THERMAL_HYSTERESIS_CELSIUS,
+        }
+    }
+}
+
+impl EppAutoSettings {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self
+            .load_breakpoints
+            .iter()
+            .any(|breakpoint| !(0.0..=100.0).contains(breakpoint))
+            || !self.load_breakpoints.is_sorted_by(|a, b| a < b)
+        {
+            return Err(ConfigError::Validation(format!(
+                "EPP load breakpoints {:?} must be ascending and between 0% and 100%",
+                self.load_breakpoints
+            )));
+        }
+
+        if self.load_hysteresis < 0.0 || self.temp_hysteresis_celsius < 0.0 {
+            return Err(ConfigError::Validation(
+                "EPP hysteresis values cannot be negative".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// How the daemon drives `scaling_setspeed` while the `userspace` governor is selected
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct UserspaceSettings {
+    /// Raise a core's frequency once its usage reaches this percentage
+    #[serde(default = "default_userspace_load_threshold_up")]
+    pub load_threshold_up: f32,
+    /// Lower a core's frequency once its usage falls to this percentage
+    #[serde(default = "default_userspace_load_threshold_down")]
+    pub load_threshold_down: f32,
+    #[serde(default = "default_userspace_step_up_mhz")]
+    pub step_up_mhz: u32,
+    #[serde(default = "default_userspace_step_down_mhz")]
+    pub step_down_mhz: u32,
+    /// How long usage must stay above `load_threshold_up` before each step up
+    #[serde(default)]
+    pub up_delay_sec: u64,
+    /// How long usage must stay below `load_threshold_down` before each step down
+    #[serde(default = "default_userspace_down_delay_sec")]
+    pub down_delay_sec: u64,
+}
+
+default_const!(default_userspace_load_threshold_up, f32, 70.0);
+default_const!(default_userspace_load_threshold_down, f32, 30.0);
+default_const!(default_userspace_step_up_mhz, u32, 400);
+default_const!(default_userspace_step_down_mhz, u32, 200);
+default_const!(default_userspace_down_delay_sec, u64, 3);
+
+impl Default for UserspaceSettings {
+    fn default() -> Self {
+        Self {
+            load_threshold_up: default_userspace_load_threshold_up(),
+            load_threshold_down: default_userspace_load_threshold_down(),
+            step_up_mhz: default_userspace_step_up_mhz(),
+            step_down_mhz: default_userspace_step_down_mhz(),
+            up_delay_sec: 0,
+            down_delay_sec: default_userspace_down_delay_sec(),
+        }
+    }
+}
+
+impl UserspaceSettings {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.load_threshold_down < 0.0
+            || self.load_threshold_up > 100.0
+            || self.load_threshold_down >= self.load_threshold_up
+        {
+            return Err(ConfigError::Validation(format!(
+                "Userspace load thresholds must be between 0% and 100% with up ({}%) > down ({}%)",
+                self.load_threshold_up, self.load_threshold_down
+            )));
+        }
+
+        if self.step_up_mhz == 0 || self.step_down_mhz == 0 {
+            return Err(ConfigError::Validation(
+                "Userspace frequency steps must be above 0 MHz".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Settings for one type of core on hybrid CPUs, or for the cores of one
+/// cpufreq policy, applied after the profile's own settings
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct CoreGroupConfig {
+    pub governor: Option<String>,
+    pub epp: Option<String>,
+    pub max_freq_mhz: Option<u32>,
+}
+
+/// The ID of a cpufreq policy named like its sysfs directory, e.g. `policy4`
+pub fn parse_policy_name(name: &str) -> Option<u32> {
+    name.strip_prefix("policy")?.parse().ok()
+}
+
+/// RAPL package power limits and the time windows they are averaged over
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RaplConfig {
+    /// Sustained power limit (PL1)
+    pub long_term_watts: Option<f32>,
+    /// Time window PL1 is averaged over (tau), which bounds how long boosts last
+    pub long_term_window_us: Option<u64>,
+    /// Boost power limit (PL2)
+    pub short_term_watts: Option<f32>,
+    pub short_term_window_us: Option<u64>,
+}
+
+impl RaplConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for watts in [self.long_term_watts, self.short_term_watts]
+            .into_iter()
+            .flatten()
+        {
+            if watts <= 0.0 {
+                return Err(ConfigError::Validation(format!(
+                    "RAPL power limit ({watts} W) must be above 0 W"
+                )));
+            }
+        }
+
+        if let (Some(long_term), Some(short_term)) = (self.long_term_watts, self.short_term_watts) {
+            if short_term < long_term {
+                return Err(ConfigError::Validation(format!(
+                    "RAPL short-term limit ({short_term} W) cannot be below the long-term limit ({long_term} W)"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Frequency limits of Intel integrated GPUs driven by i915, and the power
+/// limit of NVIDIA GPUs
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct GpuConfig {
+    pub gt_min_freq_mhz: Option<u32>,
+    pub gt_max_freq_mhz: Option<u32>,
+    /// Frequency the GPU jumps to when a client waits on it
+    pub gt_boost_freq_mhz: Option<u32>,
+    /// Set through NVML, needs the `nvml` feature
+    pub nvidia_power_limit_watts: Option<f32>,
+}
+
+impl GpuConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let (Some(min), Some(max)) = (self.gt_min_freq_mhz, self.gt_max_freq_mhz) {
+            if min > max {
+                return Err(ConfigError::Validation(format!(
+                    "GPU minimum frequency ({min} MHz) cannot be above the maximum ({max} MHz)"
+                )));
+            }
+        }
+
+        if let Some(watts) = self.nvidia_power_limit_watts {
+            if watts <= 0.0 {
+                return Err(ConfigError::Validation(format!(
+                    "NVIDIA power limit ({watts} W) must be above 0 W"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether any i915 frequency limit is set
+    pub const fn has_intel_limits(&self) -> bool {
+        self.gt_min_freq_mhz.is_some()
+            || self.gt_max_freq_mhz.is_some()
+            || self.gt_boost_freq_mhz.is_some()
+    }
+}
+
+/// Settings of the asus-wmi platform driver, for ASUS laptops whose firmware
+/// ignores the ACPI platform profile
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AsusConfig {
+    pub fan_boost_mode: Option<AsusMode>,
+    pub throttle_thermal_policy: Option<AsusMode>,
+    pub panel_od: Option<bool>,
+}
+
+/// What the daemon does when the battery reaches `critical_percent`
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CriticalAction {
+    Suspend,
+    #[default]
+    Hibernate,
+    /// Write a hibernation image, then suspend
+    HybridSleep,
+    /// Run `critical_script`
+    Script,
+}
+
+impl fmt::Display for CriticalAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Suspend => write!(f, "suspend"),
+            Self::Hibernate => write!(f, "hibernate"),
+            Self::HybridSleep => write!(f, "hybrid-sleep"),
+            Self::Script => write!(f, "script"),
+        }
+    }
+}
+
+/// Tunables of the `schedutil` governor
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SchedutilTunables {
+    /// Minimum time between two frequency changes
+    pub rate_limit_us: Option<u32>,
+}
+
+/// Tunables of the `ondemand` governor
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct OndemandTunables {
+    /// CPU usage percentage above which the frequency is raised to the maximum
+    pub up_threshold: Option<u32>,
+    pub sampling_rate_us: Option<u32>,
+    /// Multiplier of the sampling rate while at the maximum frequency
+    pub sampling_down_factor: Option<u32>,
+    /// Per mille the target frequency is lowered by
+    pub powersave_bias: Option<u32>,
+    /// Do not count the usage of niced processes
+    pub ignore_nice_load: Option<bool>,
+    /// Count time waiting for I/O as usage
+    pub io_is_busy: Option<bool>,
+}
+
+/// Tunables of the `conservative` governor
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ConservativeTunables {
+    /// CPU usage percentage above which the frequency is raised
+    pub up_threshold: Option<u32>,
+    /// CPU usage percentage below which the frequency is lowered
+    pub down_threshold: Option<u32>,
+    /// Percentage of the maximum frequency to change by at a time
+    pub freq_step: Option<u32>,
+    pub sampling_rate_us: Option<u32>,
+    pub sampling_down_factor: Option<u32>,
+    pub ignore_nice_load: Option<bool>,
+}
+
+/// Governor tunables, which are applied while their governor is in use
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct GovernorTunables {
+    #[serde(default)]
+    pub schedutil: SchedutilTunables,
+    #[serde(default)]
+    pub ondemand: OndemandTunables,
+    #[serde(default)]
+    pub conservative: ConservativeTunables,
+}
+
+impl GovernorTunables {
+    /// The tunables set for a governor as `(sysfs name, value)` pairs
+    pub fn for_governor(&self, governor: &str) -> Vec<(&'static str, String)> {
+        let number = |name, value: Option<u32>| value.map(|value| (name, value.to_string()));
+        let flag =
+            |name, value: Option<bool>| value.map(|value| (name, u8::from(value).to_string()));
+
+        let tunables = match governor {
+            "schedutil" => vec![number("rate_limit_us", self.schedutil.rate_limit_us)],
+            "ondemand" => {
+                let ondemand = &self.ondemand;
+                vec![
+                    number("up_threshold", ondemand.up_threshold),
+                    number("sampling_rate", ondemand.sampling_rate_us),
+                    number("sampling_down_factor", ondemand.sampling_down_factor),
+                    number("powersave_bias", ondemand.powersave_bias),
+                    flag("ignore_nice_load", ondemand.ignore_nice_load),
+                    flag("io_is_busy", ondemand.io_is_busy),
+                ]
+            }
+            "conservative" => {
+                let conservative = &self.conservative;
+                vec![
+                    number("up_threshold", conservative.up_threshold),
+                    number("down_threshold", conservative.down_threshold),
+                    number("freq_step", conservative.freq_step),
+                    number("sampling_rate", conservative.sampling_rate_us),
+                    number("sampling_down_factor", conservative.sampling_down_factor),
+                    flag("ignore_nice_load", conservative.ignore_nice_load),
+                ]
+            }
+            _ => Vec::new(),
+        };
+
+        tunables.into_iter().flatten().collect()
+    }
+
+    /// Check the ranges the kernel accepts for each tunable
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let check =
+            |name: &str, value: Option<u32>, range: std::ops::RangeInclusive<u32>| match value {
+                Some(value) if !range.contains(&value) => Err(ConfigError::Validation(format!(
+                    "Governor tunable {name} ({value}) must be between {} and {}",
+                    range.start(),
+                    range.end()
+                ))),
+                _ => Ok(()),
+            };
+
+        check("ondemand.up_threshold", self.ondemand.up_threshold, 1..=100)?;
+        check(
+            "ondemand.sampling_rate_us",
+            self.ondemand.sampling_rate_us,
+            1..=u32::MAX,
+        )?;
+        check(
+            "ondemand.sampling_down_factor",
+            self.ondemand.sampling_down_factor,
+            1..=100_000,
+        )?;
+        check(
+            "ondemand.powersave_bias",
+            self.ondemand.powersave_bias,
+            0..=1000,
+        )?;
+        check(
+            "conservative.up_threshold",
+            self.conservative.up_threshold,
+            1..=100,
+        )?;
+        check(
+            "conservative.down_threshold",
+            self.conservative.down_threshold,
+            1..=99,
+        )?;
+        check(
+            "conservative.freq_step",
+            self.conservative.freq_step,
+            0..=100,
+        )?;
+        check(
+            "conservative.sampling_rate_us",
+            self.conservative.sampling_rate_us,
+            1..=u32::MAX,
+        )?;
+        check(
+            "conservative.sampling_down_factor",
+            self.conservative.sampling_down_factor,
+            1..=10,
+        )?;
+
+        if let (Some(up), Some(down)) = (
+            self.conservative.up_threshold,
+            self.conservative.down_threshold,
+        ) {
+            if down >= up {
+                return Err(ConfigError::Validation(format!(
+                    "Governor tunable conservative.down_threshold ({down}) must be below up_threshold ({up})"
+                )));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl From<ProfileConfigToml> for ProfileConfig {
@@ -226,6 +1398,29 @@ impl From<ProfileConfigToml> for ProfileConfig {
             turbo_auto_settings: toml_config.turbo_auto_settings.unwrap_or_default(),
             enable_auto_turbo: toml_config.enable_auto_turbo,
             battery_charge_thresholds: toml_config.battery_charge_thresholds,
+            charge_behaviour: toml_config.charge_behaviour,
+            thermal: toml_config.thermal,
+            epp_auto_settings: toml_config.epp_auto_settings,
+            userspace: toml_config.userspace,
+            disabled_idle_states: toml_config.disabled_idle_states,
+            cpu_dma_latency_us: toml_config.cpu_dma_latency_us,
+            pcore: toml_config.pcore,
+            ecore: toml_config.ecore,
+            clusters: toml_config.clusters,
+            sched_itmt: toml_config.sched_itmt,
+            rapl: toml_config.rapl,
+            gpu: toml_config.gpu,
+            asus: toml_config.asus,
+            fan_curve: toml_config.fan_curve,
+            poll_interval_sec: toml_config.poll_interval_sec,
+            sysctl: toml_config.sysctl,
+            sysfs: toml_config.sysfs,
+            governor_tunables: toml_config.governor_tunables,
+            pstate_mode: toml_config.pstate_mode,
+            critical_percent: toml_config.critical_percent,
+            critical_action: toml_config.critical_action,
+            critical_script: toml_config.critical_script,
+            keyboard_backlight_percent: toml_config.keyboard_backlight_percent,
         }
     }
 }
@@ -246,6 +1441,119 @@ pub struct DaemonConfig {
     pub log_level: LogLevel,
     #[serde(default = "default_stats_file_path")]
     pub stats_file_path: Option<String>,
+    /// Format of the stats file: `text` or `json`
+    #[serde(default = "default_stats_file_format")]
+    pub stats_file_format: StatsFileFormat,
+    #[serde(default = "default_safe_mode_failures")]
+    pub safe_mode_failures: u32,
+    #[serde(default = "default_safe_mode_retry_sec")]
+    pub safe_mode_retry_sec: u64,
+    #[serde(default = "default_history_csv_path")]
+    pub history_csv_path: Option<String>,
+    /// Write the daemon log to this file instead of stderr
+    #[serde(default = "default_log_file_path")]
+    pub log_file_path: Option<String>,
+    /// Rotate the log file once it reaches this size
+    #[serde(default = "default_log_max_size_mb")]
+    pub log_max_size_mb: u64,
+    /// Rotate the log file once it is this old
+    #[serde(default = "default_log_max_age_days")]
+    pub log_max_age_days: Option<u64>,
+    /// Number of rotated log files to keep
+    #[serde(default = "default_log_max_files")]
+    pub log_max_files: u32,
+    /// Format of log records: `text` or `json`
+    #[serde(default = "default_log_format")]
+    pub log_format: LogFormat,
+    /// How user activity is detected for adaptive polling
+    #[serde(default = "default_activity_detection")]
+    pub activity_detection: ActivityDetection,
+    /// What to do when another power management service is running
+    #[serde(default = "default_on_conflict")]
+    pub on_conflict: OnConflict,
+    /// Revert governor and EPP changes made by other tools between polls
+    #[serde(default = "default_enforce")]
+    pub enforce: bool,
+    /// Whether the daemon applies settings or only logs what it would apply
+    #[serde(default = "default_mode")]
+    pub mode: DaemonMode,
+    /// Apply profiles inside virtual machines and containers, which are only
+    /// monitored by default
+    #[serde(default = "default_apply_virtualized")]
+    pub apply_virtualized: bool,
+    /// How long a keyboard backlight changed by hand is kept through profile switches
+    #[serde(default = "default_keyboard_backlight_grace_sec")]
+    pub keyboard_backlight_grace_sec: u64,
+    /// Switch to this user once the files the daemon writes are open
+    #[serde(default = "default_user")]
+    pub user: Option<String>,
+    /// Profile applied regardless of rules and the power state, set with
+    /// `superfreq daemon --profile`
+    #[serde(skip)]
+    pub profile: Option<String>,
+}
+
+/// Format of the daemon's log records
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// `[LEVEL module] message`
+    #[default]
+    Text,
+    /// One JSON object per line, for log aggregators
+    Json,
+}
+
+/// Format of the daemon's stats file
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsFileFormat {
+    /// `key=value` lines with a summary of the latest report
+    #[default]
+    Text,
+    /// The full latest report as a JSON object, with the daemon's own state
+    /// under `daemon`
+    Json,
+}
+
+/// Where the daemon learns about user activity, which lengthens the poll
+/// interval while the user is away
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityDetection {
+    /// Input devices if they can be read, then logind, then the heuristic
+    #[default]
+    Auto,
+    /// Time of the last keyboard, mouse or touchpad event
+    Input,
+    /// Idle hint of logind sessions, set by desktop environments
+    Logind,
+    /// Guess from CPU usage, temperature and load changes
+    Heuristic,
+}
+
+/// What the daemon does when it finds another power management service running
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OnConflict {
+    /// Log a warning for each conflicting service and keep running
+    #[default]
+    Warn,
+    /// Refuse to start
+    Abort,
+    /// Do not look for conflicting services
+    Ignore,
+}
+
+/// Whether the daemon changes settings
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DaemonMode {
+    /// Apply the selected profile
+    #[default]
+    Apply,
+    /// Only log what would be applied, without writing anything
+    Observe,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
@@ -266,6 +1574,23 @@ impl Default for DaemonConfig {
             throttle_on_battery: default_throttle_on_battery(),
             log_level: default_log_level(),
             stats_file_path: default_stats_file_path(),
+            stats_file_format: default_stats_file_format(),
+            safe_mode_failures: default_safe_mode_failures(),
+            safe_mode_retry_sec: default_safe_mode_retry_sec(),
+            history_csv_path: default_history_csv_path(),
+            log_file_path: default_log_file_path(),
+            log_max_size_mb: default_log_max_size_mb(),
+            log_max_age_days: default_log_max_age_days(),
+            log_max_files: default_log_max_files(),
+            log_format: default_log_format(),
+            activity_detection: default_activity_detection(),
+            on_conflict: default_on_conflict(),
+            enforce: default_enforce(),
+            mode: default_mode(),
+            apply_virtualized: default_apply_virtualized(),
+            keyboard_backlight_grace_sec: default_keyboard_backlight_grace_sec(),
+            user: default_user(),
+            profile: None,
         }
     }
 }
@@ -277,7 +1602,31 @@ default_const!(default_max_poll_interval_sec, u64, 30);
 default_const!(default_throttle_on_battery, bool, true);
 default_const!(default_log_level, LogLevel, LogLevel::Info);
 default_const!(default_stats_file_path, Option<String>, None);
+default_const!(
+    default_stats_file_format,
+    StatsFileFormat,
+    StatsFileFormat::Text
+);
+default_const!(default_history_csv_path, Option<String>, None);
+default_const!(default_safe_mode_failures, u32, 5);
+default_const!(default_safe_mode_retry_sec, u64, 300);
 default_const!(default_enable_auto_turbo, bool, true);
+default_const!(default_log_file_path, Option<String>, None);
+default_const!(default_log_max_size_mb, u64, 10);
+default_const!(default_log_max_age_days, Option<u64>, None);
+default_const!(default_log_max_files, u32, 5);
+default_const!(default_log_format, LogFormat, LogFormat::Text);
+default_const!(
+    default_activity_detection,
+    ActivityDetection,
+    ActivityDetection::Auto
+);
+default_const!(default_on_conflict, OnConflict, OnConflict::Warn);
+default_const!(default_enforce, bool, false);
+default_const!(default_mode, DaemonMode, DaemonMode::Apply);
+default_const!(default_apply_virtualized, bool, false);
+default_const!(default_keyboard_backlight_grace_sec, u64, 600);
+default_const!(default_user, Option<String>, None);
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct DaemonConfigToml {
@@ -295,6 +1644,52 @@ pub struct DaemonConfigToml {
     pub log_level: LogLevel,
     #[serde(default = "default_stats_file_path")]
     pub stats_file_path: Option<String>,
+    /// Format of the stats file: `text` or `json`
+    #[serde(default = "default_stats_file_format")]
+    pub stats_file_format: StatsFileFormat,
+    #[serde(default = "default_safe_mode_failures")]
+    pub safe_mode_failures: u32,
+    #[serde(default = "default_safe_mode_retry_sec")]
+    pub safe_mode_retry_sec: u64,
+    #[serde(default = "default_history_csv_path")]
+    pub history_csv_path: Option<String>,
+    /// Write the daemon log to this file instead of stderr
+    #[serde(default = "default_log_file_path")]
+    pub log_file_path: Option<String>,
+    /// Rotate the log file once it reaches this size
+    #[serde(default = "default_log_max_size_mb")]
+    pub log_max_size_mb: u64,
+    /// Rotate the log file once it is this old
+    #[serde(default = "default_log_max_age_days")]
+    pub log_max_age_days: Option<u64>,
+    /// Number of rotated log files to keep
+    #[serde(default = "default_log_max_files")]
+    pub log_max_files: u32,
+    /// Format of log records: `text` or `json`
+    #[serde(default = "default_log_format")]
+    pub log_format: LogFormat,
+    /// How user activity is detected for adaptive polling
+    #[serde(default = "default_activity_detection")]
+    pub activity_detection: ActivityDetection,
+    /// What to do when another power management service is running
+    #[serde(default = "default_on_conflict")]
+    pub on_conflict: OnConflict,
+    /// Revert governor and EPP changes made by other tools between polls
+    #[serde(default = "default_enforce")]
+    pub enforce: bool,
+    /// Whether the daemon applies settings or only logs what it would apply
+    #[serde(default = "default_mode")]
+    pub mode: DaemonMode,
+    /// Apply profiles inside virtual machines and containers, which are only
+    /// monitored by default
+    #[serde(default = "default_apply_virtualized")]
+    pub apply_virtualized: bool,
+    /// How long a keyboard backlight changed by hand is kept through profile switches
+    #[serde(default = "default_keyboard_backlight_grace_sec")]
+    pub keyboard_backlight_grace_sec: u64,
+    /// Switch to this user once the files the daemon writes are open
+    #[serde(default = "default_user")]
+    pub user: Option<String>,
 }
 
 impl Default for DaemonConfigToml {
@@ -307,6 +1702,443 @@ impl Default for DaemonConfigToml {
             throttle_on_battery: default_throttle_on_battery(),
             log_level: default_log_level(),
             stats_file_path: default_stats_file_path(),
+            stats_file_format: default_stats_file_format(),
+            safe_mode_failures: default_safe_mode_failures(),
+            safe_mode_retry_sec: default_safe_mode_retry_sec(),
+            history_csv_path: default_history_csv_path(),
+            log_file_path: default_log_file_path(),
+            log_max_size_mb: default_log_max_size_mb(),
+            log_max_age_days: default_log_max_age_days(),
+            log_max_files: default_log_max_files(),
+            log_format: default_log_format(),
+            activity_detection: default_activity_detection(),
+            on_conflict: default_on_conflict(),
+            enforce: default_enforce(),
+            mode: default_mode(),
+            apply_virtualized: default_apply_virtualized(),
+            keyboard_backlight_grace_sec: default_keyboard_backlight_grace_sec(),
+            user: default_user(),
+        }
+    }
+}
+
+/// Alerts raised when metrics change abnormally fast
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AlertConfig {
+    /// Alert when the battery drops by more than this many percent within 10 minutes
+    pub battery_drop_percent: Option<f32>,
+    /// Alert when the CPU temperature rises by more than this many °C within a minute
+    pub temperature_rise_celsius: Option<f32>,
+    /// Alert when the batteries discharge at more than this many watts
+    pub drain_watts: Option<f32>,
+    /// Alert when the battery discharges at more than this many percent per hour
+    pub drain_percent_per_hour: Option<f32>,
+    /// Profile to switch to after a drain alert, until AC is connected again
+    pub drain_profile: Option<String>,
+    /// Send a desktop notification in addition to logging the alert
+    #[serde(default)]
+    pub notify: bool,
+    /// Command run through `sh -c` for every alert
+    pub hook: Option<String>,
+    /// Minimum time between two alerts of the same kind
+    #[serde(default = "default_alert_cooldown_sec")]
+    pub cooldown_sec: u64,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            battery_drop_percent: None,
+            temperature_rise_celsius: None,
+            drain_watts: None,
+            drain_percent_per_hour: None,
+            drain_profile: None,
+            notify: false,
+            hook: None,
+            cooldown_sec: default_alert_cooldown_sec(),
+        }
+    }
+}
+
+default_const!(default_alert_cooldown_sec, u64, 600);
+
+/// How docking is detected for the `docked` rule condition
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DockConfig {
+    /// USB or Thunderbolt devices that mean the system is docked, as
+    /// `vendor:product` IDs in hex (e.g. `17ef:a396`)
+    #[serde(default)]
+    pub devices: Vec<String>,
+    /// Count a connected external display as docked
+    #[serde(default = "default_dock_external_displays")]
+    pub external_displays: bool,
+}
+
+impl Default for DockConfig {
+    fn default() -> Self {
+        Self {
+            devices: Vec::new(),
+            external_displays: default_dock_external_displays(),
+        }
+    }
+}
+
+impl DockConfig {
+    /// Parse a `vendor:product` device ID
+    pub fn parse_device_id(id: &str) -> Option<(u16, u16)> {
+        let (vendor, product) = id.split_once(':')?;
+        Some((
+            u16::from_str_radix(vendor.trim(), 16).ok()?,
+            u16::from_str_radix(product.trim(), 16).ok()?,
+        ))
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for id in &self.devices {
+            if Self::parse_device_id(id).is_none() {
+                return Err(ConfigError::Validation(format!(
+                    "Invalid dock device '{id}', expected a vendor:product ID such as 17ef:a396"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+default_const!(default_dock_external_displays, bool, true);
+
+/// Local REST API served by the daemon. Requires the `http` cargo feature.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HttpConfig {
+    #[serde(default)]
+    pub enable: bool,
+    /// A loopback address such as `127.0.0.1:8378`, or the path of a unix socket
+    #[serde(default = "default_http_listen")]
+    pub listen: String,
+    /// Token clients must send as `Authorization: Bearer <token>`. Required
+    /// when listening on TCP, where any local user can connect.
+    pub token: Option<String>,
+    /// Group allowed to connect to the unix socket. Without it only root can.
+    pub group: Option<String>,
+}
+
+fn default_http_listen() -> String {
+    "127.0.0.1:8378".to_string()
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            listen: default_http_listen(),
+            token: None,
+            group: None,
+        }
+    }
+}
+
+impl HttpConfig {
+    /// Whether `listen` is the path of a unix socket rather than a TCP address
+    pub fn is_unix_socket(&self) -> bool {
+        self.listen.starts_with('/')
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.token.as_deref().is_some_and(str::is_empty) {
+            return Err(ConfigError::Validation(
+                "http.token must not be empty".to_string(),
+            ));
+        }
+        if self.is_unix_socket() {
+            return Ok(());
+        }
+
+        let address: std::net::SocketAddr = self.listen.parse().map_err(|_| {
+            ConfigError::Validation(format!(
+                "Invalid http.listen '{}', expected an address such as 127.0.0.1:8378 or a socket path",
+                self.listen
+            ))
+        })?;
+        if !address.ip().is_loopback() {
+            return Err(ConfigError::Validation(format!(
+                "http.listen '{}' must be a loopback address",
+                self.listen
+            )));
+        }
+        if self.group.is_some() {
+            return Err(ConfigError::Validation(
+                "http.group only applies to a unix socket".to_string(),
+            ));
+        }
+        if self.enable && self.token.is_none() {
+            return Err(ConfigError::Validation(
+                "http.token is required when listening on TCP".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// MQTT telemetry for home automation. Requires the `mqtt` cargo feature.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MqttConfig {
+    #[serde(default)]
+    pub enable: bool,
+    /// Address of the broker
+    #[serde(default = "default_mqtt_host")]
+    pub host: String,
+    #[serde(default = "default_mqtt_port")]
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Topic the state is published under (default: `superfreq/<hostname>`)
+    pub topic_prefix: Option<String>,
+    /// Publish Home Assistant discovery messages
+    #[serde(default = "default_mqtt_discovery")]
+    pub discovery: bool,
+    #[serde(default = "default_mqtt_discovery_prefix")]
+    pub discovery_prefix: String,
+    #[serde(default = "default_mqtt_keep_alive_sec")]
+    pub keep_alive_sec: u16,
+}
+
+fn default_mqtt_host() -> String {
+    "localhost".to_string()
+}
+
+fn default_mqtt_discovery_prefix() -> String {
+    "homeassistant".to_string()
+}
+
+default_const!(default_mqtt_port, u16, 1883);
+default_const!(default_mqtt_discovery, bool, true);
+default_const!(default_mqtt_keep_alive_sec, u16, 60);
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            host: default_mqtt_host(),
+            port: default_mqtt_port(),
+            username: None,
+            password: None,
+            topic_prefix: None,
+            discovery: default_mqtt_discovery(),
+            discovery_prefix: default_mqtt_discovery_prefix(),
+            keep_alive_sec: default_mqtt_keep_alive_sec(),
+        }
+    }
+}
+
+impl MqttConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for (name, topic) in [
+            ("topic_prefix", self.topic_prefix.as_deref()),
+            ("discovery_prefix", Some(self.discovery_prefix.as_str())),
+        ] {
+            if topic.is_some_and(|topic| {
+                topic.is_empty() || topic.contains(['+', '#']) || topic.ends_with('/')
+            }) {
+                return Err(ConfigError::Validation(format!(
+                    "mqtt.{name} must be a topic without wildcards or a trailing '/'"
+                )));
+            }
+        }
+        if self.keep_alive_sec == 0 {
+            return Err(ConfigError::Validation(
+                "mqtt.keep_alive_sec must be at least 1".to_string(),
+            ));
+        }
+        if self.password.is_some() && self.username.is_none() {
+            return Err(ConfigError::Validation(
+                "mqtt.password requires mqtt.username".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// SQLite history of system reports recorded by the daemon
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HistoryConfig {
+    /// Record a report on every poll. Requires the `history` cargo feature.
+    #[serde(default)]
+    pub enable: bool,
+    #[serde(default = "default_history_database_path")]
+    pub database_path: String,
+    /// Reports older than this are deleted
+    #[serde(default = "default_history_retention_hours")]
+    pub retention_hours: u64,
+}
+
+pub const DEFAULT_HISTORY_DATABASE_PATH: &str = "/var/lib/superfreq/history.db";
+
+fn default_history_database_path() -> String {
+    DEFAULT_HISTORY_DATABASE_PATH.to_string()
+}
+
+default_const!(default_history_retention_hours, u64, 24 * 7);
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            database_path: default_history_database_path(),
+            retention_hours: default_history_retention_hours(),
+        }
+    }
+}
+
+/// Profiles selected through the power-profiles-daemon D-Bus interface,
+/// which desktop power sliders use
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PowerProfilesConfig {
+    /// Provide `net.hadess.PowerProfiles` on the system bus. Requires the `dbus` cargo feature.
+    #[serde(default)]
+    pub enable: bool,
+    /// Profile applied for `power-saver`
+    #[serde(default = "default_power_saver_profile")]
+    pub power_saver: String,
+    /// Profile applied for `balanced`. If unset, the profile is selected by
+    /// rules and the power state as usual.
+    #[serde(default)]
+    pub balanced: Option<String>,
+    /// Profile applied for `performance`
+    #[serde(default = "default_performance_profile")]
+    pub performance: String,
+    /// Also provide system76-power's `com.system76.PowerDaemon`, for the
+    /// Pop!_OS power menu. Requires the `dbus` cargo feature.
+    #[serde(default)]
+    pub system76: bool,
+}
+
+fn default_power_saver_profile() -> String {
+    "battery".to_string()
+}
+
+fn default_performance_profile() -> String {
+    "charger".to_string()
+}
+
+impl Default for PowerProfilesConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            power_saver: default_power_saver_profile(),
+            balanced: None,
+            performance: default_performance_profile(),
+            system76: false,
+        }
+    }
+}
+
+/// Desktop notifications sent by the daemon
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct NotificationConfig {
+    #[serde(default)]
+    pub enable: bool,
+    /// Notify when the daemon switches to another profile
+    #[serde(default = "default_notify_profile_changes")]
+    pub profile_changes: bool,
+    /// Notify when a thermal frequency cap or emergency takes effect
+    #[serde(default = "default_notify_thermal")]
+    pub thermal: bool,
+    /// Notify about configuration errors the daemon runs into
+    #[serde(default = "default_notify_errors")]
+    pub errors: bool,
+}
+
+default_const!(default_notify_profile_changes, bool, true);
+default_const!(default_notify_thermal, bool, true);
+default_const!(default_notify_errors, bool, true);
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            profile_changes: default_notify_profile_changes(),
+            thermal: default_notify_thermal(),
+            errors: default_notify_errors(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(value: &str) -> Result<TimeWindow, ConfigError> {
+        TimeWindow::try_from(value.to_string())
+    }
+
+    const fn minute(hours: u16, minutes: u16) -> u16 {
+        hours * 60 + minutes
+    }
+
+    #[test]
+    fn window_within_a_day() {
+        let window = window("09:30-17:00").unwrap();
+        assert!(!window.contains(minute(9, 29)));
+        assert!(window.contains(minute(9, 30)));
+        assert!(window.contains(minute(16, 59)));
+        assert!(!window.contains(minute(17, 0)));
+        assert!(!window.contains(0));
+    }
+
+    #[test]
+    fn window_across_midnight() {
+        let window = window("22:00-07:00").unwrap();
+        assert!(!window.contains(minute(21, 59)));
+        assert!(window.contains(minute(22, 0)));
+        assert!(window.contains(minute(23, 59)));
+        assert!(window.contains(0));
+        assert!(window.contains(minute(6, 59)));
+        assert!(!window.contains(minute(7, 0)));
+        assert!(!window.contains(minute(12, 0)));
+    }
+
+    #[test]
+    fn window_with_equal_start_and_end_is_empty() {
+        for value in ["00:00-00:00", "12:00-12:00"] {
+            let window = window(value).unwrap();
+            assert!((0..minute(24, 0)).all(|minute| !window.contains(minute)));
+        }
+    }
+
+    #[test]
+    fn malformed_windows_are_rejected() {
+        for value in [
+            "",
+            "09:00",
+            "0900-1700",
+            "09:00-",
+            "-17:00",
+            "24:00-07:00",
+            "22:00-07:60",
+            "ab:cd-07:00",
+            "09:00-17:00-18:00",
+            "09:-17:00",
+            "-1:00-07:00",
+        ] {
+            assert!(
+                matches!(window(value), Err(ConfigError::Validation(_))),
+                "{value:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn window_round_trips() {
+        for value in ["00:00-23:59", "22:00-07:00", "09:05-09:05"] {
+            assert_eq!(String::from(window(value).unwrap()), value);
         }
+        assert_eq!(String::from(window(" 9:5 - 17:0 ").unwrap()), "09:05-17:00");
     }
 }
@@ -0,0 +1,194 @@
+use log::debug;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// A power management tool that changes the same settings as superfreq
+#[derive(Debug)]
+pub struct KnownService {
+    /// Name of the tool, e.g. `TLP`
+    pub name: &'static str,
+    /// systemd unit the tool runs as
+    pub systemd_unit: &'static str,
+    /// OpenRC and runit service name
+    pub service: &'static str,
+    /// Process name (`/proc/<pid>/comm`, at most 15 characters) while it runs,
+    /// for tools that stay running rather than apply settings once
+    pub process: Option<&'static str>,
+    /// Settings both it and superfreq change
+    pub settings: &'static str,
+}
+
+/// How a conflicting service was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Detection {
+    Systemd,
+    OpenRc,
+    Runit,
+    /// A running process, with its PID
+    Process(u32),
+}
+
+/// A running service that fights superfreq over the same settings
+#[derive(Debug, Clone, Copy)]
+pub struct Conflict {
+    pub service: &'static KnownService,
+    pub detection: Detection,
+}
+
+impl std::fmt::Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let service = self.service;
+        match self.detection {
+            Detection::Systemd => write!(f, "{} ({})", service.name, service.systemd_unit)?,
+            Detection::OpenRc => {
+                write!(f, "{} (OpenRC service {})", service.name, service.service)?
+            }
+            Detection::Runit => write!(f, "{} (runit service {})", service.name, service.service)?,
+            Detection::Process(pid) => write!(f, "{} (process {pid})", service.name)?,
+        }
+        write!(f, " also manages {}", service.settings)
+    }
+}
+
+/// Services known to fight superfreq over CPU and battery settings
+const KNOWN_SERVICES: &[KnownService] = &[
+    KnownService {
+        name: "TLP",
+        systemd_unit: "tlp.service",
+        service: "tlp",
+        process: None,
+        settings: "the governor, EPP, turbo and charge thresholds",
+    },
+    KnownService {
+        name: "power-profiles-daemon",
+        systemd_unit: "power-profiles-daemon.service",
+        service: "power-profiles-daemon",
+        process: Some("power-profiles-"),
+        settings: "EPP, turbo and the platform profile",
+    },
+    KnownService {
+        name: "TuneD",
+        systemd_unit: "tuned.service",
+        service: "tuned",
+        process: Some("tuned"),
+        settings: "the governor, EPP and turbo",
+    },
+    KnownService {
+        name: "auto-cpufreq",
+        systemd_unit: "auto-cpufreq.service",
+        service: "auto-cpufreq",
+        process: Some("auto-cpufreq"),
+        settings: "the governor, EPP and turbo",
+    },
+    KnownService {
+        name: "system76-power",
+        systemd_unit: "system76-power.service",
+        service: "system76-power",
+        process: Some("system76-power"),
+        settings: "the governor, turbo and charge thresholds",
+    },
+    KnownService {
+        name: "laptop-mode-tools",
+        systemd_unit: "laptop-mode.service",
+        service: "laptop_mode",
+        process: None,
+        settings: "the governor and CPU frequencies",
+    },
+];
+
+/// Where OpenRC links the services it has started
+const OPENRC_STARTED_DIR: &str = "/run/openrc/started";
+
+/// Service directories supervised by runsvdir on Void, Artix and others
+const RUNIT_SERVICE_DIRS: &[&str] = &["/var/service", "/run/runit/service", "/etc/service"];
+
+/// Find power management services that are running alongside superfreq.
+/// Services are looked up through systemd, OpenRC and runit, and finally in
+/// the process table, so each tool is reported once, by the first that finds it.
+pub fn detect_conflicts() -> Vec<Conflict> {
+    let running_processes = running_processes();
+
+    KNOWN_SERVICES
+        .iter()
+        .zip(systemd_active_units())
+        .filter_map(|(service, systemd_active)| {
+            let detection = if systemd_active {
+                Detection::Systemd
+            } else if is_openrc_started(service.service) {
+                Detection::OpenRc
+            } else if is_runit_running(service.service) {
+                Detection::Runit
+            } else {
+                let process = service.process?;
+                let pid = running_processes
+                    .iter()
+                    .find(|(_, comm)| comm == process)?
+                    .0;
+                Detection::Process(pid)
+            };
+
+            Some(Conflict { service, detection })
+        })
+        .collect()
+}
+
+/// Whether each of the known services is active according to systemd, in the
+/// same order. All are inactive if systemd is not running.
+fn systemd_active_units() -> Vec<bool> {
+    let inactive = vec![false; KNOWN_SERVICES.len()];
+
+    let output = Command::new("systemctl")
+        .arg("is-active")
+        .args(KNOWN_SERVICES.iter().map(|service| service.systemd_unit))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output();
+
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => {
+            debug!("Cannot run systemctl to look for conflicting services: {e}");
+            return inactive;
+        }
+    };
+
+    // One state per unit, in the order they were given. The command fails
+    // if any unit is inactive, so its exit status says nothing here.
+    let mut active: Vec<bool> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|state| matches!(state.trim(), "active" | "activating" | "reloading"))
+        .collect();
+    active.resize(inactive.len(), false);
+    active
+}
+
+fn is_openrc_started(service: &str) -> bool {
+    Path::new(OPENRC_STARTED_DIR).join(service).exists()
+}
+
+/// Whether runsv reports the service as up. Its `supervise/stat` holds e.g.
+/// `run`, `down` or `finish`.
+fn is_runit_running(service: &str) -> bool {
+    RUNIT_SERVICE_DIRS.iter().any(|dir| {
+        fs::read_to_string(Path::new(dir).join(service).join("supervise/stat"))
+            .is_ok_and(|stat| stat.trim() == "run")
+    })
+}
+
+/// PID and process name of every running process
+fn running_processes() -> Vec<(u32, String)> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid = entry.file_name().to_str()?.parse().ok()?;
+            // The process may have exited since the directory was listed
+            let comm = fs::read_to_string(entry.path().join("comm")).ok()?;
+            Some((pid, comm.trim_end().to_string()))
+        })
+        .collect()
+}
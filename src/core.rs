@@ -1,5 +1,8 @@
+//! Types shared by the monitor, the engine and the CLI.
+
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
@@ -9,6 +12,106 @@ pub enum TurboSetting {
     Never,  // turbo is forced off
 }
 
+/// Mechanisms for controlling turbo boost, tried in a configurable order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BoostControl {
+    IntelPstate,          // intel_pstate/no_turbo (inverted)
+    AmdPstate,            // amd_pstate/cpufreq/boost
+    AmdPstateEnableBoost, // cpufreq/amd_pstate_enable_boost
+    Global,               // cpufreq/boost
+    PerPolicy,            // cpufreq/policy*/boost
+}
+
+impl fmt::Display for BoostControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IntelPstate => write!(f, "intel_pstate"),
+            Self::AmdPstate => write!(f, "amd_pstate"),
+            Self::AmdPstateEnableBoost => write!(f, "amd_pstate_enable_boost"),
+            Self::Global => write!(f, "global"),
+            Self::PerPolicy => write!(f, "per_policy"),
+        }
+    }
+}
+
+/// Kernel battery `charge_behaviour` modes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChargeBehaviour {
+    Auto,           // charge normally
+    InhibitCharge,  // do not charge while on AC
+    ForceDischarge, // discharge even while on AC
+}
+
+impl fmt::Display for ChargeBehaviour {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto => write!(f, "auto"),
+            Self::InhibitCharge => write!(f, "inhibit-charge"),
+            Self::ForceDischarge => write!(f, "force-discharge"),
+        }
+    }
+}
+
+/// Operating modes of the `intel_pstate` scaling driver
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum PstateMode {
+    Active,  // the driver picks frequencies, with its own powersave and performance
+    Passive, // runs as intel_cpufreq under the generic governors
+    Off,     // leaves frequency scaling to another driver, if one is loaded
+}
+
+impl fmt::Display for PstateMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Active => write!(f, "active"),
+            Self::Passive => write!(f, "passive"),
+            Self::Off => write!(f, "off"),
+        }
+    }
+}
+
+/// Modes of the asus-wmi `fan_boost_mode` and `throttle_thermal_policy` attributes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AsusMode {
+    Normal,    // the firmware's balanced default
+    Overboost, // higher power limits and fan speeds
+    Silent,    // lower power limits and fan speeds
+}
+
+impl AsusMode {
+    /// The value the asus-wmi attributes use for the mode
+    pub const fn sysfs_value(self) -> &'static str {
+        match self {
+            Self::Normal => "0",
+            Self::Overboost => "1",
+            Self::Silent => "2",
+        }
+    }
+
+    pub fn from_sysfs_value(value: &str) -> Option<Self> {
+        match value {
+            "0" => Some(Self::Normal),
+            "1" => Some(Self::Overboost),
+            "2" => Some(Self::Silent),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AsusMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Normal => write!(f, "normal"),
+            Self::Overboost => write!(f, "overboost"),
+            Self::Silent => write!(f, "silent"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum)]
 pub enum GovernorOverrideMode {
     Performance,
@@ -26,13 +129,68 @@ impl fmt::Display for GovernorOverrideMode {
     }
 }
 
+#[derive(Serialize)]
 pub struct SystemInfo {
     // Overall system details
     pub cpu_model: String,
     pub architecture: String,
     pub linux_distribution: String,
+    pub virtualization: Option<Virtualization>, // None on bare metal
+}
+
+/// Whether the system runs as a virtual machine or inside a container
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VirtualizationKind {
+    VirtualMachine,
+    Container,
+}
+
+/// The virtualization the system runs under, named the way
+/// `systemd-detect-virt` names it, e.g. `kvm` or `docker`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Virtualization {
+    pub kind: VirtualizationKind,
+    pub technology: String,
+}
+
+impl fmt::Display for Virtualization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            VirtualizationKind::VirtualMachine => write!(f, "{} virtual machine", self.technology),
+            VirtualizationKind::Container => write!(f, "{} container", self.technology),
+        }
+    }
+}
+
+/// Kind of core on hybrid CPUs (e.g. Intel P-cores and E-cores)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoreType {
+    Performance,
+    Efficiency,
+}
+
+impl CoreType {
+    /// Short label, `P` or `E`
+    pub const fn short_name(self) -> &'static str {
+        match self {
+            Self::Performance => "P",
+            Self::Efficiency => "E",
+        }
+    }
 }
 
+impl fmt::Display for CoreType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Performance => write!(f, "performance"),
+            Self::Efficiency => write!(f, "efficiency"),
+        }
+    }
+}
+
+#[derive(Serialize)]
 pub struct CpuCoreInfo {
     // Per-core data
     pub core_id: u32,
@@ -41,8 +199,133 @@ pub struct CpuCoreInfo {
     pub max_frequency_mhz: Option<u32>,
     pub usage_percent: Option<f32>,
     pub temperature_celsius: Option<f32>,
+    pub core_type: Option<CoreType>, // only set on hybrid CPUs
+}
+
+/// A logical CPU and where it sits in the topology
+#[derive(Debug, Clone, Serialize)]
+pub struct CpuThread {
+    pub cpu: u32,
+    pub package_id: Option<u32>,
+    pub core_id: Option<u32>,        // physical core within the package
+    pub thread_siblings: Vec<u32>,   // logical CPUs on the same physical core
+    pub cpufreq_policy: Option<u32>, // ID of the cpufreq policy controlling this CPU
+    pub last_level_cache: Vec<u32>,  // logical CPUs sharing the last-level cache
+}
+
+/// A cpufreq policy and the logical CPUs it controls
+#[derive(Debug, Clone, Serialize)]
+pub struct CpufreqPolicy {
+    pub id: u32,
+    pub cpus: Vec<u32>,
 }
 
+/// A cooling device the kernel engages at a trip point, e.g. a fan or the
+/// processor frequency limit
+#[derive(Debug, Clone, Serialize)]
+pub struct CoolingDevice {
+    pub id: u32,
+    #[serde(rename = "type")]
+    pub device_type: String,
+    pub cur_state: Option<u64>,
+    pub max_state: Option<u64>,
+}
+
+/// A temperature at which the kernel's thermal framework acts on a zone
+#[derive(Debug, Clone, Serialize)]
+pub struct TripPoint {
+    pub id: u32,
+    /// `active` (fans), `passive` (throttling), `hot` or `critical` (shutdown)
+    #[serde(rename = "type")]
+    pub trip_type: String,
+    pub temperature_celsius: Option<f32>,
+    pub hysteresis_celsius: Option<f32>,
+    pub cooling_devices: Vec<CoolingDevice>,
+}
+
+/// A thermal zone from `/sys/class/thermal`
+#[derive(Debug, Clone, Serialize)]
+pub struct ThermalZone {
+    pub id: u32,
+    #[serde(rename = "type")]
+    pub zone_type: String,
+    pub temperature_celsius: Option<f32>,
+    /// `enabled` or `disabled`
+    pub mode: Option<String>,
+    /// Governor deciding how cooling devices are engaged, e.g. `step_wise`
+    pub policy: Option<String>,
+    pub trip_points: Vec<TripPoint>,
+}
+
+impl ThermalZone {
+    /// The lowest trip point the zone has not reached yet
+    pub fn next_trip_point(&self) -> Option<&TripPoint> {
+        let temperature = self.temperature_celsius?;
+        self.trip_points
+            .iter()
+            .filter_map(|trip| Some((trip, trip.temperature_celsius?)))
+            .filter(|&(_, trip_temperature)| trip_temperature > temperature)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(trip, _)| trip)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CpuTopology {
+    pub threads: Vec<CpuThread>,
+    pub policies: Vec<CpufreqPolicy>,
+}
+
+impl CpuTopology {
+    pub fn package_count(&self) -> usize {
+        let mut packages: Vec<u32> = self.threads.iter().filter_map(|t| t.package_id).collect();
+        packages.sort_unstable();
+        packages.dedup();
+        packages.len()
+    }
+
+    /// Number of physical cores across all packages
+    pub fn core_count(&self) -> usize {
+        let mut cores: Vec<(Option<u32>, u32)> = self
+            .threads
+            .iter()
+            .filter_map(|t| Some((t.package_id, t.core_id?)))
+            .collect();
+        cores.sort_unstable();
+        cores.dedup();
+        cores.len()
+    }
+
+    pub fn thread_count(&self) -> usize {
+        self.threads.len()
+    }
+
+    pub fn policy(&self, id: u32) -> Option<&CpufreqPolicy> {
+        self.policies.iter().find(|policy| policy.id == id)
+    }
+
+    /// The cpufreq policy that covers a CPU
+    pub fn policy_of(&self, cpu: u32) -> Option<&CpufreqPolicy> {
+        self.policies
+            .iter()
+            .find(|policy| policy.cpus.contains(&cpu))
+    }
+
+    /// One CPU of each cpufreq policy that covers any of `cpus`, in the order
+    /// of `cpus`. CPUs without a policy are returned as they are.
+    pub fn policy_representatives(&self, cpus: &[u32]) -> Vec<u32> {
+        let mut seen = BTreeSet::new();
+        cpus.iter()
+            .copied()
+            .filter(|&cpu| {
+                self.policy_of(cpu)
+                    .is_none_or(|policy| seen.insert(policy.id))
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize)]
 pub struct CpuGlobalInfo {
     // System-wide CPU settings
     pub current_governor: Option<String>,
@@ -51,9 +334,38 @@ pub struct CpuGlobalInfo {
     pub epp: Option<String>,        // Energy Performance Preference
     pub epb: Option<String>,        // Energy Performance Bias
     pub platform_profile: Option<String>,
+    pub available_platform_profiles: Vec<String>,
+    pub scaling_drivers: BTreeMap<u32, String>, // cpufreq scaling driver by policy ID
+    pub pstate_mode: Option<String>,            // intel_pstate/amd-pstate mode, e.g. "active"
     pub average_temperature_celsius: Option<f32>, // Average temperature across all cores
 }
 
+impl CpuGlobalInfo {
+    /// The scaling driver and P-state mode, e.g. `intel_pstate (active)`. Each
+    /// policy is listed if they differ, e.g. `policy0: intel_pstate, policy4: acpi-cpufreq`.
+    pub fn scaling_driver_summary(&self) -> Option<String> {
+        let mut drivers: Vec<&String> = self.scaling_drivers.values().collect();
+        drivers.dedup();
+
+        let summary = match drivers.as_slice() {
+            [] => return None,
+            [driver] => (*driver).clone(),
+            _ => self
+                .scaling_drivers
+                .iter()
+                .map(|(policy, driver)| format!("policy{policy}: {driver}"))
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+
+        Some(match &self.pstate_mode {
+            Some(mode) => format!("{summary} ({mode})"),
+            None => summary,
+        })
+    }
+}
+
+#[derive(Serialize)]
 pub struct BatteryInfo {
     // Battery status (AC connected, charging state, capacity, power rate, charge start/stop thresholds if available).
     pub name: String,
@@ -63,8 +375,17 @@ pub struct BatteryInfo {
     pub power_rate_watts: Option<f32>, // positive for charging, negative for discharging
     pub charge_start_threshold: Option<u8>,
     pub charge_stop_threshold: Option<u8>,
+    pub charge_behaviour: Option<String>, // active kernel charge_behaviour, e.g. "auto"
+    pub cycle_count: Option<u32>,
+    pub energy_full_wh: Option<f32>,        // current full capacity
+    pub energy_full_design_wh: Option<f32>, // capacity when new
+    pub health_percent: Option<f32>,        // full capacity relative to design capacity
+    pub voltage_volts: Option<f32>,
+    pub manufacturer: Option<String>,
+    pub model_name: Option<String>,
 }
 
+#[derive(Serialize)]
 pub struct SystemLoad {
     // System load averages.
     pub load_avg_1min: f32,
@@ -72,6 +393,156 @@ pub struct SystemLoad {
     pub load_avg_15min: f32,
 }
 
+/// Why data for a single core is missing from a report
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollectionErrorKind {
+    /// The core has no entry in /proc/stat (e.g. offline or hot-unplugged)
+    MissingCpuTimes,
+    /// Reading the core's data failed
+    ReadFailed(String),
+}
+
+impl fmt::Display for CollectionErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingCpuTimes => write!(f, "missing CPU time data"),
+            Self::ReadFailed(reason) => write!(f, "read failed: {reason}"),
+        }
+    }
+}
+
+/// A per-core data collection failure recorded in a report
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectionError {
+    pub core_id: u32,
+    pub kind: CollectionErrorKind,
+}
+
+/// A discrete NVIDIA GPU as NVML reports it
+#[derive(Debug, Clone, Serialize)]
+pub struct NvidiaGpuInfo {
+    pub index: u32,
+    pub name: String,
+    pub utilization_percent: Option<u32>,
+    pub memory_utilization_percent: Option<u32>,
+    pub temperature_celsius: Option<u32>,
+    pub power_watts: Option<f32>,
+    pub power_limit_watts: Option<f32>, // the limit the driver enforces
+}
+
+#[derive(Serialize)]
+pub struct PowerInfo {
+    // Power draw estimates, in watts
+    pub cpu_package_watts: Option<f32>, // RAPL package domains
+    pub gpu_watts: Option<f32>,         // GPU hwmon sensors and NVML
+    pub battery_discharge_watts: Option<f32>,
+    // Battery discharge rate when on battery (it covers the whole system),
+    // otherwise the sum of the components that could be measured
+    pub estimated_system_watts: Option<f32>,
+    pub charger_watts: Option<f32>, // rated power of the connected charger
+}
+
+/// Thermal and power limit throttle events counted by the CPU, summed over
+/// cores and packages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ThrottleCounts {
+    pub core_thermal: Option<u64>,
+    pub package_thermal: Option<u64>,
+    pub core_power_limit: Option<u64>,
+    pub package_power_limit: Option<u64>,
+}
+
+impl ThrottleCounts {
+    /// Events counted after `previous`. Counters restart when a CPU goes
+    /// offline, which reads as no new events.
+    pub fn since(&self, previous: &Self) -> Self {
+        let delta = |current: Option<u64>, previous: Option<u64>| {
+            Some(current?.saturating_sub(previous.unwrap_or(0)))
+        };
+        Self {
+            core_thermal: delta(self.core_thermal, previous.core_thermal),
+            package_thermal: delta(self.package_thermal, previous.package_thermal),
+            core_power_limit: delta(self.core_power_limit, previous.core_power_limit),
+            package_power_limit: delta(self.package_power_limit, previous.package_power_limit),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Serialize)]
+pub struct ThrottleInfo {
+    /// Events since boot
+    pub counts: ThrottleCounts,
+    /// Events since the previous report
+    pub deltas: ThrottleCounts,
+    /// Whether PROCHOT# is asserted, i.e. another component such as the
+    /// embedded controller or VRM is throttling the CPU (Intel, needs `msr`)
+    pub prochot_active: Option<bool>,
+}
+
+/// Throttling the Raspberry Pi firmware reports through `get_throttled`, both
+/// right now and at any point since boot
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PiThrottled {
+    pub under_voltage: bool,
+    pub arm_frequency_capped: bool,
+    pub throttled: bool,
+    pub soft_temp_limit: bool,
+    pub under_voltage_occurred: bool,
+    pub arm_frequency_capped_occurred: bool,
+    pub throttled_occurred: bool,
+    pub soft_temp_limit_occurred: bool,
+}
+
+impl PiThrottled {
+    /// Decode the bit field, whose low bits hold the current state and whose
+    /// bits from 16 up record what happened since boot
+    pub const fn from_bits(bits: u32) -> Self {
+        Self {
+            under_voltage: bits & (1 << 0) != 0,
+            arm_frequency_capped: bits & (1 << 1) != 0,
+            throttled: bits & (1 << 2) != 0,
+            soft_temp_limit: bits & (1 << 3) != 0,
+            under_voltage_occurred: bits & (1 << 16) != 0,
+            arm_frequency_capped_occurred: bits & (1 << 17) != 0,
+            throttled_occurred: bits & (1 << 18) != 0,
+            soft_temp_limit_occurred: bits & (1 << 19) != 0,
+        }
+    }
+}
+
+/// State of a Raspberry Pi as its firmware sees it, which the CPU's own
+/// counters and sensors do not show
+#[derive(Debug, Clone, Serialize)]
+pub struct RaspberryPiInfo {
+    pub model: String,
+    pub soc_temperature_celsius: Option<f32>,
+    pub arm_clock_mhz: Option<u32>, // the clock the firmware actually runs, after its own caps
+    pub throttled: Option<PiThrottled>,
+}
+
+/// A source of interrupts from `/proc/interrupts`, e.g. a device or the local timer
+#[derive(Debug, Clone, Serialize)]
+pub struct WakeupSource {
+    pub name: String,
+    pub per_second: f32,
+}
+
+/// Interrupts handled per second, each of which can wake a CPU from an idle
+/// state. Local timer interrupts count the timer wakeups.
+#[derive(Debug, Clone, Serialize)]
+pub struct WakeupInfo {
+    /// Across all CPUs, between the previous report and this one
+    pub per_second: f32,
+    /// The sources with the most wakeups, most first
+    pub top_sources: Vec<WakeupSource>,
+}
+
+#[derive(Serialize)]
 pub struct SystemReport {
     // Now combine all the above for a snapshot of the system state.
     pub system_info: SystemInfo,
@@ -79,11 +550,61 @@ pub struct SystemReport {
     pub cpu_global: CpuGlobalInfo,
     pub batteries: Vec<BatteryInfo>,
     pub system_load: SystemLoad,
-    pub timestamp: std::time::SystemTime, // so we know when the report was generated
+    pub power: PowerInfo,
+    pub throttle: ThrottleInfo,
+    pub wakeups: Option<WakeupInfo>, // None if /proc/interrupts is unreadable
+    pub raspberry_pi: Option<RaspberryPiInfo>, // None on anything but a Raspberry Pi
+    pub nvidia_gpus: Vec<NvidiaGpuInfo>, // empty without the nvml feature
+    pub lid_closed: Option<bool>,    // None if there is no lid switch
+    pub docked: bool,
+    pub collection_errors: Vec<CollectionError>, // cores whose data could not be collected
+    pub permission_denied: Vec<String>,          // files that could not be read without privileges
+    pub timestamp: std::time::SystemTime,        // so we know when the report was generated
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum OperationalMode {
     Powersave,
     Performance,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big_little() -> CpuTopology {
+        CpuTopology {
+            threads: Vec::new(),
+            policies: vec![
+                CpufreqPolicy {
+                    id: 0,
+                    cpus: vec![0, 1, 2, 3],
+                },
+                CpufreqPolicy {
+                    id: 4,
+                    cpus: vec![4, 5],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn one_cpu_per_policy_is_written() {
+        let topology = big_little();
+        assert_eq!(
+            topology.policy_representatives(&[1, 2, 5, 4, 3]),
+            vec![1, 5]
+        );
+    }
+
+    #[test]
+    fn cpus_without_policy_are_kept() {
+        let topology = big_little();
+        assert_eq!(
+            topology.policy_representatives(&[8, 0, 9, 1]),
+            vec![8, 0, 9]
+        );
+        assert!(topology.policy_of(8).is_none());
+        assert_eq!(topology.policy(4).map(|policy| policy.id), Some(4));
+    }
+}
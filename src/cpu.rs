@@ -1,8 +1,23 @@
-use crate::core::{GovernorOverrideMode, TurboSetting};
+//! Changing CPU settings through sysfs: governors, EPP and EPB, turbo,
+//! frequency limits, platform profiles and the `intel_pstate` mode.
+
+use crate::core::{BoostControl, CoreType, GovernorOverrideMode, PstateMode, TurboSetting};
+use crate::monitor;
+#[cfg(feature = "msr")]
+use crate::msr;
 use crate::util::error::ControlError;
+use crate::util::sysfs;
+use crate::vendor;
 use core::str;
 use log::debug;
-use std::{fs, io, path::Path, string::ToString};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    string::ToString,
+    sync::{Mutex, PoisonError},
+};
 
 pub type Result<T, E = ControlError> = std::result::Result<T, E>;
 
@@ -27,20 +42,11 @@ const EPP_FALLBACK_VALUES: &[&str] = &[
     "power",
 ];
 
+const PLATFORM_PROFILE_PATH: &str = "/sys/firmware/acpi/platform_profile";
+
 // Write a value to a sysfs file
 fn write_sysfs_value(path: impl AsRef<Path>, value: &str) -> Result<()> {
-    let p = path.as_ref();
-
-    fs::write(p, value).map_err(|e| {
-        let error_msg = format!("Path: {:?}, Value: '{}', Error: {}", p.display(), value, e);
-        match e.kind() {
-            io::ErrorKind::PermissionDenied => ControlError::PermissionDenied(error_msg),
-            io::ErrorKind::NotFound => {
-                ControlError::PathMissing(format!("Path '{}' does not exist", p.display()))
-            }
-            _ => ControlError::WriteError(error_msg),
-        }
-    })
+    sysfs::write_sysfs_value(path, value)
 }
 
 pub fn get_logical_core_count() -> Result<u32> {
@@ -50,7 +56,7 @@ pub fn get_logical_core_count() -> Result<u32> {
     // However, settings usually apply to cores with cpufreq.
     // Let's use a similar discovery to monitor's get_logical_core_count
     let mut num_cores: u32 = 0;
-    let path = Path::new("/sys/devices/system/cpu");
+    let path = sysfs::path("/sys/devices/system/cpu");
     if !path.exists() {
         return Err(ControlError::NotSupported(format!(
             "No logical cores found at {}.",
@@ -58,7 +64,7 @@ pub fn get_logical_core_count() -> Result<u32> {
         )));
     }
 
-    let entries = fs::read_dir(path)
+    let entries = fs::read_dir(&path)
         .map_err(|_| {
             ControlError::PermissionDenied(format!("Cannot read contents of {}.", path.display()))
         })?
@@ -91,13 +97,152 @@ pub fn get_logical_core_count() -> Result<u32> {
     Ok(num_cores)
 }
 
+/// Parse a kernel CPU list such as `0-3,8,10-11`
+pub fn parse_cpu_list(list: &str) -> Vec<u32> {
+    list.trim()
+        .split(',')
+        .filter(|part| !part.is_empty())
+        .flat_map(|part| match part.split_once('-') {
+            Some((start, end)) => match (start.parse::<u32>(), end.parse::<u32>()) {
+                (Ok(start), Ok(end)) => (start..=end).collect(),
+                _ => Vec::new(),
+            },
+            None => part.parse().into_iter().collect(),
+        })
+        .collect()
+}
+
+/// Detect the type of each core on hybrid CPUs. Returns an empty map on CPUs
+/// whose cores are all of the same type.
+///
+/// Intel hybrid CPUs list their cores in `/sys/devices/cpu_core/cpus` and
+/// `/sys/devices/cpu_atom/cpus`. Elsewhere cores with less than the highest
+/// `cpu_capacity` are treated as efficiency cores.
+pub fn get_core_types() -> BTreeMap<u32, CoreType> {
+    let pcores = fs::read_to_string(sysfs::path("/sys/devices/cpu_core/cpus"));
+    let ecores = fs::read_to_string(sysfs::path("/sys/devices/cpu_atom/cpus"));
+    if let (Ok(pcores), Ok(ecores)) = (pcores, ecores) {
+        return parse_cpu_list(&pcores)
+            .into_iter()
+            .map(|id| (id, CoreType::Performance))
+            .chain(
+                parse_cpu_list(&ecores)
+                    .into_iter()
+                    .map(|id| (id, CoreType::Efficiency)),
+            )
+            .collect();
+    }
+
+    let Ok(num_cores) = get_logical_core_count() else {
+        return BTreeMap::new();
+    };
+    let capacities: BTreeMap<u32, u32> = (0..num_cores)
+        .filter_map(|id| {
+            let capacity = fs::read_to_string(sysfs::path(format!(
+                "/sys/devices/system/cpu/cpu{id}/cpu_capacity"
+            )))
+            .ok()?;
+            Some((id, capacity.trim().parse().ok()?))
+        })
+        .collect();
+
+    let (Some(&max), Some(&min)) = (capacities.values().max(), capacities.values().min()) else {
+        return BTreeMap::new();
+    };
+    if max == min {
+        return BTreeMap::new();
+    }
+
+    capacities
+        .into_iter()
+        .map(|(id, capacity)| {
+            let core_type = if capacity == max {
+                CoreType::Performance
+            } else {
+                CoreType::Efficiency
+            };
+            (id, core_type)
+        })
+        .collect()
+}
+
+/// IDs of the cores of a type, empty on CPUs that are not hybrid
+pub fn cores_of_type(core_type: CoreType) -> Vec<u32> {
+    get_core_types()
+        .into_iter()
+        .filter(|(_, t)| *t == core_type)
+        .map(|(id, _)| id)
+        .collect()
+}
+
+/// Cores that are managed elsewhere, e.g. isolated real-time cores
+static IGNORED_CORES: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+
+/// Set the cores that settings are not applied to when no core is given
+pub fn set_ignored_cores(cores: &[u32]) {
+    *IGNORED_CORES.lock().unwrap_or_else(PoisonError::into_inner) = cores.iter().copied().collect();
+}
+
+/// Whether settings are applied to a core when no core is given
+pub fn is_core_ignored(core_id: u32) -> bool {
+    IGNORED_CORES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .contains(&core_id)
+}
+
+/// Run `action` once per cpufreq policy, on one CPU of each. Attributes under
+/// `cpufreq/` are shared by all CPUs of a policy, so writing them through every
+/// CPU would be redundant. Falls back to every core if no policies are found.
+/// Policies that include an ignored core are skipped, as writing them would
+/// change that core too.
+fn for_each_cpufreq_policy<F>(mut action: F) -> Result<()>
+where
+    F: FnMut(u32) -> Result<()>,
+{
+    let topology = monitor::get_cpu_topology();
+    if topology.policies.is_empty() {
+        return for_each_cpu_core(action);
+    }
+
+    for policy in &topology.policies {
+        if policy.cpus.iter().any(|&cpu| is_core_ignored(cpu)) {
+            debug!(
+                "Skipping cpufreq policy{}, it includes an ignored core",
+                policy.id
+            );
+            continue;
+        }
+        if let Some(&core_id) = policy.cpus.first() {
+            action(core_id)?;
+        }
+    }
+    Ok(())
+}
+
+/// One CPU of each cpufreq policy that covers any of `cores`, so that settings
+/// for a group of cores are written once per policy. On ARM big.LITTLE systems a
+/// policy usually covers a whole cluster. Cores without a policy are returned
+/// as they are.
+pub fn policy_cores(cores: &[u32]) -> Vec<u32> {
+    monitor::get_cpu_topology().policy_representatives(cores)
+}
+
+/// The CPUs of a cpufreq policy, e.g. the cores of one cluster
+pub fn policy_cpus(policy_id: u32) -> Result<Vec<u32>> {
+    monitor::get_cpu_topology()
+        .policy(policy_id)
+        .map(|policy| policy.cpus.clone())
+        .ok_or_else(|| ControlError::NotSupported(format!("cpufreq policy{policy_id} not found")))
+}
+
 fn for_each_cpu_core<F>(mut action: F) -> Result<()>
 where
     F: FnMut(u32) -> Result<()>,
 {
     let num_cores: u32 = get_logical_core_count()?;
 
-    for core_id in 0u32..num_cores {
+    for core_id in (0u32..num_cores).filter(|&id| !is_core_ignored(id)) {
         action(core_id)?;
     }
     Ok(())
@@ -117,7 +262,9 @@ pub fn set_governor(governor: &str, core_id: Option<u32>) -> Result<()> {
     }
 
     let action = |id: u32| {
-        let path = format!("/sys/devices/system/cpu/cpu{id}/cpufreq/scaling_governor");
+        let path = sysfs::path(format!(
+            "/sys/devices/system/cpu/cpu{id}/cpufreq/scaling_governor"
+        ));
         if Path::new(&path).exists() {
             write_sysfs_value(&path, governor)
         } else {
@@ -127,7 +274,115 @@ pub fn set_governor(governor: &str, core_id: Option<u32>) -> Result<()> {
         }
     };
 
-    core_id.map_or_else(|| for_each_cpu_core(action), action)
+    core_id.map_or_else(|| for_each_cpufreq_policy(action), action)
+}
+
+const INTEL_PSTATE_STATUS_PATH: &str = "/sys/devices/system/cpu/intel_pstate/status";
+
+/// Whether `intel_pstate` is the scaling driver, so its mode can be switched
+pub fn is_intel_pstate_available() -> bool {
+    sysfs::path(INTEL_PSTATE_STATUS_PATH).exists()
+}
+
+/// Switch the operating mode of `intel_pstate`. The governors on offer change
+/// with it: active mode only has `performance` and `powersave`, while passive
+/// mode has the generic governors (`schedutil`, ...) but no EPP. Turning the
+/// driver off is refused by the kernel while hardware P-states (HWP) are on.
+pub fn set_intel_pstate_mode(mode: PstateMode) -> Result<()> {
+    let path = sysfs::path(INTEL_PSTATE_STATUS_PATH);
+    if !path.exists() {
+        return Err(ControlError::NotSupported(
+            "intel_pstate is not the scaling driver".to_string(),
+        ));
+    }
+
+    let current = fs::read_to_string(&path)
+        .map_err(|e| ControlError::ReadError(format!("Failed to read {}: {e}", path.display())))?;
+    if current.trim() == mode.to_string() {
+        return Ok(());
+    }
+
+    if sysfs::is_observe_only() {
+        debug!("Observe mode, not switching intel_pstate to {mode} mode");
+        return Ok(());
+    }
+
+    fs::write(&path, mode.to_string()).map_err(|e| match e.kind() {
+        io::ErrorKind::PermissionDenied => {
+            ControlError::PermissionDenied(format!("Path: {}, Error: {e}", path.display()))
+        }
+        io::ErrorKind::ResourceBusy if mode == PstateMode::Off => ControlError::NotSupported(
+            "intel_pstate cannot be turned off while HWP is enabled".to_string(),
+        ),
+        _ => ControlError::WriteError(format!(
+            "Path: {}, Value: '{mode}', Error: {e}",
+            path.display()
+        )),
+    })?;
+
+    // Governors, EPP and the cpufreq policies are read again for the new mode
+    monitor::invalidate_static_system_info();
+    debug!(
+        "Switched intel_pstate from {} to {mode} mode",
+        current.trim()
+    );
+    Ok(())
+}
+
+/// Write tunables of the active governor (e.g. `rate_limit_us` of schedutil).
+/// Drivers that allow a governor per policy keep them in each policy's
+/// directory, others in one directory shared by all CPUs, which is left alone
+/// while cores are ignored.
+pub fn set_governor_tunables(governor: &str, tunables: &[(&str, String)]) -> Result<()> {
+    let shared = sysfs::path("/sys/devices/system/cpu/cpufreq").join(governor);
+    if shared.is_dir() {
+        if !IGNORED_CORES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_empty()
+        {
+            return Err(ControlError::NotSupported(format!(
+                "{governor} tunables are shared by all CPUs, including ignored cores"
+            )));
+        }
+        return write_tunables(&shared, tunables);
+    }
+
+    let mut found = false;
+    for_each_cpufreq_policy(|id| {
+        let dir = sysfs::path(format!(
+            "/sys/devices/system/cpu/cpu{id}/cpufreq/{governor}"
+        ));
+        // The directory only exists while the policy uses this governor
+        if !dir.is_dir() {
+            return Ok(());
+        }
+        found = true;
+        write_tunables(&dir, tunables)
+    })?;
+
+    if found {
+        Ok(())
+    } else {
+        Err(ControlError::NotSupported(format!(
+            "The {governor} governor is not in use or has no tunables with this scaling driver"
+        )))
+    }
+}
+
+/// Write tunables to a governor directory. Some depend on each other (e.g.
+/// conservative's `down_threshold` must stay below `up_threshold`), so those
+/// that fail are tried again once the others have been written.
+fn write_tunables(dir: &Path, tunables: &[(&str, String)]) -> Result<()> {
+    let failed: Vec<_> = tunables
+        .iter()
+        .filter(|(name, value)| write_sysfs_value(dir.join(name), value).is_err())
+        .collect();
+
+    for (name, value) in failed {
+        write_sysfs_value(dir.join(name), value)?;
+    }
+    Ok(())
 }
 
 /// Check if the provided governor is available in the system
@@ -146,16 +401,16 @@ fn is_governor_valid(governor: &str) -> Result<(bool, Vec<String>)> {
 }
 
 /// Get available CPU governors from the system
-fn get_available_governors() -> Result<Vec<String>> {
-    let cpu_base_path = Path::new("/sys/devices/system/cpu");
+pub fn get_available_governors() -> Result<Vec<String>> {
+    let cpu_base_path = sysfs::path("/sys/devices/system/cpu");
 
     // First try the traditional path with cpu0. This is the most common case
     // and will usually catch early, but we should try to keep the code to handle
     // "edge" cases lightweight, for the (albeit smaller) number of users that
     // run Superfreq on unusual systems.
-    let cpu0_path = "/sys/devices/system/cpu/cpu0/cpufreq/scaling_available_governors";
-    if Path::new(cpu0_path).exists() {
-        let content = fs::read_to_string(cpu0_path).map_err(|e| {
+    let cpu0_path = sysfs::path("/sys/devices/system/cpu/cpu0/cpufreq/scaling_available_governors");
+    if cpu0_path.exists() {
+        let content = fs::read_to_string(&cpu0_path).map_err(|e| {
             ControlError::ReadError(format!("Failed to read available governors from cpu0: {e}"))
         })?;
 
@@ -171,7 +426,7 @@ fn get_available_governors() -> Result<Vec<String>> {
 
     // If cpu0 doesn't have the file or it's empty, scan all CPUs
     // This handles heterogeneous systems where cpu0 might not have cpufreq
-    if let Ok(entries) = fs::read_dir(cpu_base_path) {
+    if let Ok(entries) = fs::read_dir(&cpu_base_path) {
         for entry in entries.flatten() {
             let path = entry.path();
             let file_name = entry.file_name();
@@ -213,70 +468,154 @@ fn get_available_governors() -> Result<Vec<String>> {
     ))
 }
 
-pub fn set_turbo(setting: TurboSetting) -> Result<()> {
-    let value_pstate = match setting {
-        TurboSetting::Always => "0", // no_turbo = 0 means turbo is enabled
-        TurboSetting::Never => "1",  // no_turbo = 1 means turbo is disabled
-        // Auto mode is handled at the engine level, not directly at the sysfs level
-        TurboSetting::Auto => {
-            debug!("Turbo Auto mode is managed by engine logic based on system conditions");
-            return Ok(());
+/// Boost control mechanisms in the order they are tried when no preference is configured
+pub const DEFAULT_BOOST_ORDER: &[BoostControl] = &[
+    BoostControl::IntelPstate,
+    BoostControl::AmdPstate,
+    BoostControl::AmdPstateEnableBoost,
+    BoostControl::Global,
+    BoostControl::PerPolicy,
+];
+
+/// Get the cpufreq scaling driver in use, e.g. `intel_pstate` or `amd-pstate-epp`
+pub fn get_scaling_driver() -> Option<String> {
+    fs::read_to_string(sysfs::path(
+        "/sys/devices/system/cpu/cpu0/cpufreq/scaling_driver",
+    ))
+    .ok()
+    .map(|s| s.trim().to_string())
+    .filter(|s| !s.is_empty())
+}
+
+/// Resolve the boost control order for the active scaling driver, falling back
+/// to [`DEFAULT_BOOST_ORDER`] when the driver has no configured preference
+pub fn boost_order(preferences: &BTreeMap<String, Vec<BoostControl>>) -> Vec<BoostControl> {
+    get_scaling_driver()
+        .and_then(|driver| preferences.get(&driver))
+        .filter(|order| !order.is_empty())
+        .map_or_else(|| DEFAULT_BOOST_ORDER.to_vec(), Clone::clone)
+}
+
+/// Sysfs files backing a boost control mechanism. Empty if the mechanism is unavailable.
+pub fn boost_control_paths(control: BoostControl) -> Vec<PathBuf> {
+    let single = |path: &str| {
+        let path = sysfs::path(path);
+        if path.exists() {
+            vec![path]
+        } else {
+            Vec::new()
         }
     };
-    let value_boost = match setting {
-        TurboSetting::Always => "1", // boost = 1 means turbo is enabled
-        TurboSetting::Never => "0",  // boost = 0 means turbo is disabled
+
+    match control {
+        BoostControl::IntelPstate => single("/sys/devices/system/cpu/intel_pstate/no_turbo"),
+        BoostControl::AmdPstate => single("/sys/devices/system/cpu/amd_pstate/cpufreq/boost"),
+        BoostControl::AmdPstateEnableBoost => {
+            single("/sys/devices/system/cpu/cpufreq/amd_pstate_enable_boost")
+        }
+        BoostControl::Global => single("/sys/devices/system/cpu/cpufreq/boost"),
+        BoostControl::PerPolicy => {
+            let Ok(entries) = fs::read_dir(sysfs::path("/sys/devices/system/cpu/cpufreq")) else {
+                return Vec::new();
+            };
+            let mut paths: Vec<PathBuf> = entries
+                .flatten()
+                .filter(|entry| entry.file_name().to_string_lossy().starts_with("policy"))
+                .map(|entry| entry.path().join("boost"))
+                .filter(|path| path.exists())
+                .collect();
+            paths.sort();
+            paths
+        }
+    }
+}
+
+/// Value to write to a boost control file to enable or disable boost
+const fn boost_control_value(control: BoostControl, enable: bool) -> &'static str {
+    // intel_pstate exposes `no_turbo`, so its meaning is inverted
+    let inverted = matches!(control, BoostControl::IntelPstate);
+    if enable != inverted { "1" } else { "0" }
+}
+
+pub fn set_turbo(setting: TurboSetting, order: &[BoostControl]) -> Result<()> {
+    let enable = match setting {
+        TurboSetting::Always => true,
+        TurboSetting::Never => false,
+        // Auto mode is handled at the engine level, not directly at the sysfs level
         TurboSetting::Auto => {
             debug!("Turbo Auto mode is managed by engine logic based on system conditions");
             return Ok(());
         }
     };
 
-    // AMD specific paths
-    let amd_pstate_path = "/sys/devices/system/cpu/amd_pstate/cpufreq/boost";
-    let msr_boost_path = "/sys/devices/system/cpu/cpufreq/amd_pstate_enable_boost";
-
-    // Path priority (from most to least specific)
-    let pstate_path = "/sys/devices/system/cpu/intel_pstate/no_turbo";
-    let boost_path = "/sys/devices/system/cpu/cpufreq/boost";
-
-    // Try each boost control path in order of specificity
-    if Path::new(pstate_path).exists() {
-        write_sysfs_value(pstate_path, value_pstate)
-    } else if Path::new(amd_pstate_path).exists() {
-        write_sysfs_value(amd_pstate_path, value_boost)
-    } else if Path::new(msr_boost_path).exists() {
-        write_sysfs_value(msr_boost_path, value_boost)
-    } else if Path::new(boost_path).exists() {
-        write_sysfs_value(boost_path, value_boost)
-    } else {
-        // Also try per-core cpufreq boost for some AMD systems
-        let result = try_set_per_core_boost(value_boost)?;
-        if result {
-            Ok(())
-        } else {
-            Err(ControlError::NotSupported(
-                "No supported CPU boost control mechanism found.".to_string(),
-            ))
+    // Use the first available mechanism in order of preference
+    for &control in order {
+        let paths = boost_control_paths(control);
+        if paths.is_empty() {
+            continue;
+        }
+
+        debug!("Setting boost via {control} ({} file(s))", paths.len());
+        let value = boost_control_value(control, enable);
+        for path in &paths {
+            write_sysfs_value(path, value)?;
         }
+        return Ok(());
     }
+
+    Err(ControlError::NotSupported(
+        "No supported CPU boost control mechanism found.".to_string(),
+    ))
 }
 
-/// Try to set boost on a per-core basis for systems that support it
-fn try_set_per_core_boost(value: &str) -> Result<bool> {
-    let mut success = false;
-    let num_cores = get_logical_core_count()?;
+/// Outcome of a write-verify test of a boost control mechanism
+pub enum BoostControlStatus {
+    /// No backing files exist on this system
+    Unavailable,
+    /// Writing the current value back succeeded and reads back unchanged
+    Verified,
+    /// The files exist but could not be written or did not keep the written value
+    Failed(String),
+}
+
+/// Check whether a boost control mechanism actually works on this system.
+///
+/// The current value of every backing file is written back and read again, so
+/// the boost state itself is left untouched.
+pub fn verify_boost_control(control: BoostControl) -> BoostControlStatus {
+    let paths = boost_control_paths(control);
+    if paths.is_empty() {
+        return BoostControlStatus::Unavailable;
+    }
+
+    for path in &paths {
+        let current = match fs::read_to_string(path) {
+            Ok(content) => content.trim().to_string(),
+            Err(e) => {
+                return BoostControlStatus::Failed(format!("{}: {e}", path.display()));
+            }
+        };
 
-    for core_id in 0..num_cores {
-        let boost_path = format!("/sys/devices/system/cpu/cpu{core_id}/cpufreq/boost");
+        if let Err(e) = write_sysfs_value(path, &current) {
+            return BoostControlStatus::Failed(e.to_string());
+        }
 
-        if Path::new(&boost_path).exists() {
-            write_sysfs_value(&boost_path, value)?;
-            success = true;
+        match fs::read_to_string(path) {
+            Ok(content) if content.trim() == current => {}
+            Ok(content) => {
+                return BoostControlStatus::Failed(format!(
+                    "{}: wrote '{current}' but read back '{}'",
+                    path.display(),
+                    content.trim()
+                ));
+            }
+            Err(e) => {
+                return BoostControlStatus::Failed(format!("{}: {e}", path.display()));
+            }
         }
     }
 
-    Ok(success)
+    BoostControlStatus::Verified
 }
 
 pub fn set_epp(epp: &str, core_id: Option<u32>) -> Result<()> {
@@ -291,28 +630,37 @@ pub fn set_epp(epp: &str, core_id: Option<u32>) -> Result<()> {
     }
 
     let action = |id: u32| {
-        let path = format!("/sys/devices/system/cpu/cpu{id}/cpufreq/energy_performance_preference");
+        let path = sysfs::path(format!(
+            "/sys/devices/system/cpu/cpu{id}/cpufreq/energy_performance_preference"
+        ));
         if Path::new(&path).exists() {
             write_sysfs_value(&path, epp)
         } else {
             Ok(())
         }
     };
-    core_id.map_or_else(|| for_each_cpu_core(action), action)
+    core_id.map_or_else(|| for_each_cpufreq_policy(action), action)
+}
+
+/// Check whether the cpufreq driver exposes Energy Performance Preference control
+pub fn is_epp_supported() -> bool {
+    sysfs::path("/sys/devices/system/cpu/cpu0/cpufreq/energy_performance_preference").exists()
 }
 
 /// Get available EPP values from the system
-fn get_available_epp_values() -> Result<Vec<String>> {
-    let path = "/sys/devices/system/cpu/cpu0/cpufreq/energy_performance_available_preferences";
+pub fn get_available_epp_values() -> Result<Vec<String>> {
+    let path = sysfs::path(
+        "/sys/devices/system/cpu/cpu0/cpufreq/energy_performance_available_preferences",
+    );
 
-    if !Path::new(path).exists() {
+    if !path.exists() {
         // If the file doesn't exist, fall back to a default set of common values
         // This is safer than failing outright, as some systems may allow these values     │
         // even without explicitly listing them
         return Ok(EPP_FALLBACK_VALUES.iter().map(|&s| s.to_string()).collect());
     }
 
-    let content = fs::read_to_string(path).map_err(|e| {
+    let content = fs::read_to_string(&path).map_err(|e| {
         ControlError::ReadError(format!("Failed to read available EPP values: {e}"))
     })?;
 
@@ -326,8 +674,25 @@ pub fn set_epb(epb: &str, core_id: Option<u32>) -> Result<()> {
     // Validate EPB value - should be a number 0-15 or a recognized string value
     validate_epb_value(epb)?;
 
+    // Without the sysfs node, fall back to writing the MSR directly where possible
+    #[cfg(feature = "msr")]
+    if !sysfs::path(format!(
+        "/sys/devices/system/cpu/cpu{}/cpufreq/energy_performance_bias",
+        core_id.unwrap_or(0)
+    ))
+    .exists()
+        && msr::is_epb_supported()
+    {
+        debug!("EPB sysfs node is missing, writing the EPB MSR instead");
+        let value = epb_numeric_value(epb)?;
+        let action = |id: u32| msr::write_epb(id, value);
+        return core_id.map_or_else(|| for_each_cpu_core(action), action);
+    }
+
     let action = |id: u32| {
-        let path = format!("/sys/devices/system/cpu/cpu{id}/cpufreq/energy_performance_bias");
+        let path = sysfs::path(format!(
+            "/sys/devices/system/cpu/cpu{id}/cpufreq/energy_performance_bias"
+        ));
         if Path::new(&path).exists() {
             write_sysfs_value(&path, epb)
         } else {
@@ -337,6 +702,24 @@ pub fn set_epb(epb: &str, core_id: Option<u32>) -> Result<()> {
     core_id.map_or_else(|| for_each_cpu_core(action), action)
 }
 
+/// Numeric EPB value of a validated EPB setting, using the kernel's mapping of names
+#[cfg(feature = "msr")]
+fn epb_numeric_value(epb: &str) -> Result<u8> {
+    if let Ok(value) = epb.parse::<u8>() {
+        return Ok(value);
+    }
+
+    match epb.to_ascii_lowercase().replace('_', "-").as_str() {
+        "performance" => Ok(0),
+        "balance-performance" => Ok(4),
+        "balance-power" => Ok(8),
+        "power" => Ok(15),
+        _ => Err(ControlError::InvalidValueError(format!(
+            "Invalid EPB value: '{epb}'"
+        ))),
+    }
+}
+
 fn validate_epb_value(epb: &str) -> Result<()> {
     // EPB can be a number from 0-15 or a recognized string
     // Try parsing as a number first
@@ -382,14 +765,16 @@ pub fn set_min_frequency(freq_mhz: u32, core_id: Option<u32>) -> Result<()> {
     let freq_khz_str = freq_khz.to_string();
 
     let action = |id: u32| {
-        let path = format!("/sys/devices/system/cpu/cpu{id}/cpufreq/scaling_min_freq");
+        let path = sysfs::path(format!(
+            "/sys/devices/system/cpu/cpu{id}/cpufreq/scaling_min_freq"
+        ));
         if Path::new(&path).exists() {
             write_sysfs_value(&path, &freq_khz_str)
         } else {
             Ok(())
         }
     };
-    core_id.map_or_else(|| for_each_cpu_core(action), action)
+    core_id.map_or_else(|| for_each_cpufreq_policy(action), action)
 }
 
 pub fn set_max_frequency(freq_mhz: u32, core_id: Option<u32>) -> Result<()> {
@@ -409,34 +794,320 @@ pub fn set_max_frequency(freq_mhz: u32, core_id: Option<u32>) -> Result<()> {
     let freq_khz_str = freq_khz.to_string();
 
     let action = |id: u32| {
-        let path = format!("/sys/devices/system/cpu/cpu{id}/cpufreq/scaling_max_freq");
+        let path = sysfs::path(format!(
+            "/sys/devices/system/cpu/cpu{id}/cpufreq/scaling_max_freq"
+        ));
         if Path::new(&path).exists() {
             write_sysfs_value(&path, &freq_khz_str)
         } else {
             Ok(())
         }
     };
-    core_id.map_or_else(|| for_each_cpu_core(action), action)
+    core_id.map_or_else(|| for_each_cpufreq_policy(action), action)
+}
+
+/// Restore the maximum frequency of every core to its hardware limit
+pub fn reset_max_frequency() -> Result<()> {
+    for_each_cpufreq_policy(|id| {
+        let path = sysfs::path(format!(
+            "/sys/devices/system/cpu/cpu{id}/cpufreq/scaling_max_freq"
+        ));
+        if !Path::new(&path).exists() {
+            return Ok(());
+        }
+
+        let limit_khz = read_sysfs_value_as_u32(sysfs::path(format!(
+            "/sys/devices/system/cpu/cpu{id}/cpufreq/cpuinfo_max_freq"
+        )))?;
+        write_sysfs_value(&path, &limit_khz.to_string())
+    })
+}
+
+/// RAPL constraint names used by the kernel for PL1 and PL2
+pub const RAPL_LONG_TERM: &str = "long_term";
+pub const RAPL_SHORT_TERM: &str = "short_term";
+
+/// Top-level RAPL (package) domains
+fn rapl_package_domains() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(sysfs::path("/sys/class/powercap")) else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("intel-rapl:") && name.matches(':').count() == 1
+        })
+        .map(|entry| entry.path())
+        .collect()
+}
+
+/// Set the power limit and/or time window of a RAPL constraint (e.g. `long_term`)
+/// on every package
+pub fn set_rapl_constraint(
+    constraint: &str,
+    power_watts: Option<f32>,
+    time_window_us: Option<u64>,
+) -> Result<()> {
+    let domains = rapl_package_domains();
+    if domains.is_empty() {
+        return Err(ControlError::NotSupported(
+            "No RAPL package domains found under /sys/class/powercap".to_string(),
+        ));
+    }
+
+    for domain in domains {
+        // Constraints are numbered, their names tell them apart
+        let Some(index) = (0..4).find(|index| {
+            fs::read_to_string(domain.join(format!("constraint_{index}_name")))
+                .is_ok_and(|name| name.trim() == constraint)
+        }) else {
+            return Err(ControlError::NotSupported(format!(
+                "{} has no {constraint} constraint",
+                domain.display()
+            )));
+        };
+
+        if let Some(watts) = power_watts {
+            let microwatts = (f64::from(watts) * 1_000_000.0).round() as u64;
+            write_sysfs_value(
+                domain.join(format!("constraint_{index}_power_limit_uw")),
+                &microwatts.to_string(),
+            )?;
+        }
+        if let Some(window) = time_window_us {
+            write_sysfs_value(
+                domain.join(format!("constraint_{index}_time_window_us")),
+                &window.to_string(),
+            )?;
+        }
+    }
+
+    Ok(())
 }
 
-fn read_sysfs_value_as_u32(path: &str) -> Result<u32> {
-    if !Path::new(path).exists() {
+const SCHED_ITMT_PATH: &str = "/proc/sys/kernel/sched_itmt_enabled";
+
+/// Enable or disable ITMT (Turbo Boost Max 3.0 preferred-core scheduling)
+pub fn set_sched_itmt(enabled: bool) -> Result<()> {
+    let path = sysfs::path(SCHED_ITMT_PATH);
+    if !path.exists() {
         return Err(ControlError::NotSupported(format!(
-            "File does not exist: {path}"
+            "{} does not exist, the CPU does not support ITMT",
+            path.display()
+        )));
+    }
+
+    write_sysfs_value(path, if enabled { "1" } else { "0" })
+}
+
+const CPU_DMA_LATENCY_PATH: &str = "/dev/cpu_dma_latency";
+
+/// Open handle on `/dev/cpu_dma_latency` and the latency it requests. The kernel
+/// honours the request for as long as the file stays open.
+static DMA_LATENCY_HOLDER: Mutex<Option<(fs::File, u32)>> = Mutex::new(None);
+
+/// Request a maximum wakeup latency in microseconds, or release the request with `None`
+pub fn set_dma_latency(latency_us: Option<u32>) -> Result<()> {
+    let mut holder = DMA_LATENCY_HOLDER
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+    let Some(latency_us) = latency_us else {
+        if holder.take().is_some() {
+            debug!("Released the CPU DMA latency request");
+        }
+        return Ok(());
+    };
+
+    if holder.as_ref().is_some_and(|(_, held)| *held == latency_us) {
+        return Ok(());
+    }
+
+    if sysfs::is_observe_only() {
+        debug!("Observe mode, not requesting a CPU DMA latency of {latency_us} us");
+        return Ok(());
+    }
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .open(CPU_DMA_LATENCY_PATH)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => {
+                ControlError::NotSupported(format!("{CPU_DMA_LATENCY_PATH} does not exist"))
+            }
+            io::ErrorKind::PermissionDenied => {
+                ControlError::PermissionDenied(format!("Cannot open {CPU_DMA_LATENCY_PATH}"))
+            }
+            _ => ControlError::Io(e),
+        })?;
+    // The kernel expects a native-endian 32-bit integer
+    let value = i32::try_from(latency_us).unwrap_or(i32::MAX);
+    file.write_all(&value.to_ne_bytes())?;
+
+    // Replacing the previous handle closes it and drops its request
+    *holder = Some((file, latency_us));
+    Ok(())
+}
+
+/// `cpuidle/state*` directories of a core, ordered by state index
+fn idle_state_dirs(core_id: u32) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(sysfs::path(format!(
+        "/sys/devices/system/cpu/cpu{core_id}/cpuidle"
+    ))) else {
+        return Vec::new();
+    };
+
+    let mut states: Vec<(u32, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let index = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("state")?
+                .parse()
+                .ok()?;
+            Some((index, entry.path()))
+        })
+        .collect();
+    states.sort_unstable_by_key(|(index, _)| *index);
+    states.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Names of the idle states (C-states) of the system, shallowest first
+pub fn get_idle_states() -> Result<Vec<String>> {
+    let states: Vec<String> = idle_state_dirs(0)
+        .iter()
+        .filter_map(|dir| fs::read_to_string(dir.join("name")).ok())
+        .map(|name| name.trim().to_string())
+        .collect();
+
+    if states.is_empty() {
+        return Err(ControlError::NotSupported(
+            "cpuidle states are not available".to_string(),
+        ));
+    }
+    Ok(states)
+}
+
+/// Disable the named idle states on every core and enable all others
+pub fn set_idle_states_disabled(disabled: &[String]) -> Result<()> {
+    let available = get_idle_states()?;
+    if let Some(unknown) = disabled
+        .iter()
+        .find(|name| !available.iter().any(|a| a.eq_ignore_ascii_case(name)))
+    {
+        return Err(ControlError::InvalidValueError(format!(
+            "Unknown idle state '{unknown}'. Available states: {}",
+            available.join(", ")
+        )));
+    }
+
+    for_each_cpufreq_policy(|id| {
+        for dir in idle_state_dirs(id) {
+            let Ok(name) = fs::read_to_string(dir.join("name")) else {
+                continue;
+            };
+            let disable = disabled.iter().any(|d| d.eq_ignore_ascii_case(name.trim()));
+            write_sysfs_value(dir.join("disable"), if disable { "1" } else { "0" })?;
+        }
+        Ok(())
+    })
+}
+
+/// Clamp every core's maximum frequency to its hardware minimum
+pub fn force_min_frequency() -> Result<()> {
+    for_each_cpu_core(|id| {
+        let path = sysfs::path(format!(
+            "/sys/devices/system/cpu/cpu{id}/cpufreq/scaling_max_freq"
+        ));
+        if !Path::new(&path).exists() {
+            return Ok(());
+        }
+
+        let floor_khz = read_sysfs_value_as_u32(sysfs::path(format!(
+            "/sys/devices/system/cpu/cpu{id}/cpufreq/cpuinfo_min_freq"
+        )))?;
+        write_sysfs_value(&path, &floor_khz.to_string())
+    })
+}
+
+/// The lowest and highest frequency the hardware supports, in MHz, as
+/// reported for the first CPU
+pub fn get_hardware_frequency_limits_mhz() -> Result<(u32, u32)> {
+    let min_khz = read_sysfs_value_as_u32(sysfs::path(
+        "/sys/devices/system/cpu/cpu0/cpufreq/cpuinfo_min_freq",
+    ))?;
+    let max_khz = read_sysfs_value_as_u32(sysfs::path(
+        "/sys/devices/system/cpu/cpu0/cpufreq/cpuinfo_max_freq",
+    ))?;
+    Ok((min_khz / 1000, max_khz / 1000))
+}
+
+/// Find the cooling device registered by the `intel_powerclamp` driver
+fn find_powerclamp_device() -> Option<PathBuf> {
+    fs::read_dir(sysfs::path("/sys/class/thermal"))
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| {
+            fs::read_to_string(path.join("type")).is_ok_and(|t| t.trim() == "intel_powerclamp")
+        })
+}
+
+/// Inject idle time through the kernel's powerclamp interface.
+/// A percentage of 0 stops idle injection.
+pub fn set_idle_injection(percent: u8) -> Result<()> {
+    let device = find_powerclamp_device().ok_or_else(|| {
+        ControlError::NotSupported("The intel_powerclamp driver is not loaded".to_string())
+    })?;
+
+    let max_state = read_sysfs_value_as_u32(device.join("max_state"))?;
+    let state = u32::from(percent).min(max_state);
+    write_sysfs_value(device.join("cur_state"), &state.to_string())
+}
+
+/// Set the target frequency of a core running the `userspace` governor
+pub fn set_scaling_setspeed(freq_mhz: u32, core_id: u32) -> Result<()> {
+    let path = sysfs::path(format!(
+        "/sys/devices/system/cpu/cpu{core_id}/cpufreq/scaling_setspeed"
+    ));
+    if !Path::new(&path).exists() {
+        return Err(ControlError::NotSupported(format!(
+            "scaling_setspeed is not available for CPU {core_id}"
+        )));
+    }
+
+    let freq_khz = u64::from(freq_mhz) * 1000;
+    write_sysfs_value(&path, &freq_khz.to_string())
+}
+
+fn read_sysfs_value_as_u32(path: impl AsRef<Path>) -> Result<u32> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Err(ControlError::NotSupported(format!(
+            "File does not exist: {}",
+            path.display()
         )));
     }
 
     let content = fs::read_to_string(path)
-        .map_err(|e| ControlError::ReadError(format!("Failed to read {path}: {e}")))?;
+        .map_err(|e| ControlError::ReadError(format!("Failed to read {}: {e}", path.display())))?;
 
-    content
-        .trim()
-        .parse::<u32>()
-        .map_err(|e| ControlError::ParseError(format!("Failed to parse value from {path}: {e}")))
+    content.trim().parse::<u32>().map_err(|e| {
+        ControlError::ParseError(format!(
+            "Failed to parse value from {}: {e}",
+            path.display()
+        ))
+    })
 }
 
 fn validate_min_frequency(core_id: u32, new_min_freq_mhz: u32) -> Result<()> {
-    let max_freq_path = format!("/sys/devices/system/cpu/cpu{core_id}/cpufreq/scaling_max_freq");
+    let max_freq_path = sysfs::path(format!(
+        "/sys/devices/system/cpu/cpu{core_id}/cpufreq/scaling_max_freq"
+    ));
 
     if !Path::new(&max_freq_path).exists() {
         return Ok(());
@@ -458,7 +1129,9 @@ fn validate_min_frequency(core_id: u32, new_min_freq_mhz: u32) -> Result<()> {
 }
 
 fn validate_max_frequency(core_id: u32, new_max_freq_mhz: u32) -> Result<()> {
-    let min_freq_path = format!("/sys/devices/system/cpu/cpu{core_id}/cpufreq/scaling_min_freq");
+    let min_freq_path = sysfs::path(format!(
+        "/sys/devices/system/cpu/cpu{core_id}/cpufreq/scaling_min_freq"
+    ));
 
     if !Path::new(&min_freq_path).exists() {
         return Ok(());
@@ -486,17 +1159,23 @@ fn validate_max_frequency(core_id: u32, new_max_freq_mhz: u32) -> Result<()> {
 ///
 /// [`The Kernel docs`]: <https://docs.kernel.org/userspace-api/sysfs-platform_profile.html>
 ///
+/// Dell and MSI laptops without an ACPI platform profile get the same profile
+/// names through their BIOS thermal management modes or `msi-ec` shift modes.
+///
 /// # Examples
 ///
-/// ```
-/// set_platform_profile("balanced");
+/// ```no_run
+/// superfreq::cpu::set_platform_profile("balanced")?;
+/// # Ok::<(), superfreq::error::ControlError>(())
 /// ```
 ///
 pub fn set_platform_profile(profile: &str) -> Result<()> {
-    let path = "/sys/firmware/acpi/platform_profile";
-    if !Path::new(path).exists() {
+    let path = sysfs::path(PLATFORM_PROFILE_PATH);
+    let backend = vendor_profile_backend();
+    if !path.exists() && backend.is_none() {
         return Err(ControlError::NotSupported(format!(
-            "Platform profile control not found at {path}.",
+            "Platform profile control not found at {}.",
+            path.display()
         )));
     }
 
@@ -512,10 +1191,32 @@ pub fn set_platform_profile(profile: &str) -> Result<()> {
             available_profiles.join(", ")
         )));
     }
+    if let Some(backend) = backend {
+        return backend.set_profile(profile);
+    }
     write_sysfs_value(path, profile)
 }
 
-/// Returns the list of available platform profiles.
+/// The vendor driver that stands in for the ACPI platform profile, if the
+/// firmware has none
+pub fn vendor_profile_backend() -> Option<vendor::ProfileBackend> {
+    if sysfs::path(PLATFORM_PROFILE_PATH).exists() {
+        return None;
+    }
+    vendor::ProfileBackend::detect()
+}
+
+/// Returns the current platform profile, or the mode of the vendor driver
+/// that stands in for it.
+pub fn get_platform_profile() -> Option<String> {
+    if let Some(backend) = vendor_profile_backend() {
+        return backend.get_profile();
+    }
+    sysfs::read_sysfs_value(sysfs::path(PLATFORM_PROFILE_PATH)).ok()
+}
+
+/// Returns the list of available platform profiles, or of the modes of the
+/// vendor driver that stands in for the ACPI platform profile.
 ///
 /// # Errors
 ///
@@ -528,16 +1229,21 @@ pub fn set_platform_profile(profile: &str) -> Result<()> {
 /// - [`ControlError::PermissionDenied`] if the file `/sys/firmware/acpi/platform_profile_choices` cannot be read.
 ///
 pub fn get_platform_profiles() -> Result<Vec<String>> {
-    let path = "/sys/firmware/acpi/platform_profile_choices";
+    let path = sysfs::path("/sys/firmware/acpi/platform_profile_choices");
 
-    if !Path::new(path).exists() {
+    if let Some(backend) = vendor_profile_backend() {
+        return backend.get_profiles();
+    }
+    if !path.exists() {
         return Err(ControlError::NotSupported(format!(
-            "Platform profile choices not found at {path}."
+            "Platform profile choices not found at {}.",
+            path.display()
         )));
     }
 
-    let content = fs::read_to_string(path)
-        .map_err(|_| ControlError::PermissionDenied(format!("Cannot read contents of {path}.")))?;
+    let content = fs::read_to_string(&path).map_err(|_| {
+        ControlError::PermissionDenied(format!("Cannot read contents of {}.", path.display()))
+    })?;
 
     Ok(content
         .split_whitespace()
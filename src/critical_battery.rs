@@ -0,0 +1,115 @@
+use crate::config::{CriticalAction, ProfileConfig};
+use crate::core::SystemReport;
+use crate::util::sysfs;
+use log::{debug, error, info, warn};
+use std::io;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+/// Whether the action has run during this discharge. It runs again only after
+/// AC was connected or the charge rose above the threshold, so resuming on a
+/// critical battery does not immediately suspend again.
+static TRIGGERED: AtomicBool = AtomicBool::new(false);
+
+/// Average charge of the batteries while running on them
+fn battery_percent(report: &SystemReport) -> Option<f32> {
+    if report.batteries.iter().all(|battery| battery.ac_connected) {
+        return None;
+    }
+
+    let levels: Vec<f32> = report
+        .batteries
+        .iter()
+        .filter_map(|battery| battery.capacity_percent.map(f32::from))
+        .collect();
+    if levels.is_empty() {
+        return None;
+    }
+    Some(levels.iter().sum::<f32>() / levels.len() as f32)
+}
+
+/// Run the critical action of the `[battery]` profile once the batteries reach
+/// its `critical_percent`. It applies whichever profile is active, since the
+/// battery runs out all the same.
+pub fn check(report: &SystemReport, battery_profile: &ProfileConfig) {
+    let Some(threshold) = battery_profile.critical_percent else {
+        return;
+    };
+
+    let percent = match battery_percent(report) {
+        Some(percent) if percent <= f32::from(threshold) => percent,
+        _ => {
+            if TRIGGERED.swap(false, Ordering::Relaxed) {
+                debug!("Battery is no longer critical, re-arming the critical action");
+            }
+            return;
+        }
+    };
+
+    if TRIGGERED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    let action = battery_profile.critical_action;
+    if sysfs::is_observe_only() {
+        info!("Battery is critical at {percent:.0}%, observe mode: not running {action}");
+        return;
+    }
+    warn!("Battery is critical at {percent:.0}%, running the critical action: {action}");
+    if let Err(e) = run_action(action, battery_profile.critical_script.as_deref(), percent) {
+        error!("Failed to run the critical battery action ({action}): {e}");
+    }
+}
+
+fn run_action(action: CriticalAction, script: Option<&str>, percent: f32) -> io::Result<()> {
+    if action == CriticalAction::Script {
+        let script = script.ok_or_else(|| io::Error::other("critical_script is not set"))?;
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .env("SUPERFREQ_BATTERY_PERCENT", format!("{percent:.0}"))
+            .stdin(Stdio::null())
+            .spawn()?;
+        // Reap the script without blocking the daemon loop
+        thread::spawn(move || match child.wait() {
+            Ok(status) if !status.success() => {
+                warn!("Critical battery script exited with {status}")
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Failed to wait for the critical battery script: {e}"),
+        });
+        return Ok(());
+    }
+
+    match Command::new("systemctl")
+        .arg(action.to_string())
+        .stdin(Stdio::null())
+        .status()
+    {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(io::Error::other(format!(
+            "systemctl {action} exited with {status}"
+        ))),
+        // Without systemd, ask the kernel directly
+        Err(e) if e.kind() == io::ErrorKind::NotFound => sleep_through_sysfs(action),
+        Err(e) => Err(e),
+    }
+}
+
+/// Suspend or hibernate through `/sys/power`, which returns after resuming
+fn sleep_through_sysfs(action: CriticalAction) -> io::Result<()> {
+    let state = match action {
+        CriticalAction::Suspend => "mem",
+        CriticalAction::Hibernate => "disk",
+        CriticalAction::HybridSleep => {
+            // Write the image, then suspend instead of powering off
+            sysfs::write_sysfs_value(sysfs::path("/sys/power/disk"), "suspend")
+                .map_err(io::Error::other)?;
+            "disk"
+        }
+        CriticalAction::Script => return Ok(()),
+    };
+
+    sysfs::write_sysfs_value(sysfs::path("/sys/power/state"), state).map_err(io::Error::other)
+}
@@ -1,15 +1,40 @@
-use crate::config::{AppConfig, LogLevel};
+use crate::activity;
+use crate::alerts::{self, AlertTracker, ObservedChanges};
+use crate::calibration;
+use crate::config::{self, AppConfig, DaemonMode, LogLevel, OnConflict, StatsFileFormat};
+use crate::conflict;
 use crate::core::SystemReport;
+use crate::cpu;
+use crate::critical_battery;
+use crate::enforce;
 use crate::engine;
+use crate::fan;
+#[cfg(feature = "history")]
+use crate::history::History;
+#[cfg(feature = "http")]
+use crate::http;
+use crate::lid;
 use crate::monitor;
+#[cfg(feature = "mqtt")]
+use crate::mqtt;
+use crate::overrides;
+#[cfg(feature = "dbus")]
+use crate::power_profiles;
+use crate::power_supply;
+use crate::resume;
+use crate::snapshot;
+use crate::util::clock::{Clock, SystemClock};
 use crate::util::error::{AppError, ControlError};
+use crate::util::logging::{self, Rotation};
+use crate::util::{notify, privilege, sysfs, wake};
 use log::{LevelFilter, debug, error, info, warn};
 use std::collections::VecDeque;
-use std::fs::File;
-use std::io::Write;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::{Duration, Instant};
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::signal::unix::{Signal, SignalKind, signal};
 
 /// Parameters for computing optimal polling interval
 struct IntervalParams {
@@ -61,9 +86,9 @@ fn idle_multiplier(idle_secs: u64) -> f32 {
 /// Calculate optimal polling interval based on system conditions and history
 ///
 /// Returns Ok with the calculated interval, or Err if the configuration is invalid
-fn compute_new(
+fn compute_new<C: Clock>(
     params: &IntervalParams,
-    system_history: &SystemHistory,
+    system_history: &SystemHistory<C>,
 ) -> Result<u64, ControlError> {
     // Use the centralized validation function
     validate_poll_intervals(params.min_interval, params.max_interval)?;
@@ -153,19 +178,26 @@ fn compute_new(
 
 /// Tracks historical system data for "advanced" adaptive polling
 #[derive(Debug)]
-struct SystemHistory {
+struct SystemHistory<C: Clock = SystemClock> {
+    /// Time source for all timestamps and durations
+    clock: C,
     /// Last several CPU usage measurements
     cpu_usage_history: VecDeque<f32>,
     /// Last several temperature readings
     temperature_history: VecDeque<f32>,
     /// Time of last detected user activity
     last_user_activity: Instant,
+    /// Time since the last user input, if it is known rather than guessed
+    user_idle: Option<Duration>,
     /// Previous battery percentage (to calculate discharge rate)
     last_battery_percentage: Option<f32>,
     /// Timestamp of last battery reading
     last_battery_timestamp: Option<Instant>,
     /// Battery discharge rate (%/hour)
     battery_discharge_rate: Option<f32>,
+    /// Power drawn from the batteries (W)
+    battery_discharge_watts: Option<f32>,
+    on_battery: bool,
     /// Time spent in each system state
     state_durations: std::collections::HashMap<SystemState, Duration>,
     /// Last time a state transition happened
@@ -174,28 +206,52 @@ struct SystemHistory {
     current_state: SystemState,
     /// Last computed optimal polling interval
     last_computed_interval: Option<u64>,
+    /// Timestamped battery percentages within the alert window
+    battery_samples: VecDeque<(Instant, f32)>,
+    /// Timestamped temperatures within the alert window
+    temperature_samples: VecDeque<(Instant, f32)>,
 }
 
-impl Default for SystemHistory {
+impl<C: Clock + Default> Default for SystemHistory<C> {
     fn default() -> Self {
+        Self::with_clock(C::default())
+    }
+}
+
+impl<C: Clock> SystemHistory<C> {
+    fn with_clock(clock: C) -> Self {
+        let now = clock.now();
         Self {
+            clock,
             cpu_usage_history: VecDeque::new(),
             temperature_history: VecDeque::new(),
-            last_user_activity: Instant::now(),
+            last_user_activity: now,
+            user_idle: None,
             last_battery_percentage: None,
             last_battery_timestamp: None,
             battery_discharge_rate: None,
+            battery_discharge_watts: None,
+            on_battery: false,
             state_durations: std::collections::HashMap::new(),
-            last_state_change: Instant::now(),
+            last_state_change: now,
             current_state: SystemState::default(),
             last_computed_interval: None,
+            battery_samples: VecDeque::new(),
+            temperature_samples: VecDeque::new(),
         }
     }
 }
 
-impl SystemHistory {
+impl<C: Clock> SystemHistory<C> {
     /// Update system history with new report data
     fn update(&mut self, report: &SystemReport) {
+        let now = self.clock.now();
+
+        self.user_idle = activity::idle_time();
+        if let Some(idle) = self.user_idle {
+            self.last_user_activity = now.checked_sub(idle).unwrap_or(now);
+        }
+
         // Update CPU usage history
         if !report.cpu_cores.is_empty() {
             let mut total_usage: f32 = 0.0;
@@ -225,14 +281,20 @@ impl SystemHistory {
                             .abs()
                             > 15.0)
                 {
-                    self.last_user_activity = Instant::now();
-                    debug!("User activity detected based on CPU usage");
+                    self.heuristic_activity(now, "CPU usage");
                 }
             }
         }
 
         // Update temperature history
         if let Some(temp) = report.cpu_global.average_temperature_celsius {
+            push_sample(
+                &mut self.temperature_samples,
+                now,
+                temp,
+                alerts::TEMPERATURE_RISE_WINDOW,
+            );
+
             if self.temperature_history.len() >= 5 {
                 self.temperature_history.pop_front();
             }
@@ -244,12 +306,22 @@ impl SystemHistory {
                     temp - self.temperature_history[self.temperature_history.len() - 2];
                 if temp_change > 5.0 {
                     // 5°C rise in temperature
-                    self.last_user_activity = Instant::now();
-                    debug!("User activity detected based on temperature change");
+                    self.heuristic_activity(now, "temperature change");
                 }
             }
         }
 
+        self.on_battery = report.batteries.iter().any(|battery| !battery.ac_connected);
+        self.battery_discharge_watts = self.on_battery.then(|| {
+            report
+                .batteries
+                .iter()
+                .filter_map(|battery| battery.power_rate_watts)
+                .filter(|&watts| watts < 0.0)
+                .map(|watts| -watts)
+                .sum()
+        });
+
         // Update battery discharge rate
         if let Some(battery) = report.batteries.first() {
             // Reset when we are charging or have just connected AC
@@ -259,15 +331,25 @@ impl SystemHistory {
                 self.battery_discharge_rate = None;
                 self.last_battery_percentage = None;
                 self.last_battery_timestamp = None;
+                self.battery_samples.clear();
             }
 
             if let Some(current_percentage) = battery.capacity_percent {
                 let current_percent = f32::from(current_percentage);
 
+                if !battery.ac_connected {
+                    push_sample(
+                        &mut self.battery_samples,
+                        now,
+                        current_percent,
+                        alerts::BATTERY_DROP_WINDOW,
+                    );
+                }
+
                 if let (Some(last_percentage), Some(last_timestamp)) =
                     (self.last_battery_percentage, self.last_battery_timestamp)
                 {
-                    let elapsed_hours = last_timestamp.elapsed().as_secs_f32() / 3600.0;
+                    let elapsed_hours = self.clock.elapsed(last_timestamp).as_secs_f32() / 3600.0;
                     // Only calculate discharge rate if at least 30 seconds have passed
                     // and we're not on AC power
                     if elapsed_hours > 0.0083 && !battery.ac_connected {
@@ -285,7 +367,7 @@ impl SystemHistory {
                 }
 
                 self.last_battery_percentage = Some(current_percent);
-                self.last_battery_timestamp = Some(Instant::now());
+                self.last_battery_timestamp = Some(now);
             }
         }
 
@@ -293,7 +375,7 @@ impl SystemHistory {
         let new_state = determine_system_state(report, self);
         if new_state != self.current_state {
             // Record time spent in previous state
-            let time_in_state = self.last_state_change.elapsed();
+            let time_in_state = self.clock.elapsed(self.last_state_change);
             *self
                 .state_durations
                 .entry(self.current_state.clone())
@@ -301,22 +383,53 @@ impl SystemHistory {
 
             // State changes (except to Idle) likely indicate user activity
             if new_state != SystemState::Idle && new_state != SystemState::LowLoad {
-                self.last_user_activity = Instant::now();
-                debug!("User activity detected based on system state change to {new_state:?}");
+                self.heuristic_activity(now, &format!("system state change to {new_state:?}"));
             }
 
             // Update state
             self.current_state = new_state;
-            self.last_state_change = Instant::now();
+            self.last_state_change = now;
         }
 
         // Check for significant load changes
         if report.system_load.load_avg_1min > 1.0 {
-            self.last_user_activity = Instant::now();
-            debug!("User activity detected based on system load");
+            self.heuristic_activity(now, "system load");
+        }
+    }
+
+    /// Take a guess at user activity into account, unless it is known from
+    /// input devices or logind
+    fn heuristic_activity(&mut self, now: Instant, reason: &str) {
+        if self.user_idle.is_none() {
+            self.last_user_activity = now;
+            debug!("User activity detected based on {reason}");
         }
     }
 
+    /// Changes within the alert windows, for rate-of-change alerts
+    fn observed_changes(&self) -> ObservedChanges {
+        ObservedChanges {
+            battery_drop_percent: sample_change(&self.battery_samples).map(|change| -change),
+            temperature_rise_celsius: sample_change(&self.temperature_samples),
+            discharge_watts: self.battery_discharge_watts,
+            discharge_percent_per_hour: self.battery_discharge_rate,
+            on_battery: self.on_battery,
+        }
+    }
+
+    /// Cumulative time spent in each state since the daemon started, including
+    /// the time spent in the current state so far
+    fn state_durations_so_far(&self) -> Vec<(SystemState, Duration)> {
+        let mut durations = self.state_durations.clone();
+        *durations
+            .entry(self.current_state.clone())
+            .or_insert(Duration::ZERO) += self.clock.elapsed(self.last_state_change);
+
+        let mut durations: Vec<_> = durations.into_iter().collect();
+        durations.sort_by(|(_, a), (_, b)| b.cmp(a));
+        durations
+    }
+
     /// Calculate CPU usage volatility (how much it's changing)
     fn get_cpu_volatility(&self) -> f32 {
         if self.cpu_usage_history.len() < 2 {
@@ -347,6 +460,11 @@ impl SystemHistory {
 
     /// Determine if the system appears to be idle
     fn is_system_idle(&self) -> bool {
+        // Background jobs keep the CPU busy on a system nobody is using
+        if let Some(idle) = self.user_idle {
+            return activity::is_user_idle(idle);
+        }
+
         if self.cpu_usage_history.is_empty() {
             return false;
         }
@@ -364,13 +482,13 @@ impl SystemHistory {
         on_battery: bool,
     ) -> Result<u64, ControlError> {
         let params = IntervalParams {
-            base_interval: config.daemon.poll_interval_sec,
+            base_interval: profile_poll_interval(config).unwrap_or(config.daemon.poll_interval_sec),
             min_interval: config.daemon.min_poll_interval_sec,
             max_interval: config.daemon.max_poll_interval_sec,
             cpu_volatility: self.get_cpu_volatility(),
             temp_volatility: self.get_temperature_volatility(),
             battery_discharge_rate: self.battery_discharge_rate,
-            last_user_activity: self.last_user_activity.elapsed(),
+            last_user_activity: self.clock.elapsed(self.last_user_activity),
             is_system_idle: self.is_system_idle(),
             on_battery,
         };
@@ -379,6 +497,94 @@ impl SystemHistory {
     }
 }
 
+/// Poll interval set by the profile applied last, overriding `poll_interval_sec`
+/// of the `[daemon]` section
+fn profile_poll_interval(config: &AppConfig) -> Option<u64> {
+    let profile = engine::active_profile()?;
+    config.profile(&profile)?.poll_interval_sec
+}
+
+/// Record a sample and drop those that have fallen out of `window`
+fn push_sample(samples: &mut VecDeque<(Instant, f32)>, now: Instant, value: f32, window: Duration) {
+    samples.push_back((now, value));
+    while samples
+        .front()
+        .is_some_and(|(time, _)| now.duration_since(*time) > window)
+    {
+        samples.pop_front();
+    }
+}
+
+/// Difference between the newest and oldest sample in a window
+fn sample_change(samples: &VecDeque<(Instant, f32)>) -> Option<f32> {
+    let (_, oldest) = samples.front()?;
+    let (_, newest) = samples.back()?;
+    (samples.len() > 1).then(|| newest - oldest)
+}
+
+/// Stops writing settings after repeated apply failures (e.g. firmware that has
+/// gone read-only) and only retries periodically until applying succeeds again
+struct SafeMode {
+    /// Consecutive failures before entering safe mode, 0 disables it
+    threshold: u32,
+    retry_interval: Duration,
+    consecutive_failures: u32,
+    active_since: Option<Instant>,
+    last_attempt: Option<Instant>,
+}
+
+impl SafeMode {
+    const fn new(threshold: u32, retry_interval: Duration) -> Self {
+        Self {
+            threshold,
+            retry_interval,
+            consecutive_failures: 0,
+            active_since: None,
+            last_attempt: None,
+        }
+    }
+
+    const fn is_active(&self) -> bool {
+        self.active_since.is_some()
+    }
+
+    /// Whether settings should be applied in this cycle
+    fn should_apply(&self, now: Instant) -> bool {
+        !self.is_active()
+            || self
+                .last_attempt
+                .is_none_or(|last| now.saturating_duration_since(last) >= self.retry_interval)
+    }
+
+    fn record_success(&mut self, now: Instant) {
+        if let Some(since) = self.active_since {
+            info!(
+                "Settings applied successfully again, leaving safe mode after {}s",
+                now.saturating_duration_since(since).as_secs()
+            );
+        }
+        self.consecutive_failures = 0;
+        self.active_since = None;
+        self.last_attempt = None;
+    }
+
+    fn record_failure(&mut self, now: Instant) {
+        self.consecutive_failures += 1;
+        self.last_attempt = Some(now);
+
+        if !self.is_active() && self.threshold > 0 && self.consecutive_failures >= self.threshold {
+            self.active_since = Some(now);
+            let message = format!(
+                "Applying settings failed {} times in a row, entering safe mode. Settings will be retried every {}s.",
+                self.consecutive_failures,
+                self.retry_interval.as_secs()
+            );
+            error!("{message}");
+            notify::send("Superfreq entered safe mode", &message);
+        }
+    }
+}
+
 /// Validates that poll interval configuration is consistent
 /// Returns Ok if configuration is valid, Err with a descriptive message if invalid
 fn validate_poll_intervals(min_interval: u64, max_interval: u64) -> Result<(), ControlError> {
@@ -401,8 +607,50 @@ fn validate_poll_intervals(min_interval: u64, max_interval: u64) -> Result<(), C
     }
 }
 
-/// Run the daemon
-pub fn run_daemon(config: AppConfig, verbose: bool) -> Result<(), AppError> {
+/// Settings passed to `superfreq daemon` that take precedence over the
+/// configuration file
+#[derive(Debug, Default, Clone)]
+pub struct DaemonOverrides {
+    pub poll_interval_sec: Option<u64>,
+    pub profile: Option<String>,
+    pub no_adaptive: bool,
+    pub stats_file_path: Option<String>,
+}
+
+impl DaemonOverrides {
+    /// Apply the overrides to `config`, failing if the profile does not exist
+    pub fn apply(&self, config: &mut AppConfig) -> Result<(), AppError> {
+        if let Some(interval) = self.poll_interval_sec {
+            info!("Poll interval set to {interval}s on the command line");
+            config.daemon.poll_interval_sec = interval;
+        }
+        if self.no_adaptive {
+            info!("Adaptive polling disabled on the command line");
+            config.daemon.adaptive_interval = false;
+        }
+        if let Some(path) = &self.stats_file_path {
+            info!("Stats file set to {path} on the command line");
+            config.daemon.stats_file_path = Some(path.clone());
+        }
+        if let Some(profile) = &self.profile {
+            if config.profile(profile).is_none() {
+                return Err(AppError::Generic(format!("No profile named '{profile}'")));
+            }
+            info!("Profile '{profile}' pinned on the command line");
+            config.daemon.profile = Some(profile.clone());
+        }
+        Ok(())
+    }
+}
+
+/// Run the daemon. The configuration is reloaded from `config_path`, with the
+/// overrides applied again, when the file changes or on SIGHUP.
+pub fn run_daemon(
+    config: AppConfig,
+    overrides: DaemonOverrides,
+    config_path: Option<PathBuf>,
+    verbose: bool,
+) -> Result<(), AppError> {
     // Set effective log level based on config and verbose flag
     let effective_log_level = if verbose {
         LogLevel::Debug
@@ -421,6 +669,23 @@ pub fn run_daemon(config: AppConfig, verbose: bool) -> Result<(), AppError> {
     // Update the log level filter if needed, without re-initializing the logger
     log::set_max_level(level_filter);
 
+    logging::set_format(config.daemon.log_format);
+
+    if let Some(log_path) = &config.daemon.log_file_path {
+        let rotation = Rotation {
+            max_size_bytes: config.daemon.log_max_size_mb.saturating_mul(1024 * 1024),
+            max_age: config
+                .daemon
+                .log_max_age_days
+                .map(|days| Duration::from_secs(days.saturating_mul(24 * 60 * 60))),
+            max_files: config.daemon.log_max_files,
+        };
+        match logging::log_to_file(Path::new(log_path), rotation) {
+            Ok(()) => info!("Logging to {log_path}"),
+            Err(e) => error!("Cannot log to {log_path}, logging to stderr instead: {e}"),
+        }
+    }
+
     info!("Starting superfreq daemon...");
 
     // Validate critical configuration values before proceeding
@@ -431,16 +696,168 @@ pub fn run_daemon(config: AppConfig, verbose: bool) -> Result<(), AppError> {
         return Err(AppError::Control(err));
     }
 
-    // Create a flag that will be set to true when a signal is received
-    let running = Arc::new(AtomicBool::new(true));
-    let r = running.clone();
+    let observe = config.daemon.mode == DaemonMode::Observe;
+    if observe {
+        info!("Observe mode: logging the settings that would be applied without writing them");
+        sysfs::set_observe_only(true);
+    }
+
+    // Running alongside other services is the point of observing
+    check_conflicts(config.daemon.on_conflict, observe)?;
+
+    // The daemon waits on timers, signals and wakeups from listener threads.
+    // They are all handled on this thread, so a single-threaded runtime does.
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| AppError::Generic(format!("Failed to start the async runtime: {e}")))?;
+
+    let reload = config_path.map(|path| ConfigReload {
+        watch: ConfigWatch::new(path),
+        overrides,
+    });
+    runtime.block_on(run_event_loop(config, reload))
+}
 
-    // Set up signal handlers
-    ctrlc::set_handler(move || {
-        info!("Received shutdown signal, exiting...");
-        r.store(false, Ordering::SeqCst);
-    })
-    .map_err(|e| AppError::Generic(format!("Error setting Ctrl-C handler: {e}")))?;
+/// Look for other power management services, which would overwrite the
+/// settings superfreq applies
+fn check_conflicts(on_conflict: OnConflict, observe: bool) -> Result<(), AppError> {
+    if on_conflict == OnConflict::Ignore {
+        return Ok(());
+    }
+
+    let conflicts = conflict::detect_conflicts();
+    for conflict in &conflicts {
+        if observe {
+            info!("Observing alongside {conflict}");
+        } else {
+            warn!("Conflicting service running: {conflict}");
+        }
+    }
+    if conflicts.is_empty() || on_conflict == OnConflict::Warn || observe {
+        return Ok(());
+    }
+
+    let names: Vec<&str> = conflicts
+        .iter()
+        .map(|conflict| conflict.service.name)
+        .collect();
+    Err(AppError::Generic(format!(
+        "Not starting while other power management services are running: {}. Stop them, or set on_conflict = \"warn\" in the [daemon] section to run anyway.",
+        names.join(", ")
+    )))
+}
+
+/// What ended a wait between two daemon cycles
+enum DaemonEvent {
+    PollTimer,
+    Wakeup,
+    Reload(&'static str),
+    Shutdown(&'static str),
+}
+
+/// Signals that stop the daemon or reload its configuration
+struct DaemonSignals {
+    interrupt: Signal,
+    terminate: Signal,
+    hangup: Signal,
+}
+
+impl DaemonSignals {
+    fn new() -> std::io::Result<Self> {
+        Ok(Self {
+            interrupt: signal(SignalKind::interrupt())?,
+            terminate: signal(SignalKind::terminate())?,
+            hangup: signal(SignalKind::hangup())?,
+        })
+    }
+}
+
+/// How often the configuration file is checked for changes
+const CONFIG_WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Notices changes to the configuration file from its modification time
+struct ConfigWatch {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl ConfigWatch {
+    fn new(path: PathBuf) -> Self {
+        let modified = Self::modified(&path);
+        Self { path, modified }
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    /// Wait until the file is written, replaced or removed
+    async fn changed(&mut self) {
+        loop {
+            tokio::time::sleep(CONFIG_WATCH_INTERVAL).await;
+            let modified = Self::modified(&self.path);
+            if modified != self.modified {
+                self.modified = modified;
+                return;
+            }
+        }
+    }
+}
+
+/// The configuration file the daemon was started with, and the command line
+/// overrides to apply on top of it again
+struct ConfigReload {
+    watch: ConfigWatch,
+    overrides: DaemonOverrides,
+}
+
+impl ConfigReload {
+    /// Load the configuration again, validated as strictly as on startup
+    fn load(&self) -> Result<AppConfig, AppError> {
+        let mut config = config::load_config_from_path(Some(&self.watch.path), true)?;
+        self.overrides.apply(&mut config)?;
+        validate_poll_intervals(
+            config.daemon.min_poll_interval_sec,
+            config.daemon.max_poll_interval_sec,
+        )?;
+        Ok(config)
+    }
+}
+
+/// Wait for the poll interval to pass, or for an event that needs handling sooner
+async fn next_event(
+    timeout: Duration,
+    signals: &mut DaemonSignals,
+    watch: Option<&mut ConfigWatch>,
+) -> DaemonEvent {
+    let config_changed = async {
+        match watch {
+            Some(watch) => watch.changed().await,
+            None => std::future::pending().await,
+        }
+    };
+
+    tokio::select! {
+        _ = signals.interrupt.recv() => DaemonEvent::Shutdown("SIGINT"),
+        _ = signals.terminate.recv() => DaemonEvent::Shutdown("SIGTERM"),
+        _ = signals.hangup.recv() => DaemonEvent::Reload("SIGHUP"),
+        () = config_changed => DaemonEvent::Reload("a change to the configuration file"),
+        () = wake::requested() => DaemonEvent::Wakeup,
+        () = tokio::time::sleep(timeout) => DaemonEvent::PollTimer,
+    }
+}
+
+/// Run daemon cycles until a shutdown signal. Collecting and applying
+/// settings blocks the runtime, but only for the duration of a cycle.
+async fn run_event_loop(
+    mut config: AppConfig,
+    mut reload: Option<ConfigReload>,
+) -> Result<(), AppError> {
+    let mut signals = DaemonSignals::new()
+        .map_err(|e| AppError::Generic(format!("Error setting up signal handlers: {e}")))?;
 
     info!(
         "Daemon initialized with poll interval: {}s",
@@ -451,6 +868,117 @@ pub fn run_daemon(config: AppConfig, verbose: bool) -> Result<(), AppError> {
     if let Some(stats_path) = &config.daemon.stats_file_path {
         info!("Stats will be written to: {stats_path}");
     }
+    if let Some(history_path) = &config.daemon.history_csv_path {
+        info!("Monitoring history will be appended to: {history_path}");
+    }
+
+    #[cfg(feature = "history")]
+    let report_history = if config.history.enable {
+        match History::open(&config.history) {
+            Ok(history) => {
+                info!(
+                    "Recording report history to: {}",
+                    config.history.database_path
+                );
+                Some(history)
+            }
+            Err(e) => {
+                error!("Failed to open history database: {e}");
+                None
+            }
+        }
+    } else {
+        None
+    };
+    #[cfg(not(feature = "history"))]
+    if config.history.enable {
+        warn!("Report history is enabled, but superfreq was built without the history feature");
+    }
+
+    notify::configure(&config.notifications);
+    activity::configure(config.daemon.activity_detection);
+
+    // Firmware may reset settings across suspend, so re-apply them on resume
+    resume::spawn_listener();
+
+    // Apply the matching profile as soon as AC is plugged in or out
+    power_supply::spawn_listener();
+
+    // React to the lid being opened or closed right away if rules depend on it
+    if config.uses_lid_rules() {
+        lid::spawn_listener();
+    }
+
+    // Revert governor and EPP changes made by other tools between polls
+    if config.daemon.enforce {
+        if config.daemon.mode == DaemonMode::Observe {
+            warn!("Not enforcing settings in observe mode");
+        } else {
+            enforce::spawn_watcher();
+        }
+    }
+
+    #[cfg(feature = "dbus")]
+    let _power_profiles = if config.power_profiles.enable || config.power_profiles.system76 {
+        match power_profiles::serve(&config.power_profiles) {
+            Ok(connection) => {
+                info!("Providing the power profile D-Bus interfaces");
+                Some(connection)
+            }
+            Err(e) => {
+                error!(
+                    "Failed to provide the power profile D-Bus interfaces (is power-profiles-daemon or system76-power running?): {e}"
+                );
+                None
+            }
+        }
+    } else {
+        None
+    };
+    #[cfg(feature = "http")]
+    if config.http.enable {
+        let profiles = ["charger", "battery"]
+            .into_iter()
+            .map(str::to_string)
+            .chain(config.profiles.keys().cloned())
+            .collect();
+        if let Err(e) = http::serve(&config.http, profiles) {
+            error!(
+                "Failed to serve the HTTP API on {}: {e}",
+                config.http.listen
+            );
+        }
+    }
+    #[cfg(not(feature = "http"))]
+    if config.http.enable {
+        warn!("The HTTP API is enabled, but superfreq was built without the http feature");
+    }
+    #[cfg(feature = "mqtt")]
+    if config.mqtt.enable {
+        let profiles = ["charger", "battery"]
+            .into_iter()
+            .map(str::to_string)
+            .chain(config.profiles.keys().cloned())
+            .collect();
+        mqtt::spawn(&config.mqtt, profiles);
+    }
+    #[cfg(not(feature = "mqtt"))]
+    if config.mqtt.enable {
+        warn!("MQTT is enabled, but superfreq was built without the mqtt feature");
+    }
+    #[cfg(not(feature = "dbus"))]
+    if config.power_profiles.enable {
+        warn!("Power profiles are enabled, but superfreq was built without the dbus feature");
+    }
+    #[cfg(not(feature = "dbus"))]
+    if config.battery_backend == crate::config::BatteryBackend::Upower {
+        warn!("The UPower battery backend requires the dbus feature, reading batteries from sysfs");
+    }
+
+    // Everything that needs root to start is running by now
+    if let Some(user) = &config.daemon.user {
+        drop_privileges(&config, user)?;
+    }
 
     // Variables for adaptive polling
     // Make sure that the poll interval is *never* zero to prevent a busy loop
@@ -458,46 +986,112 @@ pub fn run_daemon(config: AppConfig, verbose: bool) -> Result<(), AppError> {
     if config.daemon.poll_interval_sec == 0 {
         warn!("Poll interval is set to zero in config, using 1s minimum to prevent a busy loop");
     }
-    let mut system_history = SystemHistory::default();
+    let clock = SystemClock;
+    let mut system_history = SystemHistory::<SystemClock>::default();
+    let mut alert_tracker = AlertTracker::default();
+    let mut safe_mode = SafeMode::new(
+        config.daemon.safe_mode_failures,
+        Duration::from_secs(config.daemon.safe_mode_retry_sec),
+    );
 
     // Main loop
-    while running.load(Ordering::SeqCst) {
-        let start_time = Instant::now();
+    loop {
+        let start_time = clock.now();
 
         match monitor::collect_system_report(&config) {
             Ok(report) => {
                 debug!("Collected system report, applying settings...");
 
+                for collection_error in &report.collection_errors {
+                    warn!(
+                        "Could not collect data for core {}: {}",
+                        collection_error.core_id, collection_error.kind
+                    );
+                }
+
                 // Store the current state before updating history
                 let previous_state = system_history.current_state.clone();
 
                 // Update system history with new data
                 system_history.update(&report);
+                alert_tracker.check(
+                    &config.alerts,
+                    &system_history.observed_changes(),
+                    clock.now(),
+                );
+
+                #[cfg(feature = "http")]
+                http::set_report(&report);
+
+                // Advance a battery calibration started with `battery calibrate`
+                calibration::step(&report);
+
+                // Protect against data loss on a battery about to run out
+                critical_battery::check(&report, &config.battery);
 
                 // Update the stats file if configured
                 if let Some(stats_path) = &config.daemon.stats_file_path {
-                    if let Err(e) = write_stats_file(stats_path, &report) {
+                    if let Err(e) = write_stats_file(
+                        stats_path,
+                        config.daemon.stats_file_format,
+                        &report,
+                        safe_mode.is_active(),
+                        &system_history,
+                    ) {
                         error!("Failed to write stats file: {e}");
                     }
                 }
 
-                match engine::determine_and_apply_settings(&report, &config, None) {
-                    Ok(()) => {
-                        debug!("Successfully applied system settings");
+                if let Some(history_path) = &config.daemon.history_csv_path {
+                    if let Err(e) = append_history_csv(history_path, &report) {
+                        error!("Failed to append to history CSV: {e}");
+                    }
+                }
 
-                        // If system state changed, log the new state
-                        if system_history.current_state != previous_state {
-                            info!(
-                                "System state changed to: {:?}",
-                                system_history.current_state
-                            );
-                        }
+                #[cfg(feature = "history")]
+                if let Some(history) = &report_history {
+                    if let Err(e) = history.record(&report) {
+                        error!("Failed to record report history: {e}");
                     }
-                    Err(e) => {
-                        error!("Error applying system settings: {e}");
+                }
+
+                if safe_mode.should_apply(start_time) {
+                    match engine::determine_and_apply_settings(&report, &config, None) {
+                        Ok(()) => {
+                            debug!("Successfully applied system settings");
+                            safe_mode.record_success(clock.now());
+
+                            // If system state changed, log the new state
+                            if system_history.current_state != previous_state {
+                                info!(
+                                    "System state changed to: {:?}",
+                                    system_history.current_state
+                                );
+                            }
+                        }
+                        Err(e) if e.is_still_failing() => {
+                            // Logged in full when the setting first failed
+                            debug!("Error applying system settings: {e}");
+                            safe_mode.record_failure(clock.now());
+                        }
+                        Err(e) => {
+                            error!("Error applying system settings: {e}");
+                            safe_mode.record_failure(clock.now());
+                        }
                     }
+                } else {
+                    // Entering and leaving safe mode are logged by SafeMode
+                    debug!(
+                        "Safe mode: not applying settings after {} consecutive failures, retrying every {}s",
+                        safe_mode.consecutive_failures,
+                        safe_mode.retry_interval.as_secs()
+                    );
                 }
 
+                // Published after applying, so the state includes the active profile
+                #[cfg(feature = "mqtt")]
+                mqtt::publish_report(&report);
+
                 // Check if we're on battery
                 let on_battery = !report.batteries.is_empty()
                     && report.batteries.first().is_some_and(|b| !b.ac_connected);
@@ -529,7 +1123,11 @@ pub fn run_daemon(config: AppConfig, verbose: bool) -> Result<(), AppError> {
                         Err(e) => {
                             // Log the error and stop the daemon when an invalid configuration is detected
                             error!("Critical configuration error: {e}");
-                            running.store(false, Ordering::SeqCst);
+                            notify::event(
+                                notify::Event::Error,
+                                "Superfreq stopped",
+                                &format!("Critical configuration error: {e}"),
+                            );
                             break;
                         }
                     }
@@ -541,6 +1139,10 @@ pub fn run_daemon(config: AppConfig, verbose: bool) -> Result<(), AppError> {
                     );
 
                     debug!("Adaptive polling: set interval to {current_poll_interval}s");
+                } else if let Some(profile_interval) = profile_poll_interval(&config) {
+                    // An interval set by the profile is used as is, including on battery
+                    current_poll_interval = profile_interval;
+                    debug!("Using the active profile's poll interval of {current_poll_interval}s");
                 } else {
                     // If adaptive polling is disabled, still apply battery-saving adjustment
                     if config.daemon.throttle_on_battery && on_battery {
@@ -569,29 +1171,183 @@ pub fn run_daemon(config: AppConfig, verbose: bool) -> Result<(), AppError> {
             }
         }
 
-        // Sleep for the remaining time in the poll interval
-        let elapsed = start_time.elapsed();
-        let poll_duration = Duration::from_secs(current_poll_interval);
-        if elapsed < poll_duration {
-            let sleep_time = poll_duration - elapsed;
-            debug!("Sleeping for {}s until next cycle", sleep_time.as_secs());
-            std::thread::sleep(sleep_time);
+        // Wait for the remaining time in the poll interval
+        let elapsed = clock.elapsed(start_time);
+        let sleep_time = Duration::from_secs(current_poll_interval).saturating_sub(elapsed);
+        debug!("Sleeping for {}s until next cycle", sleep_time.as_secs());
+        let watch = reload.as_mut().map(|reload| &mut reload.watch);
+        match next_event(sleep_time, &mut signals, watch).await {
+            DaemonEvent::PollTimer => {}
+            DaemonEvent::Wakeup => debug!("Woken up early to re-evaluate settings"),
+            DaemonEvent::Reload(cause) => {
+                let Some(reload) = &reload else {
+                    info!("Received {cause}, but there is no configuration file to reload");
+                    continue;
+                };
+                let path = reload.watch.path.display();
+                match reload.load() {
+                    Ok(new_config) => {
+                        info!("Reloaded the configuration from {path} after {cause}");
+                        config = new_config;
+                        notify::configure(&config.notifications);
+                        cpu::set_ignored_cores(config.ignored_cores.as_deref().unwrap_or_default());
+                    }
+                    Err(e) => {
+                        error!("Keeping the current configuration, reloading {path} failed: {e}");
+                    }
+                }
+            }
+            DaemonEvent::Shutdown(signal) => {
+                info!("Received {signal}, exiting...");
+                break;
+            }
         }
     }
 
+    overrides::restore_all();
+    if let Err(e) = fan::restore_auto() {
+        warn!("Failed to restore automatic fan control: {e}");
+    }
     info!("Daemon stopped");
     Ok(())
 }
 
-/// Write current system stats to a file for --stats to read
-fn write_stats_file(path: &str, report: &SystemReport) -> Result<(), std::io::Error> {
-    let mut file = File::create(path)?;
+/// Append one row describing the report to a CSV file, writing the header
+/// first if the file is new
+fn append_history_csv(path: &str, report: &SystemReport) -> Result<(), std::io::Error> {
+    let mut header =
+        String::from("timestamp,cpu_usage_percent,cpu_temp_celsius,load_1m,battery_percent");
+    for core in &report.cpu_cores {
+        let id = core.core_id;
+        let _ = write!(
+            header,
+            ",cpu{id}_freq_mhz,cpu{id}_temp_celsius,cpu{id}_usage_percent"
+        );
+    }
+
+    let path = Path::new(path);
+    rotate_history_csv(path, &header)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if file.metadata()?.len() == 0 {
+        writeln!(file, "{header}")?;
+    }
+
+    let usages: Vec<f32> = report
+        .cpu_cores
+        .iter()
+        .filter_map(|core| core.usage_percent)
+        .collect();
+    let cpu_usage = (!usages.is_empty()).then(|| usages.iter().sum::<f32>() / usages.len() as f32);
+    let timestamp = jiff::Timestamp::try_from(report.timestamp)
+        .map_or_else(|_| String::new(), |t| t.to_string());
+
+    let mut row = format!(
+        "{timestamp},{},{},{:.2},{}",
+        csv_field(cpu_usage.map(|u| format!("{u:.1}"))),
+        csv_field(
+            report
+                .cpu_global
+                .average_temperature_celsius
+                .map(|t| format!("{t:.1}"))
+        ),
+        report.system_load.load_avg_1min,
+        csv_field(
+            report
+                .batteries
+                .iter()
+                .find_map(|b| b.capacity_percent)
+                .map(|c| c.to_string())
+        ),
+    );
+    for core in &report.cpu_cores {
+        let _ = write!(
+            row,
+            ",{},{},{}",
+            csv_field(core.current_frequency_mhz.map(|f| f.to_string())),
+            csv_field(core.temperature_celsius.map(|t| format!("{t:.1}"))),
+            csv_field(core.usage_percent.map(|u| format!("{u:.1}"))),
+        );
+    }
+    writeln!(file, "{row}")
+}
+
+/// Move a CSV file whose header differs from `header` to `<path>.1`, as its
+/// rows no longer line up with the new ones, e.g. after cores went offline
+fn rotate_history_csv(path: &Path, header: &str) -> Result<(), std::io::Error> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    let mut first_line = String::new();
+    BufReader::new(file).read_line(&mut first_line)?;
+    if first_line.is_empty() || first_line.trim_end() == header {
+        return Ok(());
+    }
+
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(".1");
+    fs::rename(path, &rotated)?;
+    info!(
+        "History CSV columns changed, moved the previous file to {}",
+        Path::new(&rotated).display()
+    );
+    Ok(())
+}
+
+/// Missing values are written as empty CSV fields
+fn csv_field(value: Option<String>) -> String {
+    value.unwrap_or_default()
+}
+
+/// Write the stats file through a temporary file that replaces it, so that
+/// readers never see a partially written file
+fn write_stats_file<C: Clock>(
+    path: &str,
+    format: StatsFileFormat,
+    report: &SystemReport,
+    safe_mode: bool,
+    history: &SystemHistory<C>,
+) -> Result<(), std::io::Error> {
+    let content = match format {
+        StatsFileFormat::Text => {
+            let mut content = Vec::new();
+            write_stats_text(&mut content, report, safe_mode, history)?;
+            content
+        }
+        StatsFileFormat::Json => stats_json(report, safe_mode, history)?.into_bytes(),
+    };
+
+    let path = Path::new(path);
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    fs::write(&temp_path, content)?;
+    fs::rename(&temp_path, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp_path);
+    })
+}
 
+fn write_stats_text<C: Clock>(
+    file: &mut impl Write,
+    report: &SystemReport,
+    safe_mode: bool,
+    history: &SystemHistory<C>,
+) -> Result<(), std::io::Error> {
     writeln!(file, "timestamp={:?}", report.timestamp)?;
+    writeln!(file, "safe_mode={safe_mode}")?;
+
+    if let Some(profile) = engine::active_profile() {
+        writeln!(file, "profile={profile}")?;
+    }
 
     // CPU info
-    writeln!(file, "governor={:?}", report.cpu_global.current_governor)?;
-    writeln!(file, "turbo={:?}", report.cpu_global.turbo_status)?;
+    if let Some(governor) = &report.cpu_global.current_governor {
+        writeln!(file, "governor={governor}")?;
+    }
+    if let Some(turbo) = report.cpu_global.turbo_status {
+        writeln!(file, "turbo={turbo}")?;
+    }
     if let Some(temp) = report.cpu_global.average_temperature_celsius {
         writeln!(file, "cpu_temp={temp:.1}")?;
     }
@@ -610,6 +1366,124 @@ fn write_stats_file(path: &str, report: &SystemReport) -> Result<(), std::io::Er
     writeln!(file, "load_5m={:.2}", report.system_load.load_avg_5min)?;
     writeln!(file, "load_15m={:.2}", report.system_load.load_avg_15min)?;
 
+    // Power
+    if let Some(watts) = report.power.estimated_system_watts {
+        writeln!(file, "power_watts={watts:.2}")?;
+    }
+    if let Some(wakeups) = &report.wakeups {
+        writeln!(file, "wakeups_per_sec={:.1}", wakeups.per_second)?;
+    }
+
+    // Throttle events since boot and since the previous report
+    let throttle = &report.throttle;
+    for (name, count, delta) in [
+        (
+            "core_thermal",
+            throttle.counts.core_thermal,
+            throttle.deltas.core_thermal,
+        ),
+        (
+            "package_thermal",
+            throttle.counts.package_thermal,
+            throttle.deltas.package_thermal,
+        ),
+        (
+            "core_power_limit",
+            throttle.counts.core_power_limit,
+            throttle.deltas.core_power_limit,
+        ),
+        (
+            "package_power_limit",
+            throttle.counts.package_power_limit,
+            throttle.deltas.package_power_limit,
+        ),
+    ] {
+        if let Some(count) = count {
+            writeln!(file, "throttle.{name}={count}")?;
+        }
+        if let Some(delta) = delta {
+            writeln!(file, "throttle_delta.{name}={delta}")?;
+        }
+    }
+    if let Some(active) = throttle.prochot_active {
+        writeln!(file, "prochot={active}")?;
+    }
+
+    // Seconds spent in each state since the daemon started
+    writeln!(file, "current_state={:?}", history.current_state)?;
+    for (state, duration) in history.state_durations_so_far() {
+        writeln!(file, "state_secs.{state:?}={}", duration.as_secs())?;
+    }
+
+    Ok(())
+}
+
+/// The serialized report, with the daemon's own state under `daemon`
+fn stats_json<C: Clock>(
+    report: &SystemReport,
+    safe_mode: bool,
+    history: &SystemHistory<C>,
+) -> Result<String, std::io::Error> {
+    let mut value = serde_json::to_value(report)?;
+    let state_secs: serde_json::Map<String, serde_json::Value> = history
+        .state_durations_so_far()
+        .into_iter()
+        .map(|(state, duration)| (format!("{state:?}"), duration.as_secs().into()))
+        .collect();
+    if let Some(object) = value.as_object_mut() {
+        object.insert(
+            "daemon".to_string(),
+            serde_json::json!({
+                "safe_mode": safe_mode,
+                "profile": engine::active_profile(),
+                "current_state": format!("{:?}", history.current_state),
+                "state_secs": state_secs,
+            }),
+        );
+    }
+    Ok(value.to_string())
+}
+
+/// Files kept open when the daemon drops root: the settings it manages, the
+/// files set by the `sysfs` profile option, and the RAPL energy counters, which
+/// only root can read. Kernel tunables are left out, since `/proc/sys` checks
+/// permissions on every write rather than when a file is opened.
+fn retained_paths(config: &AppConfig) -> Vec<PathBuf> {
+    let mut paths = snapshot::managed_paths();
+
+    let profiles = [&config.charger, &config.battery]
+        .into_iter()
+        .chain(config.profiles.values());
+    for profile in profiles {
+        paths.extend(profile.sysfs.iter().map(|write| sysfs::path(&write.path)));
+    }
+
+    if let Ok(entries) = fs::read_dir(sysfs::path("/sys/class/powercap")) {
+        paths.extend(
+            entries
+                .flatten()
+                .map(|entry| entry.path().join("energy_uj"))
+                .filter(|path| path.exists()),
+        );
+    }
+    paths
+}
+
+/// Keep the files the daemon reads and writes open, then switch to `user`, so
+/// that a compromised daemon can do less than root could
+fn drop_privileges(config: &AppConfig, user: &str) -> Result<(), AppError> {
+    if !privilege::is_root() {
+        warn!("Not running as root, so not switching to user '{user}'");
+        return Ok(());
+    }
+
+    let retained = sysfs::retain_handles(retained_paths(config));
+    privilege::drop_to_user(user)
+        .map_err(|e| AppError::Generic(format!("Failed to switch to user '{user}': {e}")))?;
+    info!("Dropped root privileges, running as '{user}' with {retained} files kept open");
+    warn!(
+        "Settings outside the kept files, such as kernel tunables, governor tunables, idle states and power limits, cannot be applied without root"
+    );
     Ok(())
 }
 
@@ -627,7 +1501,10 @@ enum SystemState {
 }
 
 /// Determine the current system state for adaptive polling
-fn determine_system_state(report: &SystemReport, history: &SystemHistory) -> SystemState {
+fn determine_system_state<C: Clock>(
+    report: &SystemReport,
+    history: &SystemHistory<C>,
+) -> SystemState {
     // Check power state first
     if !report.batteries.is_empty() {
         if let Some(battery) = report.batteries.first() {
@@ -669,3 +1546,130 @@ fn determine_system_state(report: &SystemReport, history: &SystemHistory) -> Sys
     // Default case
     SystemState::Unknown
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::clock::ManualClock;
+
+    const RETRY: Duration = Duration::from_secs(300);
+
+    #[test]
+    fn safe_mode_enters_after_threshold_and_retries_periodically() {
+        let clock = ManualClock::new();
+        let mut safe_mode = SafeMode::new(3, RETRY);
+
+        for _ in 0..2 {
+            safe_mode.record_failure(clock.now());
+            assert!(safe_mode.should_apply(clock.now()));
+        }
+        safe_mode.record_failure(clock.now());
+        assert!(safe_mode.is_active());
+        assert!(!safe_mode.should_apply(clock.now()));
+
+        clock.advance(RETRY - Duration::from_secs(1));
+        assert!(!safe_mode.should_apply(clock.now()));
+        clock.advance(Duration::from_secs(1));
+        assert!(safe_mode.should_apply(clock.now()));
+
+        // A failed retry waits for the next interval
+        safe_mode.record_failure(clock.now());
+        assert!(!safe_mode.should_apply(clock.now()));
+    }
+
+    #[test]
+    fn safe_mode_leaves_on_success() {
+        let clock = ManualClock::new();
+        let mut safe_mode = SafeMode::new(1, RETRY);
+
+        safe_mode.record_failure(clock.now());
+        assert!(safe_mode.is_active());
+        clock.advance(RETRY);
+        safe_mode.record_success(clock.now());
+        assert!(!safe_mode.is_active());
+        assert_eq!(safe_mode.consecutive_failures, 0);
+        assert!(safe_mode.should_apply(clock.now()));
+    }
+
+    #[test]
+    fn safe_mode_disabled_with_zero_threshold() {
+        let clock = ManualClock::new();
+        let mut safe_mode = SafeMode::new(0, RETRY);
+        for _ in 0..100 {
+            safe_mode.record_failure(clock.now());
+        }
+        assert!(!safe_mode.is_active());
+    }
+
+    #[test]
+    fn time_in_current_state_follows_clock() {
+        let clock = ManualClock::new();
+        let history = SystemHistory::with_clock(clock.clone());
+        clock.advance(Duration::from_secs(42));
+
+        assert_eq!(
+            history.state_durations_so_far(),
+            vec![(SystemState::Unknown, Duration::from_secs(42))]
+        );
+    }
+
+    #[test]
+    fn history_csv_with_other_columns_is_rotated() {
+        let dir = std::env::temp_dir().join(format!("superfreq-history-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("history.csv");
+        let rotated = dir.join("history.csv.1");
+
+        // A missing file, and one with the same columns, are left alone
+        rotate_history_csv(&path, "timestamp,cpu0_freq_mhz").unwrap();
+        fs::write(&path, "timestamp,cpu0_freq_mhz\n1,2000\n").unwrap();
+        rotate_history_csv(&path, "timestamp,cpu0_freq_mhz").unwrap();
+        assert!(path.exists());
+        assert!(!rotated.exists());
+
+        rotate_history_csv(&path, "timestamp,cpu0_freq_mhz,cpu1_freq_mhz").unwrap();
+        assert!(!path.exists());
+        assert_eq!(
+            fs::read_to_string(&rotated).unwrap(),
+            "timestamp,cpu0_freq_mhz\n1,2000\n"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reload_applies_overrides_and_rejects_invalid_files() {
+        let dir = std::env::temp_dir().join(format!("superfreq-reload-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(
+            &path,
+            "[daemon]\npoll_interval_sec = 5\n\n[profiles.quiet]\ngovernor = \"powersave\"\n",
+        )
+        .unwrap();
+
+        let reload = ConfigReload {
+            watch: ConfigWatch::new(path.clone()),
+            overrides: DaemonOverrides {
+                poll_interval_sec: Some(7),
+                profile: Some("quiet".to_string()),
+                ..DaemonOverrides::default()
+            },
+        };
+        let config = reload.load().unwrap();
+        assert_eq!(config.daemon.poll_interval_sec, 7);
+        assert_eq!(config.daemon.profile.as_deref(), Some("quiet"));
+
+        // The pinned profile is gone
+        fs::write(&path, "[daemon]\npoll_interval_sec = 5\n").unwrap();
+        assert!(reload.load().is_err());
+
+        fs::write(&path, "[daemon\n").unwrap();
+        assert!(matches!(reload.load(), Err(AppError::Config(_))));
+
+        fs::write(&path, "[daemon]\nmin_poll_interval_sec = 0\n").unwrap();
+        assert!(reload.load().is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,72 @@
+use crate::config::DockConfig;
+use crate::util::sysfs;
+use std::fs;
+use std::path::Path;
+
+const USB_DEVICES_DIR: &str = "/sys/bus/usb/devices";
+const THUNDERBOLT_DEVICES_DIR: &str = "/sys/bus/thunderbolt/devices";
+const DRM_DIR: &str = "/sys/class/drm";
+
+/// Connector types of built-in panels, which are connected whether docked or not
+const INTERNAL_CONNECTORS: &[&str] = &["eDP", "LVDS", "DSI"];
+
+/// Whether the system is docked: one of the configured dock devices is
+/// present, or an external display is connected.
+pub fn is_docked(config: &DockConfig) -> bool {
+    let devices: Vec<(u16, u16)> = config
+        .devices
+        .iter()
+        .filter_map(|id| DockConfig::parse_device_id(id))
+        .collect();
+
+    (!devices.is_empty()
+        && (has_device(USB_DEVICES_DIR, "idVendor", "idProduct", &devices)
+            || has_device(THUNDERBOLT_DEVICES_DIR, "vendor", "device", &devices)))
+        || (config.external_displays && has_external_display())
+}
+
+/// Parse a hex ID as written by the USB (`17ef`) and Thunderbolt (`0x108`) buses
+fn read_hex_id(path: &Path) -> Option<u16> {
+    let id = fs::read_to_string(path).ok()?;
+    let id = id.trim();
+    u16::from_str_radix(id.strip_prefix("0x").unwrap_or(id), 16).ok()
+}
+
+fn has_device(dir: &str, vendor_file: &str, product_file: &str, devices: &[(u16, u16)]) -> bool {
+    let Ok(entries) = fs::read_dir(sysfs::path(dir)) else {
+        return false;
+    };
+
+    entries.flatten().any(|entry| {
+        let path = entry.path();
+        let (Some(vendor), Some(product)) = (
+            read_hex_id(&path.join(vendor_file)),
+            read_hex_id(&path.join(product_file)),
+        ) else {
+            return false;
+        };
+        devices.contains(&(vendor, product))
+    })
+}
+
+/// Whether a display is connected to a connector other than the built-in
+/// panel. Connectors are named e.g. `card1-DP-3` or `card0-eDP-1`.
+fn has_external_display() -> bool {
+    let Ok(entries) = fs::read_dir(sysfs::path(DRM_DIR)) else {
+        return false;
+    };
+
+    entries.flatten().any(|entry| {
+        let name = entry.file_name();
+        let Some((_, connector)) = name.to_str().and_then(|name| name.split_once('-')) else {
+            return false;
+        };
+        let internal = INTERNAL_CONNECTORS
+            .iter()
+            .any(|internal| connector.starts_with(&format!("{internal}-")));
+
+        !internal
+            && fs::read_to_string(entry.path().join("status"))
+                .is_ok_and(|status| status.trim() == "connected")
+    })
+}
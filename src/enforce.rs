@@ -0,0 +1,245 @@
+use crate::conflict;
+use crate::core::CpufreqPolicy;
+use crate::cpu;
+use crate::monitor;
+use crate::util::sysfs;
+use crate::util::wake;
+use log::{debug, info, warn};
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::{Mutex, Once, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+/// How often the governor and EPP are compared to what was applied
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Values the daemon applied in a cycle, or `None` for settings it leaves
+/// alone. Core groups and clusters write their own values to the policies of
+/// the cores they are written through, which take precedence.
+#[derive(Debug, Clone, Default)]
+pub struct Applied {
+    governor: Option<String>,
+    epp: Option<String>,
+    /// Values written through a single core, keyed by that core
+    core_governors: BTreeMap<u32, String>,
+    core_epps: BTreeMap<u32, String>,
+}
+
+impl Applied {
+    /// Record a governor written to every policy, or to the policy of `core_id`
+    pub fn set_governor(&mut self, core_id: Option<u32>, governor: &str) {
+        match core_id {
+            Some(core_id) => {
+                self.core_governors.insert(core_id, governor.to_string());
+            }
+            None => self.governor = Some(governor.to_string()),
+        }
+    }
+
+    /// Record an EPP written to every policy, or to the policy of `core_id`
+    pub fn set_epp(&mut self, core_id: Option<u32>, epp: &str) {
+        match core_id {
+            Some(core_id) => {
+                self.core_epps.insert(core_id, epp.to_string());
+            }
+            None => self.epp = Some(epp.to_string()),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.governor.is_none()
+            && self.epp.is_none()
+            && self.core_governors.is_empty()
+            && self.core_epps.is_empty()
+    }
+
+    /// The governor and EPP expected on a policy
+    fn expected(&self, policy: &CpufreqPolicy) -> [(&'static str, &'static str, Option<&str>); 2] {
+        [
+            (
+                "governor",
+                "scaling_governor",
+                expected_on(policy, &self.core_governors, self.governor.as_deref()),
+            ),
+            (
+                "EPP",
+                "energy_performance_preference",
+                expected_on(policy, &self.core_epps, self.epp.as_deref()),
+            ),
+        ]
+    }
+}
+
+/// The value written through a core of `policy`, or else the global value
+fn expected_on<'a>(
+    policy: &CpufreqPolicy,
+    per_core: &'a BTreeMap<u32, String>,
+    global: Option<&'a str>,
+) -> Option<&'a str> {
+    policy
+        .cpus
+        .iter()
+        .find_map(|cpu| per_core.get(cpu))
+        .map(String::as_str)
+        .or(global)
+}
+
+static APPLIED: Mutex<Applied> = Mutex::new(Applied {
+    governor: None,
+    epp: None,
+    core_governors: BTreeMap::new(),
+    core_epps: BTreeMap::new(),
+});
+
+static WATCHER: Once = Once::new();
+
+/// A setting of a cpufreq policy that differs from what was applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Drift {
+    policy: u32,
+    setting: &'static str,
+    expected: String,
+    found: String,
+}
+
+/// Record the governor and EPP written in the latest cycle. Settings that
+/// were not written are not enforced.
+pub fn set_applied(applied: Applied) {
+    *APPLIED.lock().unwrap_or_else(PoisonError::into_inner) = applied;
+}
+
+/// Start a background thread that notices when another tool changes the
+/// governor or EPP between polls, and wakes the daemon to write them back
+pub fn spawn_watcher() {
+    WATCHER.call_once(|| {
+        let spawned = thread::Builder::new()
+            .name("enforce".to_string())
+            .spawn(watch);
+
+        match spawned {
+            Ok(_) => info!("Enforcing the applied governor and EPP"),
+            Err(e) => warn!("Failed to start the enforcement watcher: {e}"),
+        }
+    });
+}
+
+fn watch() {
+    let mut reported = Vec::new();
+    loop {
+        thread::sleep(CHECK_INTERVAL);
+
+        let drift = find_drift();
+        if drift.is_empty() || drift == reported {
+            // Only report a drift once, in case reverting it keeps failing
+            reported = drift;
+            continue;
+        }
+
+        let culprit = likely_culprit();
+        for drift in &drift {
+            warn!(
+                "The {} of policy{} was changed from '{}' to '{}', likely by {culprit}. Reverting it.",
+                drift.setting, drift.policy, drift.expected, drift.found
+            );
+        }
+        wake::request_wakeup("governor or EPP changed externally");
+        reported = drift;
+    }
+}
+
+/// Compare the governor and EPP of every managed cpufreq policy to what was applied
+fn find_drift() -> Vec<Drift> {
+    let applied = APPLIED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone();
+    if applied.is_empty() {
+        return Vec::new();
+    }
+
+    let mut drift = Vec::new();
+    for policy in monitor::get_cpufreq_policies() {
+        // Policies with ignored cores are managed elsewhere
+        if policy.cpus.iter().any(|&cpu| cpu::is_core_ignored(cpu)) {
+            continue;
+        }
+
+        let path =
+            sysfs::path("/sys/devices/system/cpu/cpufreq").join(format!("policy{}", policy.id));
+        for (setting, file, expected) in applied.expected(&policy) {
+            let Some(expected) = expected else {
+                continue;
+            };
+            let Ok(found) = fs::read_to_string(path.join(file)) else {
+                continue;
+            };
+            let found = found.trim();
+            if found != expected {
+                drift.push(Drift {
+                    policy: policy.id,
+                    setting,
+                    expected: expected.to_string(),
+                    found: found.to_string(),
+                });
+            }
+        }
+    }
+    drift
+}
+
+/// The running power management services that are known to change the
+/// governor or EPP, or a generic description if there are none
+fn likely_culprit() -> String {
+    let conflicts = conflict::detect_conflicts();
+    if conflicts.is_empty() {
+        debug!("No known power management service is running");
+        return "another program or a manual write".to_string();
+    }
+
+    conflicts
+        .iter()
+        .map(|conflict| conflict.service.name)
+        .collect::<Vec<_>>()
+        .join(" or ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(id: u32, cpus: &[u32]) -> CpufreqPolicy {
+        CpufreqPolicy {
+            id,
+            cpus: cpus.to_vec(),
+        }
+    }
+
+    #[test]
+    fn core_group_values_take_precedence_per_policy() {
+        let mut applied = Applied::default();
+        applied.set_governor(None, "powersave");
+        applied.set_epp(None, "power");
+        applied.set_governor(Some(5), "performance");
+
+        let little = applied.expected(&policy(0, &[0, 1, 2, 3]));
+        assert_eq!(little[0].2, Some("powersave"));
+        assert_eq!(little[1].2, Some("power"));
+
+        let big = applied.expected(&policy(4, &[4, 5]));
+        assert_eq!(big[0].2, Some("performance"));
+        assert_eq!(big[1].2, Some("power"));
+    }
+
+    #[test]
+    fn settings_not_written_are_not_enforced() {
+        let applied = Applied::default();
+        assert!(applied.is_empty());
+        assert!(
+            applied
+                .expected(&policy(0, &[0]))
+                .iter()
+                .all(|(_, _, value)| value.is_none())
+        );
+    }
+}
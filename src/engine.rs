@@ -1,11 +1,41 @@
+//! Selecting the profile for a [`SystemReport`] and applying its settings, as
+//! the daemon does on every poll.
+
+use crate::alerts;
+use crate::auto_epp;
 use crate::battery;
-use crate::config::{AppConfig, ProfileConfig, TurboAutoSettings};
-use crate::core::{OperationalMode, SystemReport, TurboSetting};
+use crate::calibration;
+use crate::config::{
+    AppConfig, CoreGroupConfig, ProfileConfig, ProfileRule, RuleConditions, TurboAutoSettings,
+    TurboController, TurboLoadMetric, TurboPidSettings, TurboPidTarget, parse_policy_name,
+};
+use crate::core::{BoostControl, CoreType, OperationalMode, SystemReport, TurboSetting};
 use crate::cpu::{self};
+use crate::enforce;
+use crate::fan;
+use crate::gpu;
+#[cfg(feature = "http")]
+use crate::http;
+use crate::keyboard_backlight;
+#[cfg(feature = "mqtt")]
+use crate::mqtt;
+use crate::overrides;
+#[cfg(feature = "dbus")]
+use crate::power_profiles;
+use crate::thermal::{self, Emergency, ThermalCap};
+use crate::userspace;
+use crate::util::clock::{Clock, SystemClock};
 use crate::util::error::{ControlError, EngineError};
+use crate::util::notify;
+use crate::util::sysfs;
+use crate::vendor;
+use crate::virt;
 use log::{debug, info, warn};
-use std::sync::OnceLock;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, Once, OnceLock};
+use std::time::{Duration, Instant};
 
 /// Track turbo boost state for AC and battery power modes
 struct TurboHysteresisStates {
@@ -47,6 +77,10 @@ struct TurboHysteresis {
     previous_state: AtomicBool,
     /// Whether the hysteresis state has been initialized
     initialized: AtomicBool,
+    /// Whether turbo is held off until the CPU cools below the resume threshold
+    thermal_lockout: AtomicBool,
+    /// State of the PID controller, used with `controller = "pid"`
+    pid: Mutex<TurboPid>,
 }
 
 impl TurboHysteresis {
@@ -54,6 +88,8 @@ impl TurboHysteresis {
         Self {
             previous_state: AtomicBool::new(false),
             initialized: AtomicBool::new(false),
+            thermal_lockout: AtomicBool::new(false),
+            pid: Mutex::new(TurboPid::new()),
         }
     }
 
@@ -92,6 +128,14 @@ impl TurboHysteresis {
         }
     }
 
+    fn is_thermally_locked_out(&self) -> bool {
+        self.thermal_lockout.load(Ordering::Acquire)
+    }
+
+    fn set_thermal_lockout(&self, locked_out: bool) {
+        self.thermal_lockout.store(locked_out, Ordering::Release);
+    }
+
     /// Update the turbo state for hysteresis
     fn update_state(&self, new_state: bool) {
         // First store the new state, then mark as initialized
@@ -115,11 +159,118 @@ impl TurboHysteresis {
     }
 }
 
+/// Longest time between two PID updates that is integrated, so a suspend or a
+/// stretch under another controller does not wind up the integral
+const MAX_PID_STEP: Duration = Duration::from_secs(30);
+
+/// Largest contribution of the integral term to the PID output
+const PID_INTEGRAL_LIMIT: f32 = 50.0;
+
+/// PID controller for auto turbo. Turbo can only be on or off, so it is
+/// enabled while the output is positive. The integral makes that follow the
+/// average of an oscillating load instead of every swing.
+#[derive(Debug)]
+struct TurboPid {
+    integral: f32,
+    /// Time and error of the previous update
+    previous: Option<(Instant, f32)>,
+}
+
+impl TurboPid {
+    const fn new() -> Self {
+        Self {
+            integral: 0.0,
+            previous: None,
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Feed the error of this poll, positive when turbo is wanted, and return
+    /// the controller output
+    fn update(&mut self, settings: &TurboPidSettings, error: f32, now: Instant) -> f32 {
+        let (dt, derivative) = match self.previous {
+            Some((at, previous_error)) => {
+                let dt = now.duration_since(at).min(MAX_PID_STEP).as_secs_f32();
+                let derivative = if dt > 0.0 {
+                    (error - previous_error) / dt
+                } else {
+                    0.0
+                };
+                (dt, derivative)
+            }
+            None => (0.0, 0.0),
+        };
+        self.previous = Some((now, error));
+
+        if settings.ki > 0.0 {
+            let limit = PID_INTEGRAL_LIMIT / settings.ki;
+            self.integral = (self.integral + error * dt).clamp(-limit, limit);
+        }
+
+        settings.kp * error + settings.ki * self.integral + settings.kd * derivative
+    }
+}
+
+/// Whether a profile has disabled idle states that must be restored later
+static IDLE_STATES_LIMITED: AtomicBool = AtomicBool::new(false);
+
+/// Whether the current thermal emergency has warned that idle injection is unavailable
+static IDLE_INJECTION_WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Delay before retrying a feature after its first failure. It doubles with
+/// every further failure, up to `MAX_RETRY_DELAY`.
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(30);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// A feature whose value keeps failing to apply
+#[derive(Debug)]
+struct FailingFeature {
+    /// The value that failed. Selecting another value retries right away.
+    value: String,
+    failures: u32,
+    retry_at: Instant,
+}
+
+/// Features that failed to apply, keyed by feature name
+static FAILING_FEATURES: Mutex<BTreeMap<String, FailingFeature>> = Mutex::new(BTreeMap::new());
+
+/// Errors of the settings that failed to apply in a cycle
+#[derive(Debug, Default)]
+struct CycleErrors(Vec<EngineError>);
+
+impl CycleErrors {
+    fn record(&mut self, result: Result<(), EngineError>) {
+        if let Err(e) = result {
+            self.0.push(e);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn into_result(mut self) -> Result<(), EngineError> {
+        match self.0.len() {
+            0 => Ok(()),
+            1 => Err(self.0.remove(0)),
+            _ => Err(EngineError::Several(self.0)),
+        }
+    }
+}
+
 /// Try applying a CPU feature and handle common error cases. Centralizes the where we
 /// previously did:
 /// 1. Try to apply a feature setting
 /// 2. If not supported, log a warning and continue
 /// 3. If other error, propagate the error
+///
+/// A feature that keeps failing with the same value is retried with exponential
+/// backoff, and skipped in the cycles in between. Only its first failure
+/// returns the full error. Later retries that fail again return
+/// [`EngineError::StillFailing`], so callers can log them quietly.
 fn try_apply_feature<F, T>(
     feature_name: &str,
     value_description: &str,
@@ -128,24 +279,353 @@ fn try_apply_feature<F, T>(
 where
     F: FnOnce() -> Result<T, ControlError>,
 {
-    info!("Setting {feature_name} to '{value_description}'");
+    let mut failing = FAILING_FEATURES
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    apply_with_backoff(
+        &mut failing,
+        SystemClock.now(),
+        feature_name,
+        value_description,
+        apply_fn,
+    )
+}
 
-    match apply_fn() {
-        Ok(_) => Ok(()),
-        Err(e) => {
-            if matches!(e, ControlError::NotSupported(_)) {
-                warn!(
-                    "{feature_name} setting is not supported on this system. Skipping {feature_name} configuration."
-                );
-                Ok(())
+/// [`try_apply_feature`] with the failing features and the current time
+/// passed in
+fn apply_with_backoff<F, T>(
+    failing: &mut BTreeMap<String, FailingFeature>,
+    now: Instant,
+    feature_name: &str,
+    value_description: &str,
+    apply_fn: F,
+) -> Result<(), EngineError>
+where
+    F: FnOnce() -> Result<T, ControlError>,
+{
+    if let Some(feature) = failing.get(feature_name) {
+        if feature.value == value_description && now < feature.retry_at {
+            debug!(
+                "Not setting {feature_name} to '{value_description}' after {} failures, retrying in {}s",
+                feature.failures,
+                feature.retry_at.duration_since(now).as_secs()
+            );
+            return Ok(());
+        }
+    }
+
+    if sysfs::is_observe_only() {
+        info!("Would set {feature_name} to '{value_description}'");
+    } else {
+        info!("Setting {feature_name} to '{value_description}'");
+    }
+
+    let error = match apply_fn() {
+        Ok(_) => {
+            if failing.remove(feature_name).is_some() {
+                info!("{feature_name} was set successfully after failing before");
+            }
+            return Ok(());
+        }
+        Err(e) => e,
+    };
+
+    let feature = failing
+        .entry(feature_name.to_string())
+        .and_modify(|feature| {
+            if feature.value != value_description {
+                feature.value = value_description.to_string();
+                feature.failures = 0;
+            }
+        })
+        .or_insert_with(|| FailingFeature {
+            value: value_description.to_string(),
+            failures: 0,
+            retry_at: now,
+        });
+    feature.failures += 1;
+    let delay = INITIAL_RETRY_DELAY
+        .saturating_mul(2u32.saturating_pow(feature.failures - 1))
+        .min(MAX_RETRY_DELAY);
+    feature.retry_at = now + delay;
+    let first_failure = feature.failures == 1;
+
+    if matches!(error, ControlError::NotSupported(_)) {
+        if first_failure {
+            warn!(
+                "{feature_name} setting is not supported on this system. Skipping {feature_name} configuration."
+            );
+        } else {
+            debug!(
+                "{feature_name} is still not supported, retrying in {}s",
+                delay.as_secs()
+            );
+        }
+        Ok(())
+    } else if first_failure {
+        // Propagate all other errors, including InvalidValueError
+        Err(EngineError::ControlError(error))
+    } else {
+        // The error was already reported when the feature first failed
+        debug!(
+            "Setting {feature_name} failed {} times in a row, retrying in {}s: {error}",
+            feature.failures,
+            delay.as_secs()
+        );
+        Err(EngineError::StillFailing {
+            feature: feature_name.to_string(),
+            failures: feature.failures,
+            retry_in: delay,
+        })
+    }
+}
+
+/// Where the governor applied in a cycle comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GovernorSource {
+    /// Persistent override set with `force-governor`
+    Override,
+    /// The `governor` field of the selected profile
+    Profile,
+}
+
+impl fmt::Display for GovernorSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Override => write!(f, "Override"),
+            Self::Profile => write!(f, "Configured"),
+        }
+    }
+}
+
+/// Resolve the governor to apply for this cycle.
+///
+/// A non-empty governor override takes precedence over the profile's governor,
+/// so the two are never both written in the same cycle.
+fn resolve_governor<'a>(
+    override_governor: Option<&'a str>,
+    profile: &'a ProfileConfig,
+) -> Option<(&'a str, GovernorSource)> {
+    override_governor
+        .map(str::trim)
+        .filter(|governor| !governor.is_empty())
+        .map(|governor| (governor, GovernorSource::Override))
+        .or_else(|| {
+            profile
+                .governor
+                .as_deref()
+                .map(|governor| (governor, GovernorSource::Profile))
+        })
+}
+
+/// Average CPU usage across all cores that report usage data
+fn average_cpu_usage(report: &SystemReport) -> Option<f32> {
+    let usages: Vec<f32> = report
+        .cpu_cores
+        .iter()
+        .filter_map(|core| core.usage_percent)
+        .collect();
+
+    if usages.is_empty() {
+        None
+    } else {
+        Some(usages.iter().sum::<f32>() / usages.len() as f32)
+    }
+}
+
+/// CPU usage used for auto turbo decisions, according to the configured metric
+fn turbo_load(report: &SystemReport, settings: &TurboAutoSettings) -> Option<f32> {
+    let mut usages: Vec<f32> = report
+        .cpu_cores
+        .iter()
+        .filter_map(|core| core.usage_percent)
+        .collect();
+
+    match settings.load_metric {
+        TurboLoadMetric::Average => average_cpu_usage(report),
+        TurboLoadMetric::MaxCore => usages.into_iter().reduce(f32::max),
+        TurboLoadMetric::TopCores => {
+            usages.sort_unstable_by(|a, b| b.total_cmp(a));
+            usages.truncate(settings.top_cores);
+            if usages.is_empty() {
+                None
             } else {
-                // Propagate all other errors, including InvalidValueError
-                Err(EngineError::ControlError(e))
+                Some(usages.iter().sum::<f32>() / usages.len() as f32)
             }
         }
     }
 }
 
+/// Current local time as minutes since midnight
+fn local_minute_of_day() -> u16 {
+    let now = jiff::Zoned::now();
+    (now.hour() as u16) * 60 + now.minute() as u16
+}
+
+/// Check whether every condition set on a rule holds for the current system state
+fn conditions_match(
+    conditions: &RuleConditions,
+    report: &SystemReport,
+    on_ac_power: bool,
+    minute_of_day: u16,
+) -> bool {
+    let battery_percent = report.batteries.iter().find_map(|b| b.capacity_percent);
+    let temperature = report.cpu_global.average_temperature_celsius;
+    let usage = average_cpu_usage(report);
+
+    conditions.on_ac.is_none_or(|on_ac| on_ac == on_ac_power)
+        && conditions
+            .battery_below
+            .is_none_or(|limit| battery_percent.is_some_and(|p| p < limit))
+        && conditions
+            .battery_above
+            .is_none_or(|limit| battery_percent.is_some_and(|p| p > limit))
+        && conditions
+            .temp_above
+            .is_none_or(|limit| temperature.is_some_and(|t| t > limit))
+        && conditions
+            .temp_below
+            .is_none_or(|limit| temperature.is_some_and(|t| t < limit))
+        && conditions
+            .load_above
+            .is_none_or(|limit| usage.is_some_and(|u| u > limit))
+        && conditions
+            .load_below
+            .is_none_or(|limit| usage.is_some_and(|u| u < limit))
+        && conditions
+            .time
+            .is_none_or(|window| window.contains(minute_of_day))
+        && conditions
+            .lid_closed
+            .is_none_or(|closed| report.lid_closed == Some(closed))
+        && conditions
+            .docked
+            .is_none_or(|docked| report.docked == docked)
+        && conditions.charger_watts_below.is_none_or(|limit| {
+            report
+                .power
+                .charger_watts
+                .is_some_and(|watts| watts < limit)
+        })
+}
+
+/// Find the first configured rule that matches, along with its index and profile
+fn find_matching_rule<'a>(
+    report: &SystemReport,
+    config: &'a AppConfig,
+    on_ac_power: bool,
+) -> Option<(usize, &'a ProfileRule, &'a ProfileConfig)> {
+    if config.rules.is_empty() {
+        return None;
+    }
+
+    let minute_of_day = local_minute_of_day();
+    config
+        .rules
+        .iter()
+        .enumerate()
+        .filter(|(_, rule)| conditions_match(&rule.when, report, on_ac_power, minute_of_day))
+        .find_map(|(index, rule)| match config.profile(&rule.profile) {
+            Some(profile) => Some((index, rule, profile)),
+            None => {
+                warn!(
+                    "Rule #{} refers to unknown profile '{}'. Skipping.",
+                    index + 1,
+                    rule.profile
+                );
+                None
+            }
+        })
+}
+
+/// Logs the monitoring-only notice on the first selection in a VM or container
+static VIRTUALIZED_NOTICE: Once = Once::new();
+
+/// Name of the profile applied last, to notice profile switches
+static ACTIVE_PROFILE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Name of the profile the daemon applied last
+pub fn active_profile() -> Option<String> {
+    ACTIVE_PROFILE
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .clone()
+}
+
+/// Profile selected through the power-profiles-daemon interface, along with its name
+#[cfg(feature = "dbus")]
+fn power_profile_selection(config: &AppConfig) -> Option<(&str, &ProfileConfig)> {
+    let name = power_profiles::selected_profile(&config.power_profiles)?;
+    config.profile(name).map(|profile| (name, profile))
+}
+
+#[cfg(not(feature = "dbus"))]
+fn power_profile_selection(_config: &AppConfig) -> Option<(&str, &ProfileConfig)> {
+    None
+}
+
+/// The profile pinned with `superfreq daemon --profile`
+fn pinned_profile_selection(config: &AppConfig) -> Option<(&str, &ProfileConfig)> {
+    let name = config.daemon.profile.as_deref()?;
+    Some((name, config.profile(name)?))
+}
+
+/// Look up a profile by a name that does not live as long as the
+/// configuration, borrowing the name from the configuration instead
+#[cfg(any(feature = "http", feature = "mqtt"))]
+fn configured_profile<'a>(
+    config: &'a AppConfig,
+    selected: &str,
+) -> Option<(&'a str, &'a ProfileConfig)> {
+    let name = ["charger", "battery"]
+        .into_iter()
+        .chain(config.profiles.keys().map(String::as_str))
+        .find(|name| *name == selected)?;
+    config.profile(name).map(|profile| (name, profile))
+}
+
+/// Profile selected through the HTTP API, along with its name
+#[cfg(feature = "http")]
+fn http_profile_selection(config: &AppConfig) -> Option<(&str, &ProfileConfig)> {
+    configured_profile(config, &http::selected_profile()?)
+}
+
+#[cfg(not(feature = "http"))]
+fn http_profile_selection(_config: &AppConfig) -> Option<(&str, &ProfileConfig)> {
+    None
+}
+
+/// Profile selected through the MQTT command topic, along with its name
+#[cfg(feature = "mqtt")]
+fn mqtt_profile_selection(config: &AppConfig) -> Option<(&str, &ProfileConfig)> {
+    configured_profile(config, &mqtt::selected_profile()?)
+}
+
+#[cfg(not(feature = "mqtt"))]
+fn mqtt_profile_selection(_config: &AppConfig) -> Option<(&str, &ProfileConfig)> {
+    None
+}
+
+/// Turbo setting selected through the HTTP API, replacing the profile's
+#[cfg(feature = "http")]
+fn http_turbo_override() -> Option<TurboSetting> {
+    http::turbo_override()
+}
+
+#[cfg(not(feature = "http"))]
+fn http_turbo_override() -> Option<TurboSetting> {
+    None
+}
+
+/// The profile to use after a battery drain alert, until AC is connected
+fn drain_profile_selection(config: &AppConfig) -> Option<(&str, &ProfileConfig)> {
+    if !alerts::drain_profile_active() {
+        return None;
+    }
+    let name = config.alerts.drain_profile.as_deref()?;
+    Some((name, config.profile(name)?))
+}
+
 /// Determines the appropriate CPU profile based on power status or forced mode,
 /// and applies the settings (via helpers defined in the `cpu` module)
 pub fn determine_and_apply_settings(
@@ -153,19 +633,6 @@ pub fn determine_and_apply_settings(
     config: &AppConfig,
     force_mode: Option<OperationalMode>,
 ) -> Result<(), EngineError> {
-    // First, check if there's a governor override set
-    if let Some(override_governor) = cpu::get_governor_override() {
-        info!(
-            "Governor override is active: '{}'. Setting governor.",
-            override_governor.trim()
-        );
-
-        // Apply the override governor setting
-        try_apply_feature("override governor", override_governor.trim(), || {
-            cpu::set_governor(override_governor.trim(), None)
-        })?;
-    }
-
     // Determine AC/Battery status once, early in the function
     // For desktops (no batteries), we should always use the AC power profile
     // For laptops, we check if all batteries report connected to AC
@@ -178,126 +645,688 @@ pub fn determine_and_apply_settings(
     };
 
     let selected_profile_config: &ProfileConfig;
+    let selected_profile_name: &str;
 
     if let Some(mode) = force_mode {
         match mode {
             OperationalMode::Powersave => {
                 info!("Forced Powersave mode selected. Applying 'battery' profile.");
                 selected_profile_config = &config.battery;
+                selected_profile_name = "battery";
             }
             OperationalMode::Performance => {
                 info!("Forced Performance mode selected. Applying 'charger' profile.");
                 selected_profile_config = &config.charger;
+                selected_profile_name = "charger";
             }
         }
+    } else if let Some((name, profile)) = pinned_profile_selection(config) {
+        info!("Profile '{name}' pinned on the command line, selecting it.");
+        selected_profile_config = profile;
+        selected_profile_name = name;
+    } else if let Some((name, profile)) = http_profile_selection(config) {
+        info!("Profile selected over HTTP, selecting '{name}' profile.");
+        selected_profile_config = profile;
+        selected_profile_name = name;
+    } else if let Some((name, profile)) = mqtt_profile_selection(config) {
+        info!("Profile selected over MQTT, selecting '{name}' profile.");
+        selected_profile_config = profile;
+        selected_profile_name = name;
+    } else if let Some((name, profile)) = drain_profile_selection(config) {
+        info!("Battery drain alert active, selecting '{name}' profile.");
+        selected_profile_config = profile;
+        selected_profile_name = name;
+    } else if let Some((name, profile)) = power_profile_selection(config) {
+        info!("Power profile selected over D-Bus, selecting '{name}' profile.");
+        selected_profile_config = profile;
+        selected_profile_name = name;
+    } else if let Some((index, rule, profile)) = find_matching_rule(report, config, on_ac_power) {
+        info!(
+            "Rule #{} matched, selecting '{}' profile.",
+            index + 1,
+            rule.profile
+        );
+        selected_profile_config = profile;
+        selected_profile_name = &rule.profile;
     } else {
         // Use the previously computed on_ac_power value
         if on_ac_power {
             info!("On AC power, selecting Charger profile.");
             selected_profile_config = &config.charger;
+            selected_profile_name = "charger";
         } else {
             info!("On Battery power, selecting Battery profile.");
             selected_profile_config = &config.battery;
+            selected_profile_name = "battery";
         }
     }
 
-    // Apply settings from selected_profile_config
-    if let Some(governor) = &selected_profile_config.governor {
-        info!("Setting governor to '{governor}'");
+    {
+        let mut active_profile = ACTIVE_PROFILE
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        // Only notify about switches, not about the profile selected on startup
+        if active_profile
+            .as_deref()
+            .is_some_and(|previous| previous != selected_profile_name)
+        {
+            notify::event(
+                notify::Event::ProfileChange,
+                "Power profile changed",
+                &format!("Switched to the '{selected_profile_name}' profile"),
+            );
+        }
+        *active_profile = Some(selected_profile_name.to_string());
+    }
+
+    // Turbo, EPP, frequency limits and battery thresholds belong to the host,
+    // so inside a VM or container the profile is only selected, not applied
+    if let Some(virtualization) = virt::detect() {
+        if !config.daemon.apply_virtualized {
+            VIRTUALIZED_NOTICE.call_once(|| {
+                info!(
+                    "Running virtualized ({virtualization}), monitoring only. Set apply_virtualized = true in the [daemon] section to apply profiles anyway."
+                );
+            });
+            debug!("Not applying the '{selected_profile_name}' profile while virtualized");
+            return Ok(());
+        }
+    }
+
+    // A setting that fails does not keep the ones after it from being applied
+    let mut errors = CycleErrors::default();
+
+    // The intel_pstate mode decides which governors and EPP are available, so
+    // it is switched first
+    if let Some(mode) = selected_profile_config.pstate_mode {
+        errors.record(try_apply_feature(
+            "intel_pstate mode",
+            &mode.to_string(),
+            || cpu::set_intel_pstate_mode(mode),
+        ));
+    }
+
+    // Apply settings from selected_profile_config. A governor override only
+    // replaces the profile's governor; every other profile field still applies.
+    let override_governor = cpu::get_governor_override();
+    let governor = resolve_governor(override_governor.as_deref(), selected_profile_config);
+    // What another tool changes from here on is reverted in enforcement mode
+    let mut applied = enforce::Applied::default();
+    let mut applied_governor = None;
+    if let Some((governor, source)) = governor {
+        match source {
+            GovernorSource::Override => {
+                info!("Governor override is active: '{governor}'. Setting governor.");
+            }
+            GovernorSource::Profile if sysfs::is_observe_only() => {
+                info!("Would set governor to '{governor}'");
+            }
+            GovernorSource::Profile => info!("Setting governor to '{governor}'"),
+        }
+
         // Let set_governor handle the validation
-        if let Err(e) = cpu::set_governor(governor, None) {
+        if let Err(e) = cpu::set_governor(governor, None).inspect(|()| {
+            applied.set_governor(None, governor);
+            applied_governor = Some(governor);
+        }) {
             // If the governor is not available, log a warning
             if matches!(e, ControlError::InvalidGovernor(_))
                 || matches!(e, ControlError::NotSupported(_))
             {
-                warn!(
-                    "Configured governor '{governor}' is not available on this system. Skipping."
-                );
+                warn!("{source} governor '{governor}' is not available on this system. Skipping.");
             } else {
-                return Err(e.into());
+                errors.record(Err(e.into()));
             }
         }
     }
 
-    if let Some(turbo_setting) = selected_profile_config.turbo {
-        info!("Setting turbo to '{turbo_setting:?}'");
+    // Tunables only exist while their governor is in use, so they follow the
+    // governor that was set, or the one already active if setting it failed
+    let active_governor = applied_governor.or(report.cpu_global.current_governor.as_deref());
+    if let Some(governor) = active_governor {
+        let tunables = selected_profile_config
+            .governor_tunables
+            .for_governor(governor);
+        if !tunables.is_empty() {
+            let description = tunables
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            errors.record(try_apply_feature(
+                &format!("{governor} tunables"),
+                &description,
+                || cpu::set_governor_tunables(governor, &tunables),
+            ));
+        }
+    }
+
+    if let Some(turbo_setting) = http_turbo_override().or(selected_profile_config.turbo) {
+        if sysfs::is_observe_only() {
+            info!("Would set turbo to '{turbo_setting:?}'");
+        } else {
+            info!("Setting turbo to '{turbo_setting:?}'");
+        }
+        let boost_order = cpu::boost_order(&config.boost_control);
         match turbo_setting {
             TurboSetting::Auto => {
                 if selected_profile_config.enable_auto_turbo {
                     debug!("Managing turbo in auto mode based on system conditions");
-                    manage_auto_turbo(report, selected_profile_config, on_ac_power)?;
+                    errors.record(manage_auto_turbo(
+                        report,
+                        selected_profile_config,
+                        on_ac_power,
+                        &boost_order,
+                    ));
                 } else {
                     debug!(
                         "Superfreq's dynamic turbo management is disabled by configuration. Ensuring system uses its default behavior for automatic turbo control."
                     );
                     // Make sure the system is set to its default automatic turbo mode.
                     // This is important if turbo was previously forced off.
-                    try_apply_feature("Turbo boost", "system default (Auto)", || {
-                        cpu::set_turbo(TurboSetting::Auto)
-                    })?;
+                    errors.record(try_apply_feature(
+                        "Turbo boost",
+                        "system default (Auto)",
+                        || cpu::set_turbo(TurboSetting::Auto, &boost_order),
+                    ));
                 }
             }
             _ => {
-                try_apply_feature("Turbo boost", &format!("{turbo_setting:?}"), || {
-                    cpu::set_turbo(turbo_setting)
-                })?;
+                errors.record(try_apply_feature(
+                    "Turbo boost",
+                    &format!("{turbo_setting:?}"),
+                    || cpu::set_turbo(turbo_setting, &boost_order),
+                ));
             }
         }
     }
 
     if let Some(epp) = &selected_profile_config.epp {
-        try_apply_feature("EPP", epp, || cpu::set_epp(epp, None))?;
+        let epp = if epp == auto_epp::AUTO_EPP {
+            auto_epp::evaluate(report, &selected_profile_config.epp_auto_settings)
+        } else {
+            Some(epp.as_str())
+        };
+        if let Some(epp) = epp {
+            errors.record(try_apply_feature("EPP", epp, || {
+                cpu::set_epp(epp, None).inspect(|()| applied.set_epp(None, epp))
+            }));
+        }
     }
 
     if let Some(epb) = &selected_profile_config.epb {
-        try_apply_feature("EPB", epb, || cpu::set_epb(epb, None))?;
+        errors.record(try_apply_feature("EPB", epb, || cpu::set_epb(epb, None)));
     }
 
     if let Some(min_freq) = selected_profile_config.min_freq_mhz {
-        try_apply_feature("min frequency", &format!("{min_freq} MHz"), || {
-            cpu::set_min_frequency(min_freq, None)
-        })?;
+        errors.record(try_apply_feature(
+            "min frequency",
+            &format!("{min_freq} MHz"),
+            || cpu::set_min_frequency(min_freq, None),
+        ));
+    }
+
+    // Thermal caps lower the profile's maximum frequency while the CPU is hot
+    let thermal_cap = thermal::evaluate(report, &selected_profile_config.thermal);
+    let max_freq = match (selected_profile_config.max_freq_mhz, thermal_cap) {
+        (Some(max_freq), ThermalCap::Capped(cap)) => Some(max_freq.min(cap)),
+        (None, ThermalCap::Capped(cap)) => Some(cap),
+        (max_freq, _) => max_freq,
+    };
+
+    // In a thermal emergency, idle injection is preferred. Where it is not
+    // available the maximum frequency is forced down to the hardware minimum.
+    let mut forced_min_freq = false;
+    match thermal::evaluate_emergency(report, &selected_profile_config.thermal) {
+        Emergency::Active => {
+            let percent = selected_profile_config.thermal.emergency_idle_percent;
+            match cpu::set_idle_injection(percent) {
+                Ok(()) => debug!("Injecting {percent}% idle time"),
+                Err(ControlError::NotSupported(reason)) => {
+                    let message = format!(
+                        "Idle injection is unavailable ({reason}), forcing minimum frequency"
+                    );
+                    if IDLE_INJECTION_WARNED.swap(true, Ordering::Relaxed) {
+                        debug!("{message}");
+                    } else {
+                        warn!("{message}");
+                    }
+                    errors.record(cpu::force_min_frequency().map_err(EngineError::from));
+                    forced_min_freq = true;
+                }
+                Err(e) => errors.record(Err(e.into())),
+            }
+        }
+        Emergency::Released => {
+            IDLE_INJECTION_WARNED.store(false, Ordering::Relaxed);
+            match cpu::set_idle_injection(0) {
+                Ok(()) | Err(ControlError::NotSupported(_)) => {}
+                Err(e) => errors.record(Err(e.into())),
+            }
+            errors.record(try_apply_feature(
+                "max frequency",
+                "hardware maximum",
+                cpu::reset_max_frequency,
+            ));
+        }
+        Emergency::Inactive => {}
+    }
+
+    if forced_min_freq {
+        debug!("Minimum frequency forced, skipping max frequency");
+    } else if let Some(max_freq) = max_freq {
+        errors.record(try_apply_feature(
+            "max frequency",
+            &format!("{max_freq} MHz"),
+            || cpu::set_max_frequency(max_freq, None),
+        ));
+    } else if thermal_cap == ThermalCap::Released {
+        errors.record(try_apply_feature(
+            "max frequency",
+            "hardware maximum",
+            cpu::reset_max_frequency,
+        ));
+    }
+
+    // Hybrid CPUs can have their core types configured separately
+    for (core_type, group) in [
+        (CoreType::Performance, &selected_profile_config.pcore),
+        (CoreType::Efficiency, &selected_profile_config.ecore),
+    ] {
+        if let Some(group) = group {
+            apply_core_group(
+                core_type,
+                group,
+                thermal_cap,
+                forced_min_freq,
+                &mut applied,
+                &mut errors,
+            );
+        }
+    }
+
+    // Clusters, i.e. cpufreq policies, are configured after the core types
+    for (name, group) in &selected_profile_config.clusters {
+        apply_cluster(
+            name,
+            group,
+            thermal_cap,
+            forced_min_freq,
+            &mut applied,
+            &mut errors,
+        );
+    }
+    enforce::set_applied(applied);
+
+    // Idle states are only touched while a profile limits them, and re-enabled
+    // once the selected profile no longer does
+    match &selected_profile_config.disabled_idle_states {
+        Some(states) => {
+            let description = if states.is_empty() {
+                "all enabled".to_string()
+            } else {
+                format!("{} disabled", states.join(", "))
+            };
+            errors.record(try_apply_feature("idle states", &description, || {
+                cpu::set_idle_states_disabled(states)
+            }));
+            IDLE_STATES_LIMITED.store(true, Ordering::Release);
+        }
+        None if IDLE_STATES_LIMITED.swap(false, Ordering::AcqRel) => {
+            errors.record(try_apply_feature("idle states", "all enabled", || {
+                cpu::set_idle_states_disabled(&[])
+            }));
+        }
+        None => {}
+    }
+
+    let rapl = &selected_profile_config.rapl;
+    for (constraint, watts, window) in [
+        (
+            cpu::RAPL_LONG_TERM,
+            rapl.long_term_watts,
+            rapl.long_term_window_us,
+        ),
+        (
+            cpu::RAPL_SHORT_TERM,
+            rapl.short_term_watts,
+            rapl.short_term_window_us,
+        ),
+    ] {
+        if watts.is_none() && window.is_none() {
+            continue;
+        }
+
+        let description = [
+            watts.map(|w| format!("{w} W")),
+            window.map(|us| format!("{us} µs window")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(", ");
+        errors.record(try_apply_feature(
+            &format!("RAPL {constraint} limit"),
+            &description,
+            || cpu::set_rapl_constraint(constraint, watts, window),
+        ));
     }
 
-    if let Some(max_freq) = selected_profile_config.max_freq_mhz {
-        try_apply_feature("max frequency", &format!("{max_freq} MHz"), || {
-            cpu::set_max_frequency(max_freq, None)
-        })?;
+    if let Some(itmt) = selected_profile_config.sched_itmt {
+        errors.record(try_apply_feature(
+            "ITMT preferred-core scheduling",
+            if itmt { "enabled" } else { "disabled" },
+            || cpu::set_sched_itmt(itmt),
+        ));
+    }
+
+    // The latency request lasts only as long as this process holds it open
+    match selected_profile_config.cpu_dma_latency_us {
+        Some(latency) => errors.record(try_apply_feature(
+            "CPU DMA latency",
+            &format!("{latency} µs"),
+            || cpu::set_dma_latency(Some(latency)),
+        )),
+        None => errors.record(cpu::set_dma_latency(None).map_err(EngineError::from)),
+    }
+
+    // With the userspace governor, the daemon picks each core's frequency itself
+    if governor.is_some_and(|(governor, _)| governor == userspace::USERSPACE_GOVERNOR) {
+        for (core_id, freq_mhz) in userspace::evaluate(report, &selected_profile_config.userspace) {
+            errors.record(try_apply_feature(
+                &format!("CPU {core_id} userspace frequency"),
+                &format!("{freq_mhz} MHz"),
+                || cpu::set_scaling_setspeed(freq_mhz, core_id),
+            ));
+        }
+    } else {
+        userspace::reset();
     }
 
     if let Some(profile) = &selected_profile_config.platform_profile {
-        try_apply_feature("platform profile", profile, || {
+        errors.record(try_apply_feature("platform profile", profile, || {
             cpu::set_platform_profile(profile)
-        })?;
+        }));
+    }
+
+    let asus = &selected_profile_config.asus;
+    if let Some(mode) = asus.throttle_thermal_policy {
+        errors.record(try_apply_feature(
+            "ASUS throttle thermal policy",
+            &mode.to_string(),
+            || vendor::asus::set_throttle_thermal_policy(mode),
+        ));
+    }
+    if let Some(mode) = asus.fan_boost_mode {
+        errors.record(try_apply_feature(
+            "ASUS fan boost mode",
+            &mode.to_string(),
+            || vendor::asus::set_fan_boost_mode(mode),
+        ));
+    }
+    if let Some(enabled) = asus.panel_od {
+        errors.record(try_apply_feature(
+            "ASUS panel overdrive",
+            if enabled { "enabled" } else { "disabled" },
+            || vendor::asus::set_panel_od(enabled),
+        ));
+    }
+
+    let gpu = &selected_profile_config.gpu;
+    if gpu.has_intel_limits() {
+        let limits = [
+            ("min", gpu.gt_min_freq_mhz),
+            ("max", gpu.gt_max_freq_mhz),
+            ("boost", gpu.gt_boost_freq_mhz),
+        ]
+        .into_iter()
+        .filter_map(|(name, mhz)| Some(format!("{name} {} MHz", mhz?)))
+        .collect::<Vec<_>>()
+        .join(", ");
+        errors.record(try_apply_feature("iGPU frequency limits", &limits, || {
+            gpu::set_intel_frequency_limits(gpu)
+        }));
+    }
+    if let Some(watts) = gpu.nvidia_power_limit_watts {
+        errors.record(try_apply_feature(
+            "NVIDIA power limit",
+            &format!("{watts} W"),
+            || gpu::set_nvidia_power_limit(watts),
+        ));
+    }
+
+    match selected_profile_config.keyboard_backlight_percent {
+        Some(percent) => {
+            let grace = Duration::from_secs(config.daemon.keyboard_backlight_grace_sec);
+            errors.record(try_apply_feature(
+                "keyboard backlight",
+                &format!("{percent}%"),
+                || keyboard_backlight::set_brightness_percent(percent, grace),
+            ));
+        }
+        None => keyboard_backlight::reset(),
+    }
+
+    // The fans are only driven while the profile has a curve and the
+    // temperature is known, and handed back to the firmware otherwise
+    let fan_duty = report
+        .cpu_global
+        .average_temperature_celsius
+        .and_then(|temp| fan::duty_at(&selected_profile_config.fan_curve, temp));
+    match fan_duty {
+        Some(duty) => errors.record(try_apply_feature(
+            "fan duty cycle",
+            &format!("{duty}%"),
+            || fan::set_duty(duty),
+        )),
+        None => {
+            if let Err(e) = fan::restore_auto() {
+                warn!("Failed to restore automatic fan control: {e}");
+            }
+        }
     }
 
-    // Set battery charge thresholds if configured
-    if let Some(thresholds) = &selected_profile_config.battery_charge_thresholds {
-        let start_threshold = thresholds.start;
-        let stop_threshold = thresholds.stop;
+    // A battery calibration temporarily owns the thresholds and charge behaviour
+    if calibration::is_active() {
+        debug!("Battery calibration in progress, leaving charge settings untouched");
+    } else {
+        // Set battery charge thresholds if configured. Per-battery thresholds take
+        // precedence over the profile's thresholds for the batteries they name.
+        let profile_thresholds = selected_profile_config.battery_charge_thresholds;
+        if profile_thresholds.is_some() || !config.batteries.is_empty() {
+            if let Some(thresholds) = profile_thresholds {
+                info!(
+                    "Setting battery charge thresholds: {}-{}%",
+                    thresholds.start, thresholds.stop
+                );
+            }
+            for (name, thresholds) in &config.batteries {
+                info!(
+                    "Setting battery charge thresholds for {name}: {}-{}%",
+                    thresholds.start, thresholds.stop
+                );
+            }
 
-        if start_threshold < stop_threshold && stop_threshold <= 100 {
-            info!("Setting battery charge thresholds: {start_threshold}-{stop_threshold}%");
-            match battery::set_battery_charge_thresholds(start_threshold, stop_threshold) {
+            match battery::set_battery_charge_thresholds_per_battery(
+                profile_thresholds,
+                &config.batteries,
+            ) {
                 Ok(()) => debug!("Battery charge thresholds set successfully"),
                 Err(e) => warn!("Failed to set battery charge thresholds: {e}"),
             }
-        } else {
-            warn!(
-                "Invalid battery threshold values: start={start_threshold}, stop={stop_threshold}"
-            );
         }
+
+        if let Some(behaviour) = selected_profile_config.charge_behaviour {
+            let ignored_supplies = config.ignored_power_supplies.as_deref().unwrap_or_default();
+            errors.record(try_apply_feature(
+                "charge behaviour",
+                &behaviour.to_string(),
+                || battery::set_charge_behaviour_all(behaviour, ignored_supplies),
+            ));
+        }
+    }
+
+    // Kernel tunables and raw sysfs writes come last, so they can override the
+    // built-in settings. Files the selected profile no longer sets go back to
+    // their original values.
+    let mut written = BTreeSet::new();
+    for (name, value) in selected_profile_config.sysctl.iter() {
+        let path = overrides::sysctl_path(name);
+        errors.record(try_apply_feature(&format!("sysctl {name}"), value, || {
+            overrides::write(&path, value, true)
+        }));
+        written.insert(path);
+    }
+    for entry in &selected_profile_config.sysfs {
+        let path = sysfs::path(&entry.path);
+        // Files of hot-pluggable devices only exist while they are present
+        errors.record(try_apply_feature(&entry.path, &entry.value, || {
+            if !path.is_file() {
+                return Err(ControlError::PathMissing(format!(
+                    "sysfs path '{}' does not exist",
+                    entry.path
+                )));
+            }
+            overrides::write(&path, &entry.value, entry.restore)
+        }));
+        written.insert(path);
+    }
+    for path in overrides::changed() {
+        if !written.contains(&path) {
+            errors.record(try_apply_feature(
+                &path.display().to_string(),
+                "original value",
+                || overrides::restore(&path),
+            ));
+        }
+    }
+
+    if errors.is_empty() {
+        debug!("Profile settings applied successfully.");
     }
 
-    debug!("Profile settings applied successfully.");
+    errors.into_result()
+}
 
-    Ok(())
+/// Apply the settings of a core group to every core of its type
+fn apply_core_group(
+    core_type: CoreType,
+    group: &CoreGroupConfig,
+    thermal_cap: ThermalCap,
+    forced_min_freq: bool,
+    applied: &mut enforce::Applied,
+    errors: &mut CycleErrors,
+) {
+    let cores: Vec<u32> = cpu::cores_of_type(core_type)
+        .into_iter()
+        .filter(|&core_id| !cpu::is_core_ignored(core_id))
+        .collect();
+    if cores.is_empty() {
+        debug!("No {core_type} cores detected, skipping {core_type} core settings");
+        return;
+    }
+
+    let label = core_type.short_name();
+    // Cores that share a cpufreq policy share its settings, so each policy is
+    // written once
+    for core_id in cpu::policy_cores(&cores) {
+        apply_group_settings(
+            &format!("{label}-core {core_id}"),
+            core_id,
+            group,
+            thermal_cap,
+            forced_min_freq,
+            applied,
+            errors,
+        );
+    }
+}
+
+/// Apply the settings of a cluster, named after its cpufreq policy, through
+/// one of its cores
+fn apply_cluster(
+    name: &str,
+    group: &CoreGroupConfig,
+    thermal_cap: ThermalCap,
+    forced_min_freq: bool,
+    applied: &mut enforce::Applied,
+    errors: &mut CycleErrors,
+) {
+    let Some(policy_id) = parse_policy_name(name) else {
+        return;
+    };
+    let cpus = match cpu::policy_cpus(policy_id) {
+        Ok(cpus) => cpus,
+        Err(e) => {
+            debug!("Skipping cluster {name}: {e}");
+            return;
+        }
+    };
+    if cpus.iter().any(|&cpu| cpu::is_core_ignored(cpu)) {
+        debug!("Skipping cluster {name}, it includes an ignored core");
+        return;
+    }
+    let Some(&core_id) = cpus.first() else {
+        return;
+    };
+
+    apply_group_settings(
+        &format!("cluster {name}"),
+        core_id,
+        group,
+        thermal_cap,
+        forced_min_freq,
+        applied,
+        errors,
+    );
+}
+
+/// Apply the governor, EPP and maximum frequency of a group through `core_id`
+fn apply_group_settings(
+    label: &str,
+    core_id: u32,
+    group: &CoreGroupConfig,
+    thermal_cap: ThermalCap,
+    forced_min_freq: bool,
+    applied: &mut enforce::Applied,
+    errors: &mut CycleErrors,
+) {
+    // A thermal emergency that forced the minimum frequency takes precedence
+    let max_freq = match (group.max_freq_mhz, thermal_cap) {
+        _ if forced_min_freq => None,
+        (Some(max_freq), ThermalCap::Capped(cap)) => Some(max_freq.min(cap)),
+        (max_freq, _) => max_freq,
+    };
+
+    if let Some(governor) = &group.governor {
+        errors.record(try_apply_feature(
+            &format!("{label} governor"),
+            governor,
+            || {
+                cpu::set_governor(governor, Some(core_id))
+                    .inspect(|()| applied.set_governor(Some(core_id), governor))
+            },
+        ));
+    }
+    if let Some(epp) = &group.epp {
+        errors.record(try_apply_feature(&format!("{label} EPP"), epp, || {
+            cpu::set_epp(epp, Some(core_id)).inspect(|()| applied.set_epp(Some(core_id), epp))
+        }));
+    }
+    if let Some(max_freq) = max_freq {
+        errors.record(try_apply_feature(
+            &format!("{label} max frequency"),
+            &format!("{max_freq} MHz"),
+            || cpu::set_max_frequency(max_freq, Some(core_id)),
+        ));
+    }
 }
 
 fn manage_auto_turbo(
     report: &SystemReport,
     config: &ProfileConfig,
     on_ac_power: bool,
+    boost_order: &[BoostControl],
 ) -> Result<(), EngineError> {
     // Get the auto turbo settings from the config
     let turbo_settings = &config.turbo_auto_settings;
@@ -308,51 +1337,61 @@ fn manage_auto_turbo(
     // Get average CPU temperature and CPU load
     let cpu_temp = report.cpu_global.average_temperature_celsius;
 
-    // Check if we have CPU usage data available
-    let avg_cpu_usage = if report.cpu_cores.is_empty() {
-        None
+    // CPU usage according to the configured load metric, if available
+    let cpu_load = turbo_load(report, turbo_settings);
+
+    let hysteresis = get_turbo_states().get_for_power_state(on_ac_power);
+
+    // Get the previous state or initialize with the configured initial state
+    let previous_turbo_enabled = if let Some(state) = hysteresis.get_previous_state() {
+        state
     } else {
-        let sum: f32 = report
-            .cpu_cores
-            .iter()
-            .filter_map(|core| core.usage_percent)
-            .sum();
-        let count = report
-            .cpu_cores
-            .iter()
-            .filter(|core| core.usage_percent.is_some())
-            .count();
-
-        if count > 0 {
-            Some(sum / count as f32)
-        } else {
-            None
-        }
+        // Initialize with the configured initial state and return it
+        hysteresis.initialize_with(turbo_settings.initial_turbo_state)
     };
 
-    // Get the previous state or initialize with the configured initial state
-    let previous_turbo_enabled = {
-        let turbo_states = get_turbo_states();
-        let hysteresis = turbo_states.get_for_power_state(on_ac_power);
-        if let Some(state) = hysteresis.get_previous_state() {
-            state
-        } else {
-            // Initialize with the configured initial state and return it
-            hysteresis.initialize_with(turbo_settings.initial_turbo_state)
+    // After a thermal shutdown, turbo stays off until the CPU has cooled below
+    // the resume threshold. Without a reading we cannot tell, so it stays off.
+    let thermally_locked_out = match turbo_settings.temp_threshold_resume {
+        Some(resume) if hysteresis.is_thermally_locked_out() => {
+            if cpu_temp.is_some_and(|temp| temp < resume) {
+                info!("Auto Turbo: CPU cooled below {resume:.1}°C, resuming load-based control");
+                hysteresis.set_thermal_lockout(false);
+                false
+            } else {
+                true
+            }
         }
+        _ => false,
     };
 
     // Decision logic for enabling/disabling turbo with hysteresis
-    let enable_turbo = match (cpu_temp, avg_cpu_usage, previous_turbo_enabled) {
+    let enable_turbo = match (cpu_temp, cpu_load, previous_turbo_enabled) {
         // If temperature is too high, disable turbo regardless of load
         (Some(temp), _, _) if temp >= turbo_settings.temp_threshold_high => {
             info!(
                 "Auto Turbo: Disabled due to high temperature ({:.1}°C >= {:.1}°C)",
                 temp, turbo_settings.temp_threshold_high
             );
+            hysteresis.set_thermal_lockout(turbo_settings.temp_threshold_resume.is_some());
             false
         }
 
+        // Still cooling down after a thermal shutdown
+        (temp, _, _) if thermally_locked_out => {
+            info!(
+                "Auto Turbo: Kept disabled until the CPU cools below {:.1}°C (currently {})",
+                turbo_settings.temp_threshold_resume.unwrap_or_default(),
+                temp.map_or_else(|| "unknown".to_string(), |t| format!("{t:.1}°C"))
+            );
+            false
+        }
+
+        // The PID controller replaces the load thresholds below
+        (temp, load, prev_state) if turbo_settings.controller == TurboController::Pid => {
+            pid_turbo_decision(&hysteresis.pid, &turbo_settings.pid, temp, load, prev_state)
+        }
+
         // If load is high enough, enable turbo (unless temp already caused it to disable)
         (_, Some(usage), _) if usage >= turbo_settings.load_threshold_high => {
             info!(
@@ -414,10 +1453,13 @@ fn manage_auto_turbo(
     };
 
     // Save the current state for next time
-    {
-        let turbo_states = get_turbo_states();
-        let hysteresis = turbo_states.get_for_power_state(on_ac_power);
-        hysteresis.update_state(enable_turbo);
+    hysteresis.update_state(enable_turbo);
+    if turbo_settings.controller != TurboController::Pid {
+        hysteresis
+            .pid
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .reset();
     }
 
     // Only apply the setting if the state has changed
@@ -439,7 +1481,7 @@ fn manage_auto_turbo(
             if enable_turbo { "enabled" } else { "disabled" }
         );
 
-        match cpu::set_turbo(turbo_setting) {
+        match cpu::set_turbo(turbo_setting, boost_order) {
             Ok(()) => {
                 debug!(
                     "Auto Turbo: Successfully set turbo to {}",
@@ -458,6 +1500,44 @@ fn manage_auto_turbo(
     }
 }
 
+/// Turbo decision of the PID controller. Without a reading of its target the
+/// previous state is kept and the controller is not updated.
+fn pid_turbo_decision(
+    pid: &Mutex<TurboPid>,
+    settings: &TurboPidSettings,
+    cpu_temp: Option<f32>,
+    cpu_load: Option<f32>,
+    prev_state: bool,
+) -> bool {
+    let (reading, error, unit) = match settings.target {
+        TurboPidTarget::Load => (cpu_load, cpu_load.map(|load| load - settings.setpoint), "%"),
+        TurboPidTarget::Temperature => (
+            cpu_temp,
+            cpu_temp.map(|temp| settings.setpoint - temp),
+            "°C",
+        ),
+    };
+    let (Some(reading), Some(error)) = (reading, error) else {
+        info!(
+            "Auto Turbo: Maintaining previous state ({}) due to missing PID input",
+            if prev_state { "enabled" } else { "disabled" }
+        );
+        return prev_state;
+    };
+
+    let output = pid
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .update(settings, error, SystemClock.now());
+    let enable = output > 0.0;
+    info!(
+        "Auto Turbo: {} by PID controller (output {output:.1} at {reading:.1}{unit}, setpoint {:.1}{unit})",
+        if enable { "Enabled" } else { "Disabled" },
+        settings.setpoint
+    );
+    enable
+}
+
 fn validate_turbo_auto_settings(settings: &TurboAutoSettings) -> Result<(), EngineError> {
     if settings.load_threshold_high <= settings.load_threshold_low
         || settings.load_threshold_high > 100.0
@@ -482,5 +1562,323 @@ fn validate_turbo_auto_settings(settings: &TurboAutoSettings) -> Result<(), Engi
         ));
     }
 
+    if let Some(resume) = settings.temp_threshold_resume {
+        if resume <= 0.0 || resume >= settings.temp_threshold_high {
+            return Err(EngineError::ConfigurationError(format!(
+                "Invalid turbo auto settings: resume temperature ({resume:.1}°C) must be above 0°C and below the high temperature threshold ({:.1}°C)",
+                settings.temp_threshold_high
+            )));
+        }
+    }
+
+    if settings.controller == TurboController::Pid {
+        let pid = &settings.pid;
+        let setpoint_valid = match pid.target {
+            TurboPidTarget::Load => (0.0..=100.0).contains(&pid.setpoint),
+            TurboPidTarget::Temperature => {
+                pid.setpoint > 0.0 && pid.setpoint < settings.temp_threshold_high
+            }
+        };
+        if !setpoint_valid {
+            return Err(EngineError::ConfigurationError(format!(
+                "Invalid turbo auto settings: PID setpoint ({:.1}) must be a load between 0 % and 100 % or a temperature between 0°C and the high temperature threshold ({:.1}°C)",
+                pid.setpoint, settings.temp_threshold_high
+            )));
+        }
+        if [pid.kp, pid.ki, pid.kd].iter().any(|&gain| gain < 0.0)
+            || pid.kp + pid.ki + pid.kd == 0.0
+        {
+            return Err(EngineError::ConfigurationError(
+                "Invalid turbo auto settings: PID gains must not be negative, and at least one must be above 0"
+                    .to_string(),
+            ));
+        }
+    }
+
+    if settings.load_metric == TurboLoadMetric::TopCores && settings.top_cores == 0 {
+        return Err(EngineError::ConfigurationError(
+            "Invalid turbo auto settings: top_cores must be at least 1".to_string(),
+        ));
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::clock::ManualClock;
+    use crate::util::fixture::Fixture;
+
+    fn profile_with_governor(governor: Option<&str>) -> ProfileConfig {
+        ProfileConfig {
+            governor: governor.map(str::to_string),
+            ..ProfileConfig::default()
+        }
+    }
+
+    #[test]
+    fn governor_override_wins_over_profile() {
+        let profile = profile_with_governor(Some("powersave"));
+        assert_eq!(
+            resolve_governor(Some("performance"), &profile),
+            Some(("performance", GovernorSource::Override))
+        );
+    }
+
+    #[test]
+    fn empty_governor_override_falls_back_to_profile() {
+        let profile = profile_with_governor(Some("powersave"));
+        for empty in ["", "   ", "\t\n"] {
+            assert_eq!(
+                resolve_governor(Some(empty), &profile),
+                Some(("powersave", GovernorSource::Profile))
+            );
+        }
+        assert_eq!(
+            resolve_governor(None, &profile),
+            Some(("powersave", GovernorSource::Profile))
+        );
+    }
+
+    #[test]
+    fn no_governor_configured() {
+        let profile = profile_with_governor(None);
+        assert_eq!(resolve_governor(None, &profile), None);
+        assert_eq!(resolve_governor(Some(" "), &profile), None);
+    }
+
+    fn failing_write() -> Result<(), ControlError> {
+        Err(ControlError::WriteError("read-only".to_string()))
+    }
+
+    #[test]
+    fn failing_feature_backs_off_and_keeps_failing() {
+        let clock = ManualClock::new();
+        let mut failing = BTreeMap::new();
+
+        let first = apply_with_backoff(&mut failing, clock.now(), "EPP", "power", failing_write);
+        assert!(matches!(first, Err(EngineError::ControlError(_))));
+
+        // Skipped while backing off, which is not a new failure
+        let mut attempts = 0;
+        let skipped = apply_with_backoff(&mut failing, clock.now(), "EPP", "power", || {
+            attempts += 1;
+            failing_write()
+        });
+        assert_eq!(attempts, 0);
+        assert!(skipped.is_ok());
+
+        clock.advance(INITIAL_RETRY_DELAY);
+        let retried = apply_with_backoff(&mut failing, clock.now(), "EPP", "power", failing_write);
+        match retried {
+            Err(EngineError::StillFailing {
+                failures, retry_in, ..
+            }) => {
+                assert_eq!(failures, 2);
+                assert_eq!(retry_in, INITIAL_RETRY_DELAY * 2);
+            }
+            other => panic!("expected StillFailing, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn cycle_errors_are_collected() {
+        let still_failing = || EngineError::StillFailing {
+            feature: "EPP".to_string(),
+            failures: 2,
+            retry_in: INITIAL_RETRY_DELAY,
+        };
+
+        let mut errors = CycleErrors::default();
+        errors.record(Ok(()));
+        assert!(errors.into_result().is_ok());
+
+        let mut errors = CycleErrors::default();
+        errors.record(Err(still_failing()));
+        errors.record(Ok(()));
+        errors.record(Err(still_failing()));
+        let result = errors.into_result();
+        assert!(matches!(&result, Err(EngineError::Several(errors)) if errors.len() == 2));
+        assert!(result.unwrap_err().is_still_failing());
+
+        let mut errors = CycleErrors::default();
+        errors.record(Err(still_failing()));
+        errors.record(Err(ControlError::WriteError("read-only".to_string()).into()));
+        assert!(!errors.into_result().unwrap_err().is_still_failing());
+    }
+
+    #[test]
+    fn failing_feature_retries_new_value_and_recovers() {
+        let clock = ManualClock::new();
+        let mut failing = BTreeMap::new();
+
+        let _ = apply_with_backoff(&mut failing, clock.now(), "EPP", "power", failing_write);
+        let other_value =
+            apply_with_backoff(&mut failing, clock.now(), "EPP", "performance", || {
+                Ok::<_, ControlError>(())
+            });
+        assert!(other_value.is_ok());
+        assert!(failing.is_empty());
+    }
+
+    #[test]
+    fn unsupported_feature_is_not_a_failure() {
+        let clock = ManualClock::new();
+        let mut failing = BTreeMap::new();
+        for _ in 0..3 {
+            let result = apply_with_backoff(&mut failing, clock.now(), "EPB", "6", || {
+                Err::<(), _>(ControlError::NotSupported("no EPB".to_string()))
+            });
+            assert!(result.is_ok());
+            clock.advance(MAX_RETRY_DELAY);
+        }
+    }
+
+    /// Feed a load that alternates between two values every 5s poll and
+    /// return the turbo decisions of the last `tail` polls
+    fn pid_decisions(high: f32, low: f32, polls: usize, tail: usize) -> Vec<bool> {
+        let clock = ManualClock::new();
+        let settings = TurboPidSettings::default();
+        let mut pid = TurboPid::new();
+        let decisions: Vec<bool> = (0..polls)
+            .map(|poll| {
+                let load = if poll % 2 == 0 { high } else { low };
+                clock.advance(Duration::from_secs(5));
+                pid.update(&settings, load - settings.setpoint, clock.now()) > 0.0
+            })
+            .collect();
+        decisions[polls - tail..].to_vec()
+    }
+
+    #[test]
+    fn pid_follows_average_of_oscillating_load() {
+        // Averages 55%, above the default setpoint of 50%
+        assert!(pid_decisions(90.0, 20.0, 60, 10).iter().all(|&on| on));
+        // Averages 45%, below it
+        assert!(pid_decisions(80.0, 10.0, 60, 10).iter().all(|&on| !on));
+    }
+
+    #[test]
+    fn pid_integral_is_limited() {
+        let clock = ManualClock::new();
+        let settings = TurboPidSettings::default();
+        let mut pid = TurboPid::new();
+        for _ in 0..1000 {
+            clock.advance(Duration::from_secs(5));
+            pid.update(&settings, 50.0, clock.now());
+        }
+        assert!(settings.ki * pid.integral <= PID_INTEGRAL_LIMIT);
+
+        // Turns around within a few polls once the load drops
+        let output = (0..5)
+            .map(|_| {
+                clock.advance(Duration::from_secs(5));
+                pid.update(&settings, -50.0, clock.now())
+            })
+            .last()
+            .unwrap();
+        assert!(output < 0.0);
+    }
+
+    #[test]
+    fn pid_step_is_capped_after_long_gap() {
+        let clock = ManualClock::new();
+        let settings = TurboPidSettings::default();
+        let mut pid = TurboPid::new();
+        pid.update(&settings, 10.0, clock.now());
+        clock.advance(Duration::from_secs(3600));
+        pid.update(&settings, 10.0, clock.now());
+        assert_eq!(pid.integral, 10.0 * MAX_PID_STEP.as_secs_f32());
+    }
+
+    /// Collect a report from the fixture tree and apply `config` to it
+    fn apply_to_fixture(config: &str) -> Result<(), EngineError> {
+        let config = crate::config::load_config_inline(config).unwrap();
+        let report = crate::monitor::collect_system_report(&config).unwrap();
+        assert_eq!(report.cpu_cores.len(), 4);
+        determine_and_apply_settings(&report, &config, None)
+    }
+
+    #[test]
+    fn profile_is_written_to_fixture_tree() {
+        let fixture = Fixture::new();
+        fixture.cpus(4);
+        fixture.write("/sys/module/snd_hda_intel/parameters/power_save", "0\n");
+
+        apply_to_fixture(
+            r#"
+            [charger]
+            governor = "performance"
+            epp = "power"
+            max_freq_mhz = 2000
+
+            [[charger.sysfs]]
+            path = "/sys/module/snd_hda_intel/parameters/power_save"
+            value = "1"
+
+            [daemon]
+            apply_virtualized = true
+            "#,
+        )
+        .unwrap();
+
+        for cpu in 0..4 {
+            let policy = format!("/sys/devices/system/cpu/cpufreq/policy{cpu}");
+            assert_eq!(
+                fixture.read(&format!("{policy}/scaling_governor")),
+                "performance"
+            );
+            assert_eq!(
+                fixture.read(&format!("{policy}/energy_performance_preference")),
+                "power"
+            );
+            assert_eq!(
+                fixture.read(&format!("{policy}/scaling_max_freq")),
+                "2000000"
+            );
+        }
+        assert_eq!(
+            fixture.read("/sys/module/snd_hda_intel/parameters/power_save"),
+            "1"
+        );
+    }
+
+    #[test]
+    fn missing_file_does_not_stop_later_settings() {
+        let fixture = Fixture::new();
+        fixture.cpus(4);
+        fixture.write("/sys/module/snd_hda_intel/parameters/power_save", "0\n");
+
+        let result = apply_to_fixture(
+            r#"
+            [charger]
+            governor = "performance"
+
+            [[charger.sysfs]]
+            path = "/sys/module/fixture_missing/parameters/enabled"
+            value = "1"
+
+            [[charger.sysfs]]
+            path = "/sys/module/snd_hda_intel/parameters/power_save"
+            value = "1"
+
+            [daemon]
+            apply_virtualized = true
+            "#,
+        );
+
+        assert!(matches!(
+            result,
+            Err(EngineError::ControlError(ControlError::PathMissing(_)))
+        ));
+        assert_eq!(
+            fixture.read("/sys/devices/system/cpu/cpufreq/policy0/scaling_governor"),
+            "performance"
+        );
+        assert_eq!(
+            fixture.read("/sys/module/snd_hda_intel/parameters/power_save"),
+            "1"
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! Driving the fans from the `fan_curve` of a profile, for machines where the
+//! kernel offers no fan control of its own.
+
+use crate::config::FanCurvePoint;
+use crate::util::error::ControlError;
+#[cfg(feature = "cros_ec")]
+use crate::vendor::cros_ec;
+use std::sync::{Mutex, PoisonError};
+
+pub type Result<T, E = ControlError> = std::result::Result<T, E>;
+
+/// Duty cycle written last, or `None` while the firmware controls the fans
+static CURRENT_DUTY: Mutex<Option<u8>> = Mutex::new(None);
+
+/// Duty cycle for a temperature, interpolated between the points of a curve
+/// in ascending order of temperature, and held flat beyond its ends
+pub fn duty_at(curve: &[FanCurvePoint], temp_celsius: f32) -> Option<u8> {
+    let first = curve.first()?;
+    let last = curve.last()?;
+    if temp_celsius <= first.temp_celsius {
+        return Some(first.duty_percent);
+    }
+    if temp_celsius >= last.temp_celsius {
+        return Some(last.duty_percent);
+    }
+
+    curve.windows(2).find_map(|pair| {
+        let (low, high) = (pair[0], pair[1]);
+        (temp_celsius < high.temp_celsius).then(|| {
+            let fraction =
+                (temp_celsius - low.temp_celsius) / (high.temp_celsius - low.temp_celsius);
+            let duty = f32::from(low.duty_percent)
+                + fraction * (f32::from(high.duty_percent) - f32::from(low.duty_percent));
+            duty.round() as u8
+        })
+    })
+}
+
+/// Whether a backend that can set the fan duty cycle is available
+pub fn is_supported() -> bool {
+    #[cfg(feature = "cros_ec")]
+    if cros_ec::is_available() {
+        return true;
+    }
+    false
+}
+
+/// Run the fans at `percent`, unless they already are
+pub fn set_duty(percent: u8) -> Result<()> {
+    let mut current = CURRENT_DUTY.lock().unwrap_or_else(PoisonError::into_inner);
+    if *current == Some(percent) {
+        return Ok(());
+    }
+
+    write_duty(percent)?;
+    *current = Some(percent);
+    Ok(())
+}
+
+fn write_duty(percent: u8) -> Result<()> {
+    #[cfg(feature = "cros_ec")]
+    if cros_ec::is_available() {
+        return cros_ec::set_fan_duty(percent);
+    }
+
+    Err(ControlError::NotSupported(format!(
+        "Cannot run the fans at {percent}%: fan control needs a ChromeOS EC and the cros_ec feature"
+    )))
+}
+
+/// Hand the fans back to the firmware if a curve was driving them
+pub fn restore_auto() -> Result<()> {
+    let mut current = CURRENT_DUTY.lock().unwrap_or_else(PoisonError::into_inner);
+    if current.is_none() {
+        return Ok(());
+    }
+
+    #[cfg(feature = "cros_ec")]
+    cros_ec::set_auto_fan_control()?;
+    *current = None;
+    Ok(())
+}
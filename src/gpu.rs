@@ -0,0 +1,158 @@
+//! Graphics cards under `/sys/class/drm`: the power their hwmon sensors report
+//! (e.g. amdgpu), and the frequency limits of Intel GPUs driven by i915. With
+//! the `nvml` feature, also the power limit of NVIDIA GPUs.
+
+use crate::config::GpuConfig;
+use crate::util::error::ControlError;
+use crate::util::sysfs;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub type Result<T, E = ControlError> = std::result::Result<T, E>;
+
+const DRM_DIR: &str = "/sys/class/drm";
+
+const GT_MIN_FREQ: &str = "gt_min_freq_mhz";
+const GT_MAX_FREQ: &str = "gt_max_freq_mhz";
+const GT_BOOST_FREQ: &str = "gt_boost_freq_mhz";
+/// The hardware's lowest and highest frequencies, which bound the limits
+const GT_RPN_FREQ: &str = "gt_RPn_freq_mhz";
+const GT_RP0_FREQ: &str = "gt_RP0_freq_mhz";
+
+/// The cards, without their connectors such as `card0-DP-1`
+pub fn cards() -> Vec<PathBuf> {
+    let mut cards: Vec<PathBuf> = fs::read_dir(sysfs::path(DRM_DIR))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| {
+                    let name = entry.file_name();
+                    let name = name.to_string_lossy();
+                    name.starts_with("card") && !name.contains('-')
+                })
+                .map(|entry| entry.path())
+                .collect()
+        })
+        .unwrap_or_default();
+    cards.sort();
+    cards
+}
+
+/// Power reported by GPU hwmon sensors, summed over all cards
+pub fn read_power_watts() -> Option<f32> {
+    let readings: Vec<f32> = cards()
+        .iter()
+        .filter_map(|card| fs::read_dir(card.join("device/hwmon")).ok())
+        .flat_map(|hwmons| hwmons.flatten())
+        .filter_map(|hwmon| {
+            let path = hwmon.path();
+            read_u64(&path.join("power1_average")).or_else(|| read_u64(&path.join("power1_input")))
+        })
+        .map(|uw| uw as f32 / 1_000_000.0)
+        .collect();
+
+    (!readings.is_empty()).then(|| readings.iter().sum())
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    sysfs::read_sysfs_value(path).ok()?.parse().ok()
+}
+
+fn read_mhz(card: &Path, attribute: &str) -> Result<u32> {
+    let path = card.join(attribute);
+    let value = sysfs::read_sysfs_value(&path)?;
+    value.parse().map_err(|_| {
+        ControlError::ParseError(format!("Invalid frequency '{value}' in {}", path.display()))
+    })
+}
+
+/// Cards whose frequency limits can be set, i.e. Intel GPUs driven by i915
+pub fn intel_cards() -> Vec<PathBuf> {
+    cards()
+        .into_iter()
+        .filter(|card| card.join(GT_MAX_FREQ).exists())
+        .collect()
+}
+
+/// The frequency attributes of every Intel card, for snapshots
+pub fn intel_frequency_paths() -> Vec<PathBuf> {
+    intel_cards()
+        .iter()
+        .flat_map(|card| {
+            [GT_BOOST_FREQ, GT_MAX_FREQ, GT_MIN_FREQ].map(|attribute| card.join(attribute))
+        })
+        .collect()
+}
+
+/// Set the frequency limits of every Intel card. i915 refuses a minimum above
+/// the current maximum and a maximum below the current minimum, so the limits
+/// are written in the order that keeps them valid in between.
+pub fn set_intel_frequency_limits(config: &GpuConfig) -> Result<()> {
+    let cards = intel_cards();
+    if cards.is_empty() {
+        return Err(ControlError::NotSupported(
+            "No Intel GPU with i915 frequency controls found.".to_string(),
+        ));
+    }
+
+    for card in &cards {
+        let hardware_min = read_mhz(card, GT_RPN_FREQ)?;
+        let hardware_max = read_mhz(card, GT_RP0_FREQ)?;
+        for (attribute, value) in [
+            (GT_MIN_FREQ, config.gt_min_freq_mhz),
+            (GT_MAX_FREQ, config.gt_max_freq_mhz),
+            (GT_BOOST_FREQ, config.gt_boost_freq_mhz),
+        ] {
+            if let Some(value) =
+                value.filter(|value| !(hardware_min..=hardware_max).contains(value))
+            {
+                return Err(ControlError::InvalidValueError(format!(
+                    "{attribute} = {value} is outside the hardware range of {} ({hardware_min}-{hardware_max} MHz)",
+                    card.file_name().unwrap_or_default().to_string_lossy()
+                )));
+            }
+        }
+
+        let raises_min_above_max = match config.gt_min_freq_mhz {
+            Some(min) => min > read_mhz(card, GT_MAX_FREQ)?,
+            None => false,
+        };
+        let min = (GT_MIN_FREQ, config.gt_min_freq_mhz);
+        let max = (GT_MAX_FREQ, config.gt_max_freq_mhz);
+        let order = if raises_min_above_max {
+            [max, min]
+        } else {
+            [min, max]
+        };
+        for (attribute, value) in order
+            .into_iter()
+            .chain([(GT_BOOST_FREQ, config.gt_boost_freq_mhz)])
+        {
+            if let Some(value) = value {
+                sysfs::write_sysfs_value(card.join(attribute), &value.to_string())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether NVML finds an NVIDIA GPU, which needs the `nvml` feature
+pub fn has_nvidia_gpu() -> bool {
+    #[cfg(feature = "nvml")]
+    if !crate::nvml::get_gpus().is_empty() {
+        return true;
+    }
+    false
+}
+
+/// Set the power limit of every NVIDIA GPU
+pub fn set_nvidia_power_limit(watts: f32) -> Result<()> {
+    #[cfg(feature = "nvml")]
+    return crate::nvml::set_power_limit(watts);
+
+    #[cfg(not(feature = "nvml"))]
+    Err(ControlError::NotSupported(format!(
+        "Cannot limit NVIDIA GPUs to {watts} W: power limits need the nvml feature"
+    )))
+}
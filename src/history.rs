@@ -0,0 +1,174 @@
+use crate::config::HistoryConfig;
+use crate::core::SystemReport;
+use crate::util::duration;
+use crate::util::error::HistoryError;
+use log::debug;
+use rusqlite::{Connection, OpenFlags, params};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub type Result<T, E = HistoryError> = std::result::Result<T, E>;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS reports (
+    timestamp INTEGER NOT NULL,
+    cpu_usage_percent REAL,
+    cpu_temp_celsius REAL,
+    avg_freq_mhz REAL,
+    load_1m REAL NOT NULL,
+    battery_percent INTEGER,
+    battery_power_watts REAL,
+    ac_connected INTEGER,
+    governor TEXT,
+    turbo INTEGER,
+    report TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS reports_timestamp ON reports (timestamp);
+";
+
+/// Summary of a recorded report. The full report is kept as JSON in the database.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct HistoryEntry {
+    /// Seconds since the Unix epoch
+    pub timestamp: i64,
+    pub cpu_usage_percent: Option<f64>,
+    pub cpu_temp_celsius: Option<f64>,
+    pub avg_freq_mhz: Option<f64>,
+    pub load_1m: f64,
+    pub battery_percent: Option<u8>,
+    pub battery_power_watts: Option<f64>,
+    pub ac_connected: Option<bool>,
+    pub governor: Option<String>,
+    pub turbo: Option<bool>,
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX))
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0u32), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum / f64::from(count))
+}
+
+/// Parse a duration such as `30s`, `15m`, `1h` or `7d`
+pub fn parse_duration(value: &str) -> Result<Duration> {
+    duration::parse_duration(value).ok_or_else(|| HistoryError::InvalidDuration(value.to_string()))
+}
+
+/// SQLite database of system reports recorded by the daemon
+pub struct History {
+    conn: Connection,
+    retention: Duration,
+}
+
+impl History {
+    /// Open or create the history database for recording
+    pub fn open(config: &HistoryConfig) -> Result<Self> {
+        if let Some(parent) = Path::new(&config.database_path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let conn = Connection::open(&config.database_path)?;
+        conn.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            conn,
+            retention: Duration::from_secs(config.retention_hours.saturating_mul(60 * 60)),
+        })
+    }
+
+    /// Open an existing history database without modifying it
+    pub fn open_read_only(config: &HistoryConfig) -> Result<Self> {
+        let conn = Connection::open_with_flags(
+            &config.database_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+
+        Ok(Self {
+            conn,
+            retention: Duration::from_secs(config.retention_hours.saturating_mul(60 * 60)),
+        })
+    }
+
+    /// Store a report and delete reports that are past the retention period
+    pub fn record(&self, report: &SystemReport) -> Result<()> {
+        let timestamp = unix_seconds(report.timestamp);
+        let battery = report
+            .batteries
+            .iter()
+            .find(|b| b.capacity_percent.is_some());
+
+        self.conn.execute(
+            "INSERT INTO reports (timestamp, cpu_usage_percent, cpu_temp_celsius, avg_freq_mhz,
+                load_1m, battery_percent, battery_power_watts, ac_connected, governor, turbo, report)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                timestamp,
+                mean(
+                    report
+                        .cpu_cores
+                        .iter()
+                        .filter_map(|c| c.usage_percent)
+                        .map(f64::from)
+                ),
+                report.cpu_global.average_temperature_celsius.map(f64::from),
+                mean(
+                    report
+                        .cpu_cores
+                        .iter()
+                        .filter_map(|c| c.current_frequency_mhz)
+                        .map(f64::from)
+                ),
+                f64::from(report.system_load.load_avg_1min),
+                battery.and_then(|b| b.capacity_percent),
+                battery.and_then(|b| b.power_rate_watts).map(f64::from),
+                battery.map(|b| b.ac_connected),
+                report.cpu_global.current_governor,
+                report.cpu_global.turbo_status,
+                serde_json::to_string(report)?,
+            ],
+        )?;
+
+        let cutoff =
+            timestamp.saturating_sub(i64::try_from(self.retention.as_secs()).unwrap_or(i64::MAX));
+        let pruned = self
+            .conn
+            .execute("DELETE FROM reports WHERE timestamp < ?1", params![cutoff])?;
+        if pruned > 0 {
+            debug!("History: pruned {pruned} reports older than the retention period");
+        }
+
+        Ok(())
+    }
+
+    /// Reports recorded at or after `since`, oldest first
+    pub fn query(&self, since: SystemTime) -> Result<Vec<HistoryEntry>> {
+        let mut statement = self.conn.prepare(
+            "SELECT timestamp, cpu_usage_percent, cpu_temp_celsius, avg_freq_mhz, load_1m,
+                battery_percent, battery_power_watts, ac_connected, governor, turbo
+             FROM reports WHERE timestamp >= ?1 ORDER BY timestamp",
+        )?;
+
+        let entries = statement
+            .query_map(params![unix_seconds(since)], |row| {
+                Ok(HistoryEntry {
+                    timestamp: row.get(0)?,
+                    cpu_usage_percent: row.get(1)?,
+                    cpu_temp_celsius: row.get(2)?,
+                    avg_freq_mhz: row.get(3)?,
+                    load_1m: row.get(4)?,
+                    battery_percent: row.get(5)?,
+                    battery_power_watts: row.get(6)?,
+                    ac_connected: row.get(7)?,
+                    governor: row.get(8)?,
+                    turbo: row.get(9)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(entries)
+    }
+}
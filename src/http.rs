@@ -0,0 +1,582 @@
+use crate::config::HttpConfig;
+use crate::core::{SystemReport, TurboSetting};
+use crate::engine;
+use crate::util::wake;
+use log::{debug, info, warn};
+use nix::unistd::Group;
+use serde_json::{Value, json};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::os::unix::fs::{PermissionsExt, chown};
+use std::os::unix::net::UnixListener;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+/// Requests with a larger body are refused
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Upper bound on the request line and headers, read before the body
+const MAX_HEADER_BYTES: usize = 16 * 1024;
+
+/// How long a client may take to send its request
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+struct State {
+    /// The latest report, serialized when it was collected
+    report: Option<Value>,
+    /// Profile selected with `PUT /profile`
+    profile: Option<String>,
+    /// Turbo setting selected with `PUT /turbo`
+    turbo: Option<TurboSetting>,
+    /// Names of the configured profiles
+    profiles: Vec<String>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    report: None,
+    profile: None,
+    turbo: None,
+    profiles: Vec::new(),
+});
+
+static SERVING: AtomicBool = AtomicBool::new(false);
+
+fn state() -> std::sync::MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Profile selected over HTTP, or `None` to select a profile by rules and power state
+pub fn selected_profile() -> Option<String> {
+    state().profile.clone()
+}
+
+/// Turbo setting selected over HTTP, replacing that of the active profile
+pub fn turbo_override() -> Option<TurboSetting> {
+    state().turbo
+}
+
+/// Keep the latest report for `GET /report`
+pub fn set_report(report: &SystemReport) {
+    if !SERVING.load(Ordering::Relaxed) {
+        return;
+    }
+    match serde_json::to_value(report) {
+        Ok(report) => state().report = Some(report),
+        Err(e) => warn!("Failed to serialize the report for the HTTP API: {e}"),
+    }
+}
+
+/// Start serving the REST API on a background thread
+pub fn serve(config: &HttpConfig, profiles: Vec<String>) -> io::Result<()> {
+    state().profiles = profiles;
+    let token = config.token.clone();
+
+    let spawned = if config.is_unix_socket() {
+        let listener = bind_unix_socket(Path::new(&config.listen), config.group.as_deref())?;
+        thread::Builder::new()
+            .name("http".to_string())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let _ = stream.set_read_timeout(Some(IO_TIMEOUT));
+                    let _ = stream.set_write_timeout(Some(IO_TIMEOUT));
+                    handle_connection(stream, token.as_deref());
+                }
+            })
+    } else {
+        let listener = TcpListener::bind(&config.listen)?;
+        thread::Builder::new()
+            .name("http".to_string())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let _ = stream.set_read_timeout(Some(IO_TIMEOUT));
+                    let _ = stream.set_write_timeout(Some(IO_TIMEOUT));
+                    handle_connection(stream, token.as_deref());
+                }
+            })
+    };
+
+    spawned?;
+    SERVING.store(true, Ordering::Relaxed);
+    info!("Serving the HTTP API on {}", config.listen);
+    Ok(())
+}
+
+/// Bind a unix socket that only root, and members of `group` if given, can use
+fn bind_unix_socket(path: &Path, group: Option<&str>) -> io::Result<UnixListener> {
+    // A socket left behind by a previous run would make binding fail
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let listener = UnixListener::bind(path)?;
+
+    let mode = match group {
+        Some(name) => {
+            let group = Group::from_name(name)
+                .map_err(io::Error::from)?
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, format!("No group named '{name}'"))
+                })?;
+            chown(path, None, Some(group.gid.as_raw()))?;
+            0o660
+        }
+        None => 0o600,
+    };
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    Ok(listener)
+}
+
+#[derive(Debug)]
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct Response {
+    status: u16,
+    body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message }),
+        }
+    }
+}
+
+fn read_request(stream: impl Read) -> Result<Request, Response> {
+    let mut reader = BufReader::new(stream.take((MAX_HEADER_BYTES + MAX_BODY_BYTES) as u64));
+    let mut head = reader.by_ref().take(MAX_HEADER_BYTES as u64);
+    let bad_request = |_| Response::error(400, "Malformed request");
+    let too_large = || Response::error(431, "Request headers too large");
+
+    let mut line = String::new();
+    head.read_line(&mut line).map_err(bad_request)?;
+    if !line.ends_with('\n') && head.limit() == 0 {
+        return Err(too_large());
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(Response::error(400, "Malformed request line"));
+    };
+    let method = method.to_string();
+    // Query strings are not used by any endpoint
+    let path = target.split('?').next().unwrap_or(target).to_string();
+
+    let mut authorization = None;
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        head.read_line(&mut line).map_err(bad_request)?;
+        if !line.ends_with('\n') && head.limit() == 0 {
+            return Err(too_large());
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            return Err(Response::error(400, "Malformed header"));
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("authorization") {
+            authorization = Some(value.to_string());
+        } else if name.eq_ignore_ascii_case("content-length") {
+            content_length = value
+                .parse()
+                .map_err(|_| Response::error(400, "Invalid Content-Length"))?;
+        }
+    }
+
+    if content_length > MAX_BODY_BYTES {
+        return Err(Response::error(413, "Request body too large"));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(bad_request)?;
+
+    Ok(Request {
+        method,
+        path,
+        authorization,
+        body,
+    })
+}
+
+/// Compare tokens in constant time, so their content cannot be guessed from
+/// how long a comparison takes
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn handle_connection(mut stream: impl Read + Write, token: Option<&str>) {
+    let response = match read_request(&mut stream) {
+        Ok(request) => {
+            let authorized = token.is_none_or(|token| {
+                request
+                    .authorization
+                    .as_deref()
+                    .and_then(|value| value.strip_prefix("Bearer "))
+                    .is_some_and(|given| tokens_match(given.trim(), token))
+            });
+            if authorized {
+                debug!("HTTP {} {}", request.method, request.path);
+                route(&request)
+            } else {
+                Response::error(401, "Missing or invalid token")
+            }
+        }
+        Err(response) => response,
+    };
+
+    if let Err(e) = write_response(&mut stream, &response) {
+        debug!("Failed to send HTTP response: {e}");
+    }
+}
+
+fn write_response(stream: &mut impl Write, response: &Response) -> io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Error",
+    };
+    let body = response.body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        response.status,
+        body.len()
+    )?;
+    stream.flush()
+}
+
+/// Read a JSON object from the body and take `field` from it
+fn body_field(request: &Request, field: &str) -> Result<Value, Response> {
+    let mut body: Value = serde_json::from_slice(&request.body)
+        .map_err(|e| Response::error(400, &format!("Invalid JSON body: {e}")))?;
+    body.get_mut(field)
+        .map(Value::take)
+        .ok_or_else(|| Response::error(400, &format!("Expected a JSON object with '{field}'")))
+}
+
+fn route(request: &Request) -> Response {
+    let result = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/report") => get_report(),
+        ("GET", "/profile") => Ok(get_profile()),
+        ("PUT", "/profile") => put_profile(request),
+        ("PUT", "/turbo") => put_turbo(request),
+        (_, "/report" | "/profile" | "/turbo") => Err(Response::error(405, "Method not allowed")),
+        _ => Err(Response::error(404, "Not found")),
+    };
+    result.unwrap_or_else(|response| response)
+}
+
+fn get_report() -> Result<Response, Response> {
+    state()
+        .report
+        .clone()
+        .map(Response::ok)
+        .ok_or_else(|| Response::error(503, "No report has been collected yet"))
+}
+
+fn get_profile() -> Response {
+    let state = state();
+    Response::ok(json!({
+        "active": engine::active_profile(),
+        "selected": state.profile,
+        "turbo": state.turbo.map(turbo_name),
+        "profiles": state.profiles,
+    }))
+}
+
+/// `{"profile": "<name>"}` selects a profile, `{"profile": null}` returns to
+/// selecting one by rules and power state
+fn put_profile(request: &Request) -> Result<Response, Response> {
+    let profile = match body_field(request, "profile")? {
+        Value::Null => None,
+        Value::String(name) => Some(name),
+        _ => return Err(Response::error(400, "'profile' must be a string or null")),
+    };
+
+    {
+        let mut state = state();
+        if let Some(name) = &profile {
+            if !state.profiles.contains(name) {
+                return Err(Response::error(400, &format!("No profile named '{name}'")));
+            }
+        }
+        state.profile.clone_from(&profile);
+    }
+
+    match &profile {
+        Some(name) => info!("Profile '{name}' selected over HTTP"),
+        None => info!("Profile selection over HTTP cleared"),
+    }
+    wake::request_wakeup("profile selected over HTTP");
+    Ok(Response::ok(json!({ "selected": profile })))
+}
+
+const fn turbo_name(setting: TurboSetting) -> &'static str {
+    match setting {
+        TurboSetting::Always => "always",
+        TurboSetting::Auto => "auto",
+        TurboSetting::Never => "never",
+    }
+}
+
+/// `{"turbo": "always" | "auto" | "never"}` overrides the turbo setting of
+/// every profile, `{"turbo": null}` returns to the profile's own
+fn put_turbo(request: &Request) -> Result<Response, Response> {
+    let turbo = match body_field(request, "turbo")? {
+        Value::Null => None,
+        Value::String(name) => Some(match name.to_lowercase().as_str() {
+            "always" => TurboSetting::Always,
+            "auto" => TurboSetting::Auto,
+            "never" => TurboSetting::Never,
+            _ => {
+                return Err(Response::error(
+                    400,
+                    "'turbo' must be \"always\", \"auto\", \"never\" or null",
+                ));
+            }
+        }),
+        _ => return Err(Response::error(400, "'turbo' must be a string or null")),
+    };
+
+    state().turbo = turbo;
+    match turbo {
+        Some(setting) => info!("Turbo set to '{}' over HTTP", turbo_name(setting)),
+        None => info!("Turbo override over HTTP cleared"),
+    }
+    wake::request_wakeup("turbo set over HTTP");
+    Ok(Response::ok(json!({ "turbo": turbo.map(turbo_name) })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigError;
+
+    fn request(raw: &[u8]) -> Result<Request, Response> {
+        read_request(raw)
+    }
+
+    fn status(result: Result<Request, Response>) -> u16 {
+        result.map_or_else(|response| response.status, |_| 200)
+    }
+
+    /// A connection that reads `input` and collects the response
+    struct Connection {
+        input: io::Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Connection {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Connection {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// The status line of the response to `raw`
+    fn respond(raw: &str, token: Option<&str>) -> String {
+        let mut connection = Connection {
+            input: io::Cursor::new(raw.as_bytes().to_vec()),
+            output: Vec::new(),
+        };
+        handle_connection(&mut connection, token);
+        let response = String::from_utf8(connection.output).unwrap();
+        response.lines().next().unwrap_or_default().to_string()
+    }
+
+    #[test]
+    fn request_is_parsed() {
+        let request = request(
+            b"PUT /profile?x=1 HTTP/1.1\r\nAuthorization: Bearer abc\r\nContent-Length: 4\r\n\r\nbody",
+        )
+        .unwrap();
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.path, "/profile");
+        assert_eq!(request.authorization.as_deref(), Some("Bearer abc"));
+        assert_eq!(request.body, b"body");
+    }
+
+    #[test]
+    fn headers_are_limited() {
+        let padding = "x".repeat(MAX_HEADER_BYTES);
+        let long_header = format!("GET /report HTTP/1.1\r\nX-Padding: {padding}\r\n\r\n");
+        assert_eq!(status(request(long_header.as_bytes())), 431);
+        let long_line = format!("GET /{padding} HTTP/1.1\r\n\r\n");
+        assert_eq!(status(request(long_line.as_bytes())), 431);
+
+        let line = "GET /report HTTP/1.1\r\n";
+        let fitting = "x".repeat(MAX_HEADER_BYTES - line.len() - "X-Padding: \r\n\r\n".len());
+        let fitting = format!("{line}X-Padding: {fitting}\r\n\r\n");
+        assert_eq!(fitting.len(), MAX_HEADER_BYTES);
+        assert_eq!(status(request(fitting.as_bytes())), 200);
+    }
+
+    #[test]
+    fn body_is_limited() {
+        let body = "x".repeat(MAX_BODY_BYTES);
+        let fitting =
+            format!("PUT /turbo HTTP/1.1\r\nContent-Length: {MAX_BODY_BYTES}\r\n\r\n{body}");
+        assert_eq!(
+            request(fitting.as_bytes()).unwrap().body.len(),
+            MAX_BODY_BYTES
+        );
+
+        let too_large = format!(
+            "PUT /turbo HTTP/1.1\r\nContent-Length: {}\r\n\r\n{body}x",
+            MAX_BODY_BYTES + 1
+        );
+        assert_eq!(status(request(too_large.as_bytes())), 413);
+    }
+
+    #[test]
+    fn content_length_is_checked() {
+        for length in ["abc", "-1", "", "1e3"] {
+            let raw = format!("PUT /turbo HTTP/1.1\r\nContent-Length: {length}\r\n\r\n");
+            assert_eq!(status(request(raw.as_bytes())), 400, "{length:?}");
+        }
+
+        // More announced than sent
+        assert_eq!(
+            status(request(
+                b"PUT /turbo HTTP/1.1\r\nContent-Length: 10\r\n\r\nshort"
+            )),
+            400
+        );
+
+        // Without Content-Length there is no body, which is not a JSON object
+        let missing = request(b"PUT /turbo HTTP/1.1\r\n\r\n{\"turbo\": null}").unwrap();
+        assert!(missing.body.is_empty());
+        assert_eq!(route(&missing).status, 400);
+    }
+
+    #[test]
+    fn malformed_requests_are_rejected() {
+        assert_eq!(status(request(b"GET\r\n\r\n")), 400);
+        assert_eq!(
+            status(request(b"GET /report HTTP/1.1\r\nNo colon\r\n\r\n")),
+            400
+        );
+    }
+
+    #[test]
+    fn token_is_required() {
+        let get = |authorization: &str| {
+            respond(
+                &format!("GET /unknown HTTP/1.1\r\n{authorization}\r\n"),
+                Some("secret"),
+            )
+        };
+        assert_eq!(get(""), "HTTP/1.1 401 Unauthorized");
+        assert_eq!(
+            get("Authorization: Bearer wrong\r\n"),
+            "HTTP/1.1 401 Unauthorized"
+        );
+        assert_eq!(
+            get("Authorization: Bearer secre\r\n"),
+            "HTTP/1.1 401 Unauthorized"
+        );
+        assert_eq!(
+            get("Authorization: Basic secret\r\n"),
+            "HTTP/1.1 401 Unauthorized"
+        );
+        assert_eq!(
+            get("Authorization: Bearer secret\r\n"),
+            "HTTP/1.1 404 Not Found"
+        );
+
+        assert_eq!(
+            respond("GET /unknown HTTP/1.1\r\n\r\n", None),
+            "HTTP/1.1 404 Not Found"
+        );
+    }
+
+    #[test]
+    fn tokens_of_other_lengths_do_not_match() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secre", "secret"));
+        assert!(!tokens_match("secrets", "secret"));
+        assert!(!tokens_match("", "secret"));
+        assert!(!tokens_match("secreT", "secret"));
+    }
+
+    #[test]
+    fn unknown_paths_and_methods_are_refused() {
+        let route_to = |method: &str, path: &str| {
+            route(&Request {
+                method: method.to_string(),
+                path: path.to_string(),
+                authorization: None,
+                body: Vec::new(),
+            })
+            .status
+        };
+        assert_eq!(route_to("GET", "/unknown"), 404);
+        assert_eq!(route_to("GET", "/"), 404);
+        assert_eq!(route_to("DELETE", "/profile"), 405);
+        assert_eq!(route_to("POST", "/report"), 405);
+        assert_eq!(route_to("GET", "/turbo"), 405);
+    }
+
+    #[test]
+    fn listen_address_is_validated() {
+        let config = |listen: &str, token: Option<&str>| HttpConfig {
+            enable: true,
+            listen: listen.to_string(),
+            token: token.map(str::to_string),
+            group: None,
+        };
+        let is_invalid =
+            |config: HttpConfig| matches!(config.validate(), Err(ConfigError::Validation(_)));
+
+        assert!(is_invalid(config("0.0.0.0:8378", Some("secret"))));
+        assert!(is_invalid(config("192.168.1.2:8378", Some("secret"))));
+        assert!(is_invalid(config("[::]:8378", Some("secret"))));
+        assert!(is_invalid(config("localhost", Some("secret"))));
+        assert!(is_invalid(config("127.0.0.1:8378", None)));
+        assert!(is_invalid(config("127.0.0.1:8378", Some(""))));
+
+        assert!(config("127.0.0.1:8378", Some("secret")).validate().is_ok());
+        assert!(config("[::1]:8378", Some("secret")).validate().is_ok());
+        assert!(config("/run/superfreq.sock", None).validate().is_ok());
+    }
+}
@@ -0,0 +1,191 @@
+//! Keyboard backlights under `/sys/class/leds`, e.g. `tpacpi::kbd_backlight`.
+//! A brightness set by hand is noticed by comparing it with the value written
+//! last, and is kept through profile switches for a grace period.
+
+use crate::util::clock::{Clock, SystemClock};
+use crate::util::error::ControlError;
+use crate::util::sysfs;
+use log::{debug, info};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+pub type Result<T, E = ControlError> = std::result::Result<T, E>;
+
+const LEDS_DIR: &str = "/sys/class/leds";
+
+#[derive(Debug, Default)]
+struct LedState {
+    /// Brightness written last, until it was changed by hand
+    written: Option<u32>,
+    /// When the brightness was last found changed by hand
+    changed_by_hand: Option<Instant>,
+}
+
+impl LedState {
+    /// Compare the current brightness with the one written last, and return
+    /// whether it was changed by hand since
+    fn check_changed_by_hand(&mut self, current: u32, now: Instant) -> bool {
+        if self.written.is_some_and(|written| written != current) {
+            self.written = None;
+            self.changed_by_hand = Some(now);
+            return true;
+        }
+        false
+    }
+
+    /// Whether a brightness changed by hand is still kept
+    fn in_grace_period(&self, grace: Duration, now: Instant) -> bool {
+        self.changed_by_hand
+            .is_some_and(|changed| now.saturating_duration_since(changed) < grace)
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Percentage of the profile applied last, so it is only written once
+    applied_percent: Option<u8>,
+    leds: BTreeMap<PathBuf, LedState>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    applied_percent: None,
+    leds: BTreeMap::new(),
+});
+
+/// The keyboard backlight LEDs
+pub fn find_leds() -> Vec<PathBuf> {
+    let mut leds: Vec<PathBuf> = fs::read_dir(sysfs::path(LEDS_DIR))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| {
+                    entry
+                        .file_name()
+                        .to_string_lossy()
+                        .contains("kbd_backlight")
+                })
+                .map(|entry| entry.path())
+                .collect()
+        })
+        .unwrap_or_default();
+    leds.sort();
+    leds
+}
+
+/// The brightness files of the keyboard backlights, for snapshots
+pub fn brightness_paths() -> Vec<PathBuf> {
+    find_leds()
+        .iter()
+        .map(|led| led.join("brightness"))
+        .collect()
+}
+
+fn read_value(led: &Path, attribute: &str) -> Result<u32> {
+    let path = led.join(attribute);
+    let value = sysfs::read_sysfs_value(&path)?;
+    value.parse().map_err(|_| {
+        ControlError::ParseError(format!(
+            "Invalid brightness '{value}' in {}",
+            path.display()
+        ))
+    })
+}
+
+/// Set every keyboard backlight to `percent` of its maximum brightness, once
+/// per profile switch. A backlight changed by hand within `grace` is left
+/// as it is.
+pub fn set_brightness_percent(percent: u8, grace: Duration) -> Result<()> {
+    let leds = find_leds();
+    if leds.is_empty() {
+        return Err(ControlError::NotSupported(
+            "No keyboard backlight found.".to_string(),
+        ));
+    }
+
+    let mut state = STATE.lock().unwrap_or_else(PoisonError::into_inner);
+    let now = SystemClock.now();
+    let switched = state.applied_percent != Some(percent);
+
+    for led in leds {
+        let name = led
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        let current = read_value(&led, "brightness")?;
+        let led_state = state.leds.entry(led.clone()).or_default();
+
+        if led_state.check_changed_by_hand(current, now) {
+            info!(
+                "Keyboard backlight {name} was changed by hand, keeping it through profile switches for {}s",
+                grace.as_secs()
+            );
+        }
+        if !switched {
+            continue;
+        }
+        if led_state.in_grace_period(grace, now) {
+            debug!("Not changing keyboard backlight {name}, it was changed by hand recently");
+            continue;
+        }
+
+        let max = read_value(&led, "max_brightness")?;
+        let brightness = (u32::from(percent.min(100)) * max + 50) / 100;
+        sysfs::write_sysfs_value(led.join("brightness"), &brightness.to_string())?;
+        // Nothing is written in observe mode, so nothing can be changed by hand
+        if !sysfs::is_observe_only() {
+            led_state.written = Some(brightness);
+        }
+    }
+
+    // Set once every backlight was handled, so a failed write is retried
+    state.applied_percent = Some(percent);
+    Ok(())
+}
+
+/// Forget the applied percentage once a profile without one is selected, so
+/// the next profile that has one applies it
+pub fn reset() {
+    STATE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .applied_percent = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::clock::ManualClock;
+
+    const GRACE: Duration = Duration::from_secs(600);
+
+    #[test]
+    fn brightness_changed_by_hand_is_kept_for_grace_period() {
+        let clock = ManualClock::new();
+        let mut led = LedState {
+            written: Some(2),
+            changed_by_hand: None,
+        };
+
+        assert!(!led.check_changed_by_hand(2, clock.now()));
+        assert!(!led.in_grace_period(GRACE, clock.now()));
+
+        assert!(led.check_changed_by_hand(0, clock.now()));
+        assert_eq!(led.written, None);
+        clock.advance(GRACE - Duration::from_secs(1));
+        assert!(led.in_grace_period(GRACE, clock.now()));
+        clock.advance(Duration::from_secs(1));
+        assert!(!led.in_grace_period(GRACE, clock.now()));
+    }
+
+    #[test]
+    fn nothing_written_is_never_changed_by_hand() {
+        let clock = ManualClock::new();
+        let mut led = LedState::default();
+        assert!(!led.check_changed_by_hand(1, clock.now()));
+        assert!(!led.in_grace_period(GRACE, clock.now()));
+    }
+}
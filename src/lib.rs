@@ -0,0 +1,101 @@
+//! Superfreq's hardware abstraction, for tools such as status bars, GUIs and
+//! fleet agents that want to read or change CPU and battery settings without
+//! running the `superfreq` binary.
+//!
+//! The supported API consists of:
+//!
+//! - [`monitor`]: collecting a [`SystemReport`](core::SystemReport) of CPU,
+//!   battery and thermal state
+//! - [`cpu`]: governors, EPP, EPB, turbo, frequency limits and platform profiles
+//! - [`battery`]: charge thresholds and charge behaviour
+//! - [`engine`]: selecting and applying the profile for a report, as the daemon does
+//! - [`config`] and [`core`]: the types the above take and return
+//! - [`error`]: their error types
+//!
+//! Writing settings needs the same privileges as the binary, usually root.
+//!
+//! ```no_run
+//! let config = superfreq::config::load_config(false)?;
+//! let report = superfreq::monitor::collect_system_report(&config)?;
+//! if let Some(governor) = &report.cpu_global.current_governor {
+//!     println!("Governor: {governor}");
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! The remaining modules are used by the binary and may change in any release.
+
+pub mod battery;
+pub mod config;
+pub mod core;
+pub mod cpu;
+pub mod engine;
+pub mod monitor;
+
+pub use util::error;
+
+#[doc(hidden)]
+pub mod activity;
+#[doc(hidden)]
+pub mod alerts;
+#[doc(hidden)]
+pub mod auto_epp;
+#[doc(hidden)]
+pub mod calibration;
+#[doc(hidden)]
+pub mod conflict;
+#[doc(hidden)]
+pub mod critical_battery;
+#[doc(hidden)]
+pub mod dock;
+#[doc(hidden)]
+pub mod enforce;
+#[doc(hidden)]
+pub mod fan;
+#[doc(hidden)]
+pub mod gpu;
+#[cfg(feature = "history")]
+#[doc(hidden)]
+pub mod history;
+#[cfg(feature = "http")]
+#[doc(hidden)]
+pub mod http;
+#[doc(hidden)]
+pub mod keyboard_backlight;
+#[doc(hidden)]
+pub mod lid;
+#[cfg(feature = "mqtt")]
+#[doc(hidden)]
+pub mod mqtt;
+#[cfg(feature = "msr")]
+#[doc(hidden)]
+pub mod msr;
+#[cfg(feature = "nvml")]
+#[doc(hidden)]
+pub mod nvml;
+#[doc(hidden)]
+pub mod overrides;
+#[cfg(feature = "dbus")]
+#[doc(hidden)]
+pub mod power_profiles;
+#[doc(hidden)]
+pub mod power_supply;
+#[doc(hidden)]
+pub mod resume;
+#[doc(hidden)]
+pub mod snapshot;
+#[doc(hidden)]
+pub mod thermal;
+#[doc(hidden)]
+pub mod tunables;
+#[cfg(feature = "dbus")]
+#[doc(hidden)]
+pub mod upower;
+#[doc(hidden)]
+pub mod userspace;
+#[doc(hidden)]
+pub mod util;
+#[doc(hidden)]
+pub mod vendor;
+#[doc(hidden)]
+pub mod virt;
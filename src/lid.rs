@@ -0,0 +1,91 @@
+use crate::util::{sysfs, wake};
+use log::{debug, info, warn};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::os::unix::net::UnixStream;
+use std::sync::Once;
+use std::thread;
+use std::time::Duration;
+
+const LID_STATE_DIR: &str = "/proc/acpi/button/lid";
+const ACPID_SOCKET: &str = "/var/run/acpid.socket";
+
+/// How often the lid state is polled when acpid is not running
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+static LISTENER: Once = Once::new();
+
+/// Whether the laptop lid is closed, or `None` if there is no lid switch
+pub fn is_lid_closed() -> Option<bool> {
+    let entries = fs::read_dir(sysfs::path(LID_STATE_DIR)).ok()?;
+
+    entries.flatten().find_map(|entry| {
+        let state = fs::read_to_string(entry.path().join("state")).ok()?;
+        // e.g. "state:      closed"
+        match state.split(':').nth(1)?.trim() {
+            "closed" => Some(true),
+            "open" => Some(false),
+            _ => None,
+        }
+    })
+}
+
+/// Start a background thread that wakes the daemon as soon as the lid is opened
+/// or closed, so lid rules take effect without waiting for the next poll.
+/// Lid events are read from acpid when it is running, and the lid state is
+/// polled otherwise.
+pub fn spawn_listener() {
+    LISTENER.call_once(|| {
+        if is_lid_closed().is_none() {
+            debug!("No lid switch found, not listening for lid events");
+            return;
+        }
+
+        let spawned = thread::Builder::new()
+            .name("lid-events".to_string())
+            .spawn(|| match UnixStream::connect(ACPID_SOCKET) {
+                Ok(stream) => {
+                    info!("Listening for lid events from acpid");
+                    listen_acpid(stream);
+                    warn!("Lost connection to acpid, polling the lid state instead");
+                    poll_lid_state();
+                }
+                Err(e) => {
+                    debug!("Cannot connect to {ACPID_SOCKET} ({e}), polling the lid state");
+                    poll_lid_state();
+                }
+            });
+
+        if let Err(e) = spawned {
+            warn!("Failed to start lid event listener: {e}");
+        }
+    });
+}
+
+/// Read acpid events (e.g. `button/lid LID close`) until the connection closes
+fn listen_acpid(stream: UnixStream) {
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+
+        if line.starts_with("button/lid") {
+            debug!("acpid event: {line}");
+            wake::request_wakeup("lid event");
+        }
+    }
+}
+
+fn poll_lid_state() {
+    let mut previous = is_lid_closed();
+    loop {
+        thread::sleep(POLL_INTERVAL);
+
+        let current = is_lid_closed();
+        if current != previous {
+            debug!("Lid state changed: {previous:?} -> {current:?}");
+            wake::request_wakeup("lid event");
+            previous = current;
+        }
+    }
+}
@@ -1,25 +1,73 @@
-mod battery;
 mod cli;
-mod config;
-mod core;
-mod cpu;
 mod daemon;
-mod engine;
-mod monitor;
-mod util;
 
+// The modules of the library are imported at the crate root, so the binary's
+// own modules refer to them as `crate::<module>` like before the split
+#[cfg(feature = "history")]
+use superfreq::history;
+#[cfg(feature = "http")]
+use superfreq::http;
+#[cfg(feature = "mqtt")]
+use superfreq::mqtt;
+#[cfg(feature = "msr")]
+use superfreq::msr;
+#[cfg(feature = "dbus")]
+use superfreq::power_profiles;
+use superfreq::{
+    activity, alerts, auto_epp, battery, calibration, config, conflict, core, cpu,
+    critical_battery, enforce, engine, fan, gpu, keyboard_backlight, lid, monitor, overrides,
+    power_supply, resume, snapshot, tunables, util, vendor,
+};
+
+use crate::cli::status::StatusFormat;
 use crate::config::AppConfig;
-use crate::core::{GovernorOverrideMode, TurboSetting};
+use crate::core::{
+    AsusMode, ChargeBehaviour, GovernorOverrideMode, OperationalMode, PstateMode, TurboSetting,
+};
 use crate::util::error::{AppError, ControlError};
 use clap::{Parser, value_parser};
-use env_logger::Builder;
+use env_logger::{Builder, Target, WriteStyle};
 use log::{debug, error, info};
 use std::error::Error;
-use std::sync::Once;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Once};
+
+/// Version string shown by `--version`, including the optional features built in
+static LONG_VERSION: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        "{}\nfeatures: {}",
+        env!("CARGO_PKG_VERSION"),
+        util::features::enabled_features_summary()
+    )
+});
 
 #[derive(Parser, Debug)]
-#[clap(author, version, about, long_about = None)]
+#[clap(author, version, long_version = LONG_VERSION.as_str(), about, long_about = None)]
 struct Cli {
+    /// Read the configuration from this file instead of the default locations
+    #[clap(
+        long,
+        global = true,
+        value_name = "PATH",
+        conflicts_with = "config_inline"
+    )]
+    config: Option<PathBuf>,
+
+    /// Read the configuration from this TOML string instead of a file ("-" reads stdin)
+    #[clap(long, global = true, value_name = "TOML")]
+    config_inline: Option<String>,
+
+    /// Exit with an error instead of falling back to defaults on an invalid configuration
+    #[clap(long, global = true)]
+    strict_config: bool,
+
+    /// Read and write `sys` and `proc` trees under this directory instead of
+    /// `/`, e.g. a fixture tree in tests, or under a tarball written by
+    /// `debug --dump-sysfs`. Also set by `SUPERFREQ_SYSFS_ROOT`.
+    #[clap(long, global = true, value_name = "DIR", hide = true)]
+    sysfs_root: Option<PathBuf>,
+
     #[clap(subcommand)]
     command: Option<Commands>,
 }
@@ -27,11 +75,42 @@ struct Cli {
 #[derive(Parser, Debug)]
 enum Commands {
     /// Display current system information
-    Info,
+    Info {
+        /// Include every monitored domain, not just CPU and battery
+        #[clap(long)]
+        all: bool,
+        /// Print the report as JSON
+        #[clap(long)]
+        json: bool,
+        /// Re-render the report every SECONDS (default 2) until interrupted
+        #[clap(long, value_name = "SECONDS", num_args = 0..=1, default_missing_value = "2")]
+        watch: Option<u64>,
+    },
+    /// Print a compact summary of the power management state
+    Status {
+        /// Output format
+        #[clap(long, value_enum, default_value_t = StatusFormat::Text)]
+        format: StatusFormat,
+        /// Print `key=value` pairs on a single line, for shell prompts and tmux
+        #[clap(long, conflicts_with = "format")]
+        short: bool,
+    },
     /// Run as a daemon in the background
     Daemon {
         #[clap(long)]
         verbose: bool,
+        /// Poll every this many seconds, overriding `poll_interval_sec`
+        #[clap(long, value_name = "SECONDS", value_parser = value_parser!(u64).range(1..))]
+        poll_interval: Option<u64>,
+        /// Always apply this profile, ignoring rules and the power state
+        #[clap(long)]
+        profile: Option<String>,
+        /// Poll at a fixed interval, overriding `adaptive_interval`
+        #[clap(long)]
+        no_adaptive: bool,
+        /// Write statistics to this file, overriding `stats_file_path`
+        #[clap(long, value_name = "PATH")]
+        stats_file: Option<String>,
     },
     /// Set CPU governor
     SetGovernor {
@@ -50,8 +129,22 @@ enum Commands {
         #[clap(value_enum)]
         setting: TurboSetting,
     },
+    /// Apply the matching profile once and exit
+    Apply {
+        /// Apply a specific mode instead of detecting the power state
+        #[clap(long, value_enum)]
+        mode: Option<OperationalMode>,
+    },
     /// Display comprehensive debug information
-    Debug,
+    Debug {
+        /// Print all sections as a single JSON object, e.g. to attach to a bug report
+        #[clap(long)]
+        json: bool,
+        /// Copy the sysfs and procfs files superfreq reads into a tarball in
+        /// this directory, which `--sysfs-root` replays
+        #[clap(long, value_name = "DIR", conflicts_with = "json")]
+        dump_sysfs: Option<PathBuf>,
+    },
     /// Set Energy Performance Preference (EPP)
     SetEpp {
         epp: String,
@@ -78,6 +171,11 @@ enum Commands {
     },
     /// Set ACPI platform profile
     SetPlatformProfile { profile: String },
+    /// Switch intel_pstate between active, passive and off
+    SetPstateMode {
+        #[clap(value_enum)]
+        mode: PstateMode,
+    },
     /// Set battery charge thresholds to extend battery lifespan
     SetBatteryThresholds {
         /// Percentage at which charging starts (when below this value)
@@ -86,19 +184,321 @@ enum Commands {
         /// Percentage at which charging stops (when it reaches this value)
         #[clap(value_parser = value_parser!(u8).range(1..=100))]
         stop_threshold: u8,
+        /// Only set the thresholds of this battery (e.g. BAT0)
+        #[clap(long)]
+        battery: Option<String>,
+    },
+    /// Set the charge behaviour of a battery
+    SetChargeBehaviour {
+        /// Battery name (e.g. BAT0)
+        battery: String,
+        #[clap(value_enum)]
+        behaviour: ChargeBehaviour,
+    },
+    /// Settings of the asus-wmi driver on ASUS laptops
+    Asus {
+        #[clap(subcommand)]
+        command: AsusCommands,
+    },
+    /// Battery maintenance commands
+    Battery {
+        #[clap(subcommand)]
+        command: BatteryCommands,
+    },
+    /// Manage the configuration file
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommands,
+    },
+    /// Deal with other power management services
+    Conflicts {
+        #[clap(subcommand)]
+        command: ConflictsCommands,
+    },
+    /// Share profiles between machines
+    Profile {
+        #[clap(subcommand)]
+        command: ProfileCommands,
+    },
+    /// Save the current settings and restore them later
+    Snapshot {
+        #[clap(subcommand)]
+        command: SnapshotCommands,
+    },
+    /// Show a histogram of the time each CPU spent in each frequency range
+    FreqStats {
+        /// Only show this CPU
+        #[clap(long)]
+        core_id: Option<u32>,
+        /// Measure over this many seconds instead of since boot. Without
+        /// cpufreq statistics, the frequency is sampled for this long (default 5).
+        #[clap(long, value_name = "SECONDS")]
+        interval: Option<u64>,
+        /// Width of each frequency range in MHz
+        #[clap(long, default_value_t = 200, value_parser = value_parser!(u32).range(1..))]
+        bucket_mhz: u32,
+        /// Print the histogram as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Apply each profile in turn and compare their power draw and performance
+    Bench {
+        /// Profile to compare, repeated for each profile: a file with a bare
+        /// profile section or a bundle written by `profile export`
+        #[clap(long = "profile", value_name = "FILE", required = true)]
+        profiles: Vec<PathBuf>,
+        /// How long to run each profile, e.g. 30s, 10m or 1h
+        #[clap(long, default_value = "1m")]
+        duration: String,
+        /// Seconds between samples
+        #[clap(long, default_value_t = 5, value_parser = value_parser!(u64).range(1..))]
+        interval: u64,
+        /// Print the results as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// List thermal zones with their trip points and cooling devices
+    Thermal {
+        /// Print the zones as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// List power tunables such as USB autosuspend and SATA link power
+    /// management, and whether they are set to save power
+    Tunables {
+        /// Print the tunables as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Set the tunables listed by `tunables` to their power-saving values
+    Tune {
+        /// Change every tunable that does not save power yet, except those
+        /// where it could cause problems
+        #[clap(long, required_unless_present = "undo", conflicts_with = "undo")]
+        auto: bool,
+        /// Write back the values replaced by `--auto`
+        #[clap(long)]
+        undo: bool,
+    },
+    /// Inspect what the daemon has recorded over time
+    History {
+        #[clap(subcommand)]
+        command: HistoryCommands,
+    },
+}
+
+#[derive(Parser, Debug)]
+enum BatteryCommands {
+    /// Calibrate the battery gauge with a full charge and controlled discharge.
+    /// Progress is tracked by the daemon, which must be running.
+    Calibrate {
+        /// Battery name (default: the first battery with charge threshold support)
+        battery: Option<String>,
+        /// Show the progress of the calibration in progress
+        #[clap(long)]
+        status: bool,
+        /// Abort the calibration in progress and restore the previous settings
+        #[clap(long, conflicts_with = "status")]
+        cancel: bool,
     },
 }
 
-fn main() -> Result<(), AppError> {
+#[derive(Parser, Debug)]
+enum AsusCommands {
+    /// Show the current fan boost mode, thermal policy and panel overdrive
+    Status {
+        /// Print the status as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Set the fan curve preset of older models
+    SetFanBoostMode {
+        #[clap(value_enum)]
+        mode: AsusMode,
+    },
+    /// Set the fan curve and power limit preset of newer models
+    SetThrottleThermalPolicy {
+        #[clap(value_enum)]
+        mode: AsusMode,
+    },
+    /// Turn panel overdrive on or off
+    SetPanelOd {
+        #[clap(value_parser = clap::builder::BoolishValueParser::new())]
+        enabled: bool,
+    },
+}
+
+#[derive(Parser, Debug)]
+enum ConflictsCommands {
+    /// Stop and mask conflicting services such as TLP, TuneD and
+    /// power-profiles-daemon, or print the commands to do so without root
+    Resolve {
+        /// Do not ask for confirmation
+        #[clap(long, short)]
+        yes: bool,
+    },
+}
+
+#[derive(Parser, Debug)]
+enum HistoryCommands {
+    /// Print the reports recorded within a period
+    #[cfg(feature = "history")]
+    Query {
+        /// How far back to look, e.g. 30m, 1h or 7d
+        #[clap(long, default_value = "1h")]
+        since: String,
+        /// Print the reports as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Show how long the running daemon has spent in each system state
+    Summary {
+        /// Print the summary as JSON
+        #[clap(long)]
+        json: bool,
+    },
+}
+
+#[derive(Parser, Debug)]
+enum ProfileCommands {
+    /// Print the settings of a profile as a bundle that can be imported on
+    /// another machine
+    Export { name: String },
+    /// Add the profiles of a bundle to the configuration file
+    Import {
+        file: PathBuf,
+        /// Import the profile under another name
+        #[clap(long)]
+        name: Option<String>,
+        /// Import settings this machine does not support
+        #[clap(long)]
+        force: bool,
+    },
+}
+
+#[derive(Parser, Debug)]
+enum SnapshotCommands {
+    /// Record governors, EPP, EPB, frequency limits, turbo, the platform
+    /// profile and battery thresholds under a name
+    Save { name: String },
+    /// Write back the settings recorded in a snapshot
+    Restore { name: String },
+    /// List the saved snapshots
+    List,
+}
+
+#[derive(Parser, Debug)]
+enum ConfigCommands {
+    /// Probe the system and generate a commented configuration file
+    Init {
+        /// Where to write the configuration (default: /etc/xdg/superfreq/config.toml)
+        #[clap(long, short)]
+        output: Option<PathBuf>,
+        /// Print the configuration instead of writing it to a file
+        #[clap(long, conflicts_with = "output")]
+        stdout: bool,
+        /// Overwrite an existing configuration file
+        #[clap(long)]
+        force: bool,
+    },
+    /// Validate the configuration and exit with an error if it is invalid
+    Check,
+}
+
+fn main() {
     // Initialize logger once for the entire application
     init_logger();
 
+    if let Err(e) = run() {
+        let source = if let AppError::Config(config_error) = &e {
+            error!("Invalid configuration: {config_error}");
+            config_error.source()
+        } else {
+            error!("Error executing command: {e}");
+            e.source()
+        };
+        if let Some(source) = source {
+            error!("Caused by: {source}");
+        }
+
+        // Check for permission denied errors
+        if let AppError::Control(control_error) = &e {
+            if matches!(control_error, ControlError::PermissionDenied(_)) {
+                error!(
+                    "Hint: This operation may require administrator privileges (e.g., run with sudo, or install the polkit policy)."
+                );
+            }
+        }
+
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), AppError> {
     let cli = Cli::parse();
 
+    let explicit_config = cli.config.is_some() || cli.config_inline.is_some();
+    if cli::polkit::is_pkexec_child() && (explicit_config || cli.sysfs_root.is_some()) {
+        // Polkit may allow these commands without a password, which must not
+        // extend to applying any configuration or writing any files the
+        // caller chooses
+        error!("--config, --config-inline and --sysfs-root cannot be used through pkexec");
+        std::process::exit(1);
+    }
+    let sysfs_root = cli.sysfs_root.clone().or_else(|| {
+        std::env::var_os(util::sysfs::ROOT_ENV)
+            .filter(|root| !root.is_empty())
+            .map(PathBuf::from)
+    });
+    // A dump is unpacked for the duration of the command
+    let mut unpacked_dump = None;
+    if let Some(root) = sysfs_root {
+        if root.is_file() {
+            match cli::sysfs_dump::unpack(&root) {
+                Ok(dump) => {
+                    util::sysfs::set_root(dump.root.clone());
+                    unpacked_dump = Some(dump);
+                }
+                Err(e) => {
+                    error!("Failed to unpack {}: {e}", root.display());
+                    std::process::exit(1);
+                }
+            }
+        } else {
+            util::sysfs::set_root(root);
+        }
+    }
+    // Let desktop users change hardware settings without sudo, as allowed by
+    // the installed polkit policy
+    if !explicit_config && !util::sysfs::has_custom_root() {
+        if let Some(command) = cli::polkit::command_to_escalate() {
+            let e = cli::polkit::reexec(cli.strict_config);
+            debug!("Failed to run '{command}' through pkexec: {e}");
+        }
+    }
+
+    // Checking the configuration implies strict semantics
+    let strict_config = cli.strict_config
+        || matches!(
+            cli.command,
+            Some(Commands::Config {
+                command: ConfigCommands::Check
+            })
+        );
+
     // Load configuration first, as it might be needed by the monitor module
     // E.g., for ignored power supplies
-    let config = match config::load_config() {
+    let config_result = match (&cli.config, &cli.config_inline) {
+        (Some(path), _) => config::load_config_from_path(Some(path), strict_config),
+        (None, Some(source)) => config::load_config_inline(source),
+        (None, None) => config::load_config(strict_config),
+    };
+    let config = match config_result {
         Ok(cfg) => cfg,
+        Err(e) if strict_config || cli.config.is_some() || cli.config_inline.is_some() => {
+            // An explicitly provided configuration must not silently fall back to defaults
+            return Err(AppError::Config(e));
+        }
         Err(e) => {
             error!("Error loading configuration: {e}. Using default values.");
             // Proceed with default config if loading fails
@@ -106,248 +506,23 @@ fn main() -> Result<(), AppError> {
         }
     };
 
-    let command_result: Result<(), AppError> = match cli.command {
-        // TODO: This will be moved to a different module in the future.
-        Some(Commands::Info) => match monitor::collect_system_report(&config) {
-            Ok(report) => {
-                // Format section headers with proper centering
-                let format_section = |title: &str| {
-                    let title_len = title.len();
-                    let total_width = title_len + 8; // 8 is for padding (4 on each side)
-                    let separator = "═".repeat(total_width);
-
-                    println!("\n╔{separator}╗");
-
-                    // Calculate centering
-                    println!("║    {title}    ║");
-
-                    println!("╚{separator}╝");
-                };
-
-                format_section("System Information");
-                println!("CPU Model:          {}", report.system_info.cpu_model);
-                println!("Architecture:       {}", report.system_info.architecture);
-                println!(
-                    "Linux Distribution: {}",
-                    report.system_info.linux_distribution
-                );
-
-                // Format timestamp in a readable way
-                println!("Current Time:       {}", jiff::Timestamp::now());
-
-                format_section("CPU Global Info");
-                println!(
-                    "Current Governor:    {}",
-                    report
-                        .cpu_global
-                        .current_governor
-                        .as_deref()
-                        .unwrap_or("N/A")
-                );
-                println!(
-                    "Available Governors: {}", // 21 length baseline
-                    report.cpu_global.available_governors.join(", ")
-                );
-                println!(
-                    "Turbo Status:        {}",
-                    match report.cpu_global.turbo_status {
-                        Some(true) => "Enabled",
-                        Some(false) => "Disabled",
-                        None => "Unknown",
-                    }
-                );
-
-                println!(
-                    "EPP:                 {}",
-                    report.cpu_global.epp.as_deref().unwrap_or("N/A")
-                );
-                println!(
-                    "EPB:                 {}",
-                    report.cpu_global.epb.as_deref().unwrap_or("N/A")
-                );
-                println!(
-                    "Platform Profile:    {}",
-                    report
-                        .cpu_global
-                        .platform_profile
-                        .as_deref()
-                        .unwrap_or("N/A")
-                );
-                println!(
-                    "CPU Temperature:     {}",
-                    report.cpu_global.average_temperature_celsius.map_or_else(
-                        || "N/A (No sensor detected)".to_string(),
-                        |t| format!("{t:.1}°C")
-                    )
-                );
-
-                format_section("CPU Core Info");
-
-                // Get max core ID length for padding
-                let max_core_id_len = report
-                    .cpu_cores
-                    .last()
-                    .map_or(1, |core| core.core_id.to_string().len());
-
-                // Table headers
-                println!(
-                    "  {:>width$}  │ {:^10} │ {:^10} │ {:^10} │ {:^7} │ {:^9}",
-                    "Core",
-                    "Current",
-                    "Min",
-                    "Max",
-                    "Usage",
-                    "Temp",
-                    width = max_core_id_len + 4
-                );
-                println!(
-                    "  {:─>width$}──┼─{:─^10}─┼─{:─^10}─┼─{:─^10}─┼─{:─^7}─┼─{:─^9}",
-                    "",
-                    "",
-                    "",
-                    "",
-                    "",
-                    "",
-                    width = max_core_id_len + 4
-                );
-
-                for core_info in &report.cpu_cores {
-                    // Format frequencies: if current > max, show in a special way
-                    let current_freq = match core_info.current_frequency_mhz {
-                        Some(freq) => {
-                            let max_freq = core_info.max_frequency_mhz.unwrap_or(0);
-                            if freq > max_freq && max_freq > 0 {
-                                // Special format for boosted frequencies
-                                format!("{freq}*")
-                            } else {
-                                format!("{freq}")
-                            }
-                        }
-                        None => "N/A".to_string(),
-                    };
-
-                    // CPU core display
-                    println!(
-                        "  Core {:<width$} │ {:>10} │ {:>10} │ {:>10} │ {:>7} │ {:>9}",
-                        core_info.core_id,
-                        format!("{} MHz", current_freq),
-                        format!(
-                            "{} MHz",
-                            core_info
-                                .min_frequency_mhz
-                                .map_or_else(|| "N/A".to_string(), |f| f.to_string())
-                        ),
-                        format!(
-                            "{} MHz",
-                            core_info
-                                .max_frequency_mhz
-                                .map_or_else(|| "N/A".to_string(), |f| f.to_string())
-                        ),
-                        format!(
-                            "{}%",
-                            core_info
-                                .usage_percent
-                                .map_or_else(|| "N/A".to_string(), |f| format!("{f:.1}"))
-                        ),
-                        format!(
-                            "{}°C",
-                            core_info
-                                .temperature_celsius
-                                .map_or_else(|| "N/A".to_string(), |f| format!("{f:.1}"))
-                        ),
-                        width = max_core_id_len
-                    );
-                }
-
-                // Only display battery info for systems that have real batteries
-                // Skip this section entirely on desktop systems
-                if !report.batteries.is_empty() {
-                    let has_real_batteries = report.batteries.iter().any(|b| {
-                        // Check if any battery has actual battery data
-                        // (as opposed to peripherals like wireless mice)
-                        b.capacity_percent.is_some() || b.power_rate_watts.is_some()
-                    });
-
-                    if has_real_batteries {
-                        format_section("Battery Info");
-                        for battery_info in &report.batteries {
-                            // Check if this appears to be a real system battery
-                            if battery_info.capacity_percent.is_some()
-                                || battery_info.power_rate_watts.is_some()
-                            {
-                                let power_status = if battery_info.ac_connected {
-                                    "Connected to AC"
-                                } else {
-                                    "Running on Battery"
-                                };
-
-                                println!("Battery {}:", battery_info.name);
-                                println!("  Power Status:     {power_status}");
-                                println!(
-                                    "  State:            {}",
-                                    battery_info.charging_state.as_deref().unwrap_or("Unknown")
-                                );
-
-                                if let Some(capacity) = battery_info.capacity_percent {
-                                    println!("  Capacity:         {capacity}%");
-                                }
-
-                                if let Some(power) = battery_info.power_rate_watts {
-                                    let direction = if power >= 0.0 {
-                                        "charging"
-                                    } else {
-                                        "discharging"
-                                    };
-                                    println!(
-                                        "  Power Rate:       {:.2} W ({})",
-                                        power.abs(),
-                                        direction
-                                    );
-                                }
-
-                                // Display charge thresholds if available
-                                if battery_info.charge_start_threshold.is_some()
-                                    || battery_info.charge_stop_threshold.is_some()
-                                {
-                                    println!(
-                                        "  Charge Thresholds: {}-{}",
-                                        battery_info
-                                            .charge_start_threshold
-                                            .map_or_else(|| "N/A".to_string(), |t| t.to_string()),
-                                        battery_info
-                                            .charge_stop_threshold
-                                            .map_or_else(|| "N/A".to_string(), |t| t.to_string())
-                                    );
-                                }
-                            }
-                        }
-                    }
-                }
+    if let Some(ignored_cores) = &config.ignored_cores {
+        cpu::set_ignored_cores(ignored_cores);
+    }
 
-                format_section("System Load");
-                println!(
-                    "Load Average (1m):  {:.2}",
-                    report.system_load.load_avg_1min
-                );
-                println!(
-                    "Load Average (5m):  {:.2}",
-                    report.system_load.load_avg_5min
-                );
-                println!(
-                    "Load Average (15m): {:.2}",
-                    report.system_load.load_avg_15min
-                );
-                Ok(())
-            }
-            Err(e) => Err(AppError::Monitor(e)),
-        },
+    let command_result: Result<(), AppError> = match cli.command {
+        Some(Commands::Info { all, json, watch }) => cli::info::run_info(&config, all, json, watch),
+        Some(Commands::Status { format, short }) => cli::status::run_status(&config, format, short),
         Some(Commands::SetGovernor { governor, core_id }) => {
             cpu::set_governor(&governor, core_id).map_err(AppError::Control)
         }
         Some(Commands::ForceGovernor { mode }) => {
             cpu::force_governor(mode).map_err(AppError::Control)
         }
-        Some(Commands::SetTurbo { setting }) => cpu::set_turbo(setting).map_err(AppError::Control),
+        Some(Commands::SetTurbo { setting }) => {
+            cpu::set_turbo(setting, &cpu::boost_order(&config.boost_control))
+                .map_err(AppError::Control)
+        }
         Some(Commands::SetEpp { epp, core_id }) => {
             cpu::set_epp(&epp, core_id).map_err(AppError::Control)
         }
@@ -364,6 +539,9 @@ fn main() -> Result<(), AppError> {
             validate_freq(freq_mhz, "Maximum")?;
             cpu::set_max_frequency(freq_mhz, core_id).map_err(AppError::Control)
         }
+        Some(Commands::SetPstateMode { mode }) => {
+            cpu::set_intel_pstate_mode(mode).map_err(AppError::Control)
+        }
         Some(Commands::SetPlatformProfile { profile }) => {
             // Get available platform profiles and validate early if possible
             match cpu::get_platform_profiles() {
@@ -393,6 +571,7 @@ fn main() -> Result<(), AppError> {
         Some(Commands::SetBatteryThresholds {
             start_threshold,
             stop_threshold,
+            battery,
         }) => {
             // We only need to check if start < stop since the range validation is handled by Clap
             if start_threshold >= stop_threshold {
@@ -402,6 +581,16 @@ fn main() -> Result<(), AppError> {
                 Err(AppError::Generic(format!(
                     "Start threshold ({start_threshold}) must be less than stop threshold ({stop_threshold})"
                 )))
+            } else if let Some(battery_name) = battery {
+                info!(
+                    "Setting battery thresholds for {battery_name}: start at {start_threshold}%, stop at {stop_threshold}%"
+                );
+                battery::set_battery_charge_thresholds_for(
+                    &battery_name,
+                    start_threshold,
+                    stop_threshold,
+                )
+                .map_err(AppError::Control)
             } else {
                 info!(
                     "Setting battery thresholds: start at {start_threshold}%, stop at {stop_threshold}%"
@@ -410,8 +599,133 @@ fn main() -> Result<(), AppError> {
                     .map_err(AppError::Control)
             }
         }
-        Some(Commands::Daemon { verbose }) => daemon::run_daemon(config, verbose),
-        Some(Commands::Debug) => cli::debug::run_debug(&config),
+        Some(Commands::Apply { mode }) => match monitor::collect_system_report(&config) {
+            Ok(report) => engine::determine_and_apply_settings(&report, &config, mode)
+                .map_err(AppError::Engine),
+            Err(e) => Err(AppError::Monitor(e)),
+        },
+        Some(Commands::SetChargeBehaviour { battery, behaviour }) => {
+            info!("Setting charge behaviour of {battery} to '{behaviour}'");
+            battery::set_charge_behaviour(&battery, behaviour).map_err(AppError::Control)
+        }
+        Some(Commands::Asus {
+            command: AsusCommands::Status { json },
+        }) => cli::asus::run_asus_status(json),
+        Some(Commands::Asus {
+            command: AsusCommands::SetFanBoostMode { mode },
+        }) => {
+            info!("Setting ASUS fan boost mode to '{mode}'");
+            vendor::asus::set_fan_boost_mode(mode).map_err(AppError::Control)
+        }
+        Some(Commands::Asus {
+            command: AsusCommands::SetThrottleThermalPolicy { mode },
+        }) => {
+            info!("Setting ASUS throttle thermal policy to '{mode}'");
+            vendor::asus::set_throttle_thermal_policy(mode).map_err(AppError::Control)
+        }
+        Some(Commands::Asus {
+            command: AsusCommands::SetPanelOd { enabled },
+        }) => {
+            info!(
+                "Turning ASUS panel overdrive {}",
+                if enabled { "on" } else { "off" }
+            );
+            vendor::asus::set_panel_od(enabled).map_err(AppError::Control)
+        }
+        Some(Commands::Battery {
+            command:
+                BatteryCommands::Calibrate {
+                    battery,
+                    status,
+                    cancel,
+                },
+        }) => cli::battery::run_battery_calibrate(battery.as_deref(), status, cancel),
+        Some(Commands::Config {
+            command:
+                ConfigCommands::Init {
+                    output,
+                    stdout,
+                    force,
+                },
+        }) => cli::config::run_config_init(output.as_deref(), stdout, force),
+        Some(Commands::Config {
+            command: ConfigCommands::Check,
+        }) => {
+            println!("Configuration is valid.");
+            Ok(())
+        }
+        Some(Commands::Conflicts {
+            command: ConflictsCommands::Resolve { yes },
+        }) => cli::conflicts::run_conflicts_resolve(yes),
+        #[cfg(feature = "history")]
+        Some(Commands::History {
+            command: HistoryCommands::Query { since, json },
+        }) => cli::history::run_history_query(&config, &since, json),
+        Some(Commands::History {
+            command: HistoryCommands::Summary { json },
+        }) => cli::history::run_history_summary(&config, json),
+        Some(Commands::Profile {
+            command: ProfileCommands::Export { name },
+        }) => cli::profile::run_profile_export(&config, &name),
+        Some(Commands::Profile {
+            command: ProfileCommands::Import { file, name, force },
+        }) => {
+            cli::profile::run_profile_import(&file, name.as_deref(), force, cli.config.as_deref())
+        }
+        Some(Commands::Snapshot {
+            command: SnapshotCommands::Save { name },
+        }) => cli::snapshot::run_snapshot_save(&name),
+        Some(Commands::Snapshot {
+            command: SnapshotCommands::Restore { name },
+        }) => cli::snapshot::run_snapshot_restore(&name),
+        Some(Commands::Snapshot {
+            command: SnapshotCommands::List,
+        }) => cli::snapshot::run_snapshot_list(),
+        Some(Commands::FreqStats {
+            core_id,
+            interval,
+            bucket_mhz,
+            json,
+        }) => cli::freq_stats::run_freq_stats(core_id, interval, bucket_mhz, json),
+        Some(Commands::Bench {
+            profiles,
+            duration,
+            interval,
+            json,
+        }) => cli::bench::run_bench(&config, &profiles, &duration, interval, json),
+        Some(Commands::Thermal { json }) => cli::thermal::run_thermal(json),
+        Some(Commands::Tunables { json }) => cli::tunables::run_tunables(&config, json),
+        Some(Commands::Tune { undo: true, .. }) => cli::tune::run_tune_undo(),
+        Some(Commands::Tune { .. }) => cli::tune::run_tune_auto(),
+        Some(Commands::Daemon {
+            verbose,
+            poll_interval,
+            profile,
+            no_adaptive,
+            stats_file,
+        }) => {
+            let mut config = config;
+            let overrides = daemon::DaemonOverrides {
+                poll_interval_sec: poll_interval,
+                profile,
+                no_adaptive,
+                stats_file_path: stats_file,
+            };
+            // The daemon reloads the file it was started with, if any
+            let config_path = match (cli.config, cli.config_inline) {
+                (Some(path), _) => Some(path),
+                (None, Some(_)) => None,
+                (None, None) => config::config_file_path(),
+            };
+            overrides
+                .apply(&mut config)
+                .and_then(|()| daemon::run_daemon(config, overrides, config_path, verbose))
+        }
+        Some(Commands::Debug {
+            dump_sysfs: Some(dir),
+            ..
+        }) => cli::sysfs_dump::run_dump_sysfs(&dir),
+        Some(Commands::Debug { json, .. }) => cli::debug::run_debug(&config, json),
         None => {
             info!("Welcome to superfreq! Use --help for commands.");
             debug!("Current effective configuration: {config:?}");
@@ -419,25 +733,9 @@ fn main() -> Result<(), AppError> {
         }
     };
 
-    if let Err(e) = command_result {
-        error!("Error executing command: {e}");
-        if let Some(source) = e.source() {
-            error!("Caused by: {source}");
-        }
-
-        // Check for permission denied errors
-        if let AppError::Control(control_error) = &e {
-            if matches!(control_error, ControlError::PermissionDenied(_)) {
-                error!(
-                    "Hint: This operation may require administrator privileges (e.g., run with sudo)."
-                );
-            }
-        }
-
-        std::process::exit(1);
-    }
-
-    Ok(())
+    // The dump only lives for the duration of the command
+    drop(unpacked_dump);
+    command_result
 }
 
 /// Initialize the logger for the entire application
@@ -447,10 +745,18 @@ fn init_logger() {
         // Set default log level based on environment or default to Info
         let env_log = std::env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string());
 
+        // Records go through LogWriter so that the daemon can redirect them to a file
+        let write_style = if std::io::stderr().is_terminal() {
+            WriteStyle::Always
+        } else {
+            WriteStyle::Never
+        };
+
         Builder::new()
             .parse_filters(&env_log)
-            .format_timestamp(None)
-            .format_module_path(false)
+            .format(util::logging::format_record)
+            .write_style(write_style)
+            .target(Target::Pipe(Box::new(util::logging::LogWriter)))
             .init();
 
         debug!("Logger initialized with RUST_LOG={env_log}");
@@ -1,29 +1,72 @@
+//! Reading the state of the system: CPU topology, usage, frequencies and
+//! temperatures, batteries and power supplies, collected into a
+//! [`SystemReport`] by [`collect_system_report`].
+
+use crate::battery;
 use crate::config::AppConfig;
-use crate::core::{BatteryInfo, CpuCoreInfo, CpuGlobalInfo, SystemInfo, SystemLoad, SystemReport};
-use crate::cpu::get_logical_core_count;
+#[cfg(feature = "dbus")]
+use crate::config::BatteryBackend;
+use crate::core::{
+    BatteryInfo, CollectionError, CollectionErrorKind, CoolingDevice, CpuCoreInfo, CpuGlobalInfo,
+    CpuThread, CpuTopology, CpufreqPolicy, NvidiaGpuInfo, PowerInfo, SystemInfo, SystemLoad,
+    SystemReport, ThermalZone, ThrottleCounts, ThrottleInfo, TripPoint, WakeupInfo, WakeupSource,
+};
+use crate::cpu::{self, get_logical_core_count};
+use crate::dock;
+use crate::gpu;
+use crate::lid;
+#[cfg(feature = "dbus")]
+use crate::upower;
 use crate::util::error::SysMonitorError;
+use crate::util::sysfs;
+use crate::vendor;
+use crate::virt;
 use log::debug;
 use std::{
-    collections::HashMap,
-    fs,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs, io,
     path::{Path, PathBuf},
     str::FromStr,
+    sync::{Mutex, PoisonError},
     thread,
     time::Duration,
-    time::SystemTime,
+    time::{Instant, SystemTime},
 };
 
 pub type Result<T, E = SysMonitorError> = std::result::Result<T, E>;
 
+/// Fewest cores that are read on several threads. Starting the threads costs
+/// about as much as reading four cores, so up to eight cores the threads save
+/// little or nothing (see `bench_core_collection`).
+const PARALLEL_COLLECTION_MIN_CORES: usize = 16;
+
+/// Files that could not be read for lack of permissions while collecting a report
+static PERMISSION_DENIED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
 // Read a sysfs file to a string, trimming whitespace
 fn read_sysfs_file_trimmed(path: impl AsRef<Path>) -> Result<String> {
-    fs::read_to_string(path.as_ref())
+    sysfs::read_retained(path.as_ref())
+        .unwrap_or_else(|| fs::read_to_string(path.as_ref()))
         .map(|s| s.trim().to_string())
         .map_err(|e| {
+            if e.kind() == io::ErrorKind::PermissionDenied {
+                PERMISSION_DENIED
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(path.as_ref().display().to_string());
+            }
             SysMonitorError::ReadError(format!("Path: {:?}, Error: {}", path.as_ref().display(), e))
         })
 }
 
+/// Take the files that could not be read for lack of permissions since the last call
+fn take_permission_denied() -> Vec<String> {
+    let mut denied = PERMISSION_DENIED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    std::mem::take(&mut *denied).into_iter().collect()
+}
+
 // Read a sysfs file and parse it to a specific type
 fn read_sysfs_value<T: FromStr>(path: impl AsRef<Path>) -> Result<T> {
     let content = read_sysfs_file_trimmed(path.as_ref())?;
@@ -36,15 +79,69 @@ fn read_sysfs_value<T: FromStr>(path: impl AsRef<Path>) -> Result<T> {
     })
 }
 
+/// System information that does not change while running, read once instead
+/// of on every poll
+#[derive(Debug, Clone)]
+struct StaticSystemInfo {
+    /// Contents of `/sys/devices/system/cpu/online` when this was read. CPU
+    /// hotplug changes it, and with it the CPUs the other fields are read from.
+    online_cpus: Option<String>,
+    cpu_model: String,
+    linux_distribution: String,
+    available_governors: Vec<String>,
+    available_platform_profiles: Vec<String>,
+}
+
+impl StaticSystemInfo {
+    fn read(online_cpus: Option<String>) -> Self {
+        debug!("Reading static system information");
+
+        Self {
+            online_cpus,
+            cpu_model: get_cpu_model().unwrap_or_else(|_| "Unknown".to_string()),
+            linux_distribution: get_linux_distribution().unwrap_or_else(|_| "Unknown".to_string()),
+            available_governors: cpu::get_available_governors().unwrap_or_default(),
+            available_platform_profiles: cpu::get_platform_profiles().unwrap_or_default(),
+        }
+    }
+}
+
+static STATIC_SYSTEM_INFO: Mutex<Option<StaticSystemInfo>> = Mutex::new(None);
+
+/// The cached static system information, read again after CPU hotplug or
+/// after [`invalidate_static_system_info`]
+fn static_system_info() -> StaticSystemInfo {
+    let online_cpus = read_sysfs_file_trimmed(sysfs::path("/sys/devices/system/cpu/online")).ok();
+    let mut cached = STATIC_SYSTEM_INFO
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    match cached.as_ref() {
+        Some(info) if info.online_cpus == online_cpus => info.clone(),
+        _ => {
+            let info = StaticSystemInfo::read(online_cpus);
+            *cached = Some(info.clone());
+            info
+        }
+    }
+}
+
+/// Read the static system information again on the next poll, e.g. after
+/// resuming from suspend, when firmware may offer other platform profiles
+pub fn invalidate_static_system_info() {
+    *STATIC_SYSTEM_INFO
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = None;
+}
+
 pub fn get_system_info() -> SystemInfo {
-    let cpu_model = get_cpu_model().unwrap_or_else(|_| "Unknown".to_string());
-    let linux_distribution = get_linux_distribution().unwrap_or_else(|_| "Unknown".to_string());
-    let architecture = std::env::consts::ARCH.to_string();
+    let static_info = static_system_info();
 
     SystemInfo {
-        cpu_model,
-        architecture,
-        linux_distribution,
+        cpu_model: static_info.cpu_model,
+        architecture: std::env::consts::ARCH.to_string(),
+        linux_distribution: static_info.linux_distribution,
+        virtualization: virt::detect().cloned(),
     }
 }
 
@@ -78,7 +175,7 @@ impl CpuTimes {
 }
 
 fn read_all_cpu_times() -> Result<HashMap<u32, CpuTimes>> {
-    let content = fs::read_to_string("/proc/stat").map_err(SysMonitorError::Io)?;
+    let content = fs::read_to_string(sysfs::path("/proc/stat")).map_err(SysMonitorError::Io)?;
     let mut cpu_times_map = HashMap::new();
 
     for line in content.lines() {
@@ -158,7 +255,7 @@ pub fn get_cpu_core_info(
     prev_times: &CpuTimes,
     current_times: &CpuTimes,
 ) -> Result<CpuCoreInfo> {
-    let cpufreq_path = PathBuf::from(format!("/sys/devices/system/cpu/cpu{core_id}/cpufreq/"));
+    let cpufreq_path = sysfs::path(format!("/sys/devices/system/cpu/cpu{core_id}/cpufreq/"));
 
     let current_frequency_mhz = read_sysfs_value::<u32>(cpufreq_path.join("scaling_cur_freq"))
         .map(|khz| khz / 1000)
@@ -176,7 +273,7 @@ pub fn get_cpu_core_info(
     let mut temperature_celsius: Option<f32> = None;
 
     // Search for temperature in hwmon devices
-    if let Ok(hwmon_dir) = fs::read_dir("/sys/class/hwmon") {
+    if let Ok(hwmon_dir) = fs::read_dir(sysfs::path("/sys/class/hwmon")) {
         for hw_entry in hwmon_dir.flatten() {
             let hw_path = hw_entry.path();
 
@@ -237,7 +334,7 @@ pub fn get_cpu_core_info(
 
     // Try /sys/devices/platform paths for thermal zones as a last resort
     if temperature_celsius.is_none() {
-        if let Ok(thermal_zones) = fs::read_dir("/sys/devices/virtual/thermal") {
+        if let Ok(thermal_zones) = fs::read_dir(sysfs::path("/sys/devices/virtual/thermal")) {
             for entry in thermal_zones.flatten() {
                 let zone_path = entry.path();
                 let name = entry.file_name().into_string().unwrap_or_default();
@@ -286,6 +383,7 @@ pub fn get_cpu_core_info(
         max_frequency_mhz,
         usage_percent,
         temperature_celsius,
+        core_type: None,
     })
 }
 
@@ -359,40 +457,565 @@ fn get_fallback_temperature(hw_path: &Path) -> Option<f32> {
     None
 }
 
-pub fn get_all_cpu_core_info() -> Result<Vec<CpuCoreInfo>> {
-    let initial_cpu_times = read_all_cpu_times()?;
-    thread::sleep(Duration::from_millis(250)); // interval for CPU usage calculation
-    let final_cpu_times = read_all_cpu_times()?;
+/// Counters that are only meaningful as the difference between two readings
+#[derive(Clone)]
+struct UsageSample {
+    taken: Instant,
+    cpu_times: HashMap<u32, CpuTimes>,
+    rapl: Option<RaplSample>,
+    throttle: ThrottleCounts,
+    interrupts: Option<BTreeMap<String, InterruptCount>>,
+}
+
+impl UsageSample {
+    fn take() -> Result<Self> {
+        Ok(Self {
+            taken: Instant::now(),
+            cpu_times: read_all_cpu_times()?,
+            rapl: sample_rapl(),
+            throttle: read_throttle_counts(),
+            interrupts: read_interrupt_counts(),
+        })
+    }
+}
+
+/// Interrupts handled by one line of `/proc/interrupts`, summed over the CPUs
+#[derive(Debug, Clone)]
+struct InterruptCount {
+    name: String,
+    count: u64,
+}
 
+/// Lines of `/proc/interrupts` that count errors rather than interrupts
+const INTERRUPT_ERROR_COUNTERS: &[&str] = &["ERR", "MIS"];
+
+/// Read the interrupt counters, keyed by IRQ number or name (e.g. `LOC`)
+fn read_interrupt_counts() -> Option<BTreeMap<String, InterruptCount>> {
+    let content = fs::read_to_string(sysfs::path("/proc/interrupts")).ok()?;
+    let mut lines = content.lines();
+    let cpu_count = lines.next()?.split_whitespace().count();
+
+    let counts = lines
+        .filter_map(|line| {
+            let (irq, rest) = line.split_once(':')?;
+            let irq = irq.trim();
+            if INTERRUPT_ERROR_COUNTERS.contains(&irq) {
+                return None;
+            }
+
+            let mut fields = rest.split_whitespace().peekable();
+            let mut count = 0u64;
+            for _ in 0..cpu_count {
+                match fields.peek().and_then(|field| field.parse::<u64>().ok()) {
+                    Some(value) => {
+                        count += value;
+                        fields.next();
+                    }
+                    None => break,
+                }
+            }
+            let description: Vec<&str> = fields.collect();
+
+            // Numbered IRQs end with the device name after the chip, hardware
+            // IRQ and trigger, named ones with a description
+            let name = if irq.chars().all(|c| c.is_ascii_digit()) {
+                description.last().map_or_else(
+                    || format!("IRQ {irq}"),
+                    |device| format!("{device} (IRQ {irq})"),
+                )
+            } else if description.is_empty() {
+                irq.to_string()
+            } else {
+                description.join(" ")
+            };
+            Some((irq.to_string(), InterruptCount { name, count }))
+        })
+        .collect();
+    Some(counts)
+}
+
+/// Number of sources listed in a report
+const TOP_WAKEUP_SOURCES: usize = 5;
+
+/// Interrupts per second between two samples
+fn get_wakeup_info(previous: &UsageSample, current: &UsageSample) -> Option<WakeupInfo> {
+    let (before, after) = (previous.interrupts.as_ref()?, current.interrupts.as_ref()?);
+    let elapsed = current.taken.duration_since(previous.taken).as_secs_f32();
+    if elapsed <= 0.0 {
+        return None;
+    }
+
+    // Lines of hot-plugged devices are only in one of the samples
+    let mut sources: Vec<WakeupSource> = after
+        .iter()
+        .filter_map(|(irq, interrupts)| {
+            let delta = interrupts.count.saturating_sub(before.get(irq)?.count);
+            Some(WakeupSource {
+                name: interrupts.name.clone(),
+                per_second: delta as f32 / elapsed,
+            })
+        })
+        .collect();
+    let per_second = sources.iter().map(|source| source.per_second).sum();
+
+    sources.retain(|source| source.per_second > 0.0);
+    sources.sort_by(|a, b| b.per_second.total_cmp(&a.per_second));
+    sources.truncate(TOP_WAKEUP_SOURCES);
+
+    Some(WakeupInfo {
+        per_second,
+        top_sources: sources,
+    })
+}
+
+/// Sum the `thermal_throttle` counters of the CPUs. Core counters are shared
+/// by the threads of a core and package counters by all CPUs of a package,
+/// so each is counted once.
+fn read_throttle_counts() -> ThrottleCounts {
+    let Ok(entries) = fs::read_dir(sysfs::path("/sys/devices/system/cpu")) else {
+        return ThrottleCounts::default();
+    };
+
+    // Thermal and power limit counts, keyed by (package, core) and by package
+    type Counts = (Option<u64>, Option<u64>);
+    let mut cores: BTreeMap<(Option<u32>, Option<u32>), Counts> = BTreeMap::new();
+    let mut packages: BTreeMap<Option<u32>, Counts> = BTreeMap::new();
+
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(cpu) = name.to_str().and_then(|name| name.strip_prefix("cpu")) else {
+            continue;
+        };
+        if cpu.parse::<u32>().is_err() {
+            continue;
+        }
+
+        let path = entry.path();
+        let throttle = path.join("thermal_throttle");
+        if !throttle.exists() {
+            continue;
+        }
+        let package_id = read_sysfs_value(path.join("topology/physical_package_id")).ok();
+        let core_id = read_sysfs_value(path.join("topology/core_id")).ok();
+
+        cores.entry((package_id, core_id)).or_insert_with(|| {
+            (
+                read_sysfs_value(throttle.join("core_throttle_count")).ok(),
+                read_sysfs_value(throttle.join("core_power_limit_count")).ok(),
+            )
+        });
+        packages.entry(package_id).or_insert_with(|| {
+            (
+                read_sysfs_value(throttle.join("package_throttle_count")).ok(),
+                read_sysfs_value(throttle.join("package_power_limit_count")).ok(),
+            )
+        });
+    }
+
+    fn sum(counts: impl Iterator<Item = Option<u64>>) -> Option<u64> {
+        counts.flatten().reduce(|a, b| a + b)
+    }
+    ThrottleCounts {
+        core_thermal: sum(cores.values().map(|counts| counts.0)),
+        package_thermal: sum(packages.values().map(|counts| counts.0)),
+        core_power_limit: sum(cores.values().map(|counts| counts.1)),
+        package_power_limit: sum(packages.values().map(|counts| counts.1)),
+    }
+}
+
+#[cfg(feature = "msr")]
+fn read_prochot() -> Option<bool> {
+    crate::msr::read_prochot(0)
+        .inspect_err(|e| debug!("Cannot read the PROCHOT status: {e}"))
+        .ok()
+}
+
+#[cfg(not(feature = "msr"))]
+fn read_prochot() -> Option<bool> {
+    None
+}
+
+#[cfg(feature = "nvml")]
+fn read_nvidia_gpus() -> Vec<NvidiaGpuInfo> {
+    crate::nvml::get_gpus()
+}
+
+#[cfg(not(feature = "nvml"))]
+fn read_nvidia_gpus() -> Vec<NvidiaGpuInfo> {
+    Vec::new()
+}
+
+/// The sample taken by the previous collection. Usage is computed across the
+/// interval between two collections, so polling never has to wait for it.
+static PREVIOUS_SAMPLE: Mutex<Option<UsageSample>> = Mutex::new(None);
+
+/// Take a new usage sample and return it with the previous one. The first
+/// collection has no previous sample, so it waits a short interval instead.
+fn take_usage_samples() -> Result<(UsageSample, UsageSample)> {
+    let previous = PREVIOUS_SAMPLE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take();
+    let previous = match previous {
+        Some(previous) => previous,
+        None => {
+            let sample = UsageSample::take()?;
+            thread::sleep(Duration::from_millis(250)); // interval for CPU usage calculation
+            sample
+        }
+    };
+
+    let current = UsageSample::take()?;
+    *PREVIOUS_SAMPLE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = Some(current.clone());
+    Ok((previous, current))
+}
+
+/// Collect per-core information for all cores, split across one thread per
+/// available CPU on machines with many cores.
+///
+/// Cores whose data could not be collected are skipped and recorded in the
+/// returned list of collection errors instead.
+pub fn get_all_cpu_core_info(
+    initial_cpu_times: &HashMap<u32, CpuTimes>,
+    final_cpu_times: &HashMap<u32, CpuTimes>,
+) -> Result<(Vec<CpuCoreInfo>, Vec<CollectionError>)> {
     let num_cores = get_logical_core_count()
         .map_err(|_| SysMonitorError::ReadError("Could not get the number of cores".to_string()))?;
 
-    let mut core_infos = Vec::with_capacity(num_cores as usize);
-
-    for core_id in 0..num_cores {
-        if let (Some(prev), Some(curr)) = (
+    let core_types = cpu::get_core_types();
+    let read_core = |core_id: u32| {
+        let (Some(prev), Some(curr)) = (
             initial_cpu_times.get(&core_id),
             final_cpu_times.get(&core_id),
-        ) {
-            match get_cpu_core_info(core_id, prev, curr) {
-                Ok(info) => core_infos.push(info),
-                Err(e) => {
-                    // Log or handle error for a single core, maybe push a partial info or skip
-                    eprintln!("Error getting info for core {core_id}: {e}");
-                }
+        ) else {
+            return Err(CollectionErrorKind::MissingCpuTimes);
+        };
+
+        get_cpu_core_info(core_id, prev, curr)
+            .map(|mut info| {
+                info.core_type = core_types.get(&core_id).copied();
+                info
+            })
+            .map_err(|e| CollectionErrorKind::ReadFailed(e.to_string()))
+    };
+
+    let core_ids: Vec<u32> = (0..num_cores).collect();
+    let workers = thread::available_parallelism().map_or(1, usize::from);
+    let results = read_cores(&core_ids, workers, read_core);
+
+    let mut core_infos = Vec::with_capacity(core_ids.len());
+    let mut collection_errors = Vec::new();
+    for (core_id, result) in core_ids.into_iter().zip(results) {
+        match result {
+            Ok(info) => core_infos.push(info),
+            Err(kind) => {
+                debug!("Error getting info for core {core_id}: {kind:?}");
+                collection_errors.push(CollectionError { core_id, kind });
             }
-        } else {
-            // Log or handle missing times for a core
-            eprintln!("Missing CPU time data for core {core_id}");
         }
     }
-    Ok(core_infos)
+    Ok((core_infos, collection_errors))
+}
+
+/// Read `core_ids` with `read_core`, split across up to `workers` threads
+/// once there are enough cores for the threads to pay off
+fn read_cores<T: Send>(
+    core_ids: &[u32],
+    workers: usize,
+    read_core: impl Fn(u32) -> T + Sync,
+) -> Vec<T> {
+    if workers == 1 || core_ids.len() < PARALLEL_COLLECTION_MIN_CORES {
+        core_ids.iter().map(|&core_id| read_core(core_id)).collect()
+    } else {
+        read_cores_parallel(core_ids, workers, read_core)
+    }
+}
+
+/// Read `core_ids` with `read_core` on `workers` threads, keeping their order
+fn read_cores_parallel<T: Send>(
+    core_ids: &[u32],
+    workers: usize,
+    read_core: impl Fn(u32) -> T + Sync,
+) -> Vec<T> {
+    // Each core takes a dozen sysfs reads, which adds up on large machines
+    let chunk_size = core_ids.len().div_ceil(workers);
+    thread::scope(|scope| {
+        let handles: Vec<_> = core_ids
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let read_core = &read_core;
+                scope.spawn(move || chunk.iter().map(|&core_id| read_core(core_id)).collect())
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| -> Vec<_> {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    })
+}
+
+/// Read a CPU list such as `0-3,8` from sysfs
+fn read_cpu_list(path: impl AsRef<Path>) -> Vec<u32> {
+    read_sysfs_file_trimmed(path)
+        .map(|list| cpu::parse_cpu_list(&list))
+        .unwrap_or_default()
+}
+
+/// Logical CPUs sharing the highest cache level of `cpu_path`
+fn last_level_cache_cpus(cpu_path: &Path) -> Vec<u32> {
+    let Ok(entries) = fs::read_dir(cpu_path.join("cache")) else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("index"))
+        .filter_map(|entry| {
+            let level = read_sysfs_value::<u32>(entry.path().join("level")).ok()?;
+            Some((level, entry.path()))
+        })
+        .max_by_key(|(level, _)| *level)
+        .map(|(_, path)| read_cpu_list(path.join("shared_cpu_list")))
+        .unwrap_or_default()
+}
+
+/// The cpufreq policies and the CPUs each one covers, in order of policy ID
+pub fn get_cpufreq_policies() -> Vec<CpufreqPolicy> {
+    let cpu_root = sysfs::path("/sys/devices/system/cpu");
+
+    let mut policies: Vec<CpufreqPolicy> = fs::read_dir(cpu_root.join("cpufreq"))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let id = entry
+                        .file_name()
+                        .to_str()?
+                        .strip_prefix("policy")?
+                        .parse()
+                        .ok()?;
+                    let cpus = read_cpu_list(entry.path().join("related_cpus"));
+                    (!cpus.is_empty()).then_some(CpufreqPolicy { id, cpus })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    policies.sort_unstable_by_key(|policy| policy.id);
+    policies
+}
+
+/// Collect the package, core, thread, cpufreq policy and cache layout of the CPUs
+pub fn get_cpu_topology() -> CpuTopology {
+    let cpu_root = sysfs::path("/sys/devices/system/cpu");
+    let policies = get_cpufreq_policies();
+
+    let mut threads: Vec<CpuThread> = fs::read_dir(&cpu_root)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let name = entry.file_name();
+                    let cpu: u32 = name.to_str()?.strip_prefix("cpu")?.parse().ok()?;
+                    let path = entry.path();
+                    let topology = path.join("topology");
+
+                    Some(CpuThread {
+                        cpu,
+                        package_id: read_sysfs_value(topology.join("physical_package_id")).ok(),
+                        core_id: read_sysfs_value(topology.join("core_id")).ok(),
+                        thread_siblings: read_cpu_list(topology.join("thread_siblings_list")),
+                        cpufreq_policy: policies
+                            .iter()
+                            .find(|policy| policy.cpus.contains(&cpu))
+                            .map(|policy| policy.id),
+                        last_level_cache: last_level_cache_cpus(&path),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    threads.sort_unstable_by_key(|thread| thread.cpu);
+
+    CpuTopology { threads, policies }
+}
+
+/// Read a millidegree Celsius value. Unused trip points read as large
+/// negative values or fail to read, and are reported as unknown.
+fn read_millicelsius(path: impl AsRef<Path>) -> Option<f32> {
+    let millicelsius: i64 = read_sysfs_value(path).ok()?;
+    (millicelsius > -273_000).then(|| millicelsius as f32 / 1000.0)
+}
+
+/// The cooling devices bound to each trip point of a zone, keyed by trip ID.
+/// A binding shows up as a `cdev<N>` link to the cooling device next to a
+/// `cdev<N>_trip_point` file naming the trip.
+fn get_zone_cooling_devices(zone_path: &Path) -> BTreeMap<u32, Vec<CoolingDevice>> {
+    let mut bound: BTreeMap<u32, Vec<CoolingDevice>> = BTreeMap::new();
+    let Ok(entries) = fs::read_dir(zone_path) else {
+        return bound;
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(cdev) = name.to_str().and_then(|name| name.strip_prefix("cdev")) else {
+            continue;
+        };
+        if cdev.parse::<u32>().is_err() {
+            continue;
+        }
+        let Ok(trip) = read_sysfs_value::<u32>(zone_path.join(format!("cdev{cdev}_trip_point")))
+        else {
+            continue;
+        };
+
+        // The link points to e.g. ../cooling_device3
+        let device_path = entry.path();
+        let Some(id) = fs::read_link(&device_path).ok().and_then(|target| {
+            target
+                .file_name()?
+                .to_str()?
+                .strip_prefix("cooling_device")?
+                .parse()
+                .ok()
+        }) else {
+            continue;
+        };
+
+        bound.entry(trip).or_default().push(CoolingDevice {
+            id,
+            device_type: read_sysfs_file_trimmed(device_path.join("type"))
+                .unwrap_or_else(|_| "unknown".to_string()),
+            cur_state: read_sysfs_value(device_path.join("cur_state")).ok(),
+            max_state: read_sysfs_value(device_path.join("max_state")).ok(),
+        });
+    }
+
+    for devices in bound.values_mut() {
+        devices.sort_unstable_by_key(|device| device.id);
+        devices.dedup_by_key(|device| device.id);
+    }
+    bound
+}
+
+/// Enumerate the thermal zones with their trip points and the cooling devices
+/// bound to them
+pub fn get_thermal_zones() -> Vec<ThermalZone> {
+    let Ok(entries) = fs::read_dir(sysfs::path("/sys/class/thermal")) else {
+        return Vec::new();
+    };
+
+    let mut zones: Vec<ThermalZone> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let id: u32 = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("thermal_zone")?
+                .parse()
+                .ok()?;
+            let path = entry.path();
+            let mut cooling_devices = get_zone_cooling_devices(&path);
+
+            let mut trip_points = Vec::new();
+            for trip in 0.. {
+                let Ok(trip_type) =
+                    read_sysfs_file_trimmed(path.join(format!("trip_point_{trip}_type")))
+                else {
+                    break;
+                };
+                trip_points.push(TripPoint {
+                    id: trip,
+                    trip_type,
+                    temperature_celsius: read_millicelsius(
+                        path.join(format!("trip_point_{trip}_temp")),
+                    ),
+                    hysteresis_celsius: read_millicelsius(
+                        path.join(format!("trip_point_{trip}_hyst")),
+                    ),
+                    cooling_devices: cooling_devices.remove(&trip).unwrap_or_default(),
+                });
+            }
+
+            Some(ThermalZone {
+                id,
+                zone_type: read_sysfs_file_trimmed(path.join("type"))
+                    .unwrap_or_else(|_| "unknown".to_string()),
+                temperature_celsius: read_millicelsius(path.join("temp")),
+                mode: read_sysfs_file_trimmed(path.join("mode")).ok(),
+                policy: read_sysfs_file_trimmed(path.join("policy")).ok(),
+                trip_points,
+            })
+        })
+        .collect();
+    zones.sort_unstable_by_key(|zone| zone.id);
+    zones
+}
+
+/// The current frequency of a CPU in MHz
+pub fn get_current_frequency_mhz(cpu: u32) -> Option<u32> {
+    read_sysfs_value::<u32>(sysfs::path(format!(
+        "/sys/devices/system/cpu/cpu{cpu}/cpufreq/scaling_cur_freq"
+    )))
+    .map(|khz| khz / 1000)
+    .ok()
+}
+
+/// Time a CPU has spent at each frequency (in MHz) since boot, from the cpufreq
+/// statistics. `None` where the driver keeps none, e.g. intel_pstate in
+/// active mode. Each line of `time_in_state` is a frequency in kHz and a time
+/// in units of 10 ms.
+pub fn get_time_in_state(cpu: u32) -> Option<BTreeMap<u32, Duration>> {
+    let content = read_sysfs_file_trimmed(sysfs::path(format!(
+        "/sys/devices/system/cpu/cpu{cpu}/cpufreq/stats/time_in_state"
+    )))
+    .ok()?;
+
+    let mut time_in_state = BTreeMap::new();
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(khz), Some(time)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let (Ok(khz), Ok(time)) = (khz.parse::<u32>(), time.parse::<u64>()) else {
+            continue;
+        };
+        *time_in_state.entry(khz / 1000).or_default() += Duration::from_millis(time * 10);
+    }
+    (!time_in_state.is_empty()).then_some(time_in_state)
+}
+
+/// The scaling driver of each cpufreq policy, keyed by policy ID
+fn get_scaling_drivers() -> BTreeMap<u32, String> {
+    let Ok(entries) = fs::read_dir(sysfs::path("/sys/devices/system/cpu/cpufreq")) else {
+        return BTreeMap::new();
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let id = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("policy")?
+                .parse()
+                .ok()?;
+            let driver = read_sysfs_file_trimmed(entry.path().join("scaling_driver")).ok()?;
+            Some((id, driver))
+        })
+        .collect()
 }
 
 pub fn get_cpu_global_info(cpu_cores: &[CpuCoreInfo]) -> CpuGlobalInfo {
     // Find a valid CPU to read global settings from
     // Try cpu0 first, then fall back to any available CPU with cpufreq
-    let mut cpufreq_base_path_buf = PathBuf::from("/sys/devices/system/cpu/cpu0/cpufreq/");
+    let mut cpufreq_base_path_buf = sysfs::path("/sys/devices/system/cpu/cpu0/cpufreq/");
 
     if !cpufreq_base_path_buf.exists() {
         let core_count = get_logical_core_count().unwrap_or_else(|e| {
@@ -401,7 +1024,7 @@ pub fn get_cpu_global_info(cpu_cores: &[CpuCoreInfo]) -> CpuGlobalInfo {
         });
 
         for i in 0..core_count {
-            let test_path = PathBuf::from(format!("/sys/devices/system/cpu/cpu{i}/cpufreq/"));
+            let test_path = sysfs::path(format!("/sys/devices/system/cpu/cpu{i}/cpufreq/"));
             if test_path.exists() {
                 cpufreq_base_path_buf = test_path;
                 break; // Exit the loop as soon as we find a valid path
@@ -409,8 +1032,8 @@ pub fn get_cpu_global_info(cpu_cores: &[CpuCoreInfo]) -> CpuGlobalInfo {
         }
     }
 
-    let turbo_status_path = Path::new("/sys/devices/system/cpu/intel_pstate/no_turbo");
-    let boost_path = Path::new("/sys/devices/system/cpu/cpufreq/boost");
+    let turbo_status_path = sysfs::path("/sys/devices/system/cpu/intel_pstate/no_turbo");
+    let boost_path = sysfs::path("/sys/devices/system/cpu/cpufreq/boost");
 
     let current_governor = if cpufreq_base_path_buf.join("scaling_governor").exists() {
         read_sysfs_file_trimmed(cpufreq_base_path_buf.join("scaling_governor")).ok()
@@ -418,18 +1041,14 @@ pub fn get_cpu_global_info(cpu_cores: &[CpuCoreInfo]) -> CpuGlobalInfo {
         None
     };
 
-    let available_governors = if cpufreq_base_path_buf
-        .join("scaling_available_governors")
-        .exists()
-    {
-        read_sysfs_file_trimmed(cpufreq_base_path_buf.join("scaling_available_governors"))
-            .map_or_else(
-                |_| vec![],
-                |s| s.split_whitespace().map(String::from).collect(),
-            )
-    } else {
-        vec![]
-    };
+    let static_info = static_system_info();
+    let scaling_drivers = get_scaling_drivers();
+    let pstate_mode = ["intel_pstate", "amd_pstate"].iter().find_map(|driver| {
+        read_sysfs_file_trimmed(sysfs::path(format!(
+            "/sys/devices/system/cpu/{driver}/status"
+        )))
+        .ok()
+    });
 
     let turbo_status = if turbo_status_path.exists() {
         // 0 means turbo enabled, 1 means disabled for intel_pstate
@@ -451,7 +1070,7 @@ pub fn get_cpu_global_info(cpu_cores: &[CpuCoreInfo]) -> CpuGlobalInfo {
     let energy_perf_bias =
         read_sysfs_file_trimmed(cpufreq_base_path_buf.join("energy_performance_bias")).ok();
 
-    let platform_profile = read_sysfs_file_trimmed("/sys/firmware/acpi/platform_profile").ok();
+    let platform_profile = cpu::get_platform_profile();
 
     // Calculate average CPU temperature from the core temperatures
     let average_temperature_celsius = if cpu_cores.is_empty() {
@@ -478,7 +1097,10 @@ pub fn get_cpu_global_info(cpu_cores: &[CpuCoreInfo]) -> CpuGlobalInfo {
     // Return the constructed CpuGlobalInfo
     CpuGlobalInfo {
         current_governor,
-        available_governors,
+        available_governors: static_info.available_governors,
+        available_platform_profiles: static_info.available_platform_profiles,
+        scaling_drivers,
+        pstate_mode,
         turbo_status,
         epp: energy_perf_pref,
         epb: energy_perf_bias,
@@ -487,9 +1109,25 @@ pub fn get_cpu_global_info(cpu_cores: &[CpuCoreInfo]) -> CpuGlobalInfo {
     }
 }
 
+/// Warn only once when UPower is unavailable, as batteries are read on every poll
+#[cfg(feature = "dbus")]
+static UPOWER_FALLBACK_WARNING: std::sync::Once = std::sync::Once::new();
+
 pub fn get_battery_info(config: &AppConfig) -> Result<Vec<BatteryInfo>> {
+    #[cfg(feature = "dbus")]
+    if config.battery_backend == BatteryBackend::Upower {
+        match upower::get_battery_info(config) {
+            Ok(batteries) => return Ok(batteries),
+            Err(e) => {
+                UPOWER_FALLBACK_WARNING.call_once(|| {
+                    log::warn!("Cannot read batteries from UPower, falling back to sysfs: {e}");
+                });
+            }
+        }
+    }
+
     let mut batteries = Vec::new();
-    let power_supply_path = Path::new("/sys/class/power_supply");
+    let power_supply_path = sysfs::path("/sys/class/power_supply");
 
     if !power_supply_path.exists() {
         return Ok(batteries); // no power supply directory
@@ -499,7 +1137,7 @@ pub fn get_battery_info(config: &AppConfig) -> Result<Vec<BatteryInfo>> {
 
     // Determine overall AC connection status
     let mut overall_ac_connected = false;
-    for entry in fs::read_dir(power_supply_path)? {
+    for entry in fs::read_dir(&power_supply_path)? {
         let entry = entry?;
         let ps_path = entry.path();
         let name = entry.file_name().into_string().unwrap_or_default();
@@ -538,7 +1176,7 @@ pub fn get_battery_info(config: &AppConfig) -> Result<Vec<BatteryInfo>> {
         overall_ac_connected = is_likely_desktop_system();
     }
 
-    for entry in fs::read_dir(power_supply_path)? {
+    for entry in fs::read_dir(&power_supply_path)? {
         let entry = entry?;
         let ps_path = entry.path();
         let name = entry.file_name().into_string().unwrap_or_default();
@@ -583,6 +1221,39 @@ pub fn get_battery_info(config: &AppConfig) -> Result<Vec<BatteryInfo>> {
                 let charge_stop_threshold =
                     read_sysfs_value::<u8>(ps_path.join("charge_control_end_threshold")).ok();
 
+                let charge_behaviour = read_sysfs_file_trimmed(ps_path.join("charge_behaviour"))
+                    .ok()
+                    .and_then(|content| battery::parse_charge_behaviour(&content).0);
+
+                // Some batteries only report charge (uAh) instead of energy (uWh)
+                let read_uwh = |file: &str| {
+                    read_sysfs_value::<u64>(ps_path.join(file))
+                        .ok()
+                        .map(|uwh| uwh as f32 / 1_000_000.0)
+                };
+                let energy_full_wh = read_uwh("energy_full");
+                let energy_full_design_wh = read_uwh("energy_full_design");
+                let health_percent = match (energy_full_wh, energy_full_design_wh) {
+                    (Some(full), Some(design)) => Some((full, design)),
+                    _ => read_uwh("charge_full").zip(read_uwh("charge_full_design")),
+                }
+                .filter(|(_, design)| *design > 0.0)
+                .map(|(full, design)| full / design * 100.0);
+
+                // A cycle count of 0 usually means the battery does not report it
+                let cycle_count = read_sysfs_value::<u32>(ps_path.join("cycle_count"))
+                    .ok()
+                    .filter(|&cycles| cycles > 0);
+                let voltage_volts = read_sysfs_value::<i64>(ps_path.join("voltage_now"))
+                    .ok()
+                    .map(|uv| uv as f32 / 1_000_000.0);
+                let manufacturer = read_sysfs_file_trimmed(ps_path.join("manufacturer"))
+                    .ok()
+                    .filter(|s| !s.is_empty());
+                let model_name = read_sysfs_file_trimmed(ps_path.join("model_name"))
+                    .ok()
+                    .filter(|s| !s.is_empty());
+
                 batteries.push(BatteryInfo {
                     name: name.clone(),
                     ac_connected: overall_ac_connected,
@@ -591,6 +1262,14 @@ pub fn get_battery_info(config: &AppConfig) -> Result<Vec<BatteryInfo>> {
                     power_rate_watts,
                     charge_start_threshold,
                     charge_stop_threshold,
+                    charge_behaviour,
+                    cycle_count,
+                    energy_full_wh,
+                    energy_full_design_wh,
+                    health_percent,
+                    voltage_volts,
+                    manufacturer,
+                    model_name,
                 });
             }
         }
@@ -644,7 +1323,7 @@ fn is_peripheral_battery(ps_path: &Path, name: &str) -> bool {
 /// Determine if this is likely a desktop system rather than a laptop
 fn is_likely_desktop_system() -> bool {
     // Check for DMI system type information
-    if let Ok(chassis_type) = fs::read_to_string("/sys/class/dmi/id/chassis_type") {
+    if let Ok(chassis_type) = fs::read_to_string(sysfs::path("/sys/class/dmi/id/chassis_type")) {
         let chassis_type = chassis_type.trim();
 
         // Chassis types:
@@ -659,8 +1338,8 @@ fn is_likely_desktop_system() -> bool {
     }
 
     // Check CPU power policies, desktops often don't have these
-    let power_saving_exists = Path::new("/sys/module/intel_pstate/parameters/no_hwp").exists()
-        || Path::new("/sys/devices/system/cpu/cpufreq/conservative").exists();
+    let power_saving_exists = sysfs::path("/sys/module/intel_pstate/parameters/no_hwp").exists()
+        || sysfs::path("/sys/devices/system/cpu/cpufreq/conservative").exists();
 
     if !power_saving_exists {
         return true; // likely a desktop
@@ -674,7 +1353,7 @@ fn is_likely_desktop_system() -> bool {
     ];
 
     for path in &laptop_acpi_paths {
-        if Path::new(path).exists() {
+        if sysfs::path(path).exists() {
             return false; // Likely a laptop
         }
     }
@@ -684,7 +1363,7 @@ fn is_likely_desktop_system() -> bool {
 }
 
 pub fn get_system_load() -> Result<SystemLoad> {
-    let loadavg_str = read_sysfs_file_trimmed("/proc/loadavg")?;
+    let loadavg_str = read_sysfs_file_trimmed(sysfs::path("/proc/loadavg"))?;
     let parts: Vec<&str> = loadavg_str.split_whitespace().collect();
     if parts.len() < 3 {
         return Err(SysMonitorError::ParseError(
@@ -708,12 +1387,177 @@ pub fn get_system_load() -> Result<SystemLoad> {
     })
 }
 
+/// RAPL energy counters of the package domains at one point in time
+#[derive(Clone)]
+struct RaplSample {
+    taken: Instant,
+    /// Energy counter in microjoules, keyed by powercap domain path
+    energy_uj: BTreeMap<PathBuf, u64>,
+}
+
+/// Read the energy counters of all top-level RAPL (package) domains
+fn sample_rapl() -> Option<RaplSample> {
+    let entries = fs::read_dir(sysfs::path("/sys/class/powercap")).ok()?;
+
+    let energy_uj: BTreeMap<PathBuf, u64> = entries
+        .flatten()
+        .filter(|entry| {
+            // Package domains are named `intel-rapl:N`, subdomains `intel-rapl:N:M`.
+            // AMD CPUs expose their counters under the same name.
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("intel-rapl:") && name.matches(':').count() == 1
+        })
+        .filter_map(|entry| {
+            let path = entry.path();
+            let energy = read_sysfs_value::<u64>(path.join("energy_uj")).ok()?;
+            Some((path, energy))
+        })
+        .collect();
+
+    (!energy_uj.is_empty()).then(|| RaplSample {
+        taken: Instant::now(),
+        energy_uj,
+    })
+}
+
+/// Average package power between two RAPL samples
+fn rapl_package_watts(start: &RaplSample, end: &RaplSample) -> Option<f32> {
+    let elapsed = end.taken.duration_since(start.taken).as_secs_f64();
+    if elapsed <= 0.0 {
+        return None;
+    }
+
+    let mut total_uj = 0u64;
+    for (path, &end_uj) in &end.energy_uj {
+        let &start_uj = start.energy_uj.get(path)?;
+        let delta = if end_uj >= start_uj {
+            end_uj - start_uj
+        } else {
+            // The counter wrapped around
+            let range = read_sysfs_value::<u64>(path.join("max_energy_range_uj")).ok()?;
+            range.saturating_sub(start_uj) + end_uj
+        };
+        total_uj += delta;
+    }
+
+    Some((total_uj as f64 / 1_000_000.0 / elapsed) as f32)
+}
+
+/// Power a connected charger can deliver. USB-C chargers report the
+/// negotiated voltage and current limit, other adapters their power, if at all.
+fn get_charger_watts(config: &AppConfig) -> Option<f32> {
+    let ignored_supplies = config.ignored_power_supplies.as_deref().unwrap_or_default();
+
+    fs::read_dir(sysfs::path("/sys/class/power_supply"))
+        .ok()?
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            !ignored_supplies
+                .iter()
+                .any(|ignored| name == ignored.as_str())
+        })
+        .map(|entry| entry.path())
+        .filter(|path| {
+            read_sysfs_file_trimmed(path.join("type"))
+                .is_ok_and(|ps_type| ps_type == "Mains" || ps_type.starts_with("USB"))
+                && read_sysfs_value::<u8>(path.join("online")).is_ok_and(|online| online == 1)
+        })
+        .filter_map(|path| {
+            if let Ok(microwatts) = read_sysfs_value::<u64>(path.join("power_now")) {
+                return Some(microwatts as f32 / 1_000_000.0);
+            }
+
+            let microvolts = read_sysfs_value::<u64>(path.join("voltage_max"))
+                .or_else(|_| read_sysfs_value::<u64>(path.join("voltage_now")))
+                .ok()?;
+            let microamps = read_sysfs_value::<u64>(path.join("current_max")).ok()?;
+            Some((microvolts as f64 * microamps as f64 / 1e12) as f32)
+        })
+        .filter(|&watts| watts > 0.0)
+        .reduce(f32::max)
+}
+
+/// Combine the available power readings into an estimate of the system's power draw
+fn get_power_info(
+    rapl_start: Option<&RaplSample>,
+    rapl_end: Option<&RaplSample>,
+    batteries: &[BatteryInfo],
+    nvidia_gpus: &[NvidiaGpuInfo],
+    charger_watts: Option<f32>,
+) -> PowerInfo {
+    let cpu_package_watts = match (rapl_start, rapl_end) {
+        (Some(start), Some(end)) => rapl_package_watts(start, end),
+        _ => None,
+    };
+    // NVIDIA GPUs on the proprietary driver have no hwmon sensors
+    let gpu_watts = gpu::read_power_watts()
+        .into_iter()
+        .chain(nvidia_gpus.iter().filter_map(|gpu| gpu.power_watts))
+        .reduce(|a, b| a + b);
+
+    let discharging: Vec<f32> = batteries
+        .iter()
+        .filter(|b| !b.ac_connected && b.charging_state.as_deref() == Some("Discharging"))
+        .filter_map(|b| b.power_rate_watts)
+        .map(f32::abs)
+        .collect();
+    let battery_discharge_watts = (!discharging.is_empty()).then(|| discharging.iter().sum());
+
+    let estimated_system_watts =
+        battery_discharge_watts.or_else(|| match (cpu_package_watts, gpu_watts) {
+            (None, None) => None,
+            (cpu, gpu) => Some(cpu.unwrap_or(0.0) + gpu.unwrap_or(0.0)),
+        });
+
+    PowerInfo {
+        cpu_package_watts,
+        gpu_watts,
+        battery_discharge_watts,
+        estimated_system_watts,
+        charger_watts,
+    }
+}
+
 pub fn collect_system_report(config: &AppConfig) -> Result<SystemReport> {
+    // Only report permission problems from this collection
+    take_permission_denied();
+
     let system_info = get_system_info();
-    let cpu_cores = get_all_cpu_core_info()?;
-    let cpu_global = get_cpu_global_info(&cpu_cores);
+    let (previous, current) = take_usage_samples()?;
+    let (mut cpu_cores, mut collection_errors) =
+        get_all_cpu_core_info(&previous.cpu_times, &current.cpu_times)?;
+    // Cores managed elsewhere would skew the usage and temperature averages
+    if let Some(ignored_cores) = &config.ignored_cores {
+        cpu_cores.retain(|core| !ignored_cores.contains(&core.core_id));
+        collection_errors.retain(|error| !ignored_cores.contains(&error.core_id));
+    }
+    let mut cpu_global = get_cpu_global_info(&cpu_cores);
+    // The Raspberry Pi firmware reports the temperature of the whole SoC,
+    // which thermal caps fall back to where no core sensor was found
+    let raspberry_pi = vendor::raspberry_pi::get_info();
+    if cpu_global.average_temperature_celsius.is_none() {
+        cpu_global.average_temperature_celsius = raspberry_pi
+            .as_ref()
+            .and_then(|pi| pi.soc_temperature_celsius);
+    }
     let batteries = get_battery_info(config)?;
     let system_load = get_system_load()?;
+    let nvidia_gpus = read_nvidia_gpus();
+    let power = get_power_info(
+        previous.rapl.as_ref(),
+        current.rapl.as_ref(),
+        &batteries,
+        &nvidia_gpus,
+        get_charger_watts(config),
+    );
+    let throttle = ThrottleInfo {
+        counts: current.throttle,
+        deltas: current.throttle.since(&previous.throttle),
+        prochot_active: read_prochot(),
+    };
+    let wakeups = get_wakeup_info(&previous, &current);
 
     Ok(SystemReport {
         system_info,
@@ -721,13 +1565,22 @@ pub fn collect_system_report(config: &AppConfig) -> Result<SystemReport> {
         cpu_global,
         batteries,
         system_load,
+        power,
+        throttle,
+        wakeups,
+        raspberry_pi,
+        nvidia_gpus,
+        lid_closed: lid::is_lid_closed(),
+        docked: dock::is_docked(&config.dock),
+        collection_errors,
+        permission_denied: take_permission_denied(),
         timestamp: SystemTime::now(),
     })
 }
 
 pub fn get_cpu_model() -> Result<String> {
-    let path = Path::new("/proc/cpuinfo");
-    let content = fs::read_to_string(path).map_err(|_| {
+    let path = sysfs::path("/proc/cpuinfo");
+    let content = fs::read_to_string(&path).map_err(|_| {
         SysMonitorError::ReadError(format!("Cannot read contents of {}.", path.display()))
     })?;
 
@@ -785,3 +1638,90 @@ pub fn get_linux_distribution() -> Result<String> {
         lsb_release_path.display()
     )))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::fixture::Fixture;
+
+    /// Add a coretemp sensor with a labelled input per core, which is what
+    /// most of the time reading a core goes to
+    fn add_coretemp(fixture: &Fixture, cores: u32) {
+        let hwmon = "/sys/class/hwmon/hwmon0";
+        fixture.write(&format!("{hwmon}/name"), "coretemp\n");
+        for core in 0..cores.min(31) {
+            let index = core + 2;
+            fixture.write(
+                &format!("{hwmon}/temp{index}_label"),
+                &format!("Core {core}\n"),
+            );
+            fixture.write(&format!("{hwmon}/temp{index}_input"), "45000\n");
+        }
+    }
+
+    #[test]
+    fn parallel_collection_keeps_core_order() {
+        let core_ids: Vec<u32> = (0..37).collect();
+        assert_eq!(
+            read_cores_parallel(&core_ids, 4, |core_id| core_id),
+            core_ids
+        );
+        assert_eq!(read_cores(&core_ids, 1, |core_id| core_id * 2)[36], 72);
+    }
+
+    /// Compare reading cores one after another with reading them on threads.
+    /// Run with `cargo test --release -- --ignored --nocapture bench_core_collection`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_core_collection() {
+        const ROUNDS: u32 = 200;
+
+        let workers = thread::available_parallelism()
+            .map_or(1, usize::from)
+            .max(2);
+        let times = CpuTimes {
+            user: 100,
+            nice: 0,
+            system: 50,
+            idle: 1000,
+            iowait: 0,
+            irq: 0,
+            softirq: 0,
+            steal: 0,
+        };
+        println!("{workers} workers, mean of {ROUNDS} rounds");
+        println!("cores   serial µs   parallel µs");
+
+        for cores in [2, 4, 8, 16, 32, 64, 128] {
+            let fixture = Fixture::new();
+            fixture.cpus(cores);
+            add_coretemp(&fixture, cores);
+
+            let core_ids: Vec<u32> = (0..cores).collect();
+            let read_core = |core_id| get_cpu_core_info(core_id, &times, &times).is_ok();
+            assert!(
+                read_cores_parallel(&core_ids, workers, read_core)
+                    .iter()
+                    .all(|&ok| ok)
+            );
+
+            let serial = Instant::now();
+            for _ in 0..ROUNDS {
+                let _ = read_cores(&core_ids, 1, read_core);
+            }
+            let serial = serial.elapsed() / ROUNDS;
+
+            let parallel = Instant::now();
+            for _ in 0..ROUNDS {
+                let _ = read_cores_parallel(&core_ids, workers, read_core);
+            }
+            let parallel = parallel.elapsed() / ROUNDS;
+
+            println!(
+                "{cores:5} {:11} {:13}",
+                serial.as_micros(),
+                parallel.as_micros()
+            );
+        }
+    }
+}
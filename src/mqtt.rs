@@ -0,0 +1,662 @@
+use crate::config::MqttConfig;
+use crate::core::SystemReport;
+use crate::engine;
+use crate::util::{sysfs, wake};
+use log::{info, warn};
+use serde_json::{Value, json};
+use std::convert::Infallible;
+use std::fs;
+use std::io::{self, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to wait for the broker to accept a connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often the connection is checked for commands and new reports
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Delays between reconnection attempts, doubling up to the maximum
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(5);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+/// Payload of the command topic that returns to selecting a profile by rules
+/// and power state
+const AUTO_PROFILE: &str = "auto";
+
+/// Whether the client thread is running to publish reports
+static PUBLISHING: AtomicBool = AtomicBool::new(false);
+
+/// Summary of the latest report the client thread has not published yet.
+/// A newer report replaces it, so states queued up while the broker is
+/// unreachable are not published in a burst once it is back.
+static LATEST_REPORT: Mutex<Option<Value>> = Mutex::new(None);
+
+/// Profile selected through the command topic
+static SELECTED_PROFILE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Profile selected over MQTT, or `None` to select a profile by rules and power state
+pub fn selected_profile() -> Option<String> {
+    SELECTED_PROFILE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Publish a summary of `report` to the state topic
+pub fn publish_report(report: &SystemReport) {
+    if PUBLISHING.load(Ordering::Relaxed) {
+        *LATEST_REPORT.lock().unwrap_or_else(PoisonError::into_inner) = Some(summary(report));
+    }
+}
+
+fn take_latest_report() -> Option<Value> {
+    LATEST_REPORT
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take()
+}
+
+/// The state published for a report, which the discovery messages refer to
+fn summary(report: &SystemReport) -> Value {
+    let usages: Vec<f32> = report
+        .cpu_cores
+        .iter()
+        .filter_map(|core| core.usage_percent)
+        .collect();
+    let cpu_usage = (!usages.is_empty()).then(|| usages.iter().sum::<f32>() / usages.len() as f32);
+    let battery = report.batteries.first();
+
+    json!({
+        "profile": engine::active_profile(),
+        "governor": report.cpu_global.current_governor,
+        "turbo": report.cpu_global.turbo_status,
+        "cpu_usage": cpu_usage.map(|usage| (usage * 10.0).round() / 10.0),
+        "cpu_temperature": report.cpu_global.average_temperature_celsius,
+        "battery": battery.and_then(|battery| battery.capacity_percent),
+        "ac_power": battery.is_none_or(|battery| battery.ac_connected),
+        "power": report.power.estimated_system_watts,
+        "load_1m": report.system_load.load_avg_1min,
+    })
+}
+
+/// Topics of one machine
+#[derive(Debug, Clone)]
+struct Topics {
+    /// Machine name used in IDs, e.g. `my_laptop`
+    node_id: String,
+    hostname: String,
+    state: String,
+    availability: String,
+    profile_command: String,
+}
+
+impl Topics {
+    fn new(config: &MqttConfig) -> Self {
+        let hostname = fs::read_to_string(sysfs::path("/proc/sys/kernel/hostname"))
+            .map(|name| name.trim().to_string())
+            .unwrap_or_else(|_| "localhost".to_string());
+        let node_id: String = hostname
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let prefix = config
+            .topic_prefix
+            .clone()
+            .unwrap_or_else(|| format!("superfreq/{node_id}"));
+
+        Self {
+            state: format!("{prefix}/state"),
+            availability: format!("{prefix}/availability"),
+            profile_command: format!("{prefix}/profile/set"),
+            node_id,
+            hostname,
+        }
+    }
+}
+
+/// Start a background thread that publishes reports and listens for profile
+/// selections, reconnecting whenever the broker goes away
+pub fn spawn(config: &MqttConfig, profiles: Vec<String>) {
+    let config = config.clone();
+
+    let spawned = thread::Builder::new()
+        .name("mqtt".to_string())
+        .spawn(move || run(&config, &profiles));
+
+    match spawned {
+        Ok(_) => PUBLISHING.store(true, Ordering::Relaxed),
+        Err(e) => warn!("Failed to start the MQTT client: {e}"),
+    }
+}
+
+fn run(config: &MqttConfig, profiles: &[String]) {
+    let topics = Topics::new(config);
+    let mut delay = MIN_RETRY_DELAY;
+
+    loop {
+        let started = Instant::now();
+        let Err(e) = session(config, &topics, profiles);
+
+        // A connection that lasted a while was not a configuration problem
+        if started.elapsed() > MAX_RETRY_DELAY {
+            delay = MIN_RETRY_DELAY;
+        }
+        warn!(
+            "MQTT connection to {}:{} failed: {e}. Retrying in {}s.",
+            config.host,
+            config.port,
+            delay.as_secs()
+        );
+        thread::sleep(delay);
+        delay = (delay * 2).min(MAX_RETRY_DELAY);
+    }
+}
+
+/// Connect, announce the machine and exchange messages until the connection
+/// fails
+fn session(config: &MqttConfig, topics: &Topics, profiles: &[String]) -> io::Result<Infallible> {
+    let address = (config.host.as_str(), config.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "the host has no address"))?;
+    let mut stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+    stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+
+    stream.write_all(&packet::connect(config, topics))?;
+    match packet::read(&mut stream)? {
+        Some((packet::CONNACK, body)) if body.get(1) == Some(&0) => {}
+        Some((packet::CONNACK, body)) => {
+            return Err(io::Error::other(format!(
+                "the broker refused the connection (code {})",
+                body.get(1).copied().unwrap_or_default()
+            )));
+        }
+        _ => {
+            return Err(io::Error::other(
+                "the broker did not acknowledge the connection",
+            ));
+        }
+    }
+    info!(
+        "Connected to the MQTT broker at {}:{}",
+        config.host, config.port
+    );
+
+    stream.write_all(&packet::publish(&topics.availability, b"online", true))?;
+    if config.discovery {
+        for (topic, payload) in discovery_messages(config, topics, profiles) {
+            stream.write_all(&packet::publish(
+                &topic,
+                payload.to_string().as_bytes(),
+                true,
+            ))?;
+        }
+    }
+    stream.write_all(&packet::subscribe(1, &topics.profile_command))?;
+
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let keep_alive = Duration::from_secs(config.keep_alive_sec.into());
+    let mut last_sent = Instant::now();
+    let mut last_received = Instant::now();
+
+    loop {
+        if let Some(state) = take_latest_report() {
+            stream.write_all(&packet::publish(
+                &topics.state,
+                state.to_string().as_bytes(),
+                true,
+            ))?;
+            last_sent = Instant::now();
+        }
+
+        if last_sent.elapsed() >= keep_alive / 2 {
+            stream.write_all(&packet::pingreq())?;
+            last_sent = Instant::now();
+        }
+        // The broker answers pings, so silence means the connection is gone
+        if last_received.elapsed() > keep_alive * 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "the broker stopped responding",
+            ));
+        }
+
+        let Some((kind, body)) = packet::read(&mut stream)? else {
+            continue;
+        };
+        last_received = Instant::now();
+        if kind == packet::PUBLISH {
+            let (topic, payload) = packet::parse_publish(&body)?;
+            if topic == topics.profile_command {
+                select_profile(&String::from_utf8_lossy(payload), profiles);
+            }
+        }
+    }
+}
+
+/// Handle a message on the command topic
+fn select_profile(payload: &str, profiles: &[String]) {
+    let name = payload.trim();
+    let selection = match name {
+        "" | AUTO_PROFILE => None,
+        _ if profiles.iter().any(|profile| profile == name) => Some(name.to_string()),
+        _ => {
+            warn!("Ignoring MQTT selection of unknown profile '{name}'");
+            return;
+        }
+    };
+
+    match &selection {
+        Some(name) => info!("Profile '{name}' selected over MQTT"),
+        None => info!("Profile selection over MQTT cleared"),
+    }
+    *SELECTED_PROFILE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = selection;
+    wake::request_wakeup("profile selected over MQTT");
+}
+
+/// Home Assistant discovery messages, as `(topic, payload)`, describing the
+/// machine as a device with sensors for the state and a profile selector
+fn discovery_messages(
+    config: &MqttConfig,
+    topics: &Topics,
+    profiles: &[String],
+) -> Vec<(String, Value)> {
+    let device = json!({
+        "identifiers": [format!("superfreq_{}", topics.node_id)],
+        "name": topics.hostname,
+        "manufacturer": "superfreq",
+        "sw_version": env!("CARGO_PKG_VERSION"),
+    });
+    let entity = |component: &str, object: &str, name: &str, extra: Value| {
+        let mut payload = json!({
+            "name": name,
+            "unique_id": format!("superfreq_{}_{object}", topics.node_id),
+            "object_id": format!("superfreq_{}_{object}", topics.node_id),
+            "availability_topic": topics.availability,
+            "state_topic": topics.state,
+            "device": device,
+        });
+        if let (Some(payload), Value::Object(extra)) = (payload.as_object_mut(), extra) {
+            payload.extend(extra);
+        }
+        (
+            format!(
+                "{}/{component}/superfreq_{}/{object}/config",
+                config.discovery_prefix, topics.node_id
+            ),
+            payload,
+        )
+    };
+
+    let mut options = vec![AUTO_PROFILE.to_string()];
+    options.extend(profiles.iter().cloned());
+
+    vec![
+        entity(
+            "sensor",
+            "cpu_usage",
+            "CPU usage",
+            json!({
+                "value_template": "{{ value_json.cpu_usage }}",
+                "unit_of_measurement": "%",
+                "state_class": "measurement",
+            }),
+        ),
+        entity(
+            "sensor",
+            "cpu_temperature",
+            "CPU temperature",
+            json!({
+                "value_template": "{{ value_json.cpu_temperature }}",
+                "unit_of_measurement": "°C",
+                "device_class": "temperature",
+                "state_class": "measurement",
+            }),
+        ),
+        entity(
+            "sensor",
+            "battery",
+            "Battery",
+            json!({
+                "value_template": "{{ value_json.battery }}",
+                "unit_of_measurement": "%",
+                "device_class": "battery",
+                "state_class": "measurement",
+            }),
+        ),
+        entity(
+            "sensor",
+            "power",
+            "Power draw",
+            json!({
+                "value_template": "{{ value_json.power }}",
+                "unit_of_measurement": "W",
+                "device_class": "power",
+                "state_class": "measurement",
+            }),
+        ),
+        entity(
+            "sensor",
+            "governor",
+            "CPU governor",
+            json!({ "value_template": "{{ value_json.governor }}" }),
+        ),
+        entity(
+            "binary_sensor",
+            "ac_power",
+            "AC power",
+            json!({
+                "value_template": "{{ 'ON' if value_json.ac_power else 'OFF' }}",
+                "device_class": "plug",
+            }),
+        ),
+        entity(
+            "select",
+            "profile",
+            "Profile",
+            json!({
+                "value_template": "{{ value_json.profile }}",
+                "command_topic": topics.profile_command,
+                "options": options,
+            }),
+        ),
+    ]
+}
+
+/// Encoding and decoding of the MQTT 3.1.1 packets the client uses
+mod packet {
+    use super::Topics;
+    use crate::config::MqttConfig;
+    use std::io::{self, Read};
+
+    pub const CONNACK: u8 = 2;
+    pub const PUBLISH: u8 = 3;
+
+    const CONNECT: u8 = 1;
+    const SUBSCRIBE: u8 = 8;
+    const PINGREQ: u8 = 12;
+
+    /// Packets larger than this are refused
+    const MAX_PACKET_BYTES: usize = 256 * 1024;
+
+    fn put_string(buffer: &mut Vec<u8>, value: &[u8]) {
+        // Strings are limited to 64 KiB by the protocol
+        let length = value.len().min(usize::from(u16::MAX));
+        buffer.extend_from_slice(&(length as u16).to_be_bytes());
+        buffer.extend_from_slice(&value[..length]);
+    }
+
+    /// Prefix `body` with the fixed header of a packet
+    fn finish(kind: u8, flags: u8, body: &[u8]) -> Vec<u8> {
+        let mut packet = vec![kind << 4 | flags];
+        let mut length = body.len();
+        loop {
+            let mut byte = (length % 128) as u8;
+            length /= 128;
+            if length > 0 {
+                byte |= 0x80;
+            }
+            packet.push(byte);
+            if length == 0 {
+                break;
+            }
+        }
+        packet.extend_from_slice(body);
+        packet
+    }
+
+    /// A connection request with a will that marks the machine offline
+    pub fn connect(config: &MqttConfig, topics: &Topics) -> Vec<u8> {
+        let mut flags = 0x02 | 0x04 | 0x20; // Clean session, retained will
+        if config.username.is_some() {
+            flags |= 0x80;
+        }
+        if config.password.is_some() {
+            flags |= 0x40;
+        }
+
+        let mut body = Vec::new();
+        put_string(&mut body, b"MQTT");
+        body.push(4); // Protocol level 3.1.1
+        body.push(flags);
+        body.extend_from_slice(&config.keep_alive_sec.to_be_bytes());
+        put_string(
+            &mut body,
+            format!("superfreq-{}", topics.node_id).as_bytes(),
+        );
+        put_string(&mut body, topics.availability.as_bytes());
+        put_string(&mut body, b"offline");
+        if let Some(username) = &config.username {
+            put_string(&mut body, username.as_bytes());
+        }
+        if let Some(password) = &config.password {
+            put_string(&mut body, password.as_bytes());
+        }
+        finish(CONNECT, 0, &body)
+    }
+
+    pub fn publish(topic: &str, payload: &[u8], retain: bool) -> Vec<u8> {
+        let mut body = Vec::new();
+        put_string(&mut body, topic.as_bytes());
+        body.extend_from_slice(payload);
+        finish(PUBLISH, u8::from(retain), &body)
+    }
+
+    pub fn subscribe(packet_id: u16, topic: &str) -> Vec<u8> {
+        let mut body = packet_id.to_be_bytes().to_vec();
+        put_string(&mut body, topic.as_bytes());
+        body.push(0); // At most once
+        finish(SUBSCRIBE, 0x02, &body)
+    }
+
+    pub fn pingreq() -> Vec<u8> {
+        finish(PINGREQ, 0, &[])
+    }
+
+    /// Read a packet as `(type, body)`, or `None` if none arrived before the
+    /// read timeout
+    pub fn read(stream: &mut impl Read) -> io::Result<Option<(u8, Vec<u8>)>> {
+        let mut header = [0; 1];
+        match stream.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        }
+
+        let mut length = 0;
+        for shift in (0..4).map(|i| i * 7) {
+            let mut byte = [0; 1];
+            stream.read_exact(&mut byte)?;
+            length |= usize::from(byte[0] & 0x7f) << shift;
+            if byte[0] & 0x80 == 0 {
+                break;
+            }
+        }
+        if length > MAX_PACKET_BYTES {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("packet of {length} bytes is too large"),
+            ));
+        }
+
+        let mut body = vec![0; length];
+        stream.read_exact(&mut body)?;
+        // The flags of PUBLISH carry its QoS, which the body layout depends on
+        let kind = header[0] >> 4;
+        if kind == PUBLISH && header[0] & 0x06 != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "received a PUBLISH with QoS above 0",
+            ));
+        }
+        Ok(Some((kind, body)))
+    }
+
+    /// Split the body of a QoS 0 PUBLISH into its topic and payload
+    pub fn parse_publish(body: &[u8]) -> io::Result<(String, &[u8])> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "malformed PUBLISH");
+        let length = usize::from(u16::from_be_bytes([
+            *body.first().ok_or_else(invalid)?,
+            *body.get(1).ok_or_else(invalid)?,
+        ]));
+        let topic = body.get(2..2 + length).ok_or_else(invalid)?;
+        let topic = String::from_utf8(topic.to_vec()).map_err(|_| invalid())?;
+        Ok((topic, &body[2 + length..]))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn topics() -> Topics {
+            Topics {
+                node_id: "host".to_string(),
+                hostname: "host".to_string(),
+                state: "sf/state".to_string(),
+                availability: "sf/availability".to_string(),
+                profile_command: "sf/profile/set".to_string(),
+            }
+        }
+
+        fn read_all(mut bytes: &[u8]) -> io::Result<Option<(u8, Vec<u8>)>> {
+            read(&mut bytes)
+        }
+
+        #[test]
+        fn remaining_length_boundaries() {
+            for (length, encoded) in [
+                (0, &[0x00][..]),
+                (127, &[0x7f]),
+                (128, &[0x80, 0x01]),
+                (16_383, &[0xff, 0x7f]),
+                (16_384, &[0x80, 0x80, 0x01]),
+            ] {
+                let body = vec![0xaa; length];
+                let packet = finish(PINGREQ, 0, &body);
+                assert_eq!(&packet[1..=encoded.len()], encoded, "length {length}");
+                assert_eq!(packet.len(), 1 + encoded.len() + length);
+                assert_eq!(read_all(&packet).unwrap(), Some((PINGREQ, body)));
+            }
+        }
+
+        #[test]
+        fn connect_without_credentials() {
+            let config = MqttConfig {
+                keep_alive_sec: 60,
+                ..MqttConfig::default()
+            };
+            let mut expected = vec![0x10, 52, 0, 4, b'M', b'Q', b'T', b'T', 4, 0x26, 0, 60];
+            expected.extend_from_slice(b"\0\x0esuperfreq-host");
+            expected.extend_from_slice(b"\0\x0fsf/availability");
+            expected.extend_from_slice(b"\0\x07offline");
+            assert_eq!(connect(&config, &topics()), expected);
+        }
+
+        #[test]
+        fn connect_with_credentials() {
+            let config = MqttConfig {
+                username: Some("user".to_string()),
+                password: Some("secret".to_string()),
+                keep_alive_sec: 60,
+                ..MqttConfig::default()
+            };
+            let packet = connect(&config, &topics());
+            assert_eq!(packet[1], 52 + 6 + 8);
+            assert_eq!(packet[9], 0x26 | 0x80 | 0x40);
+            assert!(packet.ends_with(b"\0\x04user\0\x06secret"));
+        }
+
+        #[test]
+        fn publish_layout() {
+            assert_eq!(
+                publish("a/b", b"on", true),
+                [0x31, 7, 0, 3, b'a', b'/', b'b', b'o', b'n']
+            );
+            assert_eq!(
+                publish("a/b", b"", false),
+                [0x30, 5, 0, 3, b'a', b'/', b'b']
+            );
+        }
+
+        #[test]
+        fn subscribe_and_ping_layout() {
+            assert_eq!(
+                subscribe(1, "a/b"),
+                [0x82, 8, 0, 1, 0, 3, b'a', b'/', b'b', 0]
+            );
+            assert_eq!(pingreq(), [0xc0, 0]);
+        }
+
+        #[test]
+        fn read_rejects_truncated_input() {
+            for truncated in [&[][..], &[0x30], &[0x30, 0x80], &[0x30, 5, 0, 1]] {
+                let error = read_all(truncated).unwrap_err();
+                assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof, "{truncated:?}");
+            }
+        }
+
+        #[test]
+        fn read_rejects_oversized_packets() {
+            // MAX_PACKET_BYTES + 1, without the body that would follow
+            let error = read_all(&[0x30, 0x81, 0x80, 0x10]).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+            let largest = finish(PUBLISH, 0, &vec![0; MAX_PACKET_BYTES]);
+            assert!(read_all(&largest).unwrap().is_some());
+        }
+
+        #[test]
+        fn read_returns_none_on_timeout() {
+            struct TimedOut;
+            impl Read for TimedOut {
+                fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                    Err(io::ErrorKind::WouldBlock.into())
+                }
+            }
+            assert!(read(&mut TimedOut).unwrap().is_none());
+        }
+
+        #[test]
+        fn publish_with_qos_0_is_parsed() {
+            let (kind, body) = read_all(&publish("sf/profile/set", b"gaming", false))
+                .unwrap()
+                .unwrap();
+            assert_eq!(kind, PUBLISH);
+            let (topic, payload) = parse_publish(&body).unwrap();
+            assert_eq!(topic, "sf/profile/set");
+            assert_eq!(payload, b"gaming");
+
+            for malformed in [&[][..], &[0], &[0, 5, b'a'], &[0, 1, 0xff]] {
+                assert!(parse_publish(malformed).is_err(), "{malformed:?}");
+            }
+        }
+
+        #[test]
+        fn publish_with_qos_1_is_rejected() {
+            // Subscriptions ask for QoS 0, so a QoS 1 PUBLISH with its packet
+            // identifier after the topic is a protocol error
+            let packet = [0x32, 8, 0, 3, b'a', b'/', b'b', 0, 1, b'x'];
+            let error = read_all(&packet).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
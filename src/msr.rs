@@ -0,0 +1,103 @@
+use crate::util::error::ControlError;
+use crate::util::sysfs;
+use log::debug;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+
+pub type Result<T, E = ControlError> = std::result::Result<T, E>;
+
+/// `IA32_ENERGY_PERF_BIAS`, whose low four bits hold the energy-performance bias
+const MSR_IA32_ENERGY_PERF_BIAS: u64 = 0x1b0;
+const EPB_MASK: u64 = 0xf;
+
+/// `IA32_THERM_STATUS`, whose bit 2 is set while PROCHOT# or FORCEPR# is asserted
+const MSR_IA32_THERM_STATUS: u64 = 0x19c;
+const PROCHOT_STATUS: u64 = 1 << 2;
+
+fn msr_path(core_id: u32) -> String {
+    format!("/dev/cpu/{core_id}/msr")
+}
+
+fn map_io_error(path: &str, e: io::Error) -> ControlError {
+    match e.kind() {
+        io::ErrorKind::NotFound => ControlError::NotSupported(format!(
+            "{path} does not exist. Load the msr kernel module to use it."
+        )),
+        io::ErrorKind::PermissionDenied => {
+            ControlError::PermissionDenied(format!("Cannot access {path}"))
+        }
+        _ => ControlError::Io(e),
+    }
+}
+
+/// Whether the energy-performance bias can be written through the MSR device:
+/// the CPU advertises EPB and the `msr` kernel module is loaded
+pub fn is_epb_supported() -> bool {
+    let cpu_has_epb = fs::read_to_string(sysfs::path("/proc/cpuinfo")).is_ok_and(|cpuinfo| {
+        cpuinfo
+            .lines()
+            .find(|line| line.starts_with("flags"))
+            .is_some_and(|flags| flags.split_whitespace().any(|flag| flag == "epb"))
+    });
+
+    cpu_has_epb && Path::new(&msr_path(0)).exists()
+}
+
+/// Read the energy-performance bias (0-15) of a core
+pub fn read_epb(core_id: u32) -> Result<u8> {
+    let path = msr_path(core_id);
+    let file = OpenOptions::new()
+        .read(true)
+        .open(&path)
+        .map_err(|e| map_io_error(&path, e))?;
+
+    let mut buf = [0u8; 8];
+    file.read_exact_at(&mut buf, MSR_IA32_ENERGY_PERF_BIAS)
+        .map_err(|e| map_io_error(&path, e))?;
+    Ok((u64::from_le_bytes(buf) & EPB_MASK) as u8)
+}
+
+/// Whether an external agent is asserting PROCHOT# on a core, throttling it
+/// regardless of its own temperature
+pub fn read_prochot(core_id: u32) -> Result<bool> {
+    let path = msr_path(core_id);
+    let file = OpenOptions::new()
+        .read(true)
+        .open(&path)
+        .map_err(|e| map_io_error(&path, e))?;
+
+    let mut buf = [0u8; 8];
+    file.read_exact_at(&mut buf, MSR_IA32_THERM_STATUS)
+        .map_err(|e| map_io_error(&path, e))?;
+    Ok(u64::from_le_bytes(buf) & PROCHOT_STATUS != 0)
+}
+
+/// Set the energy-performance bias (0-15) of a core, preserving the other bits of the MSR
+pub fn write_epb(core_id: u32, value: u8) -> Result<()> {
+    if u64::from(value) > EPB_MASK {
+        return Err(ControlError::InvalidValueError(format!(
+            "EPB value must be between 0 and 15, got {value}"
+        )));
+    }
+
+    if sysfs::is_observe_only() {
+        debug!("Observe mode, not writing EPB {value} to the MSR of CPU {core_id}");
+        return Ok(());
+    }
+
+    let path = msr_path(core_id);
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .map_err(|e| map_io_error(&path, e))?;
+
+    let mut buf = [0u8; 8];
+    file.read_exact_at(&mut buf, MSR_IA32_ENERGY_PERF_BIAS)
+        .map_err(|e| map_io_error(&path, e))?;
+    let msr = (u64::from_le_bytes(buf) & !EPB_MASK) | u64::from(value);
+    file.write_all_at(&msr.to_le_bytes(), MSR_IA32_ENERGY_PERF_BIAS)
+        .map_err(|e| map_io_error(&path, e))
+}
@@ -0,0 +1,111 @@
+//! NVIDIA GPUs through NVML, the library that ships with the proprietary
+//! driver. Its GPUs have no hwmon sensors, so this is the only way to read
+//! their load, temperature and power.
+
+use crate::core::NvidiaGpuInfo;
+use crate::util::error::ControlError;
+use crate::util::sysfs;
+use log::debug;
+use nvml_wrapper::Nvml;
+use nvml_wrapper::enum_wrappers::device::TemperatureSensor;
+use nvml_wrapper::error::NvmlError;
+use std::sync::OnceLock;
+
+pub type Result<T, E = ControlError> = std::result::Result<T, E>;
+
+/// The library, loaded on first use. `None` without the driver, which is not
+/// retried, as it cannot appear while the process runs.
+static NVML: OnceLock<Option<Nvml>> = OnceLock::new();
+
+fn nvml() -> Option<&'static Nvml> {
+    NVML.get_or_init(|| {
+        Nvml::init()
+            .inspect_err(|e| debug!("NVML is unavailable: {e}"))
+            .ok()
+    })
+    .as_ref()
+}
+
+fn map_nvml_error(e: NvmlError) -> ControlError {
+    match e {
+        NvmlError::NoPermission => {
+            ControlError::PermissionDenied("NVML refused the change".to_string())
+        }
+        NvmlError::NotSupported => {
+            ControlError::NotSupported("The GPU does not support this through NVML".to_string())
+        }
+        e => ControlError::WriteError(format!("NVML error: {e}")),
+    }
+}
+
+/// Utilization, temperature and power of every NVIDIA GPU. Readings a GPU
+/// does not support are left out.
+pub fn get_gpus() -> Vec<NvidiaGpuInfo> {
+    let Some(nvml) = nvml() else {
+        return Vec::new();
+    };
+    let count = nvml.device_count().unwrap_or(0);
+
+    (0..count)
+        .filter_map(|index| {
+            let device = nvml
+                .device_by_index(index)
+                .inspect_err(|e| debug!("Cannot open NVIDIA GPU {index}: {e}"))
+                .ok()?;
+            let utilization = device.utilization_rates().ok();
+            Some(NvidiaGpuInfo {
+                index,
+                name: device.name().unwrap_or_else(|_| "Unknown".to_string()),
+                utilization_percent: utilization.as_ref().map(|rates| rates.gpu),
+                memory_utilization_percent: utilization.as_ref().map(|rates| rates.memory),
+                temperature_celsius: device.temperature(TemperatureSensor::Gpu).ok(),
+                power_watts: device
+                    .power_usage()
+                    .ok()
+                    .map(|milliwatts| milliwatts as f32 / 1000.0),
+                power_limit_watts: device
+                    .enforced_power_limit()
+                    .ok()
+                    .map(|milliwatts| milliwatts as f32 / 1000.0),
+            })
+        })
+        .collect()
+}
+
+/// Set the power limit of every NVIDIA GPU. The driver keeps it until it is
+/// reloaded.
+pub fn set_power_limit(watts: f32) -> Result<()> {
+    let nvml = nvml().ok_or_else(|| {
+        ControlError::NotSupported("NVML is unavailable, is the NVIDIA driver loaded?".to_string())
+    })?;
+    let count = nvml.device_count().map_err(map_nvml_error)?;
+    if count == 0 {
+        return Err(ControlError::NotSupported(
+            "No NVIDIA GPU found.".to_string(),
+        ));
+    }
+
+    let milliwatts = (watts * 1000.0).round() as u32;
+    for index in 0..count {
+        let mut device = nvml.device_by_index(index).map_err(map_nvml_error)?;
+        let constraints = device
+            .power_management_limit_constraints()
+            .map_err(map_nvml_error)?;
+        if !(constraints.min_limit..=constraints.max_limit).contains(&milliwatts) {
+            return Err(ControlError::InvalidValueError(format!(
+                "Power limit of {watts} W is outside the range of NVIDIA GPU {index} ({}-{} W)",
+                constraints.min_limit / 1000,
+                constraints.max_limit / 1000
+            )));
+        }
+        if sysfs::is_observe_only() {
+            debug!("Observe mode, not setting the power limit of NVIDIA GPU {index} to {watts} W");
+            continue;
+        }
+        device
+            .set_power_management_limit(milliwatts)
+            .map_err(map_nvml_error)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use crate::util::error::ControlError;
+use crate::util::sysfs;
+use log::{info, warn};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+const PROC_SYS: &str = "/proc/sys";
+
+/// Values files set from the `sysctl` and `sysfs` profile options had before
+/// they were first changed
+static ORIGINAL_VALUES: Mutex<BTreeMap<PathBuf, String>> = Mutex::new(BTreeMap::new());
+
+/// Path of a kernel tunable, e.g. `vm.laptop_mode` -> `/proc/sys/vm/laptop_mode`.
+/// Names containing a slash already use it as the separator.
+pub fn sysctl_path(name: &str) -> PathBuf {
+    let relative = if name.contains('/') {
+        name.to_string()
+    } else {
+        name.replace('.', "/")
+    };
+    sysfs::path(PROC_SYS).join(relative)
+}
+
+/// Write a value, first remembering the original one if `remember` is set
+pub fn write(path: &Path, value: &str, remember: bool) -> Result<(), ControlError> {
+    let mut originals = ORIGINAL_VALUES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    // Nothing is changed in observe mode, so there is nothing to restore
+    if remember && !sysfs::is_observe_only() && !originals.contains_key(path) {
+        let original = sysfs::read_sysfs_value(path)?;
+        if original == value {
+            return Ok(());
+        }
+        originals.insert(path.to_path_buf(), original);
+    }
+
+    sysfs::write_sysfs_value(path, value)
+}
+
+/// Files that have been changed and not restored yet
+pub fn changed() -> Vec<PathBuf> {
+    ORIGINAL_VALUES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .keys()
+        .cloned()
+        .collect()
+}
+
+/// Write back the original value of a file
+pub fn restore(path: &Path) -> Result<(), ControlError> {
+    let mut originals = ORIGINAL_VALUES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    if let Some(original) = originals.get(path) {
+        sysfs::write_sysfs_value(path, original)?;
+        originals.remove(path);
+    }
+    Ok(())
+}
+
+/// Restore every changed file, e.g. when the daemon exits
+pub fn restore_all() {
+    for path in changed() {
+        match restore(&path) {
+            Ok(()) => info!("Restored {}", path.display()),
+            Err(e) => warn!("Failed to restore {}: {e}", path.display()),
+        }
+    }
+}
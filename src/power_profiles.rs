@@ -0,0 +1,343 @@
+use crate::battery;
+use crate::config::PowerProfilesConfig;
+use crate::util::wake;
+use crate::vendor::system76;
+use log::{debug, info};
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::Value;
+use zbus::{blocking, fdo, interface};
+
+const BUS_NAME: &str = "net.hadess.PowerProfiles";
+const OBJECT_PATH: &str = "/net/hadess/PowerProfiles";
+
+const SYSTEM76_BUS_NAME: &str = "com.system76.PowerDaemon";
+const SYSTEM76_OBJECT_PATH: &str = "/com/system76/PowerDaemon";
+
+/// A profile as named by power-profiles-daemon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PpdProfile {
+    PowerSaver,
+    Balanced,
+    Performance,
+}
+
+impl PpdProfile {
+    const ALL: [Self; 3] = [Self::PowerSaver, Self::Balanced, Self::Performance];
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::PowerSaver => "power-saver",
+            Self::Balanced => "balanced",
+            Self::Performance => "performance",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|profile| profile.name() == name)
+    }
+
+    /// The name system76-power uses for the profile
+    const fn system76_name(self) -> &'static str {
+        match self {
+            Self::PowerSaver => "Battery",
+            Self::Balanced => "Balanced",
+            Self::Performance => "Performance",
+        }
+    }
+}
+
+/// A request from an application to keep a profile active until it is released
+#[derive(Debug, Clone)]
+struct Hold {
+    cookie: u32,
+    profile: PpdProfile,
+    reason: String,
+    application_id: String,
+}
+
+#[derive(Debug)]
+struct State {
+    /// Profile chosen by the user
+    selected: PpdProfile,
+    holds: Vec<Hold>,
+    next_cookie: u32,
+}
+
+impl State {
+    /// Holds override the user's choice, and `power-saver` holds win over
+    /// `performance` holds
+    fn active(&self) -> PpdProfile {
+        [PpdProfile::PowerSaver, PpdProfile::Performance]
+            .into_iter()
+            .find(|profile| self.holds.iter().any(|hold| hold.profile == *profile))
+            .unwrap_or(self.selected)
+    }
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    selected: PpdProfile::Balanced,
+    holds: Vec::new(),
+    next_cookie: 1,
+});
+
+fn state() -> std::sync::MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Name of the profile to apply for the power profile selected over D-Bus,
+/// or `None` to select a profile by rules and power state
+pub fn selected_profile(config: &PowerProfilesConfig) -> Option<&str> {
+    if !config.enable && !config.system76 {
+        return None;
+    }
+
+    match state().active() {
+        PpdProfile::PowerSaver => Some(&config.power_saver),
+        PpdProfile::Balanced => config.balanced.as_deref(),
+        PpdProfile::Performance => Some(&config.performance),
+    }
+}
+
+/// The `net.hadess.PowerProfiles` interface of power-profiles-daemon
+struct PowerProfiles;
+
+#[interface(name = "net.hadess.PowerProfiles")]
+impl PowerProfiles {
+    #[zbus(property)]
+    fn active_profile(&self) -> String {
+        state().active().name().to_string()
+    }
+
+    #[zbus(property)]
+    fn set_active_profile(&mut self, profile: String) -> fdo::Result<()> {
+        let profile = PpdProfile::from_name(&profile)
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("Invalid profile name '{profile}'")))?;
+
+        info!("Power profile set to '{}' over D-Bus", profile.name());
+        state().selected = profile;
+        wake::request_wakeup("power profile change");
+        Ok(())
+    }
+
+    #[zbus(property)]
+    fn performance_inhibited(&self) -> String {
+        String::new()
+    }
+
+    #[zbus(property)]
+    fn performance_degraded(&self) -> String {
+        String::new()
+    }
+
+    #[zbus(property)]
+    fn profiles(&self) -> Vec<HashMap<String, Value<'_>>> {
+        PpdProfile::ALL
+            .into_iter()
+            .map(|profile| {
+                HashMap::from([
+                    ("Profile".to_string(), Value::from(profile.name())),
+                    ("Driver".to_string(), Value::from("superfreq")),
+                    ("CpuDriver".to_string(), Value::from("superfreq")),
+                ])
+            })
+            .collect()
+    }
+
+    #[zbus(property)]
+    fn actions(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn active_profile_holds(&self) -> Vec<HashMap<String, Value<'_>>> {
+        state()
+            .holds
+            .iter()
+            .map(|hold| {
+                HashMap::from([
+                    ("Profile".to_string(), Value::from(hold.profile.name())),
+                    ("Reason".to_string(), Value::from(hold.reason.clone())),
+                    (
+                        "ApplicationId".to_string(),
+                        Value::from(hold.application_id.clone()),
+                    ),
+                ])
+            })
+            .collect()
+    }
+
+    #[zbus(property)]
+    fn version(&self) -> String {
+        env!("CARGO_PKG_VERSION").to_string()
+    }
+
+    async fn hold_profile(
+        &self,
+        profile: String,
+        reason: String,
+        application_id: String,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<u32> {
+        let profile = match PpdProfile::from_name(&profile) {
+            Some(profile @ (PpdProfile::PowerSaver | PpdProfile::Performance)) => profile,
+            _ => {
+                return Err(fdo::Error::InvalidArgs(format!(
+                    "Only 'power-saver' and 'performance' can be held, not '{profile}'"
+                )));
+            }
+        };
+
+        let cookie = {
+            let mut state = state();
+            let cookie = state.next_cookie;
+            state.next_cookie = state.next_cookie.wrapping_add(1).max(1);
+            info!(
+                "{application_id} is holding the '{}' power profile: {reason}",
+                profile.name()
+            );
+            state.holds.push(Hold {
+                cookie,
+                profile,
+                reason,
+                application_id,
+            });
+            cookie
+        };
+
+        wake::request_wakeup("power profile hold");
+        self.active_profile_changed(&emitter).await?;
+        self.active_profile_holds_changed(&emitter).await?;
+        Ok(cookie)
+    }
+
+    async fn release_profile(
+        &self,
+        cookie: u32,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        {
+            let mut state = state();
+            let index = state
+                .holds
+                .iter()
+                .position(|hold| hold.cookie == cookie)
+                .ok_or_else(|| fdo::Error::InvalidArgs(format!("No hold with cookie {cookie}")))?;
+            let hold = state.holds.remove(index);
+            info!(
+                "{} released the '{}' power profile",
+                hold.application_id,
+                hold.profile.name()
+            );
+        }
+
+        wake::request_wakeup("power profile release");
+        Self::profile_released(&emitter, cookie).await?;
+        self.active_profile_changed(&emitter).await?;
+        self.active_profile_holds_changed(&emitter).await?;
+        Ok(())
+    }
+
+    #[zbus(signal)]
+    async fn profile_released(emitter: &SignalEmitter<'_>, cookie: u32) -> zbus::Result<()>;
+}
+
+/// The profile and battery methods of system76-power's `com.system76.PowerDaemon`
+/// interface, as used by the Pop!_OS power menu and settings. Graphics
+/// switching is left to system76-power.
+struct System76Power;
+
+impl System76Power {
+    async fn select(&self, profile: PpdProfile, emitter: &SignalEmitter<'_>) -> fdo::Result<()> {
+        info!(
+            "Power profile set to '{}' over the system76-power interface",
+            profile.name()
+        );
+        state().selected = profile;
+        wake::request_wakeup("power profile change");
+        Self::power_profile_switch(emitter, profile.system76_name()).await?;
+        Ok(())
+    }
+}
+
+#[interface(name = "com.system76.PowerDaemon")]
+impl System76Power {
+    async fn battery(&self, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>) -> fdo::Result<()> {
+        self.select(PpdProfile::PowerSaver, &emitter).await
+    }
+
+    async fn balanced(
+        &self,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        self.select(PpdProfile::Balanced, &emitter).await
+    }
+
+    async fn performance(
+        &self,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        self.select(PpdProfile::Performance, &emitter).await
+    }
+
+    fn get_profile(&self) -> String {
+        state().active().system76_name().to_string()
+    }
+
+    fn get_charge_thresholds(&self) -> fdo::Result<(u8, u8)> {
+        let batteries =
+            battery::get_supported_batteries().map_err(|e| fdo::Error::Failed(e.to_string()))?;
+        let battery = batteries.first().ok_or_else(|| {
+            fdo::Error::NotSupported("No battery supports charge thresholds".to_string())
+        })?;
+        let thresholds = battery::get_battery_charge_thresholds(&battery.name)
+            .map_err(|e| fdo::Error::Failed(e.to_string()))?;
+        Ok((thresholds.start, thresholds.stop))
+    }
+
+    fn set_charge_thresholds(&self, thresholds: (u8, u8)) -> fdo::Result<()> {
+        let (start, stop) = thresholds;
+        info!("Charge thresholds set to {start}-{stop}% over the system76-power interface");
+        battery::set_battery_charge_thresholds(start, stop)
+            .map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    fn get_charge_profiles(&self) -> Vec<HashMap<String, Value<'_>>> {
+        system76::CHARGE_PROFILES
+            .iter()
+            .map(|profile| {
+                HashMap::from([
+                    ("id".to_string(), Value::from(profile.id)),
+                    ("title".to_string(), Value::from(profile.title)),
+                    ("description".to_string(), Value::from(profile.description)),
+                    ("start".to_string(), Value::from(profile.start)),
+                    ("end".to_string(), Value::from(profile.end)),
+                ])
+            })
+            .collect()
+    }
+
+    #[zbus(signal)]
+    async fn power_profile_switch(emitter: &SignalEmitter<'_>, profile: &str) -> zbus::Result<()>;
+}
+
+/// Serve the power-profiles-daemon interface, and the system76-power interface
+/// if enabled, on the system bus. The interfaces are served for as long as the
+/// returned connection is kept alive.
+pub fn serve(config: &PowerProfilesConfig) -> zbus::Result<blocking::Connection> {
+    let mut builder = blocking::connection::Builder::system()?;
+    if config.enable {
+        builder = builder
+            .name(BUS_NAME)?
+            .serve_at(OBJECT_PATH, PowerProfiles)?;
+        debug!("Serving {BUS_NAME} at {OBJECT_PATH}");
+    }
+    if config.system76 {
+        builder = builder
+            .name(SYSTEM76_BUS_NAME)?
+            .serve_at(SYSTEM76_OBJECT_PATH, System76Power)?;
+        debug!("Serving {SYSTEM76_BUS_NAME} at {SYSTEM76_OBJECT_PATH}");
+    }
+    builder.build()
+}
@@ -0,0 +1,122 @@
+use crate::util::{sysfs, wake};
+use log::{debug, info, warn};
+use nix::sys::socket::{
+    AddressFamily, MsgFlags, NetlinkAddr, SockFlag, SockProtocol, SockType, bind, recv, socket,
+};
+use std::fs;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::sync::Once;
+use std::thread;
+use std::time::Duration;
+
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// Netlink multicast group the kernel sends uevents to
+const KERNEL_UEVENT_GROUP: u32 = 1;
+
+/// How often the AC state is polled when uevents cannot be received
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+static LISTENER: Once = Once::new();
+
+/// Start a background thread that wakes the daemon as soon as a power supply
+/// changes (e.g. AC is plugged in or out), so the matching profile is applied
+/// without waiting for the next poll. Kernel uevents are used where available,
+/// and the `online` state of the AC adapters is polled otherwise.
+pub fn spawn_listener() {
+    LISTENER.call_once(|| {
+        let spawned = thread::Builder::new()
+            .name("power-supply-events".to_string())
+            .spawn(|| match open_uevent_socket() {
+                Ok(socket) => {
+                    info!("Listening for power supply events from the kernel");
+                    listen_uevents(&socket);
+                    warn!("Stopped receiving kernel uevents, polling the AC state instead");
+                    poll_ac_state();
+                }
+                Err(e) => {
+                    debug!("Cannot receive kernel uevents ({e}), polling the AC state");
+                    poll_ac_state();
+                }
+            });
+
+        if let Err(e) = spawned {
+            warn!("Failed to start power supply event listener: {e}");
+        }
+    });
+}
+
+fn open_uevent_socket() -> nix::Result<OwnedFd> {
+    let socket = socket(
+        AddressFamily::Netlink,
+        SockType::Datagram,
+        SockFlag::SOCK_CLOEXEC,
+        SockProtocol::NetlinkKObjectUEvent,
+    )?;
+    bind(
+        socket.as_raw_fd(),
+        &NetlinkAddr::new(0, KERNEL_UEVENT_GROUP),
+    )?;
+    Ok(socket)
+}
+
+/// Read uevents until the socket fails. Each is a header followed by
+/// NUL-separated `KEY=value` fields, e.g. `SUBSYSTEM=power_supply`.
+fn listen_uevents(socket: &OwnedFd) {
+    let mut buf = vec![0; 8192];
+
+    loop {
+        let len = match recv(socket.as_raw_fd(), &mut buf, MsgFlags::empty()) {
+            Ok(len) => len,
+            Err(nix::Error::EINTR | nix::Error::ENOBUFS) => continue,
+            Err(e) => {
+                debug!("Failed to receive uevent: {e}");
+                return;
+            }
+        };
+
+        let mut fields = buf[..len].split(|&byte| byte == 0);
+        let header = fields.next().unwrap_or_default();
+        if fields.any(|field| field == b"SUBSYSTEM=power_supply") {
+            debug!("uevent: {}", String::from_utf8_lossy(header));
+            wake::request_wakeup("power supply event");
+        }
+    }
+}
+
+/// The `online` state of every AC adapter
+fn ac_state() -> Vec<(String, String)> {
+    let Ok(entries) = fs::read_dir(sysfs::path(POWER_SUPPLY_DIR)) else {
+        return Vec::new();
+    };
+
+    let mut state: Vec<(String, String)> = entries
+        .flatten()
+        .filter(|entry| {
+            fs::read_to_string(entry.path().join("type")).is_ok_and(|t| t.trim() == "Mains")
+        })
+        .filter_map(|entry| {
+            let online = fs::read_to_string(entry.path().join("online")).ok()?;
+            Some((
+                entry.file_name().to_string_lossy().into_owned(),
+                online.trim().to_string(),
+            ))
+        })
+        .collect();
+    state.sort();
+    state
+}
+
+fn poll_ac_state() {
+    let mut previous = ac_state();
+    loop {
+        thread::sleep(POLL_INTERVAL);
+
+        let current = ac_state();
+        if current != previous {
+            debug!("AC state changed: {previous:?} -> {current:?}");
+            wake::request_wakeup("power supply event");
+            previous = current;
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::monitor;
+use crate::util::{sysfs, wake};
+use log::{debug, info, warn};
+use std::fs;
+use std::sync::Once;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the suspend detector compares the boot and monotonic clocks
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Gap between the two clocks above which the system is considered to have been suspended
+const SUSPEND_THRESHOLD: Duration = Duration::from_secs(3);
+
+static LISTENER: Once = Once::new();
+
+/// Start a background thread that wakes the daemon right after the system
+/// resumes from suspend, since firmware often resets EPP and turbo settings.
+/// logind's `PrepareForSleep` signal is used when built with the `dbus`
+/// feature, with a clock-based detector as the fallback.
+pub fn spawn_listener() {
+    LISTENER.call_once(|| {
+        #[cfg(feature = "dbus")]
+        match listen_logind() {
+            Ok(()) => return,
+            Err(e) => {
+                warn!(
+                    "Cannot subscribe to logind sleep signals ({e}), detecting resume by polling"
+                );
+            }
+        }
+
+        let spawned = thread::Builder::new()
+            .name("resume-detector".to_string())
+            .spawn(detect_resume);
+        if let Err(e) = spawned {
+            warn!("Failed to start resume detector: {e}");
+        }
+    });
+}
+
+#[cfg(feature = "dbus")]
+fn listen_logind() -> zbus::Result<()> {
+    let connection = zbus::blocking::Connection::system()?;
+    let proxy = zbus::blocking::Proxy::new(
+        &connection,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+    )?;
+    let signals = proxy.receive_signal("PrepareForSleep")?;
+
+    thread::Builder::new()
+        .name("logind-sleep".to_string())
+        .spawn(move || {
+            // Keep the connection alive for as long as signals are received
+            let _connection = connection;
+            for message in signals {
+                match message.body().deserialize::<bool>() {
+                    Ok(true) => debug!("System is about to sleep"),
+                    Ok(false) => {
+                        info!("System resumed from sleep, re-applying settings");
+                        resumed();
+                    }
+                    Err(e) => warn!("Malformed PrepareForSleep signal: {e}"),
+                }
+            }
+            warn!("Stopped receiving logind sleep signals");
+        })?;
+
+    info!("Listening for suspend and resume through logind");
+    Ok(())
+}
+
+/// Hardware may have changed while asleep, so re-read it along with re-applying settings
+fn resumed() {
+    monitor::invalidate_static_system_info();
+    wake::request_wakeup("resume");
+}
+
+/// Seconds since boot including time spent suspended, from `/proc/uptime`
+fn boot_time_secs() -> Option<f64> {
+    fs::read_to_string(sysfs::path("/proc/uptime"))
+        .ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// The monotonic clock behind [`Instant`] stops while the system is suspended,
+/// but the boot clock keeps running. A jump in the boot clock that the
+/// monotonic clock did not see means the system has just resumed.
+fn detect_resume() {
+    let Some(mut last_boot_time) = boot_time_secs() else {
+        warn!("Cannot read /proc/uptime, settings will not be re-applied on resume");
+        return;
+    };
+    let mut last_check = Instant::now();
+    debug!("Detecting resume by comparing the boot and monotonic clocks");
+
+    loop {
+        thread::sleep(CHECK_INTERVAL);
+
+        let Some(boot_time) = boot_time_secs() else {
+            continue;
+        };
+        let now = Instant::now();
+
+        let boot_elapsed = Duration::from_secs_f64((boot_time - last_boot_time).max(0.0));
+        let suspended = boot_elapsed.saturating_sub(now.duration_since(last_check));
+        if suspended >= SUSPEND_THRESHOLD {
+            info!(
+                "System resumed after {}s asleep, re-applying settings",
+                suspended.as_secs()
+            );
+            resumed();
+        }
+
+        last_boot_time = boot_time;
+        last_check = now;
+    }
+}
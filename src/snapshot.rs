@@ -0,0 +1,258 @@
+use crate::battery::{self, THRESHOLD_PATTERNS};
+use crate::core::BoostControl;
+use crate::cpu;
+use crate::gpu;
+use crate::keyboard_backlight;
+use crate::util::error::ControlError;
+use crate::util::sysfs;
+use crate::vendor;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub type Result<T, E = ControlError> = std::result::Result<T, E>;
+
+pub const SNAPSHOT_DIR: &str = "/var/lib/superfreq/snapshots";
+
+const INTEL_PSTATE_STATUS_PATH: &str = "/sys/devices/system/cpu/intel_pstate/status";
+const PLATFORM_PROFILE_PATH: &str = "/sys/firmware/acpi/platform_profile";
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// Files of each cpufreq policy, in the order they are restored. The governor
+/// comes before EPP, which some governors refuse to change.
+const POLICY_FILES: &[&str] = &[
+    "scaling_governor",
+    "energy_performance_preference",
+    "energy_performance_bias",
+    "scaling_max_freq",
+    "scaling_min_freq",
+];
+
+/// A recorded value of a sysfs file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotValue {
+    pub path: PathBuf,
+    pub value: String,
+}
+
+/// The values of every setting superfreq manages at one point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub created_at: String,
+    /// In the order they are written back
+    pub values: Vec<SnapshotValue>,
+}
+
+/// Outcome of restoring a snapshot
+#[derive(Debug, Default)]
+pub struct RestoreReport {
+    pub restored: usize,
+    /// Files that no longer exist, e.g. of a battery that was removed
+    pub missing: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, ControlError)>,
+}
+
+/// Whether a snapshot name is safe to use as a file name
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn snapshot_path(name: &str) -> Result<PathBuf> {
+    if !is_valid_name(name) {
+        return Err(ControlError::InvalidValueError(format!(
+            "Invalid snapshot name '{name}', use letters, digits, '-' and '_'"
+        )));
+    }
+    Ok(Path::new(SNAPSHOT_DIR).join(format!("{name}.toml")))
+}
+
+/// The sysfs files superfreq writes, in the order they are restored: the
+/// intel_pstate mode first since it decides which governors exist, then the
+/// cpufreq policies, turbo, the platform profile, the vendor platform driver
+/// settings and the batteries
+pub fn managed_paths() -> Vec<PathBuf> {
+    let mut paths = vec![sysfs::path(INTEL_PSTATE_STATUS_PATH)];
+
+    let mut policies: Vec<PathBuf> = fs::read_dir(sysfs::path("/sys/devices/system/cpu/cpufreq"))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.file_name().to_string_lossy().starts_with("policy"))
+                .map(|entry| entry.path())
+                .collect()
+        })
+        .unwrap_or_default();
+    policies.sort();
+    for policy in &policies {
+        paths.extend(POLICY_FILES.iter().map(|file| policy.join(file)));
+    }
+
+    for control in [
+        BoostControl::IntelPstate,
+        BoostControl::AmdPstate,
+        BoostControl::AmdPstateEnableBoost,
+        BoostControl::Global,
+        BoostControl::PerPolicy,
+    ] {
+        paths.extend(cpu::boost_control_paths(control));
+    }
+
+    paths.push(sysfs::path(PLATFORM_PROFILE_PATH));
+    paths.extend(cpu::vendor_profile_backend().map(vendor::ProfileBackend::mode_path));
+    paths.extend(vendor::asus::attribute_paths());
+    paths.extend(gpu::intel_frequency_paths());
+    paths.extend(keyboard_backlight::brightness_paths());
+
+    let mut supplies: Vec<PathBuf> = fs::read_dir(sysfs::path(POWER_SUPPLY_DIR))
+        .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+        .unwrap_or_default();
+    supplies.sort();
+    for supply in &supplies {
+        // Driver-specific patterns reuse the file names of another pattern
+        for pattern in THRESHOLD_PATTERNS
+            .iter()
+            .filter(|pattern| pattern.driver_path.is_none())
+        {
+            // The stop threshold first, so a raised start threshold fits under it
+            paths.push(supply.join(pattern.stop_path));
+            paths.push(supply.join(pattern.start_path));
+        }
+        paths.push(supply.join("charge_behaviour"));
+    }
+
+    paths.retain(|path| path.exists());
+    paths
+}
+
+/// The value to write back for the current contents of a file. Files listing
+/// their choices mark the active one in brackets, e.g. `[auto] inhibit-charge`.
+fn restorable_value(path: &Path, content: &str) -> Option<String> {
+    if path.ends_with("charge_behaviour") {
+        return battery::parse_charge_behaviour(content).0;
+    }
+    Some(content.to_string())
+}
+
+/// Record the current value of every setting superfreq manages
+pub fn capture() -> Snapshot {
+    let values = managed_paths()
+        .into_iter()
+        .filter_map(|path| match sysfs::read_sysfs_value(&path) {
+            Ok(content) => Some(SnapshotValue {
+                value: restorable_value(&path, &content)?,
+                path,
+            }),
+            Err(e) => {
+                debug!("Not recording {}: {e}", path.display());
+                None
+            }
+        })
+        .collect();
+
+    Snapshot {
+        created_at: jiff::Timestamp::now().to_string(),
+        values,
+    }
+}
+
+/// Record the current settings under `name`, replacing an earlier snapshot
+pub fn save(name: &str) -> Result<Snapshot> {
+    let snapshot = capture();
+    write(name, &snapshot)?;
+    Ok(snapshot)
+}
+
+/// Store `snapshot` under `name`, replacing an earlier snapshot
+pub fn write(name: &str, snapshot: &Snapshot) -> Result<()> {
+    let path = snapshot_path(name)?;
+    let content = toml::to_string(snapshot)
+        .map_err(|e| ControlError::WriteError(format!("Failed to serialize snapshot: {e}")))?;
+
+    fs::create_dir_all(SNAPSHOT_DIR)?;
+    fs::write(&path, content)?;
+    Ok(())
+}
+
+pub fn exists(name: &str) -> bool {
+    snapshot_path(name).is_ok_and(|path| path.exists())
+}
+
+pub fn remove(name: &str) -> Result<()> {
+    fs::remove_file(snapshot_path(name)?)?;
+    Ok(())
+}
+
+pub fn load(name: &str) -> Result<Snapshot> {
+    let path = snapshot_path(name)?;
+    let content = fs::read_to_string(&path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => {
+            ControlError::InvalidValueError(format!("No snapshot named '{name}'"))
+        }
+        _ => ControlError::Io(e),
+    })?;
+    toml::from_str(&content)
+        .map_err(|e| ControlError::ReadError(format!("Invalid snapshot {}: {e}", path.display())))
+}
+
+/// Names of the saved snapshots
+pub fn list() -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(SNAPSHOT_DIR)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let name = entry.file_name();
+                    Some(name.to_str()?.strip_suffix(".toml")?.to_string())
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Write back the values of a snapshot. Values that fail are retried once
+/// the others are written, since some only fit after another changed, e.g. a
+/// minimum frequency above the current maximum.
+pub fn restore(snapshot: &Snapshot) -> RestoreReport {
+    let mut report = RestoreReport::default();
+    let mut failed = Vec::new();
+
+    for entry in &snapshot.values {
+        if !entry.path.exists() {
+            warn!("{} no longer exists, skipping it", entry.path.display());
+            report.missing.push(entry.path.clone());
+            continue;
+        }
+        if sysfs::read_sysfs_value(&entry.path)
+            .ok()
+            .and_then(|content| restorable_value(&entry.path, &content))
+            .is_some_and(|current| current == entry.value)
+        {
+            report.restored += 1;
+            continue;
+        }
+        match sysfs::write_sysfs_value(&entry.path, &entry.value) {
+            Ok(()) => report.restored += 1,
+            Err(e) => {
+                debug!(
+                    "Failed to restore {}, retrying later: {e}",
+                    entry.path.display()
+                );
+                failed.push(entry);
+            }
+        }
+    }
+
+    for entry in failed {
+        match sysfs::write_sysfs_value(&entry.path, &entry.value) {
+            Ok(()) => report.restored += 1,
+            Err(e) => report.failed.push((entry.path.clone(), e)),
+        }
+    }
+    report
+}
@@ -0,0 +1,233 @@
+use crate::config::{ThermalConfig, ThermalStep};
+use crate::core::SystemReport;
+use crate::util::notify;
+use log::{debug, info, warn};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Index of the thermal step currently in effect, if any
+static ACTIVE_STEP: Mutex<Option<usize>> = Mutex::new(None);
+
+/// Whether a thermal emergency is in progress
+static EMERGENCY: AtomicBool = AtomicBool::new(false);
+
+/// State of the thermal emergency measure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emergency {
+    /// No emergency is in progress
+    Inactive,
+    /// The CPU is critically hot, idle injection or the minimum frequency must be enforced
+    Active,
+    /// The CPU just recovered, the emergency measure must be lifted
+    Released,
+}
+
+/// Outcome of a thermal evaluation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThermalCap {
+    /// No step is active and none was released
+    None,
+    /// Cap the maximum frequency (MHz)
+    Capped(u32),
+    /// The last cap was just released, restore the uncapped maximum frequency
+    Released,
+}
+
+/// Select the step to apply at `temp`. Moving to a hotter step happens as soon as
+/// its temperature is reached, but a step is only left once the temperature has
+/// fallen `hysteresis` degrees below it.
+fn select_step(
+    steps: &[ThermalStep],
+    hysteresis: f32,
+    temp: f32,
+    current: Option<usize>,
+) -> Option<usize> {
+    let reached = steps.iter().rposition(|step| temp >= step.temp_celsius);
+
+    match current.filter(|&current| current < steps.len()) {
+        Some(current) if reached.is_none_or(|reached| reached < current) => steps[..=current]
+            .iter()
+            .rposition(|step| temp >= step.temp_celsius - hysteresis),
+        _ => reached,
+    }
+}
+
+/// Update the thermal state from the report and return the cap to apply
+pub fn evaluate(report: &SystemReport, config: &ThermalConfig) -> ThermalCap {
+    let mut active = ACTIVE_STEP
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+    let Some(temp) = report.cpu_global.average_temperature_celsius else {
+        // Without a temperature reading, keep whatever cap is in effect
+        return active
+            .and_then(|index| config.steps.get(index))
+            .map_or(ThermalCap::None, |step| {
+                ThermalCap::Capped(step.max_freq_mhz)
+            });
+    };
+
+    let previous = *active;
+    let selected = select_step(&config.steps, config.hysteresis_celsius, temp, previous);
+    *active = selected;
+
+    match (previous, selected) {
+        (_, Some(index)) => {
+            let step = config.steps[index];
+            if previous != selected {
+                info!(
+                    temp_celsius = temp, max_freq_mhz = step.max_freq_mhz;
+                    "Thermal: {temp:.1}°C, capping frequency at {} MHz (step {}°C)",
+                    step.max_freq_mhz, step.temp_celsius
+                );
+                notify::event(
+                    notify::Event::Thermal,
+                    "CPU frequency capped",
+                    &format!(
+                        "The CPU reached {temp:.1}°C, limiting it to {} MHz",
+                        step.max_freq_mhz
+                    ),
+                );
+            }
+            ThermalCap::Capped(step.max_freq_mhz)
+        }
+        (Some(_), None) => {
+            info!("Thermal: cooled down to {temp:.1}°C, releasing frequency cap");
+            ThermalCap::Released
+        }
+        (None, None) => {
+            debug!("Thermal: {temp:.1}°C, no frequency cap");
+            ThermalCap::None
+        }
+    }
+}
+
+/// Update the thermal emergency state from the report
+pub fn evaluate_emergency(report: &SystemReport, config: &ThermalConfig) -> Emergency {
+    let active = EMERGENCY.load(Ordering::Acquire);
+
+    let Some(critical) = config.emergency_celsius else {
+        // The emergency threshold was removed from the configuration, lift any measure
+        EMERGENCY.store(false, Ordering::Release);
+        return if active {
+            Emergency::Released
+        } else {
+            Emergency::Inactive
+        };
+    };
+
+    // Without a temperature reading, keep whatever state we are in
+    let Some(temp) = report.cpu_global.average_temperature_celsius else {
+        return if active {
+            Emergency::Active
+        } else {
+            Emergency::Inactive
+        };
+    };
+
+    match (active, temp) {
+        (false, temp) if temp >= critical => {
+            warn!(
+                temp_celsius = temp;
+                "Thermal emergency: {temp:.1}°C >= {critical:.1}°C, throttling the CPU"
+            );
+            notify::event(
+                notify::Event::Thermal,
+                "Thermal emergency",
+                &format!("The CPU reached {temp:.1}°C and is being throttled"),
+            );
+            EMERGENCY.store(true, Ordering::Release);
+            Emergency::Active
+        }
+        (true, temp) if temp < critical - config.hysteresis_celsius => {
+            info!("Thermal emergency over: cooled down to {temp:.1}°C");
+            EMERGENCY.store(false, Ordering::Release);
+            Emergency::Released
+        }
+        (true, _) => Emergency::Active,
+        (false, _) => Emergency::Inactive,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEPS: [ThermalStep; 3] = [
+        ThermalStep {
+            temp_celsius: 70.0,
+            max_freq_mhz: 3000,
+        },
+        ThermalStep {
+            temp_celsius: 80.0,
+            max_freq_mhz: 2400,
+        },
+        ThermalStep {
+            temp_celsius: 90.0,
+            max_freq_mhz: 1600,
+        },
+    ];
+
+    const HYSTERESIS: f32 = 5.0;
+
+    fn check(cases: &[(Option<usize>, f32, Option<usize>)]) {
+        for &(current, temp, expected) in cases {
+            assert_eq!(
+                select_step(&STEPS, HYSTERESIS, temp, current),
+                expected,
+                "from {current:?} at {temp}°C"
+            );
+        }
+    }
+
+    #[test]
+    fn rising_temperature_enters_steps_at_their_threshold() {
+        check(&[
+            (None, 69.9, None),
+            (None, 70.0, Some(0)),
+            (None, 79.9, Some(0)),
+            (None, 80.0, Some(1)),
+            (None, 95.0, Some(2)),
+            (Some(0), 79.9, Some(0)),
+            (Some(0), 80.0, Some(1)),
+            (Some(0), 90.0, Some(2)),
+            (Some(1), 89.9, Some(1)),
+            (Some(1), 90.0, Some(2)),
+        ]);
+    }
+
+    #[test]
+    fn falling_temperature_leaves_steps_below_the_hysteresis() {
+        check(&[
+            (Some(2), 89.9, Some(2)),
+            (Some(2), 85.0, Some(2)),
+            (Some(2), 84.9, Some(1)),
+            (Some(1), 79.9, Some(1)),
+            (Some(1), 75.0, Some(1)),
+            (Some(1), 74.9, Some(0)),
+            (Some(0), 69.9, Some(0)),
+            (Some(0), 65.0, Some(0)),
+            (Some(0), 64.9, None),
+            // Several steps at once
+            (Some(2), 70.0, Some(0)),
+            (Some(2), 60.0, None),
+        ]);
+    }
+
+    #[test]
+    fn step_outside_the_table_is_forgotten() {
+        // The table shrank on reload
+        check(&[(Some(5), 75.0, Some(0)), (Some(5), 60.0, None)]);
+    }
+
+    #[test]
+    fn no_hysteresis_follows_the_thresholds() {
+        for (current, temp, expected) in [
+            (Some(1), 79.9, Some(0)),
+            (Some(0), 69.9, None),
+            (Some(0), 80.0, Some(1)),
+        ] {
+            assert_eq!(select_step(&STEPS, 0.0, temp, current), expected);
+        }
+    }
+}
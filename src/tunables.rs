@@ -0,0 +1,241 @@
+use crate::util::sysfs;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Power tunables that powertop checks, each backed by one or more files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TunableKind {
+    /// The NMI watchdog wakes every CPU periodically
+    NmiWatchdog,
+    /// How often dirty pages are written back to disk
+    Writeback,
+    /// Seconds of silence after which HD audio codecs are powered down
+    AudioPowerSave,
+    /// Whether the HD audio controller is powered down with the codecs
+    AudioControllerPowerSave,
+    /// Link power management of SATA hosts
+    SataAlpm,
+    /// Runtime power management of PCI devices
+    RuntimePm,
+    /// Autosuspend of USB devices
+    UsbAutosuspend,
+}
+
+impl TunableKind {
+    pub const fn title(self) -> &'static str {
+        match self {
+            Self::NmiWatchdog => "NMI watchdog",
+            Self::Writeback => "VM writeback timeout",
+            Self::AudioPowerSave => "Audio codec power save",
+            Self::AudioControllerPowerSave => "Audio controller power save",
+            Self::SataAlpm => "SATA link power management",
+            Self::RuntimePm => "Runtime PM",
+            Self::UsbAutosuspend => "USB autosuspend",
+        }
+    }
+
+    /// The power-saving value powertop recommends
+    pub const fn good_value(self) -> &'static str {
+        match self {
+            Self::NmiWatchdog => "0",
+            Self::Writeback => "1500",
+            Self::AudioPowerSave => "1",
+            Self::AudioControllerPowerSave => "Y",
+            Self::SataAlpm => "med_power_with_dipm",
+            Self::RuntimePm | Self::UsbAutosuspend => "auto",
+        }
+    }
+
+    /// Whether `value` saves power, which for some tunables covers more than
+    /// the recommended value
+    fn is_good(self, value: &str) -> bool {
+        match self {
+            Self::Writeback => value
+                .parse::<u32>()
+                .is_ok_and(|centisecs| centisecs >= 1500),
+            Self::AudioPowerSave => value.parse::<u32>().is_ok_and(|secs| secs > 0),
+            Self::SataAlpm => matches!(value, "med_power_with_dipm" | "min_power"),
+            _ => value == self.good_value(),
+        }
+    }
+}
+
+/// A file of a power tunable and its current value
+#[derive(Debug, Clone, Serialize)]
+pub struct Tunable {
+    pub kind: TunableKind,
+    /// What the file controls, e.g. a USB device
+    pub name: String,
+    pub path: PathBuf,
+    /// `None` if the file could not be read
+    pub value: Option<String>,
+    /// Why changing the tunable is not always safe, which leaves it out of
+    /// `superfreq tune --auto`
+    pub caveat: Option<&'static str>,
+}
+
+impl Tunable {
+    fn new(kind: TunableKind, name: String, path: PathBuf) -> Self {
+        let value = sysfs::read_sysfs_value(&path).ok();
+        Self {
+            kind,
+            name,
+            path,
+            value,
+            caveat: None,
+        }
+    }
+
+    /// Whether the tunable is set to save power, or `None` if unknown
+    pub fn is_good(&self) -> Option<bool> {
+        self.value.as_deref().map(|value| self.kind.is_good(value))
+    }
+}
+
+/// Descriptions of common PCI device classes, by the class and subclass bytes
+const PCI_CLASSES: &[(&str, &str)] = &[
+    ("0x0106", "SATA controller"),
+    ("0x0108", "NVMe controller"),
+    ("0x0200", "Ethernet controller"),
+    ("0x0280", "Network controller"),
+    ("0x0300", "VGA controller"),
+    ("0x0380", "Display controller"),
+    ("0x0403", "Audio device"),
+    ("0x0600", "Host bridge"),
+    ("0x0601", "ISA bridge"),
+    ("0x0604", "PCI bridge"),
+    ("0x0c03", "USB controller"),
+    ("0x0c05", "SMBus controller"),
+];
+
+fn pci_device_name(device: &Path) -> String {
+    let address = device
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let description = sysfs::read_sysfs_value(device.join("class"))
+        .ok()
+        .and_then(|class| {
+            PCI_CLASSES
+                .iter()
+                .find(|(prefix, _)| class.starts_with(prefix))
+                .map(|(_, description)| *description)
+        })
+        .unwrap_or("PCI device");
+    format!("{description} {address}")
+}
+
+fn usb_device_name(device: &Path) -> String {
+    let id = device
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let product: Vec<String> = ["manufacturer", "product"]
+        .iter()
+        .filter_map(|file| sysfs::read_sysfs_value(device.join(file)).ok())
+        .collect();
+    if product.is_empty() {
+        format!("USB device {id}")
+    } else {
+        format!("{} [{id}]", product.join(" "))
+    }
+}
+
+/// Whether a USB device has a HID interface, e.g. a keyboard or mouse
+fn is_usb_input_device(device: &Path) -> bool {
+    let Some(id) = device
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+    else {
+        return false;
+    };
+    fs::read_dir(device)
+        .map(|entries| {
+            entries.flatten().any(|entry| {
+                entry
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with(&format!("{id}:"))
+                    && sysfs::read_sysfs_value(entry.path().join("bInterfaceClass"))
+                        .is_ok_and(|class| class == "03")
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// Entries of a directory, sorted by name
+fn sorted_entries(dir: &str) -> Vec<PathBuf> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(sysfs::path(dir))
+        .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+        .unwrap_or_default();
+    entries.sort();
+    entries
+}
+
+/// Find the power tunables of this system and read their values
+pub fn discover() -> Vec<Tunable> {
+    let mut tunables = Vec::new();
+
+    for (kind, path) in [
+        (TunableKind::NmiWatchdog, "/proc/sys/kernel/nmi_watchdog"),
+        (
+            TunableKind::Writeback,
+            "/proc/sys/vm/dirty_writeback_centisecs",
+        ),
+        (
+            TunableKind::AudioPowerSave,
+            "/sys/module/snd_hda_intel/parameters/power_save",
+        ),
+        (
+            TunableKind::AudioControllerPowerSave,
+            "/sys/module/snd_hda_intel/parameters/power_save_controller",
+        ),
+    ] {
+        let path = sysfs::path(path);
+        if path.exists() {
+            tunables.push(Tunable::new(kind, kind.title().to_string(), path));
+        }
+    }
+
+    for host in sorted_entries("/sys/class/scsi_host") {
+        let path = host.join("link_power_management_policy");
+        if path.exists() {
+            let name = host
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            tunables.push(Tunable::new(TunableKind::SataAlpm, name, path));
+        }
+    }
+
+    for device in sorted_entries("/sys/bus/pci/devices") {
+        let path = device.join("power/control");
+        if path.exists() {
+            tunables.push(Tunable::new(
+                TunableKind::RuntimePm,
+                pci_device_name(&device),
+                path,
+            ));
+        }
+    }
+
+    for device in sorted_entries("/sys/bus/usb/devices") {
+        // Interfaces (e.g. `1-2:1.0`) share the power settings of their device
+        let is_interface = device
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().contains(':'));
+        let path = device.join("power/control");
+        if !is_interface && path.exists() {
+            let mut tunable =
+                Tunable::new(TunableKind::UsbAutosuspend, usb_device_name(&device), path);
+            if is_usb_input_device(&device) {
+                tunable.caveat = Some("input devices can miss keystrokes or lag while suspended");
+            }
+            tunables.push(tunable);
+        }
+    }
+
+    tunables
+}
@@ -0,0 +1,111 @@
+use crate::battery;
+use crate::config::AppConfig;
+use crate::core::BatteryInfo;
+use crate::util::sysfs;
+use log::debug;
+use std::fs;
+use std::sync::{Mutex, PoisonError};
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::OwnedObjectPath;
+
+const UPOWER_NAME: &str = "org.freedesktop.UPower";
+const UPOWER_PATH: &str = "/org/freedesktop/UPower";
+const DEVICE_INTERFACE: &str = "org.freedesktop.UPower.Device";
+
+/// `Type` of a UPower device that is a battery
+const DEVICE_TYPE_BATTERY: u32 = 2;
+
+/// System bus connection, kept across polls
+static CONNECTION: Mutex<Option<Connection>> = Mutex::new(None);
+
+fn connection() -> zbus::Result<Connection> {
+    let mut connection = CONNECTION.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(connection) = connection.as_ref() {
+        return Ok(connection.clone());
+    }
+
+    let new = Connection::system()?;
+    *connection = Some(new.clone());
+    Ok(new)
+}
+
+/// Map a UPower device `State` onto the strings used by sysfs `status`
+fn charging_state(state: u32) -> Option<&'static str> {
+    match state {
+        1 => Some("Charging"),
+        2 | 3 => Some("Discharging"),
+        4 => Some("Full"),
+        5 | 6 => Some("Not charging"),
+        _ => None,
+    }
+}
+
+fn non_empty(value: String) -> Option<String> {
+    (!value.is_empty()).then_some(value)
+}
+
+/// Read the system batteries from UPower. Peripheral batteries (mice,
+/// keyboards, ...) are left out, as UPower tells them apart reliably.
+pub fn get_battery_info(config: &AppConfig) -> zbus::Result<Vec<BatteryInfo>> {
+    let connection = connection()?;
+    let upower = Proxy::new(&connection, UPOWER_NAME, UPOWER_PATH, UPOWER_NAME)?;
+
+    let on_battery: bool = upower.get_property("OnBattery")?;
+    let devices: Vec<OwnedObjectPath> = upower.call("EnumerateDevices", &())?;
+    let ignored_supplies = config.ignored_power_supplies.clone().unwrap_or_default();
+
+    let mut batteries = Vec::new();
+    for path in devices {
+        let device = Proxy::new(&connection, UPOWER_NAME, path.as_ref(), DEVICE_INTERFACE)?;
+
+        if device.get_property::<u32>("Type")? != DEVICE_TYPE_BATTERY {
+            continue;
+        }
+        let name: String = device.get_property("NativePath")?;
+        if !device.get_property::<bool>("PowerSupply")? {
+            debug!("Skipping peripheral battery: {name}");
+            continue;
+        }
+        if ignored_supplies.contains(&name) || !device.get_property::<bool>("IsPresent")? {
+            continue;
+        }
+
+        let energy_full_wh = device.get_property::<f64>("EnergyFull")? as f32;
+        let energy_full_design_wh = device.get_property::<f64>("EnergyFullDesign")? as f32;
+        let health_percent = device.get_property::<f64>("Capacity")? as f32;
+        let cycle_count = device.get_property::<i32>("ChargeCycles").unwrap_or(-1);
+
+        // UPower does not expose the charge controls superfreq manages, so read
+        // them from sysfs, which is what they are written through
+        let sysfs_path = sysfs::path("/sys/class/power_supply").join(&name);
+        let read_u8 = |file: &str| {
+            fs::read_to_string(sysfs_path.join(file))
+                .ok()
+                .and_then(|value| value.trim().parse::<u8>().ok())
+        };
+        let charge_behaviour = fs::read_to_string(sysfs_path.join("charge_behaviour"))
+            .ok()
+            .and_then(|content| battery::parse_charge_behaviour(content.trim()).0);
+
+        batteries.push(BatteryInfo {
+            ac_connected: !on_battery,
+            charging_state: charging_state(device.get_property("State")?).map(str::to_string),
+            capacity_percent: Some(device.get_property::<f64>("Percentage")?.round() as u8),
+            power_rate_watts: Some(device.get_property::<f64>("EnergyRate")? as f32),
+            charge_start_threshold: read_u8("charge_control_start_threshold"),
+            charge_stop_threshold: read_u8("charge_control_end_threshold"),
+            charge_behaviour,
+            cycle_count: u32::try_from(cycle_count).ok().filter(|&cycles| cycles > 0),
+            energy_full_wh: (energy_full_wh > 0.0).then_some(energy_full_wh),
+            energy_full_design_wh: (energy_full_design_wh > 0.0).then_some(energy_full_design_wh),
+            health_percent: (health_percent > 0.0).then_some(health_percent),
+            voltage_volts: Some(device.get_property::<f64>("Voltage")? as f32)
+                .filter(|&volts| volts > 0.0),
+            manufacturer: non_empty(device.get_property("Vendor")?),
+            model_name: non_empty(device.get_property("Model")?),
+            name,
+        });
+    }
+
+    Ok(batteries)
+}
@@ -0,0 +1,169 @@
+use crate::config::UserspaceSettings;
+use crate::core::{CpuCoreInfo, SystemReport};
+use crate::util::clock::{Clock, SystemClock};
+use log::debug;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Name of the governor whose frequency the daemon sets itself
+pub const USERSPACE_GOVERNOR: &str = "userspace";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CoreState {
+    /// Frequency last requested for the core
+    target_mhz: u32,
+    /// Direction the load is pushing in, and since when
+    pending: Option<(Direction, Instant)>,
+}
+
+/// Per-core state of the emulated governor, keyed by core ID
+static CORES: Mutex<BTreeMap<u32, CoreState>> = Mutex::new(BTreeMap::new());
+
+/// Update the emulated governor from the report and return the cores whose
+/// target frequency changed, along with the new target in MHz
+pub fn evaluate(report: &SystemReport, settings: &UserspaceSettings) -> Vec<(u32, u32)> {
+    let mut cores = CORES
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    step(&mut cores, &report.cpu_cores, settings, SystemClock.now())
+}
+
+/// [`evaluate`] with the per-core state and the current time passed in
+fn step(
+    cores: &mut BTreeMap<u32, CoreState>,
+    cpu_cores: &[CpuCoreInfo],
+    settings: &UserspaceSettings,
+    now: Instant,
+) -> Vec<(u32, u32)> {
+    let mut changes = Vec::new();
+
+    for core in cpu_cores {
+        let (Some(usage), Some(min_mhz), Some(max_mhz)) = (
+            core.usage_percent,
+            core.min_frequency_mhz,
+            core.max_frequency_mhz,
+        ) else {
+            continue;
+        };
+
+        let state = cores.entry(core.core_id).or_insert_with(|| CoreState {
+            target_mhz: core.current_frequency_mhz.unwrap_or(min_mhz),
+            pending: None,
+        });
+
+        let (direction, delay) = if usage >= settings.load_threshold_up {
+            (Direction::Up, Duration::from_secs(settings.up_delay_sec))
+        } else if usage <= settings.load_threshold_down {
+            (
+                Direction::Down,
+                Duration::from_secs(settings.down_delay_sec),
+            )
+        } else {
+            state.pending = None;
+            continue;
+        };
+
+        let since = match state.pending {
+            Some((pending, since)) if pending == direction => since,
+            _ => now,
+        };
+        if now.duration_since(since) < delay {
+            state.pending = Some((direction, since));
+            continue;
+        }
+        // Restart the delay so that consecutive steps are spaced out as well
+        state.pending = Some((direction, now));
+
+        let target_mhz = match direction {
+            Direction::Up => state.target_mhz.saturating_add(settings.step_up_mhz),
+            Direction::Down => state.target_mhz.saturating_sub(settings.step_down_mhz),
+        }
+        .clamp(min_mhz, max_mhz.max(min_mhz));
+
+        if target_mhz != state.target_mhz {
+            debug!(
+                "Userspace governor: CPU {} at {usage:.1}%, {} MHz -> {target_mhz} MHz",
+                core.core_id, state.target_mhz
+            );
+            state.target_mhz = target_mhz;
+            changes.push((core.core_id, target_mhz));
+        }
+    }
+
+    changes
+}
+
+/// Forget the per-core state once the `userspace` governor is no longer selected
+pub fn reset() {
+    CORES
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::clock::ManualClock;
+
+    fn core(usage: f32) -> CpuCoreInfo {
+        CpuCoreInfo {
+            core_id: 0,
+            current_frequency_mhz: Some(1000),
+            min_frequency_mhz: Some(400),
+            max_frequency_mhz: Some(2000),
+            usage_percent: Some(usage),
+            temperature_celsius: None,
+            core_type: None,
+        }
+    }
+
+    #[test]
+    fn steps_up_after_delay_and_spaces_out_steps() {
+        let clock = ManualClock::new();
+        let settings = UserspaceSettings {
+            up_delay_sec: 2,
+            ..UserspaceSettings::default()
+        };
+        let mut cores = BTreeMap::new();
+        let busy = [core(90.0)];
+
+        assert!(step(&mut cores, &busy, &settings, clock.now()).is_empty());
+        clock.advance(Duration::from_secs(1));
+        assert!(step(&mut cores, &busy, &settings, clock.now()).is_empty());
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(step(&mut cores, &busy, &settings, clock.now()), [(0, 1400)]);
+
+        // The next step waits for the delay again
+        assert!(step(&mut cores, &busy, &settings, clock.now()).is_empty());
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(step(&mut cores, &busy, &settings, clock.now()), [(0, 1800)]);
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(step(&mut cores, &busy, &settings, clock.now()), [(0, 2000)]);
+    }
+
+    #[test]
+    fn load_in_between_thresholds_resets_the_delay() {
+        let clock = ManualClock::new();
+        let settings = UserspaceSettings::default();
+        let mut cores = BTreeMap::new();
+
+        assert!(step(&mut cores, &[core(10.0)], &settings, clock.now()).is_empty());
+        clock.advance(Duration::from_secs(2));
+        assert!(step(&mut cores, &[core(50.0)], &settings, clock.now()).is_empty());
+        clock.advance(Duration::from_secs(2));
+        assert!(step(&mut cores, &[core(10.0)], &settings, clock.now()).is_empty());
+        clock.advance(Duration::from_secs(settings.down_delay_sec));
+        assert_eq!(
+            step(&mut cores, &[core(10.0)], &settings, clock.now()),
+            [(0, 800)]
+        );
+    }
+}
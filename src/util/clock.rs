@@ -0,0 +1,100 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Source of time for the daemon's timing logic (adaptive intervals, dwell
+/// times, hysteresis), so it can be driven by a controlled clock
+pub trait Clock {
+    /// Current monotonic time
+    fn now(&self) -> Instant;
+
+    /// Current wall-clock time
+    fn system_time(&self) -> SystemTime;
+
+    /// Time elapsed since `earlier`
+    fn elapsed(&self, earlier: Instant) -> Duration {
+        self.now().saturating_duration_since(earlier)
+    }
+}
+
+/// The real system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that only moves when advanced, for deterministic tests of timing
+/// logic. Clones share their time, so a test can keep one and hand another to
+/// the code under test.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    start: Instant,
+    start_system_time: SystemTime,
+    offset: Rc<Cell<Duration>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            start_system_time: SystemTime::UNIX_EPOCH,
+            offset: Rc::new(Cell::new(Duration::ZERO)),
+        }
+    }
+
+    /// Move the clock forward
+    pub fn advance(&self, duration: Duration) {
+        self.offset.set(self.offset.get() + duration);
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + self.offset.get()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.start_system_time + self.offset.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_clock_only_moves_when_advanced() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        assert_eq!(clock.elapsed(start), Duration::ZERO);
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.elapsed(start), Duration::from_secs(90));
+        assert_eq!(
+            clock.system_time(),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(90)
+        );
+    }
+
+    #[test]
+    fn manual_clock_clones_share_time() {
+        let clock = ManualClock::new();
+        let handle = clock.clone();
+        handle.advance(Duration::from_secs(5));
+        assert_eq!(clock.now(), handle.now());
+    }
+}
@@ -0,0 +1,18 @@
+use std::time::Duration;
+
+/// Parse a duration such as `30s`, `15m`, `1h` or `7d`
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount.parse().ok()?;
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return None,
+    };
+
+    Some(Duration::from_secs(amount.saturating_mul(multiplier)))
+}
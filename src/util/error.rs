@@ -55,6 +55,47 @@ pub enum EngineError {
 
     #[error("Configuration error: {0}")]
     ConfigurationError(String),
+
+    /// A setting that failed before and is backed off from, reported without
+    /// the full error so it does not flood the log
+    #[error("Setting {feature} is still failing after {failures} attempts, retrying in {}s", .retry_in.as_secs())]
+    StillFailing {
+        feature: String,
+        failures: u32,
+        retry_in: std::time::Duration,
+    },
+
+    /// Several settings failed to apply in the same cycle
+    #[error("{}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    Several(Vec<EngineError>),
+}
+
+impl EngineError {
+    /// Whether every setting in the error failed before, so it was already
+    /// reported in full
+    pub fn is_still_failing(&self) -> bool {
+        match self {
+            Self::StillFailing { .. } => true,
+            Self::Several(errors) => errors.iter().all(Self::is_still_failing),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(feature = "history")]
+#[derive(Debug, thiserror::Error)]
+pub enum HistoryError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("History database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[error("Failed to serialize report: {0}")]
+    Serialize(#[from] serde_json::Error),
+
+    #[error("Invalid duration '{0}', expected a number followed by s, m, h or d")]
+    InvalidDuration(String),
 }
 
 // A unified error type for the entire application
@@ -72,6 +113,10 @@ pub enum AppError {
     #[error("{0}")]
     Config(#[from] crate::config::ConfigError),
 
+    #[cfg(feature = "history")]
+    #[error("{0}")]
+    History(#[from] HistoryError),
+
     #[error("{0}")]
     Generic(String),
 
@@ -0,0 +1,29 @@
+/// Optional cargo features and whether this binary was built with each of them
+pub const FEATURES: &[(&str, bool)] = &[
+    ("cros_ec", cfg!(feature = "cros_ec")),
+    ("dbus", cfg!(feature = "dbus")),
+    ("http", cfg!(feature = "http")),
+    ("history", cfg!(feature = "history")),
+    ("mqtt", cfg!(feature = "mqtt")),
+    ("msr", cfg!(feature = "msr")),
+    ("nvml", cfg!(feature = "nvml")),
+];
+
+/// Names of the optional features this binary was built with
+pub fn enabled_features() -> Vec<&'static str> {
+    FEATURES
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect()
+}
+
+/// Human-readable list of enabled features, or "none"
+pub fn enabled_features_summary() -> String {
+    let features = enabled_features();
+    if features.is_empty() {
+        "none".to_string()
+    } else {
+        features.join(", ")
+    }
+}
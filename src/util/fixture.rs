@@ -0,0 +1,119 @@
+use crate::util::sysfs;
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Distinguishes the fixture trees of one test run
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A temporary directory standing in for `/`, which `/sys` and `/proc` paths
+/// resolve under for as long as it lives
+pub struct Fixture {
+    dir: PathBuf,
+    _root: sysfs::ScopedRoot,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "superfreq-fixture-{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("create fixture directory");
+
+        let root = sysfs::scoped_root(dir.clone());
+        Self { dir, _root: root }
+    }
+
+    /// The location of an absolute path in the tree
+    pub fn path(&self, path: &str) -> PathBuf {
+        self.dir.join(path.trim_start_matches('/'))
+    }
+
+    /// Create a file, and the directories leading to it
+    pub fn write(&self, path: &str, contents: &str) {
+        let path = self.path(path);
+        fs::create_dir_all(path.parent().unwrap_or(&self.dir)).expect("create fixture directory");
+        fs::write(&path, contents).expect("write fixture file");
+    }
+
+    /// The contents of a file, without the trailing newline
+    pub fn read(&self, path: &str) -> String {
+        fs::read_to_string(self.path(path))
+            .expect("read fixture file")
+            .trim_end()
+            .to_string()
+    }
+
+    /// Add `count` CPUs with one cpufreq policy each, linked from
+    /// `cpuN/cpufreq` as on real systems, and their times in `/proc/stat`
+    pub fn cpus(&self, count: u32) {
+        let mut stat = String::from("cpu  400 0 200 4000 0 0 0 0 0 0\n");
+        for cpu in 0..count {
+            let policy = format!("/sys/devices/system/cpu/cpufreq/policy{cpu}");
+            for (file, value) in [
+                ("related_cpus", cpu.to_string()),
+                ("affected_cpus", cpu.to_string()),
+                ("scaling_governor", "powersave".to_string()),
+                (
+                    "scaling_available_governors",
+                    "performance powersave".to_string(),
+                ),
+                ("scaling_cur_freq", "1800000".to_string()),
+                ("scaling_min_freq", "400000".to_string()),
+                ("scaling_max_freq", "3600000".to_string()),
+                ("cpuinfo_min_freq", "400000".to_string()),
+                ("cpuinfo_max_freq", "3600000".to_string()),
+                (
+                    "energy_performance_preference",
+                    "balance_performance".to_string(),
+                ),
+                (
+                    "energy_performance_available_preferences",
+                    "default performance balance_performance balance_power power".to_string(),
+                ),
+            ] {
+                self.write(&format!("{policy}/{file}"), &format!("{value}\n"));
+            }
+
+            let cpu_dir = self.path(&format!("/sys/devices/system/cpu/cpu{cpu}"));
+            self.write(
+                &format!("/sys/devices/system/cpu/cpu{cpu}/topology/core_id"),
+                &format!("{cpu}\n"),
+            );
+            self.write(
+                &format!("/sys/devices/system/cpu/cpu{cpu}/topology/physical_package_id"),
+                "0\n",
+            );
+            symlink(
+                Path::new("../cpufreq").join(format!("policy{cpu}")),
+                cpu_dir.join("cpufreq"),
+            )
+            .expect("link cpufreq policy");
+
+            stat.push_str(&format!("cpu{cpu} 100 0 50 1000 0 0 0 0 0 0\n"));
+        }
+
+        self.write("/proc/stat", &stat);
+        self.write("/proc/loadavg", "0.50 0.40 0.30 1/100 1000\n");
+        self.write(
+            "/sys/devices/system/cpu/online",
+            &format!("0-{}\n", count.saturating_sub(1)),
+        );
+    }
+}
+
+impl Default for Fixture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::config::LogFormat;
+use env_logger::fmt::Formatter;
+use log::Record;
+use log::kv::{self, VisitSource};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
+
+/// Whether records are written as JSON instead of text
+static JSON_FORMAT: AtomicBool = AtomicBool::new(false);
+
+/// Select the format of all following log records
+pub fn set_format(format: LogFormat) {
+    JSON_FORMAT.store(format == LogFormat::Json, Ordering::Relaxed);
+}
+
+/// Collects the key-value pairs of a record as JSON fields
+struct JsonFields(serde_json::Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(value) = value.to_bool() {
+            value.into()
+        } else if let Some(value) = value.to_i64() {
+            value.into()
+        } else if let Some(value) = value.to_f64() {
+            value.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.insert(key.to_string(), value);
+        Ok(())
+    }
+}
+
+/// Format a record as `[LEVEL module] message`, or as a JSON object with the
+/// timestamp, level, module, message and any structured fields
+pub fn format_record(buf: &mut Formatter, record: &Record<'_>) -> io::Result<()> {
+    if !JSON_FORMAT.load(Ordering::Relaxed) {
+        let style = buf.default_level_style(record.level());
+        return writeln!(
+            buf,
+            "[{style}{:<5}{style:#} {}] {}",
+            record.level(),
+            record.target(),
+            record.args()
+        );
+    }
+
+    let mut fields = JsonFields(serde_json::Map::new());
+    // A field that cannot be visited is not worth losing the record over
+    let _ = record.key_values().visit(&mut fields);
+
+    let mut object = serde_json::json!({
+        "timestamp": jiff::Timestamp::now().to_string(),
+        "level": record.level().as_str(),
+        "module": record.target(),
+        "message": record.args().to_string(),
+    });
+    if !fields.0.is_empty() {
+        object["fields"] = fields.0.into();
+    }
+
+    writeln!(buf, "{object}")
+}
+
+/// When the log file is rotated
+#[derive(Debug, Clone, Copy)]
+pub struct Rotation {
+    pub max_size_bytes: u64,
+    pub max_age: Option<Duration>,
+    /// Number of rotated files (`<path>.1`, `<path>.2`, ...) to keep
+    pub max_files: u32,
+}
+
+/// A log file that is rotated by size and age
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    created: SystemTime,
+    rotation: Rotation,
+}
+
+impl RotatingFile {
+    fn open(path: &Path, rotation: Rotation) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let metadata = file.metadata()?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            size: metadata.len(),
+            // Not every filesystem records the creation time
+            created: metadata
+                .created()
+                .or_else(|_| metadata.modified())
+                .unwrap_or_else(|_| SystemTime::now()),
+            rotation,
+        })
+    }
+
+    fn needs_rotation(&self, incoming: usize) -> bool {
+        let too_large = self.size > 0
+            && self.size.saturating_add(incoming as u64) > self.rotation.max_size_bytes;
+        let too_old = self.rotation.max_age.is_some_and(|max_age| {
+            SystemTime::now()
+                .duration_since(self.created)
+                .is_ok_and(|age| age > max_age)
+        });
+
+        too_large || too_old
+    }
+
+    fn rotated_path(&self, index: u32) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        PathBuf::from(path)
+    }
+
+    /// Shift `<path>.N` to `<path>.N+1`, dropping the oldest, and start a new file
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        if self.rotation.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for index in (1..self.rotation.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        self.created = SystemTime::now();
+        Ok(())
+    }
+
+    fn write_record(&mut self, buf: &[u8]) -> io::Result<()> {
+        if self.needs_rotation(buf.len()) {
+            self.rotate()?;
+        }
+
+        // The logger leaves out timestamps as journald adds its own, but a
+        // plain file needs them. JSON records carry their own.
+        let mut record = if JSON_FORMAT.load(Ordering::Relaxed) {
+            Vec::new()
+        } else {
+            jiff::Zoned::now()
+                .strftime("%Y-%m-%d %H:%M:%S ")
+                .to_string()
+                .into_bytes()
+        };
+        record.extend(strip_ansi(buf));
+        self.file.write_all(&record)?;
+        self.size += record.len() as u64;
+        Ok(())
+    }
+}
+
+/// Remove terminal color codes, which the logger adds when stderr is a terminal
+fn strip_ansi(buf: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(buf.len());
+    let mut bytes = buf.iter().copied();
+
+    while let Some(byte) = bytes.next() {
+        if byte == 0x1b {
+            // Skip `ESC [ ... <final byte>`
+            for byte in bytes.by_ref() {
+                if byte.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            output.push(byte);
+        }
+    }
+
+    output
+}
+
+/// The log file records go to, once the daemon has set one up
+static LOG_FILE: Mutex<Option<RotatingFile>> = Mutex::new(None);
+
+/// Send log records to `path` instead of stderr
+pub fn log_to_file(path: &Path, rotation: Rotation) -> io::Result<()> {
+    let file = RotatingFile::open(path, rotation)?;
+    *LOG_FILE.lock().unwrap_or_else(PoisonError::into_inner) = Some(file);
+    Ok(())
+}
+
+/// Logger output that writes to stderr, or to the log file if one was set up
+pub struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut log_file = LOG_FILE.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(file) = log_file.as_mut() {
+            match file.write_record(buf) {
+                Ok(()) => return Ok(buf.len()),
+                Err(e) => {
+                    // Keep logging somewhere rather than losing records
+                    *log_file = None;
+                    eprintln!("Failed to write the log file, logging to stderr: {e}");
+                }
+            }
+        }
+
+        io::stderr().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match LOG_FILE
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_mut()
+        {
+            Some(file) => file.file.flush(),
+            None => io::stderr().flush(),
+        }
+    }
+}
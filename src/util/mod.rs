@@ -1,2 +1,11 @@
+pub mod clock;
+pub mod duration;
 pub mod error;
+pub mod features;
+#[cfg(test)]
+pub mod fixture;
+pub mod logging;
+pub mod notify;
+pub mod privilege;
 pub mod sysfs;
+pub mod wake;
@@ -0,0 +1,137 @@
+use crate::config::NotificationConfig;
+use log::debug;
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, PoisonError};
+
+/// What a daemon notification is about, so each kind can be turned off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    ProfileChange,
+    Thermal,
+    Error,
+}
+
+/// Notification settings of the running daemon. Events are not sent until configured.
+static SETTINGS: Mutex<Option<NotificationConfig>> = Mutex::new(None);
+
+/// Set which daemon events are sent as notifications
+pub fn configure(config: &NotificationConfig) {
+    *SETTINGS.lock().unwrap_or_else(PoisonError::into_inner) = Some(config.clone());
+}
+
+/// Send a notification about a daemon event if notifications are enabled for it
+pub fn event(event: Event, summary: &str, body: &str) {
+    let enabled = SETTINGS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+        .is_some_and(|config| {
+            config.enable
+                && match event {
+                    Event::ProfileChange => config.profile_changes,
+                    Event::Thermal => config.thermal,
+                    Event::Error => config.errors,
+                }
+        });
+
+    if enabled {
+        send(summary, body);
+    }
+}
+
+/// Send a desktop notification, over D-Bus when built with the `dbus` feature
+/// and with `notify-send` otherwise. Failures are only logged, as
+/// notifications are best-effort.
+pub fn send(summary: &str, body: &str) {
+    #[cfg(feature = "dbus")]
+    match send_dbus(summary, body) {
+        Ok(()) => {
+            debug!("Sent notification: {summary}");
+            return;
+        }
+        Err(e) => debug!("Could not send notification over D-Bus, trying notify-send: {e}"),
+    }
+
+    let result = Command::new("notify-send")
+        .args(["--app-name=superfreq", summary, body])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+
+    match result {
+        Ok(status) if status.success() => debug!("Sent notification: {summary}"),
+        Ok(status) => debug!("notify-send exited with {status}"),
+        Err(e) => debug!("Could not run notify-send: {e}"),
+    }
+}
+
+/// Addresses of the session buses to notify. The daemon usually runs as root
+/// outside of any session, so the buses of all logged-in users are tried.
+#[cfg(feature = "dbus")]
+fn session_bus_addresses() -> Vec<String> {
+    let mut addresses: Vec<String> = std::env::var("DBUS_SESSION_BUS_ADDRESS")
+        .into_iter()
+        .collect();
+
+    if let Ok(entries) = std::fs::read_dir("/run/user") {
+        addresses.extend(
+            entries
+                .flatten()
+                .map(|entry| entry.path().join("bus"))
+                .filter(|bus| bus.exists())
+                .map(|bus| format!("unix:path={}", bus.display())),
+        );
+    }
+
+    addresses.sort();
+    addresses.dedup();
+    addresses
+}
+
+/// Send a notification to every reachable session through `org.freedesktop.Notifications`
+#[cfg(feature = "dbus")]
+fn send_dbus(summary: &str, body: &str) -> zbus::Result<()> {
+    use std::collections::HashMap;
+    use zbus::blocking::{Proxy, connection};
+    use zbus::zvariant::Value;
+
+    let mut last_error = zbus::Error::Address("No session bus found".to_string());
+    let mut sent = false;
+
+    for address in session_bus_addresses() {
+        let result = connection::Builder::address(address.as_str())
+            .and_then(connection::Builder::build)
+            .and_then(|connection| {
+                let proxy = Proxy::new(
+                    &connection,
+                    "org.freedesktop.Notifications",
+                    "/org/freedesktop/Notifications",
+                    "org.freedesktop.Notifications",
+                )?;
+                proxy.call::<_, _, u32>(
+                    "Notify",
+                    &(
+                        "superfreq",
+                        0u32,
+                        "",
+                        summary,
+                        body,
+                        Vec::<&str>::new(),
+                        HashMap::<&str, Value<'_>>::new(),
+                        -1i32,
+                    ),
+                )
+            });
+
+        match result {
+            Ok(_) => sent = true,
+            Err(e) => {
+                debug!("Could not notify through {address}: {e}");
+                last_error = e;
+            }
+        }
+    }
+
+    if sent { Ok(()) } else { Err(last_error) }
+}
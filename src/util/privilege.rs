@@ -0,0 +1,50 @@
+use nix::unistd::{self, User};
+use std::io;
+
+/// Whether the process runs with an effective user ID of 0
+pub fn is_root() -> bool {
+    unistd::geteuid().is_root()
+}
+
+/// Switch the process to `name` and its primary group, leaving every other
+/// group. Root privileges cannot be regained afterwards.
+pub fn drop_to_user(name: &str) -> io::Result<()> {
+    let user = User::from_name(name)
+        .map_err(io::Error::from)?
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("No user named '{name}'"))
+        })?;
+
+    // The groups first, as changing them needs the privileges given up below
+    unistd::setgroups(&[user.gid]).map_err(io::Error::from)?;
+    unistd::setgid(user.gid).map_err(io::Error::from)?;
+    unistd::setuid(user.uid).map_err(io::Error::from)?;
+    Ok(())
+}
+
+/// Explain what data a file that could not be read would have provided
+pub fn describe_restricted_path(path: &str) -> &'static str {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+
+    if path.contains("/powercap/") || file_name == "energy_uj" {
+        "RAPL energy counters (power usage estimates)"
+    } else if path.starts_with("/dev/cpu/") {
+        "model-specific registers"
+    } else if file_name.starts_with("charge_") {
+        "battery charge thresholds and behaviour"
+    } else if path.contains("/power_supply/") {
+        "battery and power supply status"
+    } else if file_name == "energy_perf_bias" {
+        "energy performance bias (EPB)"
+    } else if file_name.starts_with("energy_performance") {
+        "energy performance preference (EPP)"
+    } else if path.contains("/hwmon") || path.contains("/thermal") {
+        "temperature sensors"
+    } else if path.contains("/cpufreq/") {
+        "CPU frequency scaling state"
+    } else if file_name == "platform_profile" || file_name == "platform_profile_choices" {
+        "ACPI platform profile"
+    } else {
+        "other system data"
+    }
+}
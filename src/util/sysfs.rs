@@ -1,6 +1,174 @@
 use crate::util::error::ControlError;
+use log::debug;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock, PoisonError};
 use std::{fs, io, path::Path};
 
+/// Environment variable naming a directory that stands in for `/`, holding
+/// `sys` and `proc` trees to read and write instead of the real ones
+pub const ROOT_ENV: &str = "SUPERFREQ_SYSFS_ROOT";
+
+/// Directory `/sys` and `/proc` paths are resolved under
+static ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Set while the daemon runs in observe mode, where writes are only logged
+static OBSERVE_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Files opened while the process was root, used for reads and writes of
+/// their paths once it no longer is
+static RETAINED: Mutex<BTreeMap<PathBuf, Retained>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug)]
+struct Retained {
+    file: File,
+    writable: bool,
+}
+
+/// Resolve `/sys` and `/proc` paths under `root` instead of `/`, e.g. a fixture
+/// tree for tests. Only has an effect before the first path is resolved.
+pub fn set_root(root: PathBuf) {
+    let _ = ROOT.set(root);
+}
+
+fn root() -> &'static Path {
+    ROOT.get_or_init(|| {
+        std::env::var_os(ROOT_ENV)
+            .filter(|root| !root.is_empty())
+            .map_or_else(|| PathBuf::from("/"), PathBuf::from)
+    })
+}
+
+/// Root set by a test, which takes precedence over the configured one
+#[cfg(test)]
+static TEST_ROOT: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Held by the test using [`TEST_ROOT`], so tests with fixture trees run one
+/// at a time
+#[cfg(test)]
+static TEST_ROOT_LOCK: Mutex<()> = Mutex::new(());
+
+/// Resolves paths under a test's fixture tree until it is dropped
+#[cfg(test)]
+pub struct ScopedRoot {
+    _lock: std::sync::MutexGuard<'static, ()>,
+}
+
+#[cfg(test)]
+impl Drop for ScopedRoot {
+    fn drop(&mut self) {
+        *TEST_ROOT.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+}
+
+/// Resolve paths under `root` on every thread until the returned guard is
+/// dropped, unlike [`set_root`], which can only be set once per process
+#[cfg(test)]
+pub fn scoped_root(root: PathBuf) -> ScopedRoot {
+    let lock = TEST_ROOT_LOCK
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    *TEST_ROOT.lock().unwrap_or_else(PoisonError::into_inner) = Some(root);
+    ScopedRoot { _lock: lock }
+}
+
+/// Resolve `relative` under the root
+fn join_root(relative: &Path) -> PathBuf {
+    #[cfg(test)]
+    if let Some(root) = TEST_ROOT
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+    {
+        return root.join(relative);
+    }
+    root().join(relative)
+}
+
+/// Whether paths are resolved under a directory other than `/`
+pub fn has_custom_root() -> bool {
+    join_root(Path::new("")) != Path::new("/")
+}
+
+/// The location of an absolute `/sys` or `/proc` path under the configured
+/// root, which is `/` unless a fixture tree is used
+pub fn path(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    match path.strip_prefix("/") {
+        Ok(relative) => join_root(relative),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// Make every write through this module a no-op that is only logged
+pub fn set_observe_only(observe_only: bool) {
+    OBSERVE_ONLY.store(observe_only, Ordering::Relaxed);
+}
+
+/// Whether writes are only logged instead of made
+pub fn is_observe_only() -> bool {
+    OBSERVE_ONLY.load(Ordering::Relaxed)
+}
+
+/// Keep `paths` open, so they can still be read and written after the process
+/// drops root privileges. Returns how many of them could be opened.
+pub fn retain_handles(paths: impl IntoIterator<Item = PathBuf>) -> usize {
+    let mut retained = RETAINED.lock().unwrap_or_else(PoisonError::into_inner);
+    for path in paths {
+        // Some attributes can only be written or only be read
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .or_else(|_| fs::OpenOptions::new().write(true).open(&path))
+            .map(|file| Retained {
+                file,
+                writable: true,
+            })
+            .or_else(|_| {
+                File::open(&path).map(|file| Retained {
+                    file,
+                    writable: false,
+                })
+            });
+        match file {
+            Ok(file) => {
+                retained.insert(path, file);
+            }
+            Err(e) => debug!("Not keeping {} open: {e}", path.display()),
+        }
+    }
+    retained.len()
+}
+
+/// Read a retained file from the start, or `None` if `path` is not retained
+pub fn read_retained(path: &Path) -> Option<io::Result<String>> {
+    let retained = RETAINED.lock().unwrap_or_else(PoisonError::into_inner);
+    let file = &retained.get(path)?.file;
+
+    let mut content = Vec::new();
+    let mut buffer = [0; 4096];
+    loop {
+        match file.read_at(&mut buffer, content.len() as u64) {
+            Ok(0) => break,
+            Ok(read) => content.extend_from_slice(&buffer[..read]),
+            Err(e) => return Some(Err(e)),
+        }
+    }
+    Some(String::from_utf8(content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)))
+}
+
+/// Write to a retained file, or `None` if `path` is not retained. Sysfs
+/// attributes take a value in a single write at the start of the file.
+fn write_retained(path: &Path, value: &str) -> Option<io::Result<()>> {
+    let retained = RETAINED.lock().unwrap_or_else(PoisonError::into_inner);
+    let file = &retained.get(path)?.file;
+    Some(file.write_at(value.as_bytes(), 0).map(|_| ()))
+}
+
 /// Write a value to a sysfs file with consistent error handling
 ///
 /// # Arguments
@@ -14,19 +182,28 @@ use std::{fs, io, path::Path};
 /// - `ControlError::PermissionDenied` if permission is denied
 /// - `ControlError::PathMissing` if the path doesn't exist
 /// - `ControlError::WriteError` for other I/O errors
+///
+/// In observe mode nothing is written and `Ok` is returned.
 pub fn write_sysfs_value(path: impl AsRef<Path>, value: &str) -> Result<(), ControlError> {
     let p = path.as_ref();
 
-    fs::write(p, value).map_err(|e| {
-        let error_msg = format!("Path: {:?}, Value: '{}', Error: {}", p.display(), value, e);
-        match e.kind() {
-            io::ErrorKind::PermissionDenied => ControlError::PermissionDenied(error_msg),
-            io::ErrorKind::NotFound => {
-                ControlError::PathMissing(format!("Path '{}' does not exist", p.display()))
+    if is_observe_only() {
+        debug!("Observe mode, not writing '{value}' to {}", p.display());
+        return Ok(());
+    }
+
+    write_retained(p, value)
+        .unwrap_or_else(|| fs::write(p, value))
+        .map_err(|e| {
+            let error_msg = format!("Path: {:?}, Value: '{}', Error: {}", p.display(), value, e);
+            match e.kind() {
+                io::ErrorKind::PermissionDenied => ControlError::PermissionDenied(error_msg),
+                io::ErrorKind::NotFound => {
+                    ControlError::PathMissing(format!("Path '{}' does not exist", p.display()))
+                }
+                _ => ControlError::WriteError(error_msg),
             }
-            _ => ControlError::WriteError(error_msg),
-        }
-    })
+        })
 }
 
 /// Read a value from a sysfs file with consistent error handling
@@ -47,7 +224,8 @@ pub fn write_sysfs_value(path: impl AsRef<Path>, value: &str) -> Result<(), Cont
 /// - `ControlError::ReadError` for other I/O errors
 pub fn read_sysfs_value(path: impl AsRef<Path>) -> Result<String, ControlError> {
     let p = path.as_ref();
-    fs::read_to_string(p)
+    read_retained(p)
+        .unwrap_or_else(|| fs::read_to_string(p))
         .map_err(|e| {
             let error_msg = format!("Path: {:?}, Error: {}", p.display(), e);
             match e.kind() {
@@ -75,6 +253,15 @@ pub fn path_exists_and_writable(path: &Path) -> bool {
         return false;
     }
 
+    // A retained file stays writable after root privileges are dropped
+    if let Some(retained) = RETAINED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(path)
+    {
+        return retained.writable;
+    }
+
     // Try to open the file with write access to verify write permission
     fs::OpenOptions::new().write(true).open(path).is_ok()
 }
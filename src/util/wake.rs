@@ -0,0 +1,17 @@
+use log::debug;
+use tokio::sync::Notify;
+
+/// Signalled when an event asks the daemon to start its next cycle right away.
+/// A wakeup requested while the daemon is busy is kept for its next wait.
+static WAKEUP: Notify = Notify::const_new();
+
+/// Ask the daemon to re-evaluate now instead of at the end of its poll interval
+pub fn request_wakeup(reason: &str) {
+    debug!("Wakeup requested: {reason}");
+    WAKEUP.notify_one();
+}
+
+/// Wait until a wakeup is requested
+pub async fn requested() {
+    WAKEUP.notified().await;
+}
@@ -0,0 +1,129 @@
+//! Detection of virtual machines and containers, the way `systemd-detect-virt`
+//! does it. Neither can change the host's CPU or battery settings, so the
+//! engine only monitors there unless told otherwise.
+
+use crate::core::{Virtualization, VirtualizationKind};
+use crate::util::sysfs;
+use std::fs;
+use std::sync::OnceLock;
+
+/// DMI vendor and product strings of hypervisors, with the name systemd uses
+const DMI_VENDORS: &[(&str, &str)] = &[
+    ("KVM", "kvm"),
+    ("OpenStack", "kvm"),
+    ("KubeVirt", "kvm"),
+    ("Amazon EC2", "amazon"),
+    ("QEMU", "qemu"),
+    ("VMware", "vmware"),
+    ("VMW", "vmware"),
+    ("innotek GmbH", "oracle"),
+    ("VirtualBox", "oracle"),
+    ("Xen", "xen"),
+    ("Bochs", "bochs"),
+    ("Parallels", "parallels"),
+    ("BHYVE", "bhyve"),
+    ("Hyper-V", "microsoft"),
+    ("Apple Virtualization", "apple"),
+    ("Google Compute Engine", "google"),
+];
+
+const DMI_FILES: &[&str] = &[
+    "/sys/class/dmi/id/product_name",
+    "/sys/class/dmi/id/sys_vendor",
+    "/sys/class/dmi/id/board_vendor",
+    "/sys/class/dmi/id/bios_vendor",
+    "/sys/class/dmi/id/product_version",
+];
+
+static DETECTED: OnceLock<Option<Virtualization>> = OnceLock::new();
+
+fn read_trimmed(path: &str) -> Option<String> {
+    fs::read_to_string(sysfs::path(path))
+        .ok()
+        .map(|content| content.trim().to_string())
+}
+
+fn container(technology: &str) -> Virtualization {
+    Virtualization {
+        kind: VirtualizationKind::Container,
+        technology: technology.to_string(),
+    }
+}
+
+fn virtual_machine(technology: &str) -> Virtualization {
+    Virtualization {
+        kind: VirtualizationKind::VirtualMachine,
+        technology: technology.to_string(),
+    }
+}
+
+fn detect_container() -> Option<Virtualization> {
+    // Set by systemd-nspawn, podman, LXC and most other container managers
+    if let Some(name) = read_trimmed("/run/systemd/container").filter(|name| !name.is_empty()) {
+        return Some(container(&name));
+    }
+    // Only readable by root, but covers managers that only set the variable
+    if let Ok(environ) = fs::read(sysfs::path("/proc/1/environ")) {
+        let name = environ
+            .split(|&byte| byte == 0)
+            .find_map(|variable| variable.strip_prefix(b"container="))
+            .filter(|name| !name.is_empty());
+        if let Some(name) = name {
+            return Some(container(&String::from_utf8_lossy(name)));
+        }
+    }
+    if sysfs::path("/run/.containerenv").exists() {
+        return Some(container("podman"));
+    }
+    if sysfs::path("/.dockerenv").exists() {
+        return Some(container("docker"));
+    }
+
+    let osrelease = read_trimmed("/proc/sys/kernel/osrelease")?;
+    (osrelease.contains("Microsoft") || osrelease.contains("WSL")).then(|| container("wsl"))
+}
+
+fn detect_vm() -> Option<Virtualization> {
+    for path in DMI_FILES {
+        let Some(value) = read_trimmed(path) else {
+            continue;
+        };
+        if let Some(&(_, technology)) = DMI_VENDORS
+            .iter()
+            .find(|(vendor, _)| value.starts_with(vendor))
+        {
+            return Some(virtual_machine(technology));
+        }
+    }
+    if read_trimmed("/sys/class/dmi/id/sys_vendor").as_deref() == Some("Microsoft Corporation")
+        && read_trimmed("/sys/class/dmi/id/product_name").as_deref() == Some("Virtual Machine")
+    {
+        return Some(virtual_machine("microsoft"));
+    }
+
+    // Xen guests without DMI, but not the privileged domain that runs them
+    if read_trimmed("/sys/hypervisor/type").as_deref() == Some("xen") {
+        let is_dom0 = read_trimmed("/proc/xen/capabilities")
+            .is_some_and(|capabilities| capabilities.contains("control_d"));
+        if !is_dom0 {
+            return Some(virtual_machine("xen"));
+        }
+    }
+
+    // The CPU flag every x86 hypervisor sets, for those that hide their name
+    let cpuinfo = fs::read_to_string(sysfs::path("/proc/cpuinfo")).ok()?;
+    cpuinfo
+        .lines()
+        .filter(|line| line.starts_with("flags"))
+        .any(|line| line.split_whitespace().any(|flag| flag == "hypervisor"))
+        .then(|| virtual_machine("vm-other"))
+}
+
+/// The virtualization the system runs under, or `None` on bare metal. A
+/// container is reported over the VM it runs in, as it is the more
+/// restrictive of the two.
+pub fn detect() -> Option<&'static Virtualization> {
+    DETECTED
+        .get_or_init(|| detect_container().or_else(detect_vm))
+        .as_ref()
+}